@@ -0,0 +1,41 @@
+/* C API of the parabox engine, exported by the shared library when it is
+ * built with the `cdylib` feature (the shared library itself is always
+ * built).
+ * See src/ffi.rs for the details of each function. */
+
+#ifndef PARABOX_H
+#define PARABOX_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ParaboxGame ParaboxGame;
+
+/* Returns NULL on error, setting *error (if error is not NULL) to a message
+ * to be freed with parabox_string_free. */
+ParaboxGame *parabox_game_load(const char *text, char **error);
+ParaboxGame *parabox_game_clone(const ParaboxGame *game);
+void parabox_game_free(ParaboxGame *game);
+
+/* direction is one of 'U', 'D', 'L', 'R'. Returns 1 if the state changed,
+ * 0 if it didn't and -1 if the direction is invalid. */
+int32_t parabox_game_play(ParaboxGame *game, char direction);
+bool parabox_game_won(const ParaboxGame *game);
+
+/* The results are freed with parabox_string_free and parabox_bytes_free. */
+char *parabox_game_to_text(const ParaboxGame *game);
+uint8_t *parabox_game_encode_state(const ParaboxGame *game, size_t *len);
+
+void parabox_string_free(char *text);
+void parabox_bytes_free(uint8_t *bytes, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PARABOX_H */
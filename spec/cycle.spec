@@ -0,0 +1,9 @@
+# Cycles: when a chain of pushes loops back to a cell that is already moving
+# in the same direction, the whole cycle moves together.
+#
+# Here the last box exits block 1, enters its clone and lands where the
+# player was.
+play R
+expect player at 1 1 0
+expect block 3 at 1 2 0
+expect block 4 at 1 0 0
@@ -0,0 +1,8 @@
+# Doors can only be moved onto while a cell rests on a switch of the same
+# channel.
+play DR
+expect player at 0 0 0
+
+play URD
+expect player at 0 1 0
+expect block 2 at 0 2 1
@@ -0,0 +1,6 @@
+# Eat: when the target can neither be pushed nor entered, the mover eats it:
+# the target enters the mover from the opposite side, and the mover takes
+# its place.
+play R
+expect player at 0 2 2
+expect block 2 at 1 2 1
@@ -0,0 +1,4 @@
+# Flip on exit: exiting a horizontally flipped block mirrors the direction
+# of movement, and flips the exiting cell.
+play L
+expect player at 0 3 2 fliph
@@ -0,0 +1,12 @@
+# Gravity: after each move, cells fall down until they are held up by a wall
+# or another cell, or reach the bottom of the top-level block.
+play R
+expect player at 0 1 1
+
+# Falling onto a block enters it, and cells keep falling inside of it.
+play RR
+expect player at 1 1 0
+
+# Moving up is possible, but the player falls back down.
+play U
+expect player at 1 1 0
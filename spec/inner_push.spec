@@ -0,0 +1,5 @@
+# Inner push: with the "inner_push" header, pushing a wall from inside a
+# block pushes the block itself, while the pusher stays in place.
+play R
+expect block 1 at 0 3 1
+expect player at 1 1 1
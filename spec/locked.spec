@@ -0,0 +1,7 @@
+# Locked blocks can't be pushed or eaten, but can still be entered.
+play DR
+expect player at 0 1 0
+
+play UR
+expect player at 2 0 1
+
@@ -0,0 +1,16 @@
+# One-way gates: cells can only move onto a gate in one of its directions.
+play R
+expect player at 0 2 1
+
+play L
+expect player at 0 1 1
+
+# Gates can allow several directions.
+play RR
+expect player at 0 2 1
+
+play UR
+expect player at 0 3 2
+
+play D
+expect player at 0 3 1
@@ -0,0 +1,5 @@
+# Priority: with "attempt_order enter,push,eat,possess", moving into a
+# hollow block enters it instead, at the middle of the facing side.
+play R
+expect player at 2 0 1
+expect block 2 at 0 2 2
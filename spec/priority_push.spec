@@ -0,0 +1,5 @@
+# Priority: with the default attempt order (push, enter, eat, possess),
+# moving into a hollow block pushes it if possible.
+play R
+expect player at 0 2 2
+expect block 2 at 0 3 2
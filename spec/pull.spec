@@ -0,0 +1,14 @@
+# Pull: moving away from an adjacent cell drags it along.
+play R
+expect player at 0 3 1
+expect block 1 at 0 2 1
+
+# Cells are only pulled in the direction of the move, and walls stay put.
+play UD
+expect player at 0 3 1
+expect block 1 at 0 2 1
+
+# Pushing works as before.
+play LL
+expect player at 0 1 1
+expect block 1 at 0 0 1
@@ -0,0 +1,11 @@
+# Push: moving into a cell pushes it one step in the same direction.
+# A cell that can't move (here, the box can't leave the top-level block)
+# blocks the whole chain.
+play R
+expect player at 0 2 2
+expect block 2 at 0 3 2
+play R
+expect block 2 at 0 4 2
+play R
+expect unchanged
+expect player at 0 3 2
@@ -0,0 +1,15 @@
+# Rotation: a reference turned a quarter counterclockwise shows the interior
+# of its block turned the same way. Entering it from the left enters the
+# interior from the top, and exiting the interior upwards exits the
+# reference to the left.
+play R
+expect player at 1 1 2
+play U
+expect player at 0 1 1
+
+# Moves inside the interior are not turned, and exiting the interior to the
+# left exits the reference downwards.
+play RDDL
+expect player at 1 0 0
+play L
+expect player at 0 2 0
@@ -0,0 +1,5 @@
+# Shed: with the "shed" header, a cell that fails to exit a block pushes the
+# block away in the opposite direction and takes its place.
+play L
+expect player at 0 2 1
+expect block 1 at 0 3 1
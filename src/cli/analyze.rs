@@ -0,0 +1,93 @@
+use parabox::engine::{analysis::*, batch::find_levels, difficulty::*, solver::SolverConfig, *};
+use std::{fs, path::Path};
+
+const USAGE: &str = "usage: parabox analyze <level or pack>... [--max-states <n>] [--difficulty]";
+
+/// Prints the size and complexity metrics of each level, or of every level
+/// under a pack directory, followed by the goals that are never satisfied and
+/// the possessable cells that are never possessed in the states reached.
+/// With `--difficulty`, prints the estimated difficulty of the levels
+/// instead, from the easiest to the hardest.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut config = AnalysisConfig::default();
+    let mut difficulty = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-states" => {
+                config.max_states = args.next().and_then(|n| n.parse().ok()).ok_or(USAGE)?
+            }
+            "--difficulty" => difficulty = true,
+            _ => paths.push(Path::new(arg)),
+        }
+    }
+    if paths.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let mut levels = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            levels.extend(find_levels(path)?);
+        } else {
+            levels.push(path.to_path_buf());
+        }
+    }
+    let mut estimates = Vec::new();
+    for level in levels {
+        let text = fs::read_to_string(&level).map_err(|e| format!("{}: {e}", level.display()))?;
+        let game = Game::parse(&text).map_err(|e| format!("{}: {e}", level.display()))?;
+        if difficulty {
+            let solver = SolverConfig {
+                max_nodes: config.max_states,
+                ..Default::default()
+            };
+            estimates.push((level, estimate_difficulty(&game, &solver)));
+            continue;
+        }
+
+        let analysis = analyze(&game, &config);
+        println!("{}: {analysis}", level.display());
+
+        let reachability = &analysis.reachability;
+        let within = if analysis.exhausted {
+            ""
+        } else {
+            " within the budget"
+        };
+        for &i in &reachability.unreachable_goals {
+            let goal = &game.goals()[i];
+            let kind = if goal.player { "player goal" } else { "goal" };
+            let at = describe(&game, goal.gpos);
+            println!("  {kind} {at} is never satisfied{within}");
+        }
+        for &id in &reachability.unreachable_possessables {
+            let at = describe(&game, game.cells()[id].gpos());
+            println!("  cell {at} is never possessed{within}");
+        }
+    }
+
+    // levels without a solution come last
+    estimates.sort_by(|(_, a), (_, b)| match (a.score, b.score) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    });
+    for (level, estimate) in estimates {
+        println!("{}: {estimate}", level.display());
+    }
+    Ok(())
+}
+
+// e.g. `at (1, 2) in block 3`
+fn describe(game: &Game, gpos: GlobalPos) -> String {
+    let Pos(x, y) = gpos.pos;
+    match gpos.block_id().and_then(|id| game.cells()[id].block()) {
+        Some(block) => format!(
+            "at ({x}, {y}) in block {}",
+            game.meta().block_label(block.block_no)
+        ),
+        None => format!("at ({x}, {y}) in space"),
+    }
+}
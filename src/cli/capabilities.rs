@@ -0,0 +1,27 @@
+use parabox::engine::capabilities;
+
+const USAGE: &str = "usage: parabox capabilities [--json]";
+
+/// Prints what this build supports.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let capabilities = capabilities();
+    match args {
+        [] => {
+            println!("parabox {}", capabilities.version);
+            let versions = capabilities
+                .format_versions
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>();
+            println!("format versions: {}", versions.join(", "));
+            println!(
+                "rule extensions: {}",
+                capabilities.rule_extensions.join(", ")
+            );
+            println!("features: {}", capabilities.features.join(", "));
+        }
+        [flag] if flag == "--json" => println!("{}", capabilities.to_json()),
+        _ => return Err(USAGE.to_string()),
+    }
+    Ok(())
+}
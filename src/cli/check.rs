@@ -0,0 +1,83 @@
+use parabox::engine::{batch::*, canonical::find_duplicates, Game};
+use std::{fs, path::Path};
+
+const USAGE: &str =
+    "usage: parabox check <pack> [--max-nodes <n>] [--threads <n>] [--trivial <moves>] [--near <lines>]";
+
+/// Tries to solve every level under `<pack>`, and reports the levels that are
+/// unsolvable, invalid or suspiciously trivial. Levels that are the same up
+/// to symmetry, or differ in at most `--near` cells and floors, are reported
+/// as well, but are not counted as broken.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut config = BatchConfig::default();
+    let mut near = 0;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .and_then(|value| value.parse().ok())
+                .ok_or(USAGE)
+        };
+        match arg.as_str() {
+            "--max-nodes" => config.solver.max_nodes = value()?,
+            "--threads" => config.solver.threads = value()?,
+            "--trivial" => config.trivial_moves = value()?,
+            "--near" => near = value()?,
+            _ => paths.push(arg),
+        }
+    }
+    let [pack] = paths[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let checks = check_levels(Path::new(pack), &config, |check| {
+        let tag = match check.verdict {
+            Verdict::Solved { .. } => "ok",
+            Verdict::Trivial { .. } => "TRIVIAL",
+            Verdict::Unsolvable { .. } => "UNSOLVABLE",
+            Verdict::GaveUp { .. } => "unknown",
+            Verdict::Invalid(_) => "INVALID",
+        };
+        println!("[{tag}] {}: {}", check.path.display(), check.verdict);
+    })?;
+
+    let count = |f: fn(&Verdict) -> bool| checks.iter().filter(|c| f(&c.verdict)).count();
+    let problems = count(Verdict::is_problem);
+    println!(
+        "{} levels: {} solved, {} trivial, {} unknown, {} broken",
+        checks.len(),
+        count(|v| matches!(v, Verdict::Solved { .. })),
+        count(|v| matches!(v, Verdict::Trivial { .. })),
+        count(|v| matches!(v, Verdict::GaveUp { .. })),
+        problems,
+    );
+    report_duplicates(Path::new(pack), near)?;
+
+    if problems > 0 {
+        Err(format!("{problems} levels are broken"))
+    } else {
+        Ok(())
+    }
+}
+
+fn report_duplicates(pack: &Path, near: usize) -> Result<(), String> {
+    // invalid levels are reported by the checks already
+    let (paths, levels): (Vec<_>, Vec<_>) = find_levels(pack)?
+        .into_iter()
+        .filter_map(|path| {
+            let game = Game::parse(&fs::read_to_string(&path).ok()?).ok()?;
+            Some((path, game))
+        })
+        .unzip();
+    for duplicate in find_duplicates(&levels, near) {
+        let first = paths[duplicate.first].display();
+        let second = paths[duplicate.second].display();
+        match duplicate.distance {
+            0 => println!("[DUPLICATE] {first} and {second} are the same up to symmetry"),
+            distance => println!("[near copy] {first} and {second} differ in {distance} places"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use parabox::engine::{custom_levels::*, *};
+use std::{fs, path::Path};
+
+const USAGE: &str =
+    "usage: parabox export <level> [name] [--dir <dir>] [--music <n>] [--palette <n>] [--force]";
+
+/// Writes a level into the custom level directory of the installed game, or
+/// `--dir`, in the format of the official game. The name of the level file
+/// defaults to the name of `<level>`, and existing files are only replaced
+/// with `--force`.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut dir = None;
+    let mut options = ExportOptions::default();
+    let mut force = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(USAGE);
+        match arg.as_str() {
+            "--dir" => dir = Some(value()?.into()),
+            "--music" => options.music = value()?.parse().map_err(|_| USAGE)?,
+            "--palette" => options.palette = value()?.parse().map_err(|_| USAGE)?,
+            "--force" => force = true,
+            _ => paths.push(arg),
+        }
+    }
+    let (level, name) = match paths[..] {
+        [level] => (
+            level,
+            Path::new(level).file_stem().and_then(|stem| stem.to_str()),
+        ),
+        [level, name] => (level, Some(name.as_str())),
+        _ => return Err(USAGE.to_string()),
+    };
+    let name = name.ok_or(USAGE)?;
+    let dir = dir.or_else(find_custom_level_dir).ok_or(format!(
+        "No custom levels of Patrick's Parabox found, use --dir or set {CUSTOM_LEVELS_VAR}"
+    ))?;
+
+    let text = fs::read_to_string(level).map_err(|e| format!("{level}: {e}"))?;
+    let game = Game::parse(&text).map_err(|e| format!("{level}: {e}"))?;
+    let path = export_custom_level(&game, &dir, name, &options, force)
+        .map_err(|e| format!("{level}: {e}"))?;
+    println!("Exported to {}", path.display());
+    Ok(())
+}
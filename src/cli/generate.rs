@@ -0,0 +1,60 @@
+use parabox::engine::{generator::*, *};
+use std::{fs, path::Path};
+
+const USAGE: &str = "usage: parabox generate <level> [--seed <n>] [--size <width>x<height>] [--blocks <n>] [--walls <n>] [--mechanics <list>] [--constraints <list>] [--min-moves <n>] [--max-nodes <n>] [--reverse <steps>] [--force]";
+
+/// Generates a random level that can be won, and writes it with its
+/// solution next to it. Existing files are only replaced with `--force`.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut config = GeneratorConfig::default();
+    let mut force = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(USAGE);
+        match arg.as_str() {
+            "--seed" => config.seed = value()?.parse().map_err(|_| USAGE)?,
+            "--size" => {
+                let (width, height) = value()?.split_once('x').ok_or(USAGE)?;
+                config.width = width.parse().map_err(|_| USAGE)?;
+                config.height = height.parse().map_err(|_| USAGE)?;
+            }
+            "--blocks" => config.blocks = value()?.parse().map_err(|_| USAGE)?,
+            "--walls" => config.walls = value()?.parse().map_err(|_| USAGE)?,
+            "--mechanics" => config.mechanics = Mechanics::parse(value()?)?,
+            "--constraints" => config.constraints = Constraint::parse_list(value()?)?,
+            "--min-moves" => config.min_moves = value()?.parse().map_err(|_| USAGE)?,
+            "--max-nodes" => config.solver.max_nodes = value()?.parse().map_err(|_| USAGE)?,
+            "--reverse" => {
+                let steps = value()?.parse().map_err(|_| USAGE)?;
+                config.method = Method::Reverse { steps };
+            }
+            "--force" => force = true,
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths[..] else {
+        return Err(USAGE.to_string());
+    };
+    let path = Path::new(path);
+    let solution_path = path.with_extension("solution");
+    if !force && (path.exists() || solution_path.exists()) {
+        return Err(format!(
+            "{}: The level or its solution exists already, use --force to replace them",
+            path.display()
+        ));
+    }
+
+    let generated = generate(&config)?;
+    fs::write(path, generated.game.to_text()).map_err(|e| format!("{}: {e}", path.display()))?;
+    fs::write(&solution_path, format_sequence(&generated.solution))
+        .map_err(|e| format!("{}: {e}", solution_path.display()))?;
+    println!(
+        "Generated {} in {} attempts, solved in {} moves",
+        path.display(),
+        generated.attempts,
+        generated.solution.len()
+    );
+    Ok(())
+}
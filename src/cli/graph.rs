@@ -0,0 +1,30 @@
+use parabox::engine::*;
+use std::fs;
+
+const USAGE: &str = "usage: parabox graph <level> [--json]";
+
+/// Prints the graph of how the blocks of a level are connected, in the DOT
+/// language of Graphviz or as JSON.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut json = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let game = Game::parse(&text).map_err(|e| format!("{path}: {e}"))?;
+    let graph = game.structure_graph();
+    if json {
+        println!("{}", graph.to_json());
+    } else {
+        print!("{}", graph.to_dot());
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use crossterm::{event, terminal};
+use parabox::engine::*;
+use parabox::render::{Renderer, TerminalRenderer};
+use std::io::BufWriter;
+use std::{fs, time::Duration};
+
+use crate::{print_message, print_status};
+
+const USAGE: &str = "usage: parabox replay <level> <solution>";
+
+/// Delays between moves while playing, from slowest to fastest.
+const SPEEDS: [u64; 6] = [1000, 500, 250, 125, 60, 30];
+
+/// Steps through a recorded solution in the terminal.
+///
+/// Space plays or pauses, the left and right arrow keys (or A and D) step
+/// back and forward, `+` and `-` change the speed, Home and End jump to the
+/// start and the end, and Q quits.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let [level, solution] = args else {
+        return Err(USAGE.to_string());
+    };
+
+    let text = fs::read_to_string(level).map_err(|e| format!("{level}: {e}"))?;
+    let game = Game::parse(&text).map_err(|e| format!("{level}: {e}"))?;
+    let text = fs::read_to_string(solution).map_err(|e| format!("{solution}: {e}"))?;
+    let moves = parse_sequence(&text).map_err(|e| format!("{solution}: {e}"))?;
+
+    // keep every move, so that steps match the solution file
+    let mut timeline = Timeline::new(game);
+    timeline.set_record_noops(true);
+    for &direction in &moves {
+        timeline.play(direction);
+    }
+    timeline.seek(0);
+
+    terminal::enable_raw_mode().map_err(|e| e.to_string())?;
+    let result = replay(&mut timeline).map_err(|e| e.to_string());
+    terminal::disable_raw_mode().map_err(|e| e.to_string())?;
+    println!();
+    result
+}
+
+fn replay(timeline: &mut Timeline) -> std::io::Result<()> {
+    let mut renderer = TerminalRenderer::new(BufWriter::new(std::io::stdout()));
+    let mut playing = false;
+    let mut speed = 2;
+
+    loop {
+        let game = timeline.current();
+        renderer.render(game)?;
+        let status = format!(
+            "Step {}/{} | {} at {} ms per move | Goals: {}/{}",
+            timeline.cursor(),
+            timeline.len(),
+            if playing { "Playing" } else { "Paused" },
+            SPEEDS[speed],
+            game.satisfied_goals(),
+            game.goals().len()
+        );
+        print_status(renderer.out_mut(), &status)?;
+        let message = match timeline.moves().get(timeline.cursor()) {
+            _ if game.won() => "Solved!".to_string(),
+            Some(direction) => format!("Next: {direction:?}"),
+            None => "End of the solution".to_string(),
+        };
+        print_message(renderer.out_mut(), &message)?;
+
+        if playing && timeline.cursor() == timeline.len() {
+            playing = false;
+            continue;
+        }
+
+        // advance automatically unless a key is pressed in time
+        if playing && !event::poll(Duration::from_millis(SPEEDS[speed]))? {
+            timeline.redo();
+            continue;
+        }
+
+        let event::Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != event::KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            event::KeyCode::Char(' ') => playing = !playing,
+            event::KeyCode::Right | event::KeyCode::Char('d') => {
+                playing = false;
+                timeline.redo();
+            }
+            event::KeyCode::Left | event::KeyCode::Char('a') => {
+                playing = false;
+                timeline.undo();
+            }
+            event::KeyCode::Char('+') | event::KeyCode::Char('=') => {
+                speed = (speed + 1).min(SPEEDS.len() - 1)
+            }
+            event::KeyCode::Char('-') => speed = speed.saturating_sub(1),
+            event::KeyCode::Home => {
+                timeline.seek(0);
+            }
+            event::KeyCode::End => {
+                timeline.seek(timeline.len());
+            }
+            event::KeyCode::Char('q') | event::KeyCode::Esc => return Ok(()),
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use parabox::engine::{solver::*, *};
+use parabox::render::render_ascii;
+use serde_json::{json, Value};
+use std::{
+    fs,
+    io::{BufRead, Write},
+};
+
+const USAGE: &str = "usage: parabox serve";
+
+// error codes defined by JSON-RPC
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// errors of the engine, e.g. an invalid level
+const ENGINE_ERROR: i64 = -32000;
+
+type RpcError = (i64, String);
+
+/// Drives the engine with JSON-RPC 2.0 requests on stdin, one per line, and
+/// writes one response per line to stdout, so that other programs can run
+/// it as a subprocess.
+///
+/// ```plain
+/// load {"text": ...} or {"path": ...}   loads a level, returns the state
+/// play {"moves": "RRUL"}                returns {"changed": n, "won": ...}
+/// undo {"count": n}                     returns {"undone": n}
+/// state                                 returns the state
+/// solve {"max_nodes": n}                searches from the current state
+/// ```
+///
+/// The state holds the moves played, whether the level is won, the moves
+/// that change the state, the level file text and a text rendering.
+pub fn run(args: &[String]) -> Result<(), String> {
+    if !args.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let mut server = Server { timeline: None };
+    let mut stdout = std::io::stdout().lock();
+    for line in std::io::stdin().lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = server.respond(&line);
+        writeln!(stdout, "{response}").map_err(|e| e.to_string())?;
+        stdout.flush().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+struct Server {
+    timeline: Option<Timeline>,
+}
+
+fn error(id: Value, (code, message): RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message},
+    })
+}
+
+fn moves_to_string(moves: &[Direction]) -> String {
+    moves.iter().map(|direction| direction.to_char()).collect()
+}
+
+impl Server {
+    fn respond(&mut self, line: &str) -> Value {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return error(Value::Null, (PARSE_ERROR, e.to_string())),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return error(id, (INVALID_REQUEST, "Missing method".to_string()));
+        };
+        let params = request.get("params").cloned().unwrap_or(json!({}));
+
+        match self.handle(method, &params) {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(e) => error(id, e),
+        }
+    }
+
+    fn handle(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        let param = |name: &str| params.get(name).and_then(Value::as_str);
+
+        match method {
+            "load" => {
+                let text = match (param("text"), param("path")) {
+                    (Some(text), _) => text.to_string(),
+                    (None, Some(path)) => fs::read_to_string(path)
+                        .map_err(|e| (ENGINE_ERROR, format!("{path}: {e}")))?,
+                    (None, None) => {
+                        return Err((INVALID_PARAMS, "Expected text or path".to_string()))
+                    }
+                };
+                let game = Game::parse(&text).map_err(|e| (ENGINE_ERROR, e))?;
+                self.timeline = Some(Timeline::new(game));
+                self.state()
+            }
+            "play" => {
+                let moves = param("moves").ok_or((INVALID_PARAMS, "Expected moves".to_string()))?;
+                let moves = parse_sequence(moves).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+                let timeline = self.timeline()?;
+                let mut changed = 0;
+                for direction in moves {
+                    if timeline.play(direction).changed() {
+                        changed += 1;
+                    }
+                }
+                Ok(json!({"changed": changed, "won": timeline.current().won()}))
+            }
+            "undo" => {
+                let count = params.get("count").and_then(Value::as_u64).unwrap_or(1);
+                let timeline = self.timeline()?;
+                let undone = (0..count).take_while(|_| timeline.undo()).count();
+                Ok(json!({"undone": undone}))
+            }
+            "state" => self.state(),
+            "solve" => {
+                let mut config = SolverConfig::default();
+                if let Some(max_nodes) = params.get("max_nodes").and_then(Value::as_u64) {
+                    config.max_nodes = max_nodes as usize;
+                }
+                let report = solve(self.timeline()?.current(), &config);
+                Ok(json!({
+                    "solution": report.solution.as_deref().map(moves_to_string),
+                    "exhausted": report.exhausted,
+                    "nodes": report.nodes,
+                }))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method {method}"))),
+        }
+    }
+
+    fn timeline(&mut self) -> Result<&mut Timeline, RpcError> {
+        self.timeline
+            .as_mut()
+            .ok_or((ENGINE_ERROR, "No level loaded".to_string()))
+    }
+
+    fn state(&mut self) -> Result<Value, RpcError> {
+        let timeline = self.timeline()?;
+        let game = timeline.current();
+        Ok(json!({
+            "moves": moves_to_string(&timeline.moves()[..timeline.cursor()]),
+            "won": game.won(),
+            "legal_moves": moves_to_string(&game.legal_moves()),
+            "text": game.to_text(),
+            "board": render_ascii(game),
+        }))
+    }
+}
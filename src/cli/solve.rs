@@ -0,0 +1,72 @@
+use parabox::engine::{solver::*, *};
+use std::{fs, path::Path, time::Instant};
+
+const USAGE: &str = "usage: parabox solve <level> [--strategy bfs|astar|greedy] [--max-nodes <n>] [--threads <n>] [--write]";
+
+/// Solves a level and prints the solution, optionally writing it to the
+/// `.solution` file next to the level.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut config = SolverConfig::default();
+    let mut write = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strategy" => {
+                config.strategy = match args.next().map(String::as_str) {
+                    Some("bfs") => Strategy::Bfs,
+                    Some("astar") => Strategy::AStar,
+                    Some("greedy") => Strategy::Greedy,
+                    _ => return Err(USAGE.to_string()),
+                }
+            }
+            "--max-nodes" => {
+                config.max_nodes = args.next().and_then(|n| n.parse().ok()).ok_or(USAGE)?
+            }
+            "--threads" => {
+                config.threads = args.next().and_then(|n| n.parse().ok()).ok_or(USAGE)?
+            }
+            "--write" => write = true,
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let game = Game::parse(&text).map_err(|e| format!("{path}: {e}"))?;
+
+    let start = Instant::now();
+    let report = solve(&game, &config);
+    let elapsed = start.elapsed();
+
+    let Some(solution) = report.solution else {
+        let reason = if report.exhausted {
+            "the level is unsolvable"
+        } else {
+            "gave up"
+        };
+        return Err(format!(
+            "No solution found: {reason} after {} nodes in {elapsed:.2?}",
+            report.nodes
+        ));
+    };
+
+    let sequence = format_sequence(&solution);
+    print!("{sequence}");
+    eprintln!(
+        "{} moves, {} nodes in {elapsed:.2?}",
+        solution.len(),
+        report.nodes
+    );
+
+    if write {
+        let solution_path = Path::new(path).with_extension("solution");
+        fs::write(&solution_path, sequence)
+            .map_err(|e| format!("{}: {e}", solution_path.display()))?;
+        eprintln!("Written to {}", solution_path.display());
+    }
+    Ok(())
+}
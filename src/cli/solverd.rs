@@ -0,0 +1,161 @@
+use parabox::engine::{solver::*, *};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+};
+
+const USAGE: &str = "usage: parabox solverd [--port <port>] [--threads <n>] [--max-nodes <n>]";
+
+const DEFAULT_PORT: u16 = 7435;
+
+/// A level kept in memory, together with the solutions found so far.
+struct LevelEntry {
+    text: String,
+    game: Game,
+    // locked separately for each level, so that a long search doesn't block
+    // queries on other levels
+    table: Mutex<SolutionTable>,
+}
+
+struct Daemon {
+    config: SolverConfig,
+    // keyed by the path of the level file
+    levels: Mutex<HashMap<String, Arc<LevelEntry>>>,
+}
+
+/// Serves solver queries on a local TCP port, keeping levels and their
+/// solutions in memory between requests.
+///
+/// The protocol is line-based. Each request is answered with a single line,
+/// either `ok [result]`, `unsolvable`, `unknown` (the search gave up) or
+/// `error <message>`:
+///
+/// ```plain
+/// solve <level> [sequence]     the remaining moves, e.g. `ok RRUL`
+/// hint <level> [sequence]      the next move, e.g. `ok R`
+/// distance <level> [sequence]  the number of remaining moves, e.g. `ok 4`
+/// forget <level>               drops the level from memory
+/// ```
+///
+/// `level` is the path to a level file and `sequence` the moves played from
+/// its initial state.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut port = DEFAULT_PORT;
+    let mut config = SolverConfig::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(USAGE)?;
+        match arg.as_str() {
+            "--port" => port = value.parse().map_err(|_| USAGE)?,
+            "--threads" => config.threads = value.parse().map_err(|_| USAGE)?,
+            "--max-nodes" => config.max_nodes = value.parse().map_err(|_| USAGE)?,
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", port)).map_err(|e| e.to_string())?;
+    println!("Listening on 127.0.0.1:{port}");
+
+    let daemon = Arc::new(Daemon {
+        config,
+        levels: Mutex::new(HashMap::new()),
+    });
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let daemon = daemon.clone();
+        std::thread::spawn(move || daemon.serve(stream));
+    }
+    Ok(())
+}
+
+impl Daemon {
+    fn serve(&self, stream: TcpStream) {
+        let Ok(mut writer) = stream.try_clone() else {
+            return;
+        };
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = self.handle(&line).unwrap_or_else(|e| format!("error {e}"));
+            if writeln!(writer, "{response}").is_err() {
+                break;
+            }
+        }
+    }
+
+    fn handle(&self, request: &str) -> Result<String, String> {
+        let words = request.split_whitespace().collect::<Vec<_>>();
+        let (command, path, sequence) = match words[..] {
+            [command, path] => (command, path, ""),
+            [command, path, sequence] => (command, path, sequence),
+            _ => return Err(format!("Invalid request {request:?}")),
+        };
+        if !["solve", "hint", "distance", "forget"].contains(&command) {
+            return Err(format!("Unknown command {command}"));
+        }
+
+        if command == "forget" {
+            self.levels.lock().unwrap().remove(path);
+            return Ok("ok".to_string());
+        }
+
+        let entry = self.load(path)?;
+        let mut game = entry.game.clone();
+        game.play_sequence(sequence).map_err(|e| e.to_string())?;
+
+        let report = entry.table.lock().unwrap().solve(&game, &self.config);
+        let Some(solution) = report.solution else {
+            return Ok(if report.exhausted {
+                "unsolvable"
+            } else {
+                "unknown"
+            }
+            .to_string());
+        };
+
+        match command {
+            "solve" => Ok(format!(
+                "ok {}",
+                solution.iter().map(|d| d.to_char()).collect::<String>()
+            )),
+            "hint" => match solution.first() {
+                Some(direction) => Ok(format!("ok {}", direction.to_char())),
+                None => Err("Already won".to_string()),
+            },
+            "distance" => Ok(format!("ok {}", solution.len())),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the level at the path, reloading it if the file has changed.
+    fn load(&self, path: &str) -> Result<Arc<LevelEntry>, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+
+        let mut levels = self.levels.lock().unwrap();
+        if let Some(entry) = levels.get(path) {
+            if entry.text == text {
+                return Ok(entry.clone());
+            }
+        }
+
+        let game = Game::parse(&text).map_err(|e| format!("{path}: {e}"))?;
+        let entry = Arc::new(LevelEntry {
+            text,
+            game,
+            table: Mutex::new(SolutionTable::new()),
+        });
+        levels.insert(path.to_string(), entry.clone());
+        Ok(entry)
+    }
+}
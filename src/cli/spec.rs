@@ -0,0 +1,73 @@
+use parabox::engine::{spec::Spec, Game};
+use std::{fs, path::Path};
+
+const USAGE: &str = "usage: parabox spec [dir]";
+
+/// Runs every `<name>.spec` under the directory (default `spec`) against the
+/// level `<name>.txt` next to it, and prints the results.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let dir = match args {
+        [] => "spec",
+        [dir] => dir.as_str(),
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let mut specs = fs::read_dir(dir)
+        .map_err(|e| format!("{dir}: {e}"))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "spec"))
+        .collect::<Vec<_>>();
+    specs.sort();
+
+    let mut failed = 0;
+    for path in &specs {
+        if !run_spec(path) {
+            failed += 1;
+        }
+    }
+
+    println!("{} specs passed, {} failed", specs.len() - failed, failed);
+    if failed > 0 {
+        Err(format!("{failed} specs failed"))
+    } else {
+        Ok(())
+    }
+}
+
+fn run_spec(path: &Path) -> bool {
+    let name = path.file_stem().unwrap().to_string_lossy();
+
+    let result = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| Spec::parse(&text))
+        .and_then(|spec| {
+            let level =
+                fs::read_to_string(path.with_extension("txt")).map_err(|e| e.to_string())?;
+            Ok((spec, Game::parse(&level)?))
+        });
+
+    let (spec, game) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            println!("[{name}] error: {e}\n");
+            return false;
+        }
+    };
+
+    let results = spec.run(game);
+    let passed = results.iter().all(|result| result.failure.is_none());
+
+    println!("[{name}] {}", if passed { "ok" } else { "FAILED" });
+    for line in spec.description.lines() {
+        println!("{}", format!("  {line}").trim_end());
+    }
+    for result in &results {
+        match &result.failure {
+            None => println!("    ok   {}", result.text),
+            Some(actual) => println!("    FAIL {} (line {}: {actual})", result.text, result.line),
+        }
+    }
+    println!();
+
+    passed
+}
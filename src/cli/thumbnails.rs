@@ -0,0 +1,98 @@
+use parabox::engine::{batch::find_levels, *};
+#[cfg(feature = "png")]
+use parabox::render::render_png;
+use parabox::render::{render_ascii, render_svg};
+use std::{fs, path::Path};
+
+const USAGE: &str = "usage: parabox thumbnails <pack> <output> [--format text|svg|png]";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Format {
+    Text,
+    Svg,
+    #[cfg(feature = "png")]
+    Png,
+}
+
+impl Format {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "text" => Ok(Format::Text),
+            "svg" => Ok(Format::Svg),
+            #[cfg(feature = "png")]
+            "png" => Ok(Format::Png),
+            _ => Err(format!("Unknown thumbnail format {name}")),
+        }
+    }
+
+    /// The file name suffix of thumbnails, e.g. `enter.thumb.txt`.
+    fn suffix(self) -> &'static str {
+        match self {
+            Format::Text => "thumb.txt",
+            Format::Svg => "thumb.svg",
+            #[cfg(feature = "png")]
+            Format::Png => "thumb.png",
+        }
+    }
+
+    fn render(self, game: &Game) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Text => Ok(render_ascii(game).into_bytes()),
+            Format::Svg => Ok(render_svg(game).into_bytes()),
+            #[cfg(feature = "png")]
+            Format::Png => render_png(game).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Renders the initial state of every level under `<pack>` into `<output>`,
+/// keeping the directory structure of the pack.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut format = Format::Text;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => format = Format::parse(args.next().ok_or(USAGE)?)?,
+            _ => paths.push(arg),
+        }
+    }
+    let [pack, output] = paths[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let levels = find_levels(Path::new(pack))?;
+
+    let mut failed = 0;
+    for level in &levels {
+        let relative = level.strip_prefix(pack).unwrap();
+        let target = Path::new(output)
+            .join(relative)
+            .with_extension(format.suffix());
+
+        let result = fs::read_to_string(level)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Game::parse(&text))
+            .and_then(|game| {
+                let data = format.render(&game)?;
+                fs::create_dir_all(target.parent().unwrap()).map_err(|e| e.to_string())?;
+                fs::write(&target, data).map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(()) => println!("{}", target.display()),
+            Err(e) => {
+                failed += 1;
+                eprintln!("[{}] {}", level.display(), e.lines().next().unwrap_or(""));
+            }
+        }
+    }
+
+    println!(
+        "{} thumbnails written, {} failed",
+        levels.len() - failed,
+        failed
+    );
+    Ok(())
+}
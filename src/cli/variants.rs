@@ -0,0 +1,83 @@
+use parabox::engine::{generator::*, *};
+use std::{fs, path::Path};
+
+const USAGE: &str = "usage: parabox variants <level> [--count <n>] [--seed <n>] [--mutations <n>] [--min-moves <n>] [--max-nodes <n>] [--dir <dir>] [--force]";
+
+/// Makes solvable variants of a level by small random mutations, and writes
+/// each of them as `<name>_variant_<n>.txt` with its solution next to it,
+/// into the directory of the level or `--dir`. Existing files are only
+/// replaced with `--force`.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut config = VariantConfig::default();
+    let mut dir = None;
+    let mut force = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(USAGE);
+        match arg.as_str() {
+            "--count" => config.count = value()?.parse().map_err(|_| USAGE)?,
+            "--seed" => config.seed = value()?.parse().map_err(|_| USAGE)?,
+            "--mutations" => config.mutations = value()?.parse().map_err(|_| USAGE)?,
+            "--min-moves" => config.min_moves = value()?.parse().map_err(|_| USAGE)?,
+            "--max-nodes" => config.solver.max_nodes = value()?.parse().map_err(|_| USAGE)?,
+            "--dir" => dir = Some(Path::new(value()?)),
+            "--force" => force = true,
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths[..] else {
+        return Err(USAGE.to_string());
+    };
+    let path = Path::new(path);
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let game = Game::parse(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+    let name = path
+        .file_stem()
+        .ok_or(USAGE)?
+        .to_string_lossy()
+        .into_owned();
+    let dir = dir.or(path.parent()).unwrap_or(Path::new("."));
+
+    let files = (1..=config.count)
+        .map(|n| {
+            let level = dir.join(format!("{name}_variant_{n}.txt"));
+            let solution = level.with_extension("solution");
+            (level, solution)
+        })
+        .collect::<Vec<_>>();
+    if !force {
+        if let Some((level, _)) = files
+            .iter()
+            .find(|(level, solution)| level.exists() || solution.exists())
+        {
+            return Err(format!(
+                "{}: The variant or its solution exists already, use --force to replace them",
+                level.display()
+            ));
+        }
+    }
+
+    let variants = variants(&game, &config)?;
+    for ((level, solution_path), variant) in files.iter().zip(&variants) {
+        fs::write(level, variant.game.to_text())
+            .map_err(|e| format!("{}: {e}", level.display()))?;
+        fs::write(solution_path, format_sequence(&variant.solution))
+            .map_err(|e| format!("{}: {e}", solution_path.display()))?;
+        println!(
+            "Generated {}, solved in {} moves",
+            level.display(),
+            variant.solution.len()
+        );
+    }
+    if variants.len() < config.count {
+        println!(
+            "Found only {} of {} variants in {} attempts",
+            variants.len(),
+            config.count,
+            config.attempts
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,26 @@
+//! Access to the system clipboard (`clipboard` feature), for sharing levels
+//! and states as text.
+
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<(), String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "clipboard")]
+pub fn paste() -> Result<String, String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<(), String> {
+    Err("built without the clipboard feature".to_string())
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn paste() -> Result<String, String> {
+    Err("built without the clipboard feature".to_string())
+}
@@ -0,0 +1,98 @@
+use parabox::engine::generator::{daily, Date};
+use parabox::render::Theme;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{play_level, PlayOptions};
+
+const USAGE: &str = "usage: parabox daily [--date <yyyy-mm-dd>] [--dir <dir>]";
+
+/// The file in the daily directory with the fewest moves each daily puzzle
+/// was won in, one `<date> <moves>` line per day.
+const RESULTS_FILE: &str = ".parabox-daily";
+
+/// Generates the daily puzzle of today, or of `--date`, writes it to
+/// `<date>.txt` in the daily directory (`daily` by default), and plays it.
+/// When it is won, the number of moves is recorded, and compared with the
+/// par and with the best result of the day.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut date = None;
+    let mut dir = PathBuf::from("daily");
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(USAGE);
+        match arg.as_str() {
+            "--date" => date = Some(Date::parse(value()?)?),
+            "--dir" => dir = PathBuf::from(value()?),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let date = date.unwrap_or_else(today);
+
+    let generated = daily(date)?;
+    let par = generated.solution.len();
+    fs::create_dir_all(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let path = dir.join(format!("{date}.txt"));
+    fs::write(&path, generated.game.to_text()).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    let options = PlayOptions {
+        record_noops: false,
+        graphics: false,
+        theme: Theme::default(),
+        record_solution: false,
+        overwrite_solution: false,
+        watch: false,
+        custom_levels: false,
+        spectators: None,
+    };
+    let Some(moves) = play_level(&path, None, &options)? else {
+        return Ok(());
+    };
+
+    let results_path = dir.join(RESULTS_FILE);
+    let mut results = load_results(&results_path);
+    let best = results.get(&date.to_string()).copied();
+    println!("Daily puzzle {date}: won in {moves} moves (par {par})");
+    match best {
+        Some(best) if best <= moves => println!("Your best is {best} moves"),
+        Some(best) => println!("A new best, down from {best} moves"),
+        None => (),
+    }
+    if best.is_none_or(|best| moves < best) {
+        results.insert(date.to_string(), moves);
+        save_results(&results_path, &results);
+    }
+    Ok(())
+}
+
+// the puzzle of the day changes at midnight UTC, at the same time for
+// everyone
+fn today() -> Date {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    Date::from_unix_days((seconds / 86_400) as i64)
+}
+
+fn load_results(path: &Path) -> BTreeMap<String, usize> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (date, moves) = line.split_once(' ')?;
+            Some((date.to_string(), moves.parse().ok()?))
+        })
+        .collect()
+}
+
+// results are a convenience, so failing to save them is not an error
+fn save_results(path: &Path, results: &BTreeMap<String, usize>) {
+    let text = results
+        .iter()
+        .map(|(date, moves)| format!("{date} {moves}\n"))
+        .collect::<String>();
+    let _ = fs::write(path, text);
+}
@@ -0,0 +1,710 @@
+use color_space::Hsv;
+use crossterm::style::Stylize;
+use crossterm::{cursor, event, style, terminal, QueueableCommand};
+use parabox::engine::batch::{check_game, BatchConfig, Verdict};
+use parabox::engine::*;
+use parabox::render::{glyph_at, styled, GlyphOptions, Theme};
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::{play_level, MouseCapture, PlayOptions};
+
+const USAGE: &str = "usage: parabox edit <level> [--template <name>] [--size <width>x<height>]";
+
+/// Size of the blocks placed with the block and template tools, and of the
+/// block of a new level.
+const NEW_BLOCK_SIZE: i32 = 7;
+
+/// Number of issues listed below the block, the most severe first.
+const MAX_ISSUES_SHOWN: usize = 5;
+
+/// How often the editor looks for the result of a solvability check while
+/// waiting for keys.
+const SOLVER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const HELP: &str = "Arrows: cursor | Tab/1-8: tool | Space: place | x: remove | m: move | z/y: undo/redo | i: next issue | s/S: check solvable/after every edit | Enter/Backspace: enter/exit block | ,/.: target | [/]/{/}: resize | H/V/R: mirror/rotate | p/o/f/e/F: player/possessable/fliph/exit/filled | c: colors | t: playtest | w: save | q: quit";
+
+/// The objects placed with Space.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Tool {
+    Wall,
+    Block,
+    Reference,
+    Goal,
+    PlayerGoal,
+    Template(&'static str),
+}
+
+impl Tool {
+    const ALL: [Tool; 8] = [
+        Tool::Wall,
+        Tool::Block,
+        Tool::Reference,
+        Tool::Goal,
+        Tool::PlayerGoal,
+        Tool::Template(Template::NAMES[0]),
+        Tool::Template(Template::NAMES[1]),
+        Tool::Template(Template::NAMES[2]),
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Wall => "wall",
+            Tool::Block => "block",
+            Tool::Reference => "reference",
+            Tool::Goal => "goal",
+            Tool::PlayerGoal => "player goal",
+            Tool::Template(name) => name,
+        }
+    }
+}
+
+/// The state of the editor: the level being edited, and the block shown.
+struct Editor {
+    edits: EditHistory,
+    path: PathBuf,
+    // the block shown, and the position of the cursor in it
+    block_id: usize,
+    cursor: Pos,
+    tool: Tool,
+    // the block that new references point to
+    target_no: BlockNo,
+    // the cell picked up with `m`, which the next `m` moves to the cursor
+    marked: Option<usize>,
+    modified: bool,
+    // the issues found by `Game::validate` after the last edit
+    issues: Vec<LevelIssue>,
+    // whether the solver checks the level after every edit
+    check_after_edits: bool,
+    // the result of the last solvability check, or that it is running
+    solver_status: String,
+    // the result of the running check, which is dropped when another one
+    // starts
+    solving: Option<mpsc::Receiver<Verdict>>,
+}
+
+/// Edits a level in the terminal, one block at a time, and saves it in the
+/// level file format. The level is created with a single block if the file
+/// doesn't exist. Every edit goes through the editing methods of `Game`, so
+/// edits that would conflict with the level are refused with a message, and
+/// can be undone. The level is validated again after every edit, and the
+/// cells with issues are highlighted. The solver can check in the background
+/// whether the level can still be won.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut paths = Vec::new();
+    let mut template = None;
+    let mut size = (NEW_BLOCK_SIZE, NEW_BLOCK_SIZE);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(USAGE);
+        match arg.as_str() {
+            "--template" => template = Some(value()?),
+            "--size" => {
+                let (width, height) = value()?.split_once('x').ok_or(USAGE)?;
+                size = (
+                    width.parse().map_err(|_| USAGE)?,
+                    height.parse().map_err(|_| USAGE)?,
+                );
+            }
+            _ => paths.push(arg),
+        }
+    }
+    let [path] = paths[..] else {
+        return Err(USAGE.to_string());
+    };
+    let path = Path::new(path);
+    let game = if path.exists() {
+        if template.is_some() {
+            return Err(format!(
+                "{}: The level exists already, templates only start new levels",
+                path.display()
+            ));
+        }
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Game::parse(&text)?
+    } else if let Some(name) = template {
+        Template::from_name(name, size.0, size.1)?.level()?
+    } else {
+        new_level()?
+    };
+    let Some(block) = game
+        .view_block()
+        .or_else(|| game.cells().iter().filter_map(|cell| cell.block()).next())
+    else {
+        return Err(format!("{}: The level has no blocks", path.display()));
+    };
+
+    let mut editor = Editor {
+        block_id: block.id,
+        cursor: center(block),
+        tool: Tool::Wall,
+        target_no: block.block_no,
+        marked: None,
+        modified: false,
+        path: path.to_path_buf(),
+        issues: game.validate(),
+        check_after_edits: false,
+        solver_status: String::new(),
+        solving: None,
+        edits: EditHistory::new(game),
+    };
+    let mut out = BufWriter::new(std::io::stdout());
+    let mut capture = Some(MouseCapture::enable().map_err(|e| e.to_string())?);
+    let mut message = HELP.to_string();
+    // whether `q` was pressed with unsaved changes
+    let mut quitting = false;
+
+    loop {
+        editor.draw(&mut out, &message).map_err(|e| e.to_string())?;
+        while editor.solving.is_some()
+            && !event::poll(SOLVER_POLL_INTERVAL).map_err(|e| e.to_string())?
+        {
+            if editor.receive_verdict() {
+                editor.draw(&mut out, &message).map_err(|e| e.to_string())?;
+            }
+        }
+        let event = event::read().map_err(|e| e.to_string())?;
+        if let event::Event::Mouse(mouse) = event {
+            // clicking a position moves the cursor there
+            if mouse.kind == event::MouseEventKind::Down(event::MouseButton::Left) {
+                let block = editor.block();
+                let (x, y) = (mouse.column as i32, block.height - mouse.row as i32);
+                if block.in_bounds(Pos(x, y)) {
+                    editor.cursor = Pos(x, y);
+                }
+            }
+            continue;
+        }
+        let event::Event::Key(key) = event else {
+            continue;
+        };
+        if key.kind != event::KeyEventKind::Press {
+            continue;
+        }
+
+        message = String::new();
+        let depth = editor.edits.depth();
+        let result = match key.code {
+            event::KeyCode::Char('q') if editor.modified && !quitting => {
+                quitting = true;
+                message = "Unsaved changes, press q again to quit".to_string();
+                continue;
+            }
+            event::KeyCode::Char('q') => break,
+            event::KeyCode::Char('t') => {
+                // the playtest takes over the terminal until it is quit or won
+                drop(capture.take());
+                let result = editor.playtest();
+                capture = Some(MouseCapture::enable().map_err(|e| e.to_string())?);
+                result
+            }
+            code => editor.handle(code),
+        };
+        quitting = false;
+        match result {
+            Ok(Some(text)) => message = text,
+            Ok(None) => (),
+            Err(e) => message = format!("Can't edit: {e}"),
+        }
+        // every edit, undo and redo changes the depth of the history
+        if editor.edits.depth() != depth {
+            editor.issues = editor.game().validate();
+            if editor.check_after_edits {
+                editor.check_solvable();
+            }
+        }
+    }
+
+    drop(capture);
+    out.queue(terminal::Clear(terminal::ClearType::All))
+        .and_then(|out| out.queue(cursor::MoveTo(0, 0)))
+        .and_then(|out| out.flush())
+        .map_err(|e| e.to_string())
+}
+
+impl Editor {
+    fn game(&self) -> &Game {
+        self.edits.current()
+    }
+
+    fn block(&self) -> &Block {
+        self.game().cells()[self.block_id].block().unwrap()
+    }
+
+    fn cursor_gpos(&self) -> GlobalPos {
+        GlobalPos::new(self.block_id, self.cursor)
+    }
+
+    fn cell_at_cursor(&self) -> Result<usize, String> {
+        self.game()
+            .cell_at(self.cursor_gpos())
+            .map(|cell| cell.id())
+            .ok_or_else(|| "No cell at the cursor".to_string())
+    }
+
+    /// Applies the key, and returns a message to show, if any.
+    fn handle(&mut self, code: event::KeyCode) -> Result<Option<String>, String> {
+        let gpos = self.cursor_gpos();
+        let Pos(x, y) = self.cursor;
+        let (width, height) = (self.block().width, self.block().height);
+        match code {
+            event::KeyCode::Up => self.cursor = Pos(x, (y + 1).min(height - 1)),
+            event::KeyCode::Down => self.cursor = Pos(x, (y - 1).max(0)),
+            event::KeyCode::Left => self.cursor = Pos((x - 1).max(0), y),
+            event::KeyCode::Right => self.cursor = Pos((x + 1).min(width - 1), y),
+            event::KeyCode::Tab | event::KeyCode::BackTab => {
+                let i = Tool::ALL
+                    .iter()
+                    .position(|&tool| tool == self.tool)
+                    .unwrap();
+                let step = if code == event::KeyCode::Tab {
+                    1
+                } else {
+                    Tool::ALL.len() - 1
+                };
+                self.tool = Tool::ALL[(i + step) % Tool::ALL.len()];
+            }
+            event::KeyCode::Char(key @ '1'..='8') => {
+                self.tool = Tool::ALL[key as usize - '1' as usize];
+            }
+            event::KeyCode::Char(' ') => {
+                self.place(gpos)?;
+                self.modified = true;
+            }
+            event::KeyCode::Char('x') | event::KeyCode::Delete => {
+                match self.game().cell_at(gpos).map(|cell| cell.id()) {
+                    Some(id) => {
+                        self.edits.apply(|game| game.remove_cell(id))?;
+                        // the cells after the removed one move down by one id
+                        if self.block_id > id {
+                            self.block_id -= 1;
+                        }
+                        self.marked = None;
+                    }
+                    None => {
+                        self.edits.apply(|game| game.remove_floor(gpos))?;
+                    }
+                }
+                self.modified = true;
+            }
+            event::KeyCode::Char('m') => match self.marked.take() {
+                Some(id) => {
+                    self.edits.apply(|game| game.move_cell(id, gpos))?;
+                    self.modified = true;
+                }
+                None => {
+                    let id = self.cell_at_cursor()?;
+                    self.marked = Some(id);
+                    return Ok(Some(format!(
+                        "Picked up cell {id}, press m again to move it to the cursor"
+                    )));
+                }
+            },
+            event::KeyCode::Enter => {
+                let id = self.cell_at_cursor()?;
+                let target_no = self.game().cells()[id]
+                    .target_no()
+                    .ok_or("Walls can't be entered")?;
+                let block = self
+                    .game()
+                    .block_by_no(target_no)
+                    .ok_or_else(|| format!("Block {target_no} doesn't exist"))?;
+                (self.block_id, self.cursor) = (block.id, center(block));
+            }
+            event::KeyCode::Backspace | event::KeyCode::Esc => {
+                let gpos = self.block().gpos;
+                let outer = gpos
+                    .block_id()
+                    .filter(|&id| !self.game().is_space(id))
+                    .ok_or("This block is not inside another block")?;
+                self.block_id = outer;
+                self.cursor = gpos.pos;
+            }
+            event::KeyCode::Char(key @ (',' | '.')) => {
+                let blocks = self
+                    .game()
+                    .cells()
+                    .iter()
+                    .filter_map(|cell| cell.block())
+                    .filter(|block| !block.space)
+                    .map(|block| block.block_no)
+                    .collect::<Vec<_>>();
+                let i = blocks.iter().position(|&no| no == self.target_no);
+                let next = match (i, key) {
+                    (Some(i), ',') => (i + blocks.len() - 1) % blocks.len(),
+                    (Some(i), _) => (i + 1) % blocks.len(),
+                    (None, _) => 0,
+                };
+                self.target_no = blocks[next];
+            }
+            event::KeyCode::Char(key @ ('[' | ']' | '{' | '}')) => {
+                let (width, height) = match key {
+                    '[' => (width - 1, height),
+                    ']' => (width + 1, height),
+                    '{' => (width, height - 1),
+                    _ => (width, height + 1),
+                };
+                let id = self.block_id;
+                self.edits
+                    .apply(|game| game.resize_block(id, width, height))?;
+                self.cursor = Pos(x.min(width - 1), y.min(height - 1));
+                self.modified = true;
+            }
+            event::KeyCode::Char(key @ ('H' | 'V' | 'R')) => {
+                let transform = match key {
+                    'H' => Transform::MirrorH,
+                    'V' => Transform::MirrorV,
+                    _ => Transform::Rotate(1),
+                };
+                let id = self.block_id;
+                self.edits
+                    .apply(|game| game.transform_block(id, transform))?;
+                let block = self.block();
+                self.cursor = Pos(x.min(block.width - 1), y.min(block.height - 1));
+                self.modified = true;
+            }
+            event::KeyCode::Char('p') => {
+                let id = self.cell_at_cursor()?;
+                let player = !self.game().is_player(id);
+                self.edits.apply(|game| game.set_player(id, player))?;
+                self.modified = true;
+            }
+            event::KeyCode::Char(key @ ('o' | 'f' | 'e' | 'F')) => {
+                let id = self.cell_at_cursor()?;
+                self.edits.apply(|game| {
+                    let mut applies = true;
+                    game.edit_cell(id, |cell| match (key, cell) {
+                        ('o', Cell::Wall(wall)) => wall.possessable = !wall.possessable,
+                        ('o', Cell::Block(block)) => block.possessable = !block.possessable,
+                        ('o', Cell::Reference(reference)) => {
+                            reference.possessable = !reference.possessable
+                        }
+                        ('f', Cell::Wall(wall)) => wall.fliph = !wall.fliph,
+                        ('f', Cell::Block(block)) => block.fliph = !block.fliph,
+                        ('f', Cell::Reference(reference)) => reference.fliph = !reference.fliph,
+                        ('e', Cell::Reference(reference)) => reference.exit = !reference.exit,
+                        ('F', Cell::Block(block)) => block.filled = !block.filled,
+                        _ => applies = false,
+                    })?;
+                    // rolls the cell back, so that there is nothing to undo
+                    if !applies {
+                        return Err("The property doesn't apply to this cell".to_string());
+                    }
+                    Ok(())
+                })?;
+                self.modified = true;
+            }
+            event::KeyCode::Char(key @ ('z' | 'y')) => {
+                let block_no = self.block().block_no;
+                let done = if key == 'z' {
+                    self.edits.undo()
+                } else {
+                    self.edits.redo()
+                };
+                if !done {
+                    return Err(format!(
+                        "Nothing to {}",
+                        if key == 'z' { "undo" } else { "redo" }
+                    ));
+                }
+                self.marked = None;
+                self.modified = true;
+                self.keep_view(block_no);
+            }
+            event::KeyCode::Char('i') => {
+                // the positions of the issues that can be shown, in order
+                let game = self.game();
+                let positions = self
+                    .issues
+                    .iter()
+                    .flat_map(|issue| {
+                        issue
+                            .positions(game)
+                            .into_iter()
+                            .map(move |gpos| (gpos, issue))
+                    })
+                    .filter(|(gpos, _)| {
+                        let block = gpos.block_id().and_then(|id| game.cells()[id].block());
+                        block.is_some_and(|block| !block.space && block.in_bounds(gpos.pos))
+                    })
+                    .collect::<Vec<_>>();
+                let next = positions
+                    .iter()
+                    .position(|&(gpos, _)| gpos == self.cursor_gpos())
+                    .map_or(0, |i| (i + 1) % positions.len());
+                let &(gpos, issue) = positions
+                    .get(next)
+                    .ok_or("No issues at positions in blocks")?;
+                let text = issue.to_string();
+                (self.block_id, self.cursor) = (gpos.block_id().unwrap(), gpos.pos);
+                return Ok(Some(text));
+            }
+            event::KeyCode::Char('c') => {
+                let recolored = self.edits.apply(|game| Ok(game.assign_colors(true)))?;
+                self.modified |= !recolored.is_empty();
+                return Ok(Some(format!("Recolored {} blocks", recolored.len())));
+            }
+            event::KeyCode::Char('s') => self.check_solvable(),
+            event::KeyCode::Char('S') => {
+                self.check_after_edits = !self.check_after_edits;
+                if !self.check_after_edits {
+                    return Ok(Some("Stopped checking after every edit".to_string()));
+                }
+                self.check_solvable();
+                return Ok(Some(
+                    "Checking whether the level is solvable after every edit".to_string(),
+                ));
+            }
+            event::KeyCode::Char('w') => {
+                std::fs::write(&self.path, self.game().to_text())
+                    .map_err(|e| format!("{}: {e}", self.path.display()))?;
+                self.modified = false;
+                return Ok(Some(format!("Saved to {}", self.path.display())));
+            }
+            event::KeyCode::Char('?') => return Ok(Some(HELP.to_string())),
+            _ => (),
+        }
+        Ok(None)
+    }
+
+    // places an object of the current tool at the position
+    fn place(&mut self, gpos: GlobalPos) -> Result<(), String> {
+        match self.tool {
+            Tool::Wall => {
+                self.edits.apply(|game| game.add_wall(Wall::new(gpos)))?;
+            }
+            Tool::Block => {
+                let block = new_block(self.game(), gpos);
+                self.edits.apply(|game| game.add_block(block))?;
+            }
+            Tool::Reference => {
+                // the first reference to a block is its exit
+                let target_no = self.target_no;
+                let exit = self
+                    .game()
+                    .references_to(target_no)
+                    .all(|reference| !reference.exit);
+                self.edits.apply(|game| {
+                    game.add_reference(Reference {
+                        id: 0,
+                        gpos,
+                        target_no,
+                        exit,
+                        inf_exit: None,
+                        possessable: false,
+                        fliph: false,
+                        rotation: 0,
+                        effect: SpecialEffect::None,
+                    })
+                })?;
+            }
+            Tool::Goal => self.edits.apply(|game| game.add_goal(gpos, false))?,
+            Tool::PlayerGoal => self.edits.apply(|game| game.add_goal(gpos, true))?,
+            Tool::Template(name) => {
+                let template = Template::from_name(name, NEW_BLOCK_SIZE, NEW_BLOCK_SIZE)?;
+                self.edits.apply(|game| game.add_template(template, gpos))?;
+            }
+        }
+        Ok(())
+    }
+
+    // after undoing or redoing, which may renumber the cells, keeps showing
+    // the block with the number if it is still there, or else the block the
+    // level starts in, with the cursor inside
+    fn keep_view(&mut self, block_no: BlockNo) {
+        let game = self.edits.current();
+        if let Some(block) = game.block_by_no(block_no).or_else(|| {
+            game.view_block()
+                .or_else(|| game.cells().iter().filter_map(|cell| cell.block()).next())
+        }) {
+            let Pos(x, y) = self.cursor;
+            self.block_id = block.id;
+            self.cursor = Pos(
+                x.clamp(0, (block.width - 1).max(0)),
+                y.clamp(0, (block.height - 1).max(0)),
+            );
+        }
+    }
+
+    /// Starts the solver on the level as edited so far, in the background.
+    /// A check that is still running is left to finish, and its result is
+    /// ignored.
+    fn check_solvable(&mut self) {
+        let game = self.game().clone();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(check_game(&game, &BatchConfig::default()));
+        });
+        self.solving = Some(receiver);
+        self.solver_status = "checking...".to_string();
+    }
+
+    /// Takes the result of the running solvability check, if it is done.
+    /// Returns whether it was.
+    fn receive_verdict(&mut self) -> bool {
+        let Some(receiver) = &self.solving else {
+            return false;
+        };
+        self.solver_status = match receiver.try_recv() {
+            Ok(Verdict::Solved { moves, .. } | Verdict::Trivial { moves }) => {
+                format!("solvable in {moves} moves")
+            }
+            Ok(verdict) => verdict.to_string(),
+            Err(mpsc::TryRecvError::Empty) => return false,
+            Err(mpsc::TryRecvError::Disconnected) => "the solver failed".to_string(),
+        };
+        self.solving = None;
+        true
+    }
+
+    /// Plays the level as edited so far from a file of its own, until the
+    /// player quits or wins, and describes the result.
+    fn playtest(&self) -> Result<Option<String>, String> {
+        let path = std::env::temp_dir().join("parabox-playtest.txt");
+        std::fs::write(&path, self.game().to_text())
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        let options = PlayOptions {
+            record_noops: false,
+            graphics: false,
+            theme: Theme::default(),
+            record_solution: false,
+            overwrite_solution: false,
+            watch: false,
+            custom_levels: false,
+            spectators: None,
+        };
+        let won = play_level(&path, None, &options)?;
+        Ok(Some(
+            if won.is_some() {
+                "Playtest won"
+            } else {
+                "Playtest ended"
+            }
+            .to_string(),
+        ))
+    }
+
+    // draws the block shown with the cursor, the state of the editor, and
+    // the message
+    fn draw(&self, out: &mut impl Write, message: &str) -> std::io::Result<()> {
+        let block = self.block();
+        let label = self.game().meta().block_label(block.block_no);
+        out.queue(terminal::Clear(terminal::ClearType::All))?
+            .queue(cursor::MoveTo(0, 0))?
+            .queue(style::Print(format!(
+                "{}{} | block {label} ({}x{})",
+                self.path.display(),
+                if self.modified { " *" } else { "" },
+                block.width,
+                block.height
+            )))?;
+
+        // the most severe issue at each position
+        let mut flagged = HashMap::new();
+        for issue in &self.issues {
+            for gpos in issue.positions(self.game()) {
+                let severity = flagged.entry(gpos).or_insert(issue.severity());
+                *severity = (*severity).max(issue.severity());
+            }
+        }
+
+        let options = GlyphOptions::default();
+        for y in (0..block.height).rev() {
+            out.queue(cursor::MoveTo(0, (block.height - y) as u16))?;
+            for x in 0..block.width {
+                let gpos = GlobalPos::new(block.id, Pos(x, y));
+                let mut glyph = glyph_at(self.game(), gpos, options);
+                if Pos(x, y) == self.cursor {
+                    glyph.inverted = !glyph.inverted;
+                }
+                let content = styled(glyph, &options.theme);
+                out.queue(style::PrintStyledContent(match flagged.get(&gpos) {
+                    Some(Severity::Error) => content.on_dark_red(),
+                    Some(Severity::Warning) => content.on_dark_yellow(),
+                    None => content,
+                }))?;
+            }
+        }
+
+        let Pos(x, y) = self.cursor;
+        let mut status = format!(
+            "Tool: {} | Target: {} | Cursor: ({x}, {y})",
+            self.tool.name(),
+            self.game().meta().block_label(self.target_no)
+        );
+        if let Some(id) = self.marked {
+            status += &format!(" | Moving cell {id}");
+        }
+        if !self.solver_status.is_empty() {
+            status += &format!(" | Solver: {}", self.solver_status);
+        }
+        out.queue(cursor::MoveTo(0, block.height as u16 + 1))?
+            .queue(style::Print(status))?
+            .queue(cursor::MoveToNextLine(1))?
+            .queue(style::Print(crate::inspect(
+                self.game(),
+                self.cursor_gpos(),
+            )))?
+            .queue(cursor::MoveToNextLine(1))?
+            .queue(style::Print(message))?;
+
+        for issue in self.issues.iter().take(MAX_ISSUES_SHOWN) {
+            let severity = match issue.severity() {
+                Severity::Error => "error".red(),
+                Severity::Warning => "warning".yellow(),
+            };
+            out.queue(cursor::MoveToNextLine(1))?
+                .queue(style::PrintStyledContent(severity))?
+                .queue(style::Print(format!(": {issue}")))?;
+        }
+        if self.issues.len() > MAX_ISSUES_SHOWN {
+            out.queue(cursor::MoveToNextLine(1))?
+                .queue(style::Print(format!(
+                    "... and {} more",
+                    self.issues.len() - MAX_ISSUES_SHOWN
+                )))?;
+        }
+        out.flush()
+    }
+}
+
+/// A level with a single empty block.
+fn new_level() -> Result<Game, String> {
+    let mut game = Game::parse("version 4\n#\n")?;
+    let gpos = GlobalPos {
+        container: Container::Space,
+        pos: Pos(-1, -1),
+    };
+    game.add_block(new_block(&game, gpos))?;
+    Ok(game)
+}
+
+// an empty block with the next block number, in a color that stands out
+// from the other blocks
+fn new_block(game: &Game, gpos: GlobalPos) -> Block {
+    Block::new(
+        gpos,
+        next_block_no(game),
+        NEW_BLOCK_SIZE,
+        NEW_BLOCK_SIZE,
+        Hsv::new(game.distinct_hue(), 0.6, 0.8),
+    )
+}
+
+// the number after the largest block number
+fn next_block_no(game: &Game) -> BlockNo {
+    let max = game
+        .cells()
+        .iter()
+        .filter_map(|cell| cell.block())
+        .map(|block| block.block_no.0)
+        .max();
+    BlockNo(max.map_or(0, |max| max + 1))
+}
+
+fn center(block: &Block) -> Pos {
+    Pos(block.width / 2, block.height / 2)
+}
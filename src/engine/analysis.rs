@@ -0,0 +1,185 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::game::*;
+use super::simulation::SimulatorBuffers;
+use super::utility::*;
+
+#[derive(Clone, Debug)]
+pub struct AnalysisConfig {
+    /// Maximum number of states to expand while exploring the state space.
+    pub max_states: usize,
+}
+
+/// Size and complexity metrics of a level, for tuning the difficulty of
+/// levels and curating packs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelAnalysis {
+    /// Number of blocks, not counting the ones synthesized for infinite
+    /// enters.
+    pub blocks: usize,
+    /// Number of references, including infinite exits.
+    pub references: usize,
+    /// Length of the longest chain of blocks inside each other, where the
+    /// top-level blocks count as 1.
+    pub max_depth: usize,
+    /// Number of distinct states reached from the initial state. Unless the
+    /// exploration was exhausted, this is a lower bound.
+    pub states: usize,
+    /// Whether every reachable state was explored.
+    pub exhausted: bool,
+    /// Average number of moves that change the state, over the states
+    /// expanded.
+    pub branching_factor: f64,
+    /// Length of a shortest solution, if one was found.
+    pub solution_moves: Option<usize>,
+    pub reachability: Reachability,
+}
+
+/// Where the players can go in the states reached. Unless the exploration
+/// was exhausted, the goals and cells reported as unreachable may still be
+/// reached in states beyond the budget.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Reachability {
+    /// Every position occupied by a player.
+    pub player_positions: HashSet<GlobalPos>,
+    /// Indices in `Game::goals` of the goals that are never satisfied.
+    pub unreachable_goals: Vec<usize>,
+    /// Ids of the possessable cells that never become a player.
+    pub unreachable_possessables: Vec<usize>,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        Self {
+            max_states: 100_000,
+        }
+    }
+}
+
+impl std::fmt::Display for LevelAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} blocks, {} references, depth {}, ",
+            self.blocks, self.references, self.max_depth
+        )?;
+        if !self.exhausted {
+            write!(f, "at least ")?;
+        }
+        write!(
+            f,
+            "{} states, branching factor {:.2}, ",
+            self.states, self.branching_factor
+        )?;
+        match self.solution_moves {
+            Some(moves) => write!(f, "solved in {moves} moves"),
+            None if self.exhausted => write!(f, "unsolvable"),
+            None => write!(f, "no solution found"),
+        }
+    }
+}
+
+/// Computes the metrics of a level from its state, exploring the states
+/// reachable from it breadth-first within the budget of the configuration,
+/// and finds where the players can go in those states.
+pub fn analyze(game: &Game, config: &AnalysisConfig) -> LevelAnalysis {
+    let cells = game.cells();
+    let blocks = cells
+        .iter()
+        .filter(|cell| cell.block().is_some_and(|block| block.inf_enter.is_none()))
+        .count();
+    let references = cells
+        .iter()
+        .filter(|cell| matches!(cell, Cell::Reference(_)))
+        .count();
+
+    // blocks inside themselves never reach a top-level block, and are not
+    // counted
+    let depth_of = |block: &Cell| {
+        let mut depth = 1;
+        let mut container = block.gpos().container;
+        while let Some(block_id) = container.block_id() {
+            if depth > cells.len() {
+                return None;
+            }
+            depth += 1;
+            container = cells[block_id].gpos().container;
+        }
+        Some(depth)
+    };
+    let max_depth = cells
+        .iter()
+        .filter(|cell| cell.block().is_some())
+        .filter_map(depth_of)
+        .max()
+        .unwrap_or(0);
+
+    // whether each goal was satisfied, and each cell was a player, in any
+    // state reached
+    let mut satisfied = vec![false; game.goals().len()];
+    let mut possessed = vec![false; cells.len()];
+    let mut player_positions = HashSet::new();
+    let mut reach = |state: &Game| {
+        for &id in state.player_ids() {
+            player_positions.insert(state.cells()[id].gpos());
+            if let Some(possessed) = possessed.get_mut(id) {
+                *possessed = true;
+            }
+        }
+        for (goal, satisfied) in state.goals().iter().zip(&mut satisfied) {
+            *satisfied |= state.goal_satisfied(goal);
+        }
+    };
+
+    let mut visited = HashSet::new();
+    visited.insert(game.state_key());
+    reach(game);
+    let mut queue = VecDeque::from([(game.clone(), 0)]);
+    let mut buffers = SimulatorBuffers::default();
+    let mut expanded = 0;
+    let mut moves = 0;
+    let mut solution_moves = None;
+
+    while expanded < config.max_states {
+        let Some((state, depth)) = queue.pop_front() else {
+            break;
+        };
+        expanded += 1;
+        if state.won() && solution_moves.is_none() {
+            solution_moves = Some(depth);
+        }
+
+        for direction in Direction::ALL {
+            let mut next = state.clone();
+            if !next.play_with(direction, &mut buffers).changed() {
+                continue;
+            }
+            moves += 1;
+            if visited.insert(next.state_key()) {
+                reach(&next);
+                queue.push_back((next, depth + 1));
+            }
+        }
+    }
+
+    LevelAnalysis {
+        blocks,
+        references,
+        max_depth,
+        states: visited.len(),
+        exhausted: queue.is_empty(),
+        branching_factor: if expanded > 0 {
+            moves as f64 / expanded as f64
+        } else {
+            0.0
+        },
+        solution_moves,
+        reachability: Reachability {
+            player_positions,
+            unreachable_goals: (0..satisfied.len()).filter(|&i| !satisfied[i]).collect(),
+            unreachable_possessables: (0..cells.len())
+                .filter(|&id| cells[id].possessable() && !possessed[id])
+                .collect(),
+        },
+    }
+}
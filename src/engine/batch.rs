@@ -0,0 +1,151 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::game::*;
+use super::solver::*;
+
+/// The result of checking whether a level can be solved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Solved {
+        moves: usize,
+        nodes: usize,
+    },
+    /// Solved in suspiciously few moves, e.g. because a mechanic used by the
+    /// intended solution was broken.
+    Trivial {
+        moves: usize,
+    },
+    /// Every reachable state was explored without winning.
+    Unsolvable {
+        nodes: usize,
+    },
+    /// The search ran out of budget.
+    GaveUp {
+        nodes: usize,
+    },
+    /// The level file couldn't be read or parsed.
+    Invalid(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct LevelCheck {
+    pub path: PathBuf,
+    pub verdict: Verdict,
+}
+
+#[derive(Clone, Debug)]
+pub struct BatchConfig {
+    /// The search, which is breadth-first by default so that the number of
+    /// moves of a solution, and the `Trivial` verdict, are exact. Other
+    /// strategies, or more than one thread, make them an upper bound.
+    pub solver: SolverConfig,
+    /// Levels solved in at most this many moves are reported as trivial.
+    pub trivial_moves: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            solver: SolverConfig {
+                max_nodes: 100_000,
+                ..Default::default()
+            },
+            trivial_moves: 3,
+        }
+    }
+}
+
+impl Verdict {
+    /// Whether the level is definitely broken.
+    pub fn is_problem(&self) -> bool {
+        matches!(self, Verdict::Unsolvable { .. } | Verdict::Invalid(_))
+    }
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Solved { moves, nodes } => {
+                write!(f, "solved in {moves} moves ({nodes} nodes)")
+            }
+            Verdict::Trivial { moves } => write!(f, "trivially solved in {moves} moves"),
+            Verdict::Unsolvable { nodes } => {
+                write!(f, "unsolvable ({nodes} nodes explored)")
+            }
+            Verdict::GaveUp { nodes } => write!(f, "no solution within {nodes} nodes"),
+            Verdict::Invalid(e) => write!(f, "invalid: {e}"),
+        }
+    }
+}
+
+/// Returns the paths of all level files (`*.txt`) under the directory,
+/// recursively, in sorted order.
+pub fn find_levels(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    fn collect(dir: &Path, levels: &mut Vec<PathBuf>) -> Result<(), String> {
+        let entries = fs::read_dir(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.is_dir() {
+                collect(&path, levels)?;
+            } else if path.extension().is_some_and(|ext| ext == "txt") {
+                levels.push(path);
+            }
+        }
+        Ok(())
+    }
+
+    let mut levels = Vec::new();
+    collect(dir, &mut levels)?;
+    levels.sort();
+    Ok(levels)
+}
+
+/// Tries to solve the level within the budget of the configuration.
+pub fn check_level(text: &str, config: &BatchConfig) -> Verdict {
+    match Game::parse(text) {
+        Ok(game) => check_game(&game, config),
+        Err(e) => Verdict::Invalid(e.lines().next().unwrap_or("").to_string()),
+    }
+}
+
+/// Tries to solve a level that is already loaded, e.g. after editing it,
+/// within the budget of the configuration.
+pub fn check_game(game: &Game, config: &BatchConfig) -> Verdict {
+    let report = solve(game, &config.solver);
+    match report.solution {
+        Some(solution) if solution.len() <= config.trivial_moves => Verdict::Trivial {
+            moves: solution.len(),
+        },
+        Some(solution) => Verdict::Solved {
+            moves: solution.len(),
+            nodes: report.nodes,
+        },
+        None if report.exhausted => Verdict::Unsolvable {
+            nodes: report.nodes,
+        },
+        None => Verdict::GaveUp {
+            nodes: report.nodes,
+        },
+    }
+}
+
+/// Checks every level under the directory. `progress` is called after each
+/// level, e.g. for printing results as they come in.
+pub fn check_levels(
+    dir: &Path,
+    config: &BatchConfig,
+    mut progress: impl FnMut(&LevelCheck),
+) -> Result<Vec<LevelCheck>, String> {
+    let mut checks = Vec::new();
+    for path in find_levels(dir)? {
+        let verdict = match fs::read_to_string(&path) {
+            Ok(text) => check_level(&text, config),
+            Err(e) => Verdict::Invalid(e.to_string()),
+        };
+        let check = LevelCheck { path, verdict };
+        progress(&check);
+        checks.push(check);
+    }
+    Ok(checks)
+}
@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+
+use super::game::*;
+use super::transform::Transform;
+use super::utility::*;
+
+/// A form of a level that is the same for levels that only differ by being
+/// mirrored or rotated, by the colors of their blocks, or by the numbers of
+/// their blocks, for finding duplicates among generated levels and in level
+/// packs. Titles, authors and block names are left out as well.
+///
+/// It lists a line for each cell and floor, with the position and the number
+/// of the block it is in, and the rules of the level, in sorted order.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CanonicalLevel(Vec<String>);
+
+/// Two levels of a list that are the same, or nearly the same, up to
+/// symmetry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Duplicate {
+    /// Indices of the levels, the first one before the second one.
+    pub first: usize,
+    pub second: usize,
+    /// Number of cells and floors that differ between the levels, see
+    /// `CanonicalLevel::distance`.
+    pub distance: usize,
+}
+
+// the mirrored and rotated orientations of a level, by the transforms that
+// lead to them from the level as it is
+const ORIENTATIONS: [&[Transform]; 8] = [
+    &[],
+    &[Transform::Rotate(1)],
+    &[Transform::Rotate(2)],
+    &[Transform::Rotate(3)],
+    &[Transform::MirrorH],
+    &[Transform::MirrorH, Transform::Rotate(1)],
+    &[Transform::MirrorH, Transform::Rotate(2)],
+    &[Transform::MirrorH, Transform::Rotate(3)],
+];
+
+impl CanonicalLevel {
+    /// Returns the number of lines that are only in one of the forms, e.g. 2
+    /// for a wall moved elsewhere in the same block: the line of the wall
+    /// before and after. Both forms should be of levels in the same
+    /// orientation, which `find_duplicates` takes care of.
+    pub fn distance(&self, other: &CanonicalLevel) -> usize {
+        let (mut i, mut j) = (0, 0);
+        let mut distance = 0;
+        while i < self.0.len() && j < other.0.len() {
+            match self.0[i].cmp(&other.0[j]) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    i += 1;
+                    j += 1;
+                    continue;
+                }
+            }
+            distance += 1;
+        }
+        distance + (self.0.len() - i) + (other.0.len() - j)
+    }
+}
+
+impl Game {
+    /// Returns the canonical form of the level: the smallest of the forms of
+    /// its mirrored and rotated orientations, with the blocks numbered in
+    /// the order they are found going through the level by position.
+    /// Orientations that the level can't be transformed to are left out,
+    /// see `Game::transform_level`.
+    pub fn canonical_form(&self) -> CanonicalLevel {
+        self.oriented_forms().into_iter().min().unwrap()
+    }
+
+    // the forms of the level in the orientations it can be transformed to,
+    // including the level as it is
+    fn oriented_forms(&self) -> Vec<CanonicalLevel> {
+        ORIENTATIONS
+            .iter()
+            .filter_map(|transforms| {
+                let mut game = self.clone();
+                for &transform in *transforms {
+                    game.transform_level(transform).ok()?;
+                }
+                Some(game.form())
+            })
+            .collect()
+    }
+
+    // the form of the level in its current orientation
+    fn form(&self) -> CanonicalLevel {
+        let cells = self.cells();
+        let numbers = self.canonical_numbers();
+        let number = |block_no: BlockNo| numbers[&block_no];
+        let player = |id: usize| self.player_ids().iter().position(|&player| player == id);
+        let at = |gpos: GlobalPos| {
+            let container = match gpos.block_id().and_then(|id| cells[id].block()) {
+                Some(block) if !block.space => number(block.block_no).to_string(),
+                _ => "space".to_string(),
+            };
+            format!("{container} {} {}", gpos.pos.0, gpos.pos.1)
+        };
+
+        let mut lines = vec![format!("{:?}", self.config())];
+        for cell in cells {
+            let line = match cell {
+                Cell::Wall(wall) => format!(
+                    "wall possessable={} fliph={}",
+                    wall.possessable, wall.fliph
+                ),
+                Cell::Block(block) if block.space => continue,
+                Cell::Block(block) => format!(
+                    "block {} {}x{} filled={} possessable={} fliph={} inf_enter={:?} effect={:?} locked={}",
+                    number(block.block_no),
+                    block.width,
+                    block.height,
+                    block.filled,
+                    block.possessable,
+                    block.fliph,
+                    block.inf_enter.map(|(block_no, degree)| (number(block_no), degree)),
+                    block.effect,
+                    block.locked
+                ),
+                Cell::Reference(reference) => format!(
+                    "ref {} exit={} inf_exit={:?} possessable={} fliph={} rotation={} effect={:?}",
+                    number(reference.target_no),
+                    reference.exit,
+                    reference.inf_exit,
+                    reference.possessable,
+                    reference.fliph,
+                    reference.rotation,
+                    reference.effect
+                ),
+            };
+            lines.push(format!(
+                "{} {line} player={:?}",
+                at(cell.gpos()),
+                player(cell.id())
+            ));
+        }
+        for floor in self.floors() {
+            lines.push(format!("{} floor {}", at(floor.gpos), floor.kind));
+        }
+        lines.sort_unstable();
+        CanonicalLevel(lines)
+    }
+
+    // numbers the blocks in the order they are found going breadth-first
+    // through the cells in space and then inside each block found, by
+    // position, where a reference finds the block it refers to
+    fn canonical_numbers(&self) -> HashMap<BlockNo, usize> {
+        let cells = self.cells();
+        let mut contents = HashMap::<Option<usize>, Vec<usize>>::new();
+        for cell in cells {
+            contents
+                .entry(cell.gpos().block_id())
+                .or_default()
+                .push(cell.id());
+        }
+        for ids in contents.values_mut() {
+            ids.sort_by_key(|&id| {
+                let gpos = cells[id].gpos();
+                (gpos.pos.0, gpos.pos.1)
+            });
+        }
+
+        let mut numbers = HashMap::new();
+        let mut queue = VecDeque::from([None]);
+        while let Some(container) = queue.pop_front() {
+            for &id in contents.get(&container).into_iter().flatten() {
+                let block_no = match &cells[id] {
+                    Cell::Wall(_) => continue,
+                    Cell::Block(block) => block.block_no,
+                    Cell::Reference(reference) => reference.target_no,
+                };
+                if numbers.contains_key(&block_no) {
+                    continue;
+                }
+                numbers.insert(block_no, numbers.len());
+                if let Some(block) = self.block_by_no(block_no) {
+                    queue.push_back(Some(block.id));
+                }
+            }
+        }
+        // blocks only inside themselves are never found, and keep their
+        // order
+        for cell in cells {
+            let block_no = match cell {
+                Cell::Wall(_) => continue,
+                Cell::Block(block) => block.block_no,
+                Cell::Reference(reference) => reference.target_no,
+            };
+            let next = numbers.len();
+            numbers.entry(block_no).or_insert(next);
+        }
+        numbers
+    }
+}
+
+/// Finds the pairs of levels that differ in at most `max_distance` lines of
+/// their forms, in the orientation of the second level closest to the
+/// first. A distance of 0 means that the levels are the same up to
+/// mirroring, rotation, colors and block numbers, and a small distance that
+/// one is a near copy of the other, e.g. with a wall moved.
+pub fn find_duplicates(levels: &[Game], max_distance: usize) -> Vec<Duplicate> {
+    let forms = levels
+        .iter()
+        .map(|game| game.oriented_forms())
+        .collect::<Vec<_>>();
+    let canonical = forms
+        .iter()
+        .map(|forms| forms.iter().min().unwrap())
+        .collect::<Vec<_>>();
+
+    let mut duplicates = Vec::new();
+    for (first, canonical) in canonical.iter().enumerate() {
+        for (second, forms) in forms.iter().enumerate().skip(first + 1) {
+            let distance = forms
+                .iter()
+                .map(|form| canonical.distance(form))
+                .min()
+                .unwrap();
+            if distance <= max_distance {
+                duplicates.push(Duplicate {
+                    first,
+                    second,
+                    distance,
+                });
+            }
+        }
+    }
+    duplicates
+}
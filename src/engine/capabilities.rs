@@ -0,0 +1,96 @@
+/// What this build of the crate supports, for tools that need to adapt to
+/// different versions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Capabilities {
+    /// Version of the crate.
+    pub version: &'static str,
+    /// Supported versions of the level file format.
+    pub format_versions: Vec<u32>,
+    /// Header items beyond `version` that change or extend the rules, or
+    /// otherwise affect how a level is read.
+    pub rule_extensions: Vec<&'static str>,
+    /// Cargo features compiled in.
+    pub features: Vec<&'static str>,
+}
+
+/// Returns the capabilities of this build of the crate.
+pub fn capabilities() -> Capabilities {
+    let mut features = Vec::new();
+    if cfg!(feature = "text-ui") {
+        features.push("text-ui");
+    }
+    if cfg!(feature = "serde") {
+        features.push("serde");
+    }
+    if cfg!(feature = "json") {
+        features.push("json");
+    }
+    if cfg!(feature = "png") {
+        features.push("png");
+    }
+    if cfg!(feature = "cdylib") {
+        features.push("cdylib");
+    }
+    if cfg!(feature = "pyo3") {
+        features.push("pyo3");
+    }
+    if cfg!(feature = "wasm") {
+        features.push("wasm");
+    }
+    if cfg!(feature = "clipboard") {
+        features.push("clipboard");
+    }
+    if cfg!(feature = "check-invariants") {
+        features.push("check-invariants");
+    }
+
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION"),
+        format_versions: vec![4, 5],
+        rule_extensions: vec![
+            "attempt_order",
+            "shed",
+            "inner_push",
+            "rotation",
+            "gravity",
+            "pull",
+            "space_size",
+            "shared_space",
+            "locked",
+            "title",
+            "author",
+            "par_moves",
+            "tags",
+            "property",
+        ],
+        features,
+    }
+}
+
+impl Capabilities {
+    /// Formats the capabilities as a JSON object.
+    pub fn to_json(&self) -> String {
+        // all strings are plain identifiers, so they need no escaping
+        let strings = |items: &[&str]| {
+            let items = items
+                .iter()
+                .map(|item| format!("\"{item}\""))
+                .collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        };
+        let versions = self
+            .format_versions
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\"version\": \"{}\", \"format_versions\": [{}], \"rule_extensions\": {}, \"features\": {}}}",
+            self.version,
+            versions.join(", "),
+            strings(&self.rule_extensions),
+            strings(&self.features),
+        )
+    }
+}
@@ -0,0 +1,107 @@
+use color_space::Hsv;
+
+use super::game::*;
+
+/// Hues of colored blocks closer than this, in degrees, are hard to tell
+/// apart, and are recolored by `Game::assign_colors` if asked to.
+pub const MIN_HUE_DISTANCE: f64 = 30.0;
+
+/// The hue of the first block colored in a level without colors, the blue of
+/// most vanilla levels.
+const FIRST_HUE: f64 = 216.0;
+
+/// The saturation given to blocks without a color.
+const SATURATION: f64 = 0.8;
+
+/// Returns the distance between two hues in degrees around the color wheel,
+/// at most 180.
+pub fn hue_distance(a: f64, b: f64) -> f64 {
+    let distance = (a - b).rem_euclid(360.0);
+    distance.min(360.0 - distance)
+}
+
+/// Returns the hue furthest from all the given hues: the middle of the
+/// largest gap between them around the color wheel.
+pub fn distinct_hue(hues: &[f64]) -> f64 {
+    let mut hues = hues
+        .iter()
+        .map(|hue| hue.rem_euclid(360.0))
+        .collect::<Vec<_>>();
+    hues.sort_by(f64::total_cmp);
+    let Some(&last) = hues.last() else {
+        return FIRST_HUE;
+    };
+
+    // the gap after each hue, up to the next one
+    let mut start = last;
+    let mut gap = hues[0] + 360.0 - last;
+    for pair in hues.windows(2) {
+        if pair[1] - pair[0] > gap {
+            (start, gap) = (pair[0], pair[1] - pair[0]);
+        }
+    }
+    (start + gap / 2.0).rem_euclid(360.0)
+}
+
+impl Game {
+    /// Returns a hue far from the colors of the blocks in the level, for a
+    /// new block.
+    pub fn distinct_hue(&self) -> f64 {
+        let hues = self
+            .cells()
+            .iter()
+            .filter_map(|cell| cell.block())
+            .filter(|block| is_colored(block))
+            .map(|block| block.hsv.h)
+            .collect::<Vec<_>>();
+        distinct_hue(&hues)
+    }
+
+    /// Colors the blocks without a color, which are gray (with a saturation
+    /// of zero), with hues far from the other blocks and from each other, so
+    /// that rendered levels are legible. With `recolor_clashing`, blocks
+    /// whose hue is closer than `MIN_HUE_DISTANCE` to a block before them
+    /// get a new hue too, keeping their saturation and value.
+    ///
+    /// Returns the ids of the blocks given a new color, in the order of the
+    /// cells.
+    pub fn assign_colors(&mut self, recolor_clashing: bool) -> Vec<usize> {
+        let mut hues = Vec::new();
+        let mut recolored = Vec::new();
+        for block in self.cells().iter().filter_map(|cell| cell.block()) {
+            if block.space {
+                continue;
+            }
+            let clashing = recolor_clashing
+                && hues
+                    .iter()
+                    .any(|&hue| hue_distance(hue, block.hsv.h) < MIN_HUE_DISTANCE);
+            if !is_colored(block) || clashing {
+                recolored.push(block.id);
+            } else {
+                hues.push(block.hsv.h);
+            }
+        }
+
+        if recolored.is_empty() {
+            return recolored;
+        }
+        let cells = self.cells_mut();
+        for &id in &recolored {
+            let hue = distinct_hue(&hues);
+            let block = cells[id].block_mut().unwrap();
+            let saturation = if block.hsv.s == 0.0 {
+                SATURATION
+            } else {
+                block.hsv.s
+            };
+            block.hsv = Hsv::new(hue, saturation, block.hsv.v);
+            hues.push(hue);
+        }
+        recolored
+    }
+}
+
+fn is_colored(block: &Block) -> bool {
+    !block.space && block.hsv.s > 0.0
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::game::*;
+use super::utility::*;
+
+/// Where the cells and blocks of an embedded level ended up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Embedding {
+    /// Id of the top-level block of the embedded level, now at the position
+    /// it was embedded at.
+    pub root_id: usize,
+    /// New id of each cell of the embedded level, by its old id.
+    pub cell_ids: Vec<usize>,
+    /// New number of each block of the embedded level, by its old number.
+    pub block_nos: HashMap<BlockNo, BlockNo>,
+}
+
+impl Game {
+    /// Embeds another level into this one, e.g. to stitch puzzles into a hub
+    /// world. The first top-level block of the other level is put at the
+    /// position, which may also be in space to merge the levels side by
+    /// side. Its other top-level blocks and the spaces of its floating cells
+    /// stay in space.
+    ///
+    /// The blocks of the other level get numbers after the ones of this
+    /// level, its players come after the players of this level in the
+    /// player order, and its floors and goals are added. Block names are
+    /// kept unless this level already uses them. Both levels must play by
+    /// the same rules.
+    pub fn embed_level(&mut self, other: &Game, gpos: GlobalPos) -> Result<Embedding, String> {
+        let (config, other_config) = (self.config(), other.config());
+        if config.attempt_order != other_config.attempt_order
+            || config.shed != other_config.shed
+            || config.inner_push != other_config.inner_push
+            || config.rotation != other_config.rotation
+            || config.gravity != other_config.gravity
+            || config.pull != other_config.pull
+        {
+            return Err("The levels play by different rules".to_string());
+        }
+        let root = other
+            .cells()
+            .iter()
+            .filter_map(|cell| cell.block())
+            .find(|block| !block.space && block.gpos.container == Container::Space)
+            .ok_or("The level has no top-level block")?;
+        self.check_pos(gpos)?;
+
+        // the blocks keep their order, after the blocks of this level
+        let block_numbers = |game: &Game| {
+            game.cells()
+                .iter()
+                .filter_map(|cell| cell.block())
+                .map(|block| block.block_no.0)
+                .collect::<Vec<_>>()
+        };
+        let next = block_numbers(self).into_iter().max().map_or(0, |no| no + 1);
+        let first = block_numbers(other).into_iter().min().unwrap_or(0);
+        let block_no = |BlockNo(no): BlockNo| BlockNo(no - first + next);
+
+        let offset = self.cells.len();
+        let cell_ids = (offset..offset + other.cells().len()).collect::<Vec<_>>();
+        let moved = |other_gpos: GlobalPos| match other_gpos.container {
+            Container::Block(id) => GlobalPos::new(cell_ids[id.index()], other_gpos.pos),
+            Container::Space => other_gpos,
+        };
+
+        let mut cells = self.cells.as_ref().clone();
+        let mut block_nos = HashMap::new();
+        for cell in other.cells() {
+            let mut cell = cell.clone();
+            match &mut cell {
+                Cell::Wall(wall) => {
+                    wall.id = cell_ids[wall.id];
+                    wall.gpos = moved(wall.gpos);
+                }
+                Cell::Block(block) => {
+                    block_nos.insert(block.block_no, block_no(block.block_no));
+                    block.gpos = if block.id == root.id {
+                        gpos
+                    } else {
+                        moved(block.gpos)
+                    };
+                    block.id = cell_ids[block.id];
+                    block.block_no = block_no(block.block_no);
+                    if let Some((target_no, _)) = &mut block.inf_enter {
+                        *target_no = block_no(*target_no);
+                    }
+                }
+                Cell::Reference(reference) => {
+                    reference.id = cell_ids[reference.id];
+                    reference.gpos = moved(reference.gpos);
+                    reference.target_no = block_no(reference.target_no);
+                }
+            }
+            cells.push(cell);
+        }
+
+        let level = Arc::make_mut(&mut self.level);
+        for goal in other.goals() {
+            level.goals.push(Goal {
+                gpos: moved(goal.gpos),
+                player: goal.player,
+            });
+        }
+        for floor in other.floors() {
+            level.floors.push(Floor {
+                gpos: moved(floor.gpos),
+                kind: floor.kind.clone(),
+            });
+        }
+        // the cells of the other level are part of this level, unless this
+        // one has synthesized cells already
+        if level.initial_cells == offset {
+            level.initial_cells += other.cells().len();
+        }
+        for (no, name) in &other.meta().block_names {
+            if !level.meta.block_names.values().any(|used| used == name) {
+                level.meta.block_names.insert(block_no(*no), name.clone());
+            }
+        }
+
+        let block_map = Arc::make_mut(&mut self.block_map);
+        for (no, id) in other.block_map.iter() {
+            block_map.insert(block_no(*no), cell_ids[*id]);
+        }
+        self.player_ids
+            .extend(other.player_ids().iter().map(|&id| cell_ids[id]));
+        self.set_cells(cells);
+
+        Ok(Embedding {
+            root_id: cell_ids[root.id],
+            cell_ids,
+            block_nos,
+        })
+    }
+}
@@ -0,0 +1,195 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::batch::find_levels;
+use super::game::*;
+use super::validation::Severity;
+
+/// The environment variable which overrides where custom levels are looked
+/// for.
+pub const CUSTOM_LEVELS_VAR: &str = "PARABOX_CUSTOM_LEVELS";
+
+// the Steam app id of the game, under which Proton keeps its prefix
+const STEAM_APP_ID: &str = "1260520";
+
+// where the game keeps its data on Windows, relative to the user profile
+const WINDOWS_DATA: &str = "AppData/LocalLow/Patrick Traynor/Patrick's Parabox";
+
+/// Returns the directories where an installed copy of Patrick's Parabox may
+/// keep its custom levels on this system, most likely first. The directories
+/// don't necessarily exist.
+///
+/// `PARABOX_CUSTOM_LEVELS` comes first when it is set. On Linux, the
+/// directories of the Windows version run through Proton are included as
+/// well.
+pub fn custom_level_dirs() -> Vec<PathBuf> {
+    // the directories of the game data
+    let mut dirs = Vec::new();
+    let home = env::var_os("HOME").map(PathBuf::from);
+
+    if cfg!(windows) {
+        if let Some(profile) = env::var_os("USERPROFILE") {
+            dirs.push(Path::new(&profile).join(WINDOWS_DATA));
+        }
+    } else if cfg!(target_os = "macos") {
+        if let Some(home) = &home {
+            let support = home.join("Library/Application Support");
+            dirs.push(support.join("com.PatrickTraynor.PatricksParabox"));
+            dirs.push(support.join("Patrick Traynor/Patrick's Parabox"));
+        }
+    } else {
+        let config = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".config")));
+        if let Some(config) = config {
+            dirs.push(config.join("unity3d/Patrick Traynor/Patrick's Parabox"));
+        }
+        if let Some(home) = &home {
+            for steam in [".steam/steam", ".local/share/Steam"] {
+                let prefix = home
+                    .join(steam)
+                    .join("steamapps/compatdata")
+                    .join(STEAM_APP_ID)
+                    .join("pfx/drive_c/users/steamuser");
+                dirs.push(prefix.join(WINDOWS_DATA));
+            }
+        }
+    }
+
+    env::var_os(CUSTOM_LEVELS_VAR)
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(dirs.into_iter().map(|dir| dir.join("custom_levels")))
+        .collect()
+}
+
+/// Returns the first custom level directory from `custom_level_dirs` which
+/// exists.
+pub fn find_custom_level_dir() -> Option<PathBuf> {
+    custom_level_dirs().into_iter().find(|dir| dir.is_dir())
+}
+
+/// Returns whether the text looks like a level file, i.e. has the `#` line
+/// ending the header. Other text files in the custom level directory, such
+/// as notes, are left out by `list_custom_levels`.
+pub fn is_level_text(text: &str) -> bool {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    text.split(['\n', '\r']).any(|line| line.trim_end() == "#")
+}
+
+/// Returns the paths of the level files under a custom level directory,
+/// recursively, in sorted order.
+pub fn list_custom_levels(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    Ok(find_levels(dir)?
+        .into_iter()
+        .filter(|path| fs::read_to_string(path).is_ok_and(|text| is_level_text(&text)))
+        .collect())
+}
+
+/// Header items of levels written for the official game.
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    /// The `custom_level_music` item, -1 for no music.
+    pub music: i32,
+    /// The `custom_level_palette` item, -1 for no palette.
+    pub palette: i32,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            music: -1,
+            palette: -1,
+        }
+    }
+}
+
+/// Returns the features of the level that the official game doesn't
+/// support, such as the `gravity` header item or `Door` floors, as messages.
+pub fn official_incompatibilities(game: &Game) -> Vec<String> {
+    let mut problems = Vec::new();
+    let config = game.config();
+    for (enabled, item) in [
+        (config.rotation, "rotation"),
+        (config.gravity, "gravity"),
+        (config.pull, "pull"),
+        (
+            config.space_size != GameConfig::DEFAULT_SPACE_SIZE,
+            "space_size",
+        ),
+        (config.shared_space, "shared_space"),
+    ] {
+        if enabled {
+            problems.push(format!("The {item} header item is not supported"));
+        }
+    }
+
+    for block in game.cells().iter().filter_map(|cell| cell.block()) {
+        if block.locked {
+            let label = game.meta().block_label(block.block_no);
+            problems.push(format!("Block {label} is locked"));
+        }
+    }
+    for floor in game.floors() {
+        if let FloorKind::OneWay(_) | FloorKind::Switch(_) | FloorKind::Door(_) = floor.kind {
+            problems.push(format!("The {} floor is not supported", floor.kind));
+        }
+    }
+    problems
+}
+
+/// Writes the current state of the game as a version 4 level file which the
+/// official game can load, with the `custom_level_music` and
+/// `custom_level_palette` header items it writes itself.
+///
+/// Named blocks are written with their numbers, and the title, author and
+/// other metadata are left out. Levels with features the official game
+/// doesn't support, or with errors found by `Game::validate`, are rejected
+/// with a message for each problem.
+pub fn to_official_text(game: &Game, options: &ExportOptions) -> Result<String, String> {
+    let mut problems = official_incompatibilities(game);
+    problems.extend(
+        game.validate()
+            .into_iter()
+            .filter(|issue| issue.severity() == Severity::Error)
+            .map(|issue| issue.to_string()),
+    );
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+
+    let mut game = game.clone();
+    *game.meta_mut() = LevelMeta::default();
+    let text = game.to_text();
+    let objects = text
+        .strip_prefix("version 4\n")
+        .expect("levels without block names are written as version 4");
+    Ok(format!(
+        "version 4\ncustom_level_music {}\ncustom_level_palette {}\n{objects}",
+        options.music, options.palette
+    ))
+}
+
+/// Writes the level with `to_official_text` to `<name>.txt` in a custom
+/// level directory, e.g. from `find_custom_level_dir`, and returns the path
+/// of the file. Existing files are only replaced with `overwrite`.
+pub fn export_custom_level(
+    game: &Game,
+    dir: &Path,
+    name: &str,
+    options: &ExportOptions,
+    overwrite: bool,
+) -> Result<PathBuf, String> {
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(format!("Invalid level name {name:?}"));
+    }
+    let text = to_official_text(game, options)?;
+    let path = dir.join(format!("{name}.txt"));
+    if path.exists() && !overwrite {
+        return Err(format!("{} already exists", path.display()));
+    }
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    fs::write(&path, text).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(path)
+}
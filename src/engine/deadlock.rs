@@ -0,0 +1,126 @@
+use super::game::*;
+use super::utility::*;
+
+impl Game {
+    /// Returns whether the game can provably never be won from the current
+    /// state. This only uses cheap, conservative checks, so a `false` result
+    /// doesn't mean that the level is still solvable.
+    ///
+    /// The following states are detected:
+    ///
+    /// - there are no players, or every player is walled in on all sides;
+    /// - a wall sits on a goal;
+    /// - a cell is frozen in a corner of walls, and is on a player goal or
+    ///   leaves too few cells for the other goals.
+    pub fn is_dead_end(&self) -> bool {
+        if self.won() {
+            return false;
+        }
+
+        if self.player_ids.is_empty()
+            || self
+                .player_ids
+                .iter()
+                .all(|&id| self.is_walled_in(&self.cells[id]))
+        {
+            return true;
+        }
+
+        let player_goals = self.goals().iter().filter(|goal| goal.player).count();
+        let possessable = self
+            .cells
+            .iter()
+            .filter(|cell| cell.possessable() && !self.is_player(cell.id()))
+            .count();
+        if player_goals > self.player_ids.len() + possessable {
+            return true;
+        }
+
+        let mut free_goals = self.goals().len() - player_goals;
+        let mut movable = 0;
+        for cell in self.cells.iter() {
+            let gpos = cell.gpos();
+            if gpos.container == Container::Space || self.is_floating(gpos) {
+                continue;
+            }
+            let goal = self.goals().iter().find(|goal| goal.gpos == gpos);
+
+            if self.is_immovable_wall(cell) {
+                if goal.is_some() {
+                    return true;
+                }
+            } else if cell.is_wall() {
+                // walls can't satisfy goals
+            } else if self.is_player(cell.id()) && possessable == 0 {
+                // players can only be left behind on goals when possessing
+                // another cell
+            } else if self.is_frozen(cell) {
+                match goal {
+                    Some(goal) if goal.player => return true,
+                    Some(_) => free_goals = free_goals.saturating_sub(1),
+                    None => (),
+                }
+            } else {
+                movable += 1;
+            }
+        }
+
+        free_goals > movable
+    }
+
+    // walls which are not players and can't be possessed never move
+    fn is_immovable_wall(&self, cell: &Cell) -> bool {
+        cell.is_wall() && !cell.possessable() && !self.is_player(cell.id())
+    }
+
+    fn is_wall_towards(&self, cell: &Cell, direction: Direction) -> bool {
+        let mut gpos = cell.gpos();
+        gpos.pos.go(direction);
+        self.cell_at(gpos)
+            .is_some_and(|neighbor| self.is_immovable_wall(neighbor))
+    }
+
+    // a player surrounded by walls can't move at all, unless pushing the walls
+    // moves the enclosing block
+    fn is_walled_in(&self, cell: &Cell) -> bool {
+        !self.config().inner_push
+            && !self.is_floating(cell.gpos())
+            && Direction::ALL
+                .into_iter()
+                .all(|direction| self.is_wall_towards(cell, direction))
+    }
+
+    // A cell in a corner of walls can only be pushed towards the walls, which
+    // always fails. The only way out is being eaten by an enterable cell
+    // moving into it from one of the open sides, being pulled out, or falling
+    // down with gravity, so only a wall below holds it then.
+    fn is_frozen(&self, cell: &Cell) -> bool {
+        if cell.possessable()
+            || self.is_player(cell.id())
+            || self.config().pull
+            || self.can_eat_anything()
+        {
+            return false;
+        }
+
+        let vertical = self.is_wall_towards(cell, Direction::Down)
+            || (!self.config().gravity && self.is_wall_towards(cell, Direction::Up));
+        let horizontal = self.is_wall_towards(cell, Direction::Left)
+            || self.is_wall_towards(cell, Direction::Right);
+        vertical && horizontal
+    }
+
+    // whether any cell could eat another one
+    fn can_eat_anything(&self) -> bool {
+        self.config().attempt_order.contains(&ActionType::Eat)
+            && self.cells.iter().any(|cell| {
+                let enterable = match cell {
+                    Cell::Wall(_) => false,
+                    Cell::Block(block) => block.can_enter(),
+                    Cell::Reference(reference) => reference.can_enter(),
+                };
+                let gpos = cell.gpos();
+                enterable && gpos.container != Container::Space && !self.is_floating(gpos)
+            })
+    }
+}
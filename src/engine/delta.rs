@@ -0,0 +1,119 @@
+use super::game::*;
+use super::utility::*;
+
+/// The difference between two states of a game, usually before and after a
+/// move, which can be applied to the first state to get the second one, or
+/// reverted to go back.
+///
+/// Moves only change the positions and flips of cells, add or remove cells
+/// for infinite exits and enters at the end of the cells, and change the
+/// players by possession, so a delta is much smaller than a copy of the
+/// game.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateDelta {
+    moved: Vec<CellChange>,
+    // cells that only exist in the second state, at the end of the cells
+    added: Vec<Cell>,
+    // cells that only exist in the first state, at the end of the cells
+    removed: Vec<Cell>,
+    // the players before and after, if they changed
+    players: Option<(Vec<usize>, Vec<usize>)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct CellChange {
+    id: usize,
+    from: (GlobalPos, bool),
+    to: (GlobalPos, bool),
+}
+
+impl StateDelta {
+    /// Whether the two states are the same.
+    pub fn is_empty(&self) -> bool {
+        self.moved.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.players.is_none()
+    }
+}
+
+impl Game {
+    /// Returns the changes from this state to a later state of the same
+    /// level, e.g. after playing a move.
+    pub fn diff(&self, after: &Game) -> StateDelta {
+        let moved = self
+            .cells
+            .iter()
+            .zip(after.cells.iter())
+            .filter_map(|(old, new)| {
+                let from = (old.gpos(), old.fliph());
+                let to = (new.gpos(), new.fliph());
+                (from != to).then_some(CellChange {
+                    id: old.id(),
+                    from,
+                    to,
+                })
+            })
+            .collect();
+        let common = self.cells.len().min(after.cells.len());
+        let added = after.cells[common..].to_vec();
+        let removed = self.cells[common..].to_vec();
+        let players = (self.player_ids != after.player_ids)
+            .then(|| (self.player_ids.clone(), after.player_ids.clone()));
+
+        StateDelta {
+            moved,
+            added,
+            removed,
+            players,
+        }
+    }
+
+    /// Applies the changes of a delta taken from this state.
+    pub fn apply_delta(&mut self, delta: &StateDelta) {
+        for _ in &delta.removed {
+            self.pop_cell();
+        }
+        for cell in &delta.added {
+            self.push_cell(cell.clone());
+        }
+        self.move_cells(delta.moved.iter().map(|change| (change.id, change.to)));
+        if let Some((_, players)) = &delta.players {
+            self.set_player_ids(players);
+        }
+    }
+
+    /// Reverts the changes of a delta leading to this state.
+    pub fn revert_delta(&mut self, delta: &StateDelta) {
+        if let Some((players, _)) = &delta.players {
+            self.set_player_ids(players);
+        }
+        for _ in &delta.added {
+            self.pop_cell();
+        }
+        // the removed cells may have contained moved cells
+        for cell in &delta.removed {
+            self.push_cell(cell.clone());
+        }
+        self.move_cells(delta.moved.iter().map(|change| (change.id, change.from)));
+    }
+
+    // moves the cells to the given positions and flips, keeping the occupied
+    // positions up to date
+    fn move_cells(&mut self, moves: impl Iterator<Item = (usize, (GlobalPos, bool))> + Clone) {
+        for (id, _) in moves.clone() {
+            let gpos = self.cells[id].gpos();
+            self.occupy(gpos, false);
+        }
+        for (id, (gpos, fliph)) in moves {
+            match &mut self.cells_mut()[id] {
+                Cell::Wall(wall) => (wall.gpos, wall.fliph) = (gpos, fliph),
+                Cell::Block(block) => (block.gpos, block.fliph) = (gpos, fliph),
+                Cell::Reference(reference) => (reference.gpos, reference.fliph) = (gpos, fliph),
+            }
+            self.occupy(gpos, true);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+
+use super::game::*;
+use super::solver::{solve, SolverConfig};
+use super::trace::TraceKind;
+use super::utility::*;
+
+// weights of the parts of the score, chosen so that a level solved in a few
+// moves that need a mechanic, or a wide search, rates above a longer walk
+const EFFORT_WEIGHT: f64 = 2.0;
+const MECHANIC_WEIGHT: f64 = 4.0;
+const DECOY_WEIGHT: f64 = 1.5;
+
+/// An estimate of how hard a level is, from a solution found by the solver
+/// and what happens while playing it, for sorting generated and community
+/// levels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DifficultyReport {
+    /// Length of the solution found, which is a shortest one with a
+    /// breadth-first search.
+    pub moves: Option<usize>,
+    /// Number of states the solver expanded.
+    pub nodes: usize,
+    /// Whether the solver explored every reachable state, so that a missing
+    /// solution means the level is unsolvable.
+    pub exhausted: bool,
+    /// Number of possessions while playing the solution.
+    pub possessions: usize,
+    /// Number of times a cell is flipped while playing the solution.
+    pub flips: usize,
+    /// Number of infinite enters and exits while playing the solution.
+    pub inf_transfers: usize,
+    /// Number of cells that could move but are neither moved nor possessed
+    /// by the solution.
+    pub decoys: usize,
+    /// The parts above combined into one number, higher for harder levels,
+    /// or `None` if no solution was found.
+    pub score: Option<f64>,
+}
+
+/// How often each mechanic is used while playing a sequence of moves, from
+/// the decisions the simulator makes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MechanicUsage {
+    pub pushes: usize,
+    /// Number of times a cell enters a block, or a reference, but not
+    /// infinitely.
+    pub enters: usize,
+    /// Number of times a cell leaves a block, but not infinitely.
+    pub exits: usize,
+    pub possessions: usize,
+    /// Number of times a cell is flipped.
+    pub flips: usize,
+    pub inf_enters: usize,
+    pub inf_exits: usize,
+    /// Ids of the cells that are players, moved or possessed at some point.
+    pub touched: HashSet<usize>,
+}
+
+impl std::fmt::Display for DifficultyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(moves) = self.moves else {
+            if self.exhausted {
+                return write!(f, "unsolvable");
+            }
+            return write!(f, "no solution found in {} states", self.nodes);
+        };
+        write!(
+            f,
+            "difficulty {:.1}: {moves} moves, {} states searched, {} possessions, {} flips, {} infinite transfers, {} decoys",
+            self.score.unwrap_or_default(),
+            self.nodes,
+            self.possessions,
+            self.flips,
+            self.inf_transfers,
+            self.decoys
+        )
+    }
+}
+
+/// Plays the moves on the level and counts the mechanics they use.
+pub fn mechanic_usage(game: &Game, moves: &[Direction]) -> MechanicUsage {
+    let mut usage = MechanicUsage::default();
+    let mut state = game.clone();
+    usage.touched.extend(state.player_ids().iter().copied());
+    for &direction in moves {
+        let before = state.clone();
+        let (outcome, events) = state.play_traced(direction);
+        let cells = state.cells();
+
+        usage.possessions += outcome.possessed.len();
+        usage
+            .touched
+            .extend(outcome.possessed.iter().map(|&(_, id)| id));
+        for &id in &outcome.moved {
+            usage.touched.insert(id);
+            // cells synthesized by the move have nothing to compare with
+            let Some(old) = before.cells().get(id) else {
+                continue;
+            };
+            if old.fliph() != cells[id].fliph() {
+                usage.flips += 1;
+            }
+            let container = cells[id].gpos().container;
+            let inf_enter = container
+                .block_id()
+                .and_then(|block_id| cells[block_id].block())
+                .is_some_and(|block| block.inf_enter.is_some());
+            if inf_enter && container != old.gpos().container {
+                usage.inf_enters += 1;
+            }
+        }
+
+        for event in events.iter().filter(|event| event.success) {
+            match event.kind {
+                TraceKind::Attempt {
+                    action: ActionType::Push,
+                    ..
+                } => usage.pushes += 1,
+                TraceKind::Attempt {
+                    action: ActionType::Enter,
+                    ..
+                } => usage.enters += 1,
+                TraceKind::Exit { exit_id, .. } => {
+                    let inf_exit = cells[exit_id]
+                        .reference()
+                        .is_some_and(|reference| reference.inf_exit.is_some());
+                    if inf_exit {
+                        usage.inf_exits += 1;
+                    } else {
+                        usage.exits += 1;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    // the infinite enters were counted as enters by the decisions
+    usage.enters = usage.enters.saturating_sub(usage.inf_enters);
+    usage
+}
+
+/// Estimates the difficulty of a level by solving it with the configuration,
+/// replaying the solution to count the mechanics it uses, and counting the
+/// cells it leaves alone. The score adds the number of moves, the logarithm
+/// of the search effort, and weighted counts of the mechanic uses and the
+/// decoys.
+pub fn estimate_difficulty(game: &Game, config: &SolverConfig) -> DifficultyReport {
+    let report = solve(game, config);
+    let mut difficulty = DifficultyReport {
+        moves: None,
+        nodes: report.nodes,
+        exhausted: report.exhausted,
+        possessions: 0,
+        flips: 0,
+        inf_transfers: 0,
+        decoys: 0,
+        score: None,
+    };
+    let Some(solution) = report.solution else {
+        return difficulty;
+    };
+
+    let usage = mechanic_usage(game, &solution);
+    difficulty.possessions = usage.possessions;
+    difficulty.flips = usage.flips;
+    difficulty.inf_transfers = usage.inf_enters + usage.inf_exits;
+
+    // cells that can move are the ones inside a block other than walls,
+    // unless they can be possessed
+    difficulty.decoys = game
+        .cells()
+        .iter()
+        .filter(|cell| !cell.is_wall() || cell.possessable())
+        .filter(|cell| cell.gpos().block_id().is_some() && !usage.touched.contains(&cell.id()))
+        .count();
+
+    let mechanics = difficulty.possessions + difficulty.flips + difficulty.inf_transfers;
+    difficulty.moves = Some(solution.len());
+    difficulty.score = Some(
+        solution.len() as f64
+            + EFFORT_WEIGHT * (report.nodes as f64).max(1.0).log2()
+            + MECHANIC_WEIGHT * mechanics as f64
+            + DECOY_WEIGHT * difficulty.decoys as f64,
+    );
+    difficulty
+}
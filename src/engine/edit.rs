@@ -0,0 +1,551 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use super::game::*;
+use super::occupancy::Occupancy;
+use super::utility::*;
+
+/// How `Game::renumber_blocks` picks the new numbers of the blocks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Renumbering {
+    /// Numbers the blocks from 0 in the order of the cells, leaving no gaps.
+    Contiguous,
+    /// Gives the blocks in the map their new numbers, and keeps the numbers
+    /// of the others.
+    Map(HashMap<BlockNo, BlockNo>),
+}
+
+/// Editing a level in place, for level editors and other tooling. Each edit
+/// keeps the ids, `block_map` and the other indexes consistent, and returns
+/// an error without changing anything when it would conflict with the
+/// cells already in the level.
+///
+/// The cells passed to the `add_*` methods get the next id, whatever their
+/// `id` field is set to.
+impl Game {
+    /// Adds an empty block, and returns its id.
+    pub fn add_block(&mut self, mut block: Block) -> Result<usize, String> {
+        if self.block_map.contains_key(&block.block_no) {
+            return Err(format!("Block {} already exists", block.block_no));
+        }
+        if !block.filled && (block.width <= 0 || block.height <= 0) {
+            return Err(format!(
+                "Invalid block size: {}x{}",
+                block.width, block.height
+            ));
+        }
+        if let Some((target_no, _)) = block.inf_enter {
+            if !self.block_map.contains_key(&target_no) {
+                return Err(format!("Invalid inf enter target {target_no}"));
+            }
+        }
+        self.check_pos(block.gpos)?;
+
+        block.id = self.cells.len();
+        let (id, block_no) = (block.id, block.block_no);
+        self.push_level_cell(Cell::Block(block));
+        Arc::make_mut(&mut self.block_map).insert(block_no, id);
+        Ok(id)
+    }
+
+    /// Adds a wall inside a block, and returns its id.
+    pub fn add_wall(&mut self, mut wall: Wall) -> Result<usize, String> {
+        self.check_in_block(wall.gpos)?;
+        wall.id = self.cells.len();
+        Ok(self.push_level_cell(Cell::Wall(wall)))
+    }
+
+    /// Adds a reference to an existing block inside a block, and returns its
+    /// id.
+    pub fn add_reference(&mut self, mut reference: Reference) -> Result<usize, String> {
+        if !self.block_map.contains_key(&reference.target_no) {
+            return Err(format!("Invalid reference target {}", reference.target_no));
+        }
+        self.check_in_block(reference.gpos)?;
+        reference.id = self.cells.len();
+        Ok(self.push_level_cell(Cell::Reference(reference)))
+    }
+
+    /// Adds a button, or a player button, as a goal of the level.
+    pub fn add_goal(&mut self, gpos: GlobalPos, player: bool) -> Result<(), String> {
+        match self.container_block(gpos) {
+            None => return Err("Floor outside of block".to_string()),
+            Some(block) if !block.in_bounds(gpos.pos) => {
+                return Err(format!("Invalid position {gpos:?}"))
+            }
+            Some(_) => (),
+        }
+        if self.floors().iter().any(|floor| floor.gpos == gpos) {
+            return Err(format!("Floor already exists at {gpos:?}"));
+        }
+
+        let level = Arc::make_mut(&mut self.level);
+        level.goals.push(Goal { gpos, player });
+        level.floors.push(Floor {
+            gpos,
+            kind: if player {
+                FloorKind::PlayerButton
+            } else {
+                FloorKind::Button
+            },
+        });
+        Ok(())
+    }
+
+    /// Removes the floor at the position, along with its goal if it is a
+    /// button.
+    pub fn remove_floor(&mut self, gpos: GlobalPos) -> Result<Floor, String> {
+        let Some(index) = self.floors().iter().position(|floor| floor.gpos == gpos) else {
+            return Err(format!("No floor at {gpos:?}"));
+        };
+        let level = Arc::make_mut(&mut self.level);
+        level.goals.retain(|goal| goal.gpos != gpos);
+        Ok(level.floors.remove(index))
+    }
+
+    /// Removes a cell, along with the floors of a removed block. The cells
+    /// after it move down by one id. Blocks that still contain cells, or are
+    /// referenced or entered infinitely by other cells, can't be removed.
+    pub fn remove_cell(&mut self, id: usize) -> Result<Cell, String> {
+        let cell = self
+            .cells
+            .get(id)
+            .ok_or_else(|| format!("Invalid cell {id}"))?;
+        if let Cell::Block(block) = cell {
+            if let Some(inner) = self.cells.iter().find(|cell| cell.gpos().is_in(id)) {
+                return Err(format!(
+                    "Block {} still contains cell {}",
+                    block.block_no,
+                    inner.id()
+                ));
+            }
+            if let Some(reference) = self.references_to(block.block_no).next() {
+                return Err(format!(
+                    "Block {} is still referenced by cell {}",
+                    block.block_no, reference.id
+                ));
+            }
+            if let Some(&enter_id) = self
+                .inf_enter_map
+                .get(&block.block_no)
+                .and_then(|ids| ids.first())
+            {
+                return Err(format!(
+                    "Block {} is still the inf enter target of cell {enter_id}",
+                    block.block_no
+                ));
+            }
+        }
+
+        // the ids after the removed cell move down by one
+        let remap = |other: usize| if other > id { other - 1 } else { other };
+        let remap_pos = |gpos: GlobalPos| match gpos.container {
+            Container::Block(block_id) => GlobalPos::new(remap(block_id.index()), gpos.pos),
+            Container::Space => gpos,
+        };
+
+        let mut cells = self.cells.as_ref().clone();
+        let cell = cells.remove(id);
+        for cell in &mut cells {
+            let (cell_id, gpos) = match cell {
+                Cell::Wall(wall) => (&mut wall.id, &mut wall.gpos),
+                Cell::Block(block) => (&mut block.id, &mut block.gpos),
+                Cell::Reference(reference) => (&mut reference.id, &mut reference.gpos),
+            };
+            *cell_id = remap(*cell_id);
+            *gpos = remap_pos(*gpos);
+        }
+
+        let level = Arc::make_mut(&mut self.level);
+        level.goals.retain(|goal| !goal.gpos.is_in(id));
+        level.floors.retain(|floor| !floor.gpos.is_in(id));
+        for goal in &mut level.goals {
+            goal.gpos = remap_pos(goal.gpos);
+        }
+        for floor in &mut level.floors {
+            floor.gpos = remap_pos(floor.gpos);
+        }
+        if id < level.initial_cells {
+            level.initial_cells -= 1;
+        }
+
+        let block_map = Arc::make_mut(&mut self.block_map);
+        block_map.retain(|_, &mut block_id| block_id != id);
+        for block_id in block_map.values_mut() {
+            *block_id = remap(*block_id);
+        }
+
+        self.player_ids.retain(|&player| player != id);
+        for player in &mut self.player_ids {
+            *player = remap(*player);
+        }
+        self.set_cells(cells);
+        Ok(cell)
+    }
+
+    /// Moves a cell to another position, which must be free. A block can't
+    /// be moved inside itself, or inside a block within it, which the level
+    /// file couldn't store.
+    pub fn move_cell(&mut self, id: usize, gpos: GlobalPos) -> Result<(), String> {
+        let cell = self
+            .cells
+            .get(id)
+            .ok_or_else(|| format!("Invalid cell {id}"))?;
+        let from = cell.gpos();
+        if from == gpos {
+            return Ok(());
+        }
+        if cell.block().is_some() {
+            if self.is_within(gpos, id) {
+                return Err(format!("Block {id} can't be moved inside itself"));
+            }
+            self.check_pos(gpos)?;
+        } else {
+            self.check_in_block(gpos)?;
+        }
+
+        match &mut self.cells_mut()[id] {
+            Cell::Wall(wall) => wall.gpos = gpos,
+            Cell::Block(block) => block.gpos = gpos,
+            Cell::Reference(reference) => reference.gpos = gpos,
+        }
+        self.occupy(from, false);
+        self.occupy(gpos, true);
+        Ok(())
+    }
+
+    /// Changes the size of a block, keeping the positions of the cells and
+    /// floors inside it, which must fit in the new size.
+    pub fn resize_block(&mut self, id: usize, width: i32, height: i32) -> Result<(), String> {
+        let Some(block) = self.cells.get(id).and_then(|cell| cell.block()) else {
+            return Err(format!("Cell {id} is not a block"));
+        };
+        if !block.filled && (width <= 0 || height <= 0) {
+            return Err(format!("Invalid block size: {width}x{height}"));
+        }
+        let in_bounds = |Pos(x, y): Pos| x >= 0 && y >= 0 && x < width && y < height;
+        if let Some(cell) = self
+            .cells
+            .iter()
+            .find(|cell| cell.gpos().is_in(id) && !in_bounds(cell.gpos().pos))
+        {
+            return Err(format!(
+                "Cell {} at {:?} would be outside the block",
+                cell.id(),
+                cell.gpos()
+            ));
+        }
+        if let Some(floor) = self
+            .floors()
+            .iter()
+            .find(|floor| floor.gpos.is_in(id) && !in_bounds(floor.gpos.pos))
+        {
+            return Err(format!(
+                "Floor at {:?} would be outside the block",
+                floor.gpos
+            ));
+        }
+
+        let block = self.cells_mut()[id].block_mut().unwrap();
+        block.width = width;
+        block.height = height;
+        Arc::make_mut(&mut self.occupancy)[id] = Occupancy::new(width, height);
+        for other in 0..self.cells.len() {
+            let gpos = self.cells[other].gpos();
+            if gpos.is_in(id) {
+                self.occupy(gpos, true);
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes a cell one of the players, after the existing ones in the
+    /// player order, or no longer a player.
+    pub fn set_player(&mut self, id: usize, player: bool) -> Result<(), String> {
+        if id >= self.cells.len() {
+            return Err(format!("Invalid cell {id}"));
+        }
+        if self.is_player(id) == player {
+            return Ok(());
+        }
+        let mut player_ids = self.player_ids.clone();
+        if player {
+            player_ids.push(id);
+        } else {
+            player_ids.retain(|&other| other != id);
+        }
+        self.set_player_ids(&player_ids);
+        Ok(())
+    }
+
+    /// Changes the properties of a cell that the indexes don't depend on,
+    /// such as `possessable`, `fliph`, `exit` or `filled`. The id, position,
+    /// size, kind, block number, target and infinite enter of the cell have
+    /// their own methods, and changing them here is an error.
+    pub fn edit_cell(&mut self, id: usize, edit: impl FnOnce(&mut Cell)) -> Result<(), String> {
+        let cell = self
+            .cells
+            .get(id)
+            .ok_or_else(|| format!("Invalid cell {id}"))?;
+        let mut edited = cell.clone();
+        edit(&mut edited);
+
+        let indexed = |cell: &Cell| {
+            (
+                cell.id(),
+                cell.gpos(),
+                std::mem::discriminant(cell),
+                cell.target_no(),
+                cell.block()
+                    .map(|block| (block.width, block.height, block.space, block.inf_enter)),
+            )
+        };
+        if indexed(&edited) != indexed(cell) {
+            return Err(format!("Can't change how cell {id} is indexed"));
+        }
+
+        let gpos = edited.gpos();
+        self.cells_mut()[id] = edited;
+        // whether the cell and its container look trivial may have changed
+        if let Some(trivial) = Arc::make_mut(&mut self.trivial).get_mut(id) {
+            trivial.take();
+        }
+        self.occupy(gpos, true);
+        Ok(())
+    }
+
+    /// Gives the blocks new numbers, e.g. to close the gaps left by removed
+    /// blocks or merged levels, and updates the references, infinite enters
+    /// and block names to match. The blocks of spaces get the numbers after
+    /// the others. Returns the new number of each block by its old number.
+    pub fn renumber_blocks(
+        &mut self,
+        renumbering: &Renumbering,
+    ) -> Result<HashMap<BlockNo, BlockNo>, String> {
+        let blocks = self
+            .cells
+            .iter()
+            .filter_map(|cell| cell.block())
+            .filter(|block| !block.space)
+            .map(|block| block.block_no)
+            .collect::<Vec<_>>();
+        let numbers = match renumbering {
+            Renumbering::Contiguous => blocks
+                .iter()
+                .enumerate()
+                .map(|(i, &block_no)| (block_no, BlockNo(i as i32)))
+                .collect::<HashMap<_, _>>(),
+            Renumbering::Map(map) => {
+                if let Some(block_no) = map.keys().find(|no| !self.block_map.contains_key(no)) {
+                    return Err(format!("Block {block_no} doesn't exist"));
+                }
+                blocks
+                    .iter()
+                    .map(|&block_no| (block_no, map.get(&block_no).copied().unwrap_or(block_no)))
+                    .collect()
+            }
+        };
+        let mut used = HashSet::new();
+        if let Some(block_no) = numbers.values().find(|&&block_no| !used.insert(block_no)) {
+            return Err(format!("Block number {block_no} would be used twice"));
+        }
+
+        let renumber = |block_no: BlockNo| numbers.get(&block_no).copied().unwrap_or(block_no);
+        let mut next_space = numbers.values().map(|no| no.0 + 1).max().unwrap_or(0);
+        let mut cells = self.cells.as_ref().clone();
+        for cell in &mut cells {
+            match cell {
+                Cell::Wall(_) => (),
+                Cell::Block(block) if block.space => {
+                    block.block_no = BlockNo(next_space);
+                    next_space += 1;
+                }
+                Cell::Block(block) => {
+                    block.block_no = renumber(block.block_no);
+                    if let Some((target_no, _)) = &mut block.inf_enter {
+                        *target_no = renumber(*target_no);
+                    }
+                }
+                Cell::Reference(reference) => reference.target_no = renumber(reference.target_no),
+            }
+        }
+
+        self.block_map = Arc::new(
+            self.block_map
+                .iter()
+                .map(|(&block_no, &id)| (renumber(block_no), id))
+                .collect(),
+        );
+        let meta = &mut Arc::make_mut(&mut self.level).meta;
+        meta.block_names = std::mem::take(&mut meta.block_names)
+            .into_iter()
+            .map(|(block_no, name)| (renumber(block_no), name))
+            .collect();
+        self.set_cells(cells);
+        Ok(numbers)
+    }
+
+    // adds a cell that is part of the level, unlike the cells synthesized
+    // while playing, which come after the cells of the level
+    fn push_level_cell(&mut self, cell: Cell) -> usize {
+        let id = self.push_cell(cell);
+        let level = Arc::make_mut(&mut self.level);
+        if level.initial_cells == id {
+            level.initial_cells += 1;
+        }
+        id
+    }
+
+    // walls and references can't float in space on their own
+    fn check_in_block(&self, gpos: GlobalPos) -> Result<(), String> {
+        if self.container_block(gpos).is_none() {
+            return Err(format!("Position {gpos:?} is outside of blocks"));
+        }
+        self.check_pos(gpos)
+    }
+
+    // whether the position is inside the block, directly or through the
+    // blocks containing it; the chain of containers of a block that is
+    // inside itself during play is cut off after visiting every cell
+    pub(super) fn is_within(&self, gpos: GlobalPos, block_id: usize) -> bool {
+        let mut container = gpos.container;
+        for _ in 0..self.cells.len() {
+            match container.block_id() {
+                Some(id) if id == block_id => return true,
+                Some(id) => match self.cells.get(id) {
+                    Some(cell) => container = cell.gpos().container,
+                    None => return false,
+                },
+                None => return false,
+            }
+        }
+        false
+    }
+}
+
+/// A level being edited, with unlimited undo and redo of the edits.
+///
+/// Each edit is a closure calling the editing methods of `Game`, and is
+/// undone as a whole. Copies of the game share their cells until changed, so
+/// the history keeps the state before every edit instead of inverting them.
+/// Edits that fail, or don't touch the level, leave no trace in the history.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EditHistory {
+    current: Game,
+
+    // the states before the applied edits, the last one on top
+    undo: Vec<Game>,
+
+    // the states after the undone edits, the next one on top
+    redo: Vec<Game>,
+
+    // whether a batch is open, whose edits are undone together
+    batching: bool,
+}
+
+impl EditHistory {
+    pub fn new(game: Game) -> Self {
+        EditHistory {
+            current: game,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            batching: false,
+        }
+    }
+
+    pub fn current(&self) -> &Game {
+        &self.current
+    }
+
+    pub fn into_current(self) -> Game {
+        self.current
+    }
+
+    /// Returns the number of edits that can be undone.
+    pub fn depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Returns the number of undone edits that can be redone.
+    pub fn redo_depth(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Applies an edit and returns its result. If it fails, the level is
+    /// restored to the state before it, even if the edit changed something
+    /// before failing. Otherwise the undone edits are dropped.
+    pub fn apply<T>(
+        &mut self,
+        edit: impl FnOnce(&mut Game) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let before = self.current.clone();
+        match edit(&mut self.current) {
+            Ok(value) => {
+                self.record(before);
+                Ok(value)
+            }
+            Err(e) => {
+                self.current = before;
+                Err(e)
+            }
+        }
+    }
+
+    /// Applies several edits as one, which are undone together. If the batch
+    /// fails, all of its edits are rolled back, so that scripts can check the
+    /// result, e.g. with `Game::validate`, and return an error to discard it.
+    /// Batches may be nested, and become part of the outer batch.
+    pub fn batch<T>(
+        &mut self,
+        edits: impl FnOnce(&mut EditHistory) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let before = self.current.clone();
+        let batching = std::mem::replace(&mut self.batching, true);
+        let result = edits(self);
+        self.batching = batching;
+        match result {
+            Ok(value) => {
+                self.record(before);
+                Ok(value)
+            }
+            Err(e) => {
+                self.current = before;
+                Err(e)
+            }
+        }
+    }
+
+    /// Undoes the last edit or batch. Returns false if there is none.
+    pub fn undo(&mut self) -> bool {
+        assert!(!self.batching, "can't undo during a batch");
+        let Some(before) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(std::mem::replace(&mut self.current, before));
+        true
+    }
+
+    /// Redoes the last undone edit or batch. Returns false if there is none.
+    pub fn redo(&mut self) -> bool {
+        assert!(!self.batching, "can't redo during a batch");
+        let Some(after) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(std::mem::replace(&mut self.current, after));
+        true
+    }
+
+    // records the state before a successful edit, unless it is part of a
+    // batch, or didn't touch the cells, the level or the players
+    fn record(&mut self, before: Game) {
+        let unchanged = Arc::ptr_eq(&before.level, &self.current.level)
+            && Arc::ptr_eq(&before.cells, &self.current.cells)
+            && before.player_ids == self.current.player_ids;
+        if self.batching || unchanged {
+            return;
+        }
+        self.undo.push(before);
+        self.redo.clear();
+    }
+}
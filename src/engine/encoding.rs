@@ -0,0 +1,258 @@
+use super::game::*;
+use super::utility::*;
+
+/// The cells of a level as it is loaded, which encoded states of the level
+/// are decoded against.
+///
+/// An encoded state only holds what changes while playing: the positions and
+/// fliph flags of the cells, the players, and the kinds of the cells
+/// synthesized for infinite exits and enters. Everything else comes from the
+/// schema, so encoded states are small enough for transposition tables and
+/// save files.
+#[derive(Clone, Debug)]
+pub struct StateSchema {
+    game: Game,
+}
+
+// a cell synthesized while playing, which is added again when decoding
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Synthesized {
+    InfExit(BlockNo, u32),
+    InfEnter(BlockNo, u32),
+}
+
+// writes values bit by bit, least significant bits first
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u64, bits: u32) {
+        for i in 0..bits {
+            if self.len.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 != 0 {
+                *self.bytes.last_mut().unwrap() |= 1 << (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+
+    // groups of 7 bits, each followed by whether more groups follow
+    fn write_varint(&mut self, mut value: u64) {
+        loop {
+            self.write(value & 0x7f, 7);
+            value >>= 7;
+            self.write((value != 0) as u64, 1);
+            if value == 0 {
+                break;
+            }
+        }
+    }
+}
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, bits: u32) -> Result<u64, String> {
+        let mut value = 0;
+        for i in 0..bits {
+            let byte = self
+                .bytes
+                .get(self.pos / 8)
+                .ok_or("Unexpected end of encoded state")?;
+            value |= (((byte >> (self.pos % 8)) & 1) as u64) << i;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn read_varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            value |= self.read(7)? << shift;
+            if self.read(1)? == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Invalid number in encoded state".to_string())
+    }
+}
+
+// the number of bits needed to store values up to `max`
+fn bits_for(max: u64) -> u32 {
+    u64::BITS - max.leading_zeros()
+}
+
+// maps coordinates to unsigned numbers, keeping small negative ones small
+fn zigzag(value: i32) -> u64 {
+    ((value << 1) ^ (value >> 31)) as u32 as u64
+}
+
+fn unzigzag(value: u64) -> i32 {
+    let value = value as u32;
+    ((value >> 1) as i32) ^ -((value & 1) as i32)
+}
+
+fn is_space(cell: &Cell) -> bool {
+    cell.block().is_some_and(|block| block.space)
+}
+
+impl Game {
+    /// Encodes the dynamic state of the game into a few bytes, which can be
+    /// decoded with the `StateSchema` of any game of the same level.
+    ///
+    /// The encoding is canonical: the same state gives the same bytes, even
+    /// when the synthesized cells were added in a different order. Unlike
+    /// `StateKey`, interchangeable walls and references are still told apart
+    /// by their ids.
+    pub fn encode_state(&self) -> Vec<u8> {
+        let initial = self.level.initial_cells.min(self.cells.len());
+
+        // synthesized cells come right after their spaces, and are encoded
+        // in the order of their kinds
+        let mut synthesized = self.cells[initial..]
+            .iter()
+            .filter_map(|cell| {
+                let kind = match cell {
+                    Cell::Reference(reference) => {
+                        Synthesized::InfExit(reference.target_no, reference.inf_exit?)
+                    }
+                    Cell::Block(block) if !block.space => {
+                        let (block_no, degree) = block.inf_enter?;
+                        Synthesized::InfEnter(block_no, degree)
+                    }
+                    _ => return None,
+                };
+                Some((kind, cell.id()))
+            })
+            .collect::<Vec<_>>();
+        synthesized.sort_unstable();
+
+        let mut order = (0..initial).collect::<Vec<_>>();
+        for (_, id) in &synthesized {
+            order.extend([id - 1, *id]);
+        }
+        let mut remap = vec![usize::MAX; self.cells.len()];
+        for (new_id, id) in order.iter().enumerate() {
+            remap[*id] = new_id;
+        }
+
+        let mut writer = BitWriter::default();
+        writer.write_varint(synthesized.len() as u64);
+        for (kind, _) in &synthesized {
+            let (inf_enter, block_no, degree) = match *kind {
+                Synthesized::InfExit(block_no, degree) => (false, block_no, degree),
+                Synthesized::InfEnter(block_no, degree) => (true, block_no, degree),
+            };
+            writer.write(inf_enter as u64, 1);
+            writer.write_varint(block_no.0 as u32 as u64);
+            writer.write_varint(degree as u64);
+        }
+
+        // spaces never move, so they are left out
+        let cells = order
+            .iter()
+            .map(|id| &self.cells[*id])
+            .filter(|cell| !is_space(cell));
+        let max_pos = cells
+            .clone()
+            .map(|cell| {
+                let Pos(x, y) = cell.gpos().pos;
+                zigzag(x).max(zigzag(y))
+            })
+            .max()
+            .unwrap_or(0);
+        let pos_bits = bits_for(max_pos);
+        // containers are stored as ids plus one, with zero for none
+        let id_bits = bits_for(order.len() as u64);
+
+        writer.write(pos_bits as u64, 6);
+        for cell in cells {
+            let gpos = cell.gpos();
+            let container = gpos.block_id().map_or(0, |id| remap[id] + 1);
+            writer.write(container as u64, id_bits);
+            writer.write(zigzag(gpos.pos.0), pos_bits);
+            writer.write(zigzag(gpos.pos.1), pos_bits);
+            writer.write(cell.fliph() as u64, 1);
+        }
+
+        writer.write_varint(self.player_ids.len() as u64);
+        for id in &self.player_ids {
+            writer.write(remap[*id] as u64, id_bits);
+        }
+        writer.bytes
+    }
+
+    /// Returns the schema of the level, for decoding encoded states.
+    pub fn state_schema(&self) -> StateSchema {
+        let mut game = self.clone();
+        while game.cells.len() > game.level.initial_cells {
+            game.pop_cell();
+        }
+        StateSchema { game }
+    }
+}
+
+impl StateSchema {
+    /// Decodes a state encoded by `Game::encode_state`.
+    pub fn decode(&self, bytes: &[u8]) -> Result<Game, String> {
+        let mut reader = BitReader { bytes, pos: 0 };
+        let mut game = self.game.clone();
+
+        for _ in 0..reader.read_varint()? {
+            let inf_enter = reader.read(1)? != 0;
+            let block_no = BlockNo(reader.read_varint()? as u32 as i32);
+            let degree = reader.read_varint()? as u32;
+            if game.block_by_no(block_no).is_none() {
+                return Err(format!("Invalid block {} in encoded state", block_no.0));
+            }
+            if inf_enter {
+                game.add_inf_enter_for(block_no, degree);
+            } else {
+                game.add_inf_exit_for(block_no, degree);
+            }
+        }
+
+        let len = game.cells.len();
+        let pos_bits = reader.read(6)? as u32;
+        let id_bits = bits_for(len as u64);
+        let mut cells = game.cells.to_vec();
+        for cell in cells.iter_mut().filter(|cell| !is_space(cell)) {
+            let container = match reader.read(id_bits)? as usize {
+                0 => Container::Space,
+                id if id <= len => Container::Block(CellId(id - 1)),
+                id => return Err(format!("Invalid container {} in encoded state", id - 1)),
+            };
+            let x = unzigzag(reader.read(pos_bits)?);
+            let y = unzigzag(reader.read(pos_bits)?);
+            let gpos = GlobalPos {
+                container,
+                pos: Pos(x, y),
+            };
+            let fliph = reader.read(1)? != 0;
+            match cell {
+                Cell::Wall(wall) => (wall.gpos, wall.fliph) = (gpos, fliph),
+                Cell::Block(block) => (block.gpos, block.fliph) = (gpos, fliph),
+                Cell::Reference(reference) => (reference.gpos, reference.fliph) = (gpos, fliph),
+            }
+        }
+
+        let players = (0..reader.read_varint()?)
+            .map(|_| match reader.read(id_bits)? as usize {
+                id if id < len => Ok(id),
+                id => Err(format!("Invalid player {id} in encoded state")),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        game.set_cells(cells);
+        game.set_player_ids(&players);
+        Ok(game)
+    }
+}
@@ -0,0 +1,252 @@
+use super::game::*;
+use super::utility::*;
+
+/// An environment for reinforcement learning around a level, with the usual
+/// reset and step semantics: each step plays one input, and returns the
+/// observation, the reward and whether the episode is over.
+///
+/// ```
+/// # use parabox::engine::{env::*, *};
+/// # let text = std::fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+/// let mut env = Environment::new(Game::parse(&text).unwrap(), EnvConfig::default());
+/// env.reset();
+/// loop {
+///     let step = env.step(Direction::Right);
+///     if step.done || step.truncated {
+///         break;
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Environment {
+    initial: Game,
+    game: Game,
+    config: EnvConfig,
+    steps: usize,
+    satisfied: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub observation: ObservationKind,
+    /// Width and height of grid observations. Larger blocks are cropped,
+    /// smaller ones are padded with empty positions.
+    pub grid_size: (usize, usize),
+    /// Episodes are truncated after this many steps. `None` means unlimited.
+    pub max_steps: Option<usize>,
+    /// Reward for winning the level.
+    pub win_reward: f64,
+    /// Reward for each goal satisfied by a step, and penalty for each goal
+    /// no longer satisfied.
+    pub goal_reward: f64,
+    /// Reward for every step, usually negative to favor short solutions.
+    pub step_reward: f64,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            observation: ObservationKind::Grid,
+            grid_size: (16, 16),
+            max_steps: Some(1000),
+            win_reward: 10.0,
+            goal_reward: 1.0,
+            step_reward: -0.01,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObservationKind {
+    Grid,
+    /// The state encoded by `Game::encode_state`.
+    Encoded,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Observation {
+    Grid(GridObservation),
+    Encoded(Vec<u8>),
+}
+
+/// The block containing the first player as a tensor of shape `channels` ×
+/// `height` × `width`, with one plane per `GridObservation::CHANNELS`, and
+/// the top row first. Each value is 0 or 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridObservation {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<f32>,
+}
+
+/// The result of a step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub observation: Observation,
+    pub reward: f64,
+    /// Whether the level is won.
+    pub done: bool,
+    /// Whether the episode ran out of steps before the level was won.
+    pub truncated: bool,
+    /// Whether the input changed the state.
+    pub changed: bool,
+}
+
+impl GridObservation {
+    /// The names of the planes, in order: positions inside the block,
+    /// walls, blocks, references, players, block goals and player goals.
+    pub const CHANNELS: [&'static str; 7] = [
+        "inside",
+        "wall",
+        "block",
+        "reference",
+        "player",
+        "goal",
+        "player_goal",
+    ];
+
+    /// Returns the value of a channel at a position, with `y` counted from
+    /// the top.
+    pub fn get(&self, channel: usize, x: usize, y: usize) -> f32 {
+        self.data[(channel * self.height + y) * self.width + x]
+    }
+
+    fn set(&mut self, channel: usize, x: usize, y: usize) {
+        self.data[(channel * self.height + y) * self.width + x] = 1.0;
+    }
+}
+
+impl Environment {
+    /// The number of actions, which are the indices of `Direction::ALL`.
+    pub const ACTIONS: usize = Direction::ALL.len();
+
+    pub fn new(game: Game, config: EnvConfig) -> Self {
+        let satisfied = game.satisfied_goals();
+        Self {
+            initial: game.clone(),
+            game,
+            config,
+            steps: 0,
+            satisfied,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// The number of steps since the last reset.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Starts a new episode from the initial state of the level.
+    pub fn reset(&mut self) -> Observation {
+        self.game = self.initial.clone();
+        self.steps = 0;
+        self.satisfied = self.game.satisfied_goals();
+        self.observe()
+    }
+
+    /// Plays one input. Steps after the level is won or the episode is
+    /// truncated keep playing, but the caller should reset instead.
+    pub fn step(&mut self, direction: Direction) -> Step {
+        let changed = self.game.play(direction).changed();
+        self.steps += 1;
+
+        let satisfied = self.game.satisfied_goals();
+        let done = self.game.won();
+        let mut reward = self.config.step_reward
+            + (satisfied as f64 - self.satisfied as f64) * self.config.goal_reward;
+        if done {
+            reward += self.config.win_reward;
+        }
+        self.satisfied = satisfied;
+
+        let truncated = !done && self.config.max_steps.is_some_and(|max| self.steps >= max);
+        Step {
+            observation: self.observe(),
+            reward,
+            done,
+            truncated,
+            changed,
+        }
+    }
+
+    /// Plays the action with the given index, see `ACTIONS`.
+    pub fn step_action(&mut self, action: usize) -> Result<Step, String> {
+        let direction = Direction::ALL
+            .get(action)
+            .ok_or(format!("Invalid action {action}"))?;
+        Ok(self.step(*direction))
+    }
+
+    /// Returns the observation of the current state.
+    pub fn observe(&self) -> Observation {
+        match self.config.observation {
+            ObservationKind::Grid => Observation::Grid(self.grid()),
+            ObservationKind::Encoded => Observation::Encoded(self.game.encode_state()),
+        }
+    }
+
+    fn grid(&self) -> GridObservation {
+        let (width, height) = self.config.grid_size;
+        let mut grid = GridObservation {
+            width,
+            height,
+            data: vec![0.0; GridObservation::CHANNELS.len() * width * height],
+        };
+        let game = &self.game;
+        let Some(block) = game.view_block() else {
+            return grid;
+        };
+
+        // the top left corner of the block, at the top left of the grid
+        let (w, h) = (
+            block.width.min(width as i32),
+            block.height.min(height as i32),
+        );
+        let at = |Pos(x, y): Pos| {
+            (x >= 0 && x < w && y >= block.height - h && y < block.height)
+                .then(|| (x as usize, (block.height - 1 - y) as usize))
+        };
+
+        for y in 0..block.height {
+            for x in 0..block.width {
+                if let Some((x, y)) = at(Pos(x, y)) {
+                    grid.set(0, x, y);
+                }
+            }
+        }
+        for cell in game.cells() {
+            let gpos = cell.gpos();
+            let Some((x, y)) = gpos.is_in(block.id).then(|| at(gpos.pos)).flatten() else {
+                continue;
+            };
+            let channel = match cell {
+                Cell::Wall(_) => 1,
+                Cell::Block(_) => 2,
+                Cell::Reference(_) => 3,
+            };
+            grid.set(channel, x, y);
+            if game.is_player(cell.id()) {
+                grid.set(4, x, y);
+            }
+        }
+        for goal in game.goals() {
+            if let Some((x, y)) = goal
+                .gpos
+                .is_in(block.id)
+                .then(|| at(goal.gpos.pos))
+                .flatten()
+            {
+                grid.set(if goal.player { 6 } else { 5 }, x, y);
+            }
+        }
+        grid
+    }
+}
@@ -1,18 +1,70 @@
 use color_space::Hsv;
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, OnceLock};
 
+use super::occupancy::Occupancy;
+use super::simulation::*;
 use super::utility::*;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Game {
-    pub(super) cells: Vec<Cell>,
-    pub(super) goals: Vec<Goal>,
-    pub(super) block_map: HashMap<BlockNo, usize>,
+    // static data, shared between all states of the same level
+    pub(super) level: Arc<Level>,
+
+    // the following are copied on write, so that cloning a game is cheap
+    pub(super) cells: Arc<Vec<Cell>>,
+    pub(super) block_map: Arc<HashMap<BlockNo, usize>>,
+    // the ids of the references to each block, in order
+    pub(super) ref_map: Arc<HashMap<BlockNo, Vec<usize>>>,
+    // the ids of the blocks that are the infinite enters of each block, in
+    // order
+    pub(super) inf_enter_map: Arc<HashMap<BlockNo, Vec<usize>>>,
+    // the occupied positions of each block, by cell id
+    pub(super) occupancy: Arc<Vec<Occupancy>>,
+    // whether each block is trivial, by cell id, computed when first needed
+    // and cleared when a cell inside the block changes
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(super) trivial: Arc<Vec<OnceLock<bool>>>,
+
     pub(super) player_ids: Vec<usize>,
+    // whether each cell is a player, by cell id
+    pub(super) player_flags: Vec<bool>,
+}
+
+/// The static part of a game, which doesn't change when playing.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Level {
+    // the buttons among the floors, which are the goals of the level
+    pub(super) goals: Vec<Goal>,
+    pub(super) floors: Vec<Floor>,
     pub(super) config: GameConfig,
+    pub(super) meta: LevelMeta,
+    // the number of cells when the level is loaded; the cells after them are
+    // synthesized while playing
+    pub(super) initial_cells: usize,
+}
+
+/// Information about a level which doesn't affect the gameplay.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LevelMeta {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Number of moves of a good solution, for players to compare with.
+    pub par_moves: Option<usize>,
+    pub tags: Vec<String>,
+    /// Custom properties from `property <key> <value>` header lines, e.g. hint
+    /// texts or music cues used by level packs. Values can't contain newlines.
+    pub extra: BTreeMap<String, String>,
+    /// Names of the blocks declared as `@name` in version 5 level files.
+    pub block_names: BTreeMap<BlockNo, String>,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Cell {
     Wall(Wall),
     Block(Block),
@@ -20,6 +72,7 @@ pub enum Cell {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Wall {
     pub id: usize,
     pub gpos: GlobalPos,
@@ -27,25 +80,36 @@ pub struct Wall {
     pub fliph: bool,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockNo(pub i32);
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     pub id: usize,
     pub gpos: GlobalPos,
     pub block_no: BlockNo,
     pub width: i32,
     pub height: i32,
+    #[cfg_attr(feature = "serde", serde(with = "hsv_serde"))]
     pub hsv: Hsv,
+    /// How far the camera zooms in the original game; the interior is drawn
+    /// smaller by this factor when the block is nested. Defaults to 1.
+    pub zoom: f64,
     pub filled: bool,
     pub space: bool,
     pub possessable: bool,
     pub fliph: bool,
     pub inf_enter: Option<(BlockNo, u32)>,
+    pub effect: SpecialEffect,
+    /// Locked blocks can't be pushed, pulled or eaten, and only move when
+    /// they are the player.
+    pub locked: bool,
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Reference {
     pub id: usize,
     pub gpos: GlobalPos,
@@ -54,22 +118,182 @@ pub struct Reference {
     pub inf_exit: Option<u32>,
     pub possessable: bool,
     pub fliph: bool,
+    /// Number of quarter turns counterclockwise of the interior as seen
+    /// through the reference, with the `rotation` rule extension.
+    pub rotation: u8,
+    pub effect: SpecialEffect,
+}
+
+/// The `specialeffect` field of blocks and references, which selects a
+/// visual effect of the original game. The effects don't change the
+/// gameplay, so they are only kept by number.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpecialEffect {
+    #[default]
+    None,
+    Effect(u32),
+}
+
+impl SpecialEffect {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text.parse::<u32>() {
+            Ok(0) => Ok(SpecialEffect::None),
+            Ok(effect) => Ok(SpecialEffect::Effect(effect)),
+            Err(_) => Err(format!("Invalid special effect {text}")),
+        }
+    }
+
+    /// Returns the number written in level files.
+    pub fn number(self) -> u32 {
+        match self {
+            SpecialEffect::None => 0,
+            SpecialEffect::Effect(effect) => effect,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Goal {
     pub gpos: GlobalPos,
     pub player: bool,
 }
 
+/// A floor tile of the level, in the order of the level file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Floor {
+    pub gpos: GlobalPos,
+    pub kind: FloorKind,
+}
+
+/// The type of a floor tile. Only buttons, one-way gates, switches and doors
+/// take part in the gameplay; the other types of the official levels are
+/// kept so that the level is written back unchanged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FloorKind {
+    Button,
+    PlayerButton,
+    FastTravel,
+    /// A sign with the given text.
+    Info(String),
+    DemoEnd,
+    Break,
+    Gallery,
+    /// A portal to the level of the given name.
+    Portal(String),
+    SmallPortal(String),
+    /// A one-way gate, which cells can only move onto in the given
+    /// directions.
+    OneWay(Vec<Direction>),
+    /// A switch that opens the doors of the given channel while a cell rests
+    /// on it.
+    Switch(u32),
+    /// A door that cells can only move onto while it is open.
+    Door(u32),
+}
+
+impl FloorKind {
+    /// Parses the type and arguments of a `Floor` line.
+    pub fn parse(parts: &[&str]) -> Result<Self, String> {
+        let argument = || parts[1..].join(" ");
+        Ok(match parts.first().copied().unwrap_or("") {
+            "Button" => FloorKind::Button,
+            "PlayerButton" => FloorKind::PlayerButton,
+            "FastTravel" => FloorKind::FastTravel,
+            "Info" => FloorKind::Info(argument()),
+            "DemoEnd" => FloorKind::DemoEnd,
+            "Break" => FloorKind::Break,
+            "Gallery" => FloorKind::Gallery,
+            "Portal" => FloorKind::Portal(argument()),
+            "SmallPortal" => FloorKind::SmallPortal(argument()),
+            "OneWay" => {
+                // the directions may be spaced out, as in `OneWay U R`
+                let directions = parts[1..]
+                    .concat()
+                    .chars()
+                    .map(|c| Direction::from_char(c).ok_or(format!("Invalid direction {c}")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if directions.is_empty() {
+                    return Err("Missing directions of one-way gate".to_string());
+                }
+                FloorKind::OneWay(directions)
+            }
+            "Switch" | "Door" => {
+                let channel = parts
+                    .get(1)
+                    .and_then(|part| part.parse().ok())
+                    .ok_or(format!("Invalid channel of {}", parts[0]))?;
+                match parts[0] {
+                    "Switch" => FloorKind::Switch(channel),
+                    _ => FloorKind::Door(channel),
+                }
+            }
+            kind => return Err(format!("Unknown floor type {kind}")),
+        })
+    }
+
+    /// Returns whether this floor is a goal, and whether it is for players.
+    pub fn goal(&self) -> Option<bool> {
+        match self {
+            FloorKind::Button => Some(false),
+            FloorKind::PlayerButton => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for FloorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FloorKind::Button => write!(f, "Button"),
+            FloorKind::PlayerButton => write!(f, "PlayerButton"),
+            FloorKind::FastTravel => write!(f, "FastTravel"),
+            FloorKind::Info(text) => write!(f, "Info {text}"),
+            FloorKind::DemoEnd => write!(f, "DemoEnd"),
+            FloorKind::Break => write!(f, "Break"),
+            FloorKind::Gallery => write!(f, "Gallery"),
+            FloorKind::Portal(name) => write!(f, "Portal {name}"),
+            FloorKind::SmallPortal(name) => write!(f, "SmallPortal {name}"),
+            FloorKind::OneWay(directions) => {
+                let directions = directions.iter().map(|d| d.to_char()).collect::<String>();
+                write!(f, "OneWay {directions}")
+            }
+            FloorKind::Switch(channel) => write!(f, "Switch {channel}"),
+            FloorKind::Door(channel) => write!(f, "Door {channel}"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GameConfig {
     pub attempt_order: Vec<ActionType>,
     pub shed: bool,
     pub inner_push: bool,
+    /// Whether references may be rotated, from the `rotation` header item.
+    pub rotation: bool,
+    /// Whether cells fall down after each move, from the `gravity` header
+    /// item.
+    pub gravity: bool,
+    /// Whether players drag the cell behind them along, from the `pull`
+    /// header item.
+    pub pull: bool,
+    /// Width and height of the spaces that floating cells are put in, from
+    /// the `space_size` header item.
+    pub space_size: (i32, i32),
+    /// Whether floating cells share a single space, where they keep their
+    /// own coordinates, from the `shared_space` header item.
+    pub shared_space: bool,
+    /// Maximum number of simulation steps for a single player's move, which
+    /// guards against pathological recursion. `None` means unlimited.
+    pub fuel: Option<u32>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ActionType {
     Push,
     Enter,
@@ -77,6 +301,40 @@ pub enum ActionType {
     Possess,
 }
 
+impl Level {
+    pub fn goals(&self) -> &Vec<Goal> {
+        &self.goals
+    }
+
+    /// Returns all floors of the level, including the buttons.
+    pub fn floors(&self) -> &Vec<Floor> {
+        &self.floors
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn meta(&self) -> &LevelMeta {
+        &self.meta
+    }
+}
+
+impl LevelMeta {
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.extra.get(key).map(String::as_str)
+    }
+
+    /// Returns how a block is written in level files: `@name` if it has a
+    /// name, or its number otherwise.
+    pub fn block_label(&self, block_no: BlockNo) -> String {
+        match self.block_names.get(&block_no) {
+            Some(name) => format!("@{name}"),
+            None => block_no.to_string(),
+        }
+    }
+}
+
 impl Cell {
     pub fn id(&self) -> usize {
         match self {
@@ -110,6 +368,31 @@ impl Cell {
         }
     }
 
+    /// Returns the number of quarter turns of a reference, or 0 for other
+    /// cells.
+    pub fn rotation(&self) -> u8 {
+        match self {
+            Cell::Reference(reference) => reference.rotation,
+            _ => 0,
+        }
+    }
+
+    /// Returns the special effect of a block or reference.
+    pub fn effect(&self) -> SpecialEffect {
+        match self {
+            Cell::Wall(_) => SpecialEffect::None,
+            Cell::Block(block) => block.effect,
+            Cell::Reference(reference) => reference.effect,
+        }
+    }
+
+    pub fn locked(&self) -> bool {
+        match self {
+            Cell::Block(block) => block.locked,
+            _ => false,
+        }
+    }
+
     pub fn is_wall(&self) -> bool {
         matches!(self, Cell::Wall(_))
     }
@@ -128,6 +411,16 @@ impl Cell {
         }
     }
 
+    /// Returns the number of the block that this cell leads into, i.e. the
+    /// block itself or the target of the reference.
+    pub fn target_no(&self) -> Option<BlockNo> {
+        match self {
+            Cell::Wall(_) => None,
+            Cell::Block(block) => Some(block.block_no),
+            Cell::Reference(reference) => Some(reference.target_no),
+        }
+    }
+
     pub fn reference(&self) -> Option<&Reference> {
         match &self {
             Cell::Reference(reference) => Some(reference),
@@ -142,7 +435,41 @@ impl std::fmt::Display for BlockNo {
     }
 }
 
+impl Wall {
+    /// Returns a wall at the position that can't be possessed, for adding it
+    /// to a level with `Game::add_wall`, which sets the id.
+    pub fn new(gpos: GlobalPos) -> Self {
+        Wall {
+            id: 0,
+            gpos,
+            possessable: false,
+            fliph: false,
+        }
+    }
+}
+
 impl Block {
+    /// Returns a hollow block with the other properties at their defaults,
+    /// for adding it to a level with `Game::add_block`, which sets the id.
+    pub fn new(gpos: GlobalPos, block_no: BlockNo, width: i32, height: i32, hsv: Hsv) -> Self {
+        Block {
+            id: 0,
+            gpos,
+            block_no,
+            width,
+            height,
+            hsv,
+            zoom: 1.0,
+            filled: false,
+            space: false,
+            possessable: false,
+            fliph: false,
+            inf_enter: None,
+            effect: SpecialEffect::None,
+            locked: false,
+        }
+    }
+
     pub fn in_bounds(&self, Pos(x, y): Pos) -> bool {
         x >= 0 && y >= 0 && x < self.width && y < self.height
     }
@@ -154,6 +481,16 @@ impl Block {
     pub fn can_exit(&self) -> bool {
         !self.space
     }
+
+    /// Returns the size of the interior relative to the cell when the block
+    /// is drawn nested. Zoom factors below 1 don't enlarge it.
+    pub fn nested_scale(&self) -> f64 {
+        if self.zoom > 1.0 {
+            1.0 / self.zoom
+        } else {
+            1.0
+        }
+    }
 }
 
 impl Reference {
@@ -162,6 +499,20 @@ impl Reference {
     }
 }
 
+impl GameConfig {
+    /// Vanilla levels take fewer than 200 steps per move, so this leaves
+    /// plenty of headroom while keeping the recursion depth bounded.
+    pub const DEFAULT_FUEL: u32 = 2_000;
+
+    pub const DEFAULT_SPACE_SIZE: (i32, i32) = (7, 7);
+
+    /// Returns the center of a space, where a cell floating on its own is
+    /// placed.
+    pub fn space_center(&self) -> Pos {
+        Pos(self.space_size.0 / 2, self.space_size.1 / 2)
+    }
+}
+
 impl Default for GameConfig {
     fn default() -> Self {
         Self {
@@ -173,77 +524,156 @@ impl Default for GameConfig {
             ],
             shed: false,
             inner_push: false,
+            rotation: false,
+            gravity: false,
+            pull: false,
+            space_size: Self::DEFAULT_SPACE_SIZE,
+            shared_space: false,
+            fuel: Some(Self::DEFAULT_FUEL),
         }
     }
 }
 
 impl Game {
-    const SPACE_SIZE: i32 = 3;
-    const SPACE_CENTER: Pos = Pos(Self::SPACE_SIZE, Self::SPACE_SIZE);
-
     pub fn cells(&self) -> &Vec<Cell> {
         &self.cells
     }
 
+    /// Returns the cells for modification, copying them first if they are
+    /// shared with another state.
+    pub(super) fn cells_mut(&mut self) -> &mut Vec<Cell> {
+        Arc::make_mut(&mut self.cells)
+    }
+
+    pub fn level(&self) -> &Level {
+        &self.level
+    }
+
     pub fn goals(&self) -> &Vec<Goal> {
-        &self.goals
+        &self.level.goals
+    }
+
+    pub fn floors(&self) -> &Vec<Floor> {
+        &self.level.floors
+    }
+
+    /// Returns the floor that keeps cells from moving onto the given
+    /// position in the given direction: a one-way gate or a closed door.
+    pub fn blocking_floor(&self, gpos: GlobalPos, direction: Direction) -> Option<&Floor> {
+        self.level.floors.iter().find(|floor| match &floor.kind {
+            _ if floor.gpos != gpos => false,
+            FloorKind::OneWay(directions) => !directions.contains(&direction),
+            FloorKind::Door(channel) => !self.is_door_open(*channel),
+            _ => false,
+        })
+    }
+
+    /// Returns whether the doors of the given channel are open, which is the
+    /// case while any cell rests on one of the switches of the channel.
+    ///
+    /// Since cells only move at the end of a move, this is the state left by
+    /// the previous move until the current one is done.
+    pub fn is_door_open(&self, channel: u32) -> bool {
+        self.level.floors.iter().any(|floor| {
+            floor.kind == FloorKind::Switch(channel) && self.cell_at(floor.gpos).is_some()
+        })
     }
 
     pub fn player_ids(&self) -> &Vec<usize> {
         &self.player_ids
     }
 
+    /// Returns whether the cell is one of the players.
+    pub fn is_player(&self, id: usize) -> bool {
+        self.player_flags.get(id).copied().unwrap_or(false)
+    }
+
+    /// Replaces the players, in order.
+    pub(super) fn set_player_ids(&mut self, ids: &[usize]) {
+        for &id in &self.player_ids {
+            self.player_flags[id] = false;
+        }
+        self.player_ids.clear();
+        self.player_ids.extend_from_slice(ids);
+        for &id in ids {
+            self.player_flags[id] = true;
+        }
+    }
+
+    /// Replaces the player at the given index in the player order, e.g. when
+    /// it possesses another cell.
+    pub(super) fn replace_player(&mut self, index: usize, id: usize) {
+        self.player_flags[self.player_ids[index]] = false;
+        self.player_ids[index] = id;
+        self.player_flags[id] = true;
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.level.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut GameConfig {
+        &mut Arc::make_mut(&mut self.level).config
+    }
+
+    pub fn meta(&self) -> &LevelMeta {
+        &self.level.meta
+    }
+
+    pub fn meta_mut(&mut self) -> &mut LevelMeta {
+        &mut Arc::make_mut(&mut self.level).meta
+    }
+
     pub fn cell_at(&self, gpos: GlobalPos) -> Option<&Cell> {
-        return self.cells.iter().find(|cell| cell.gpos() == gpos);
+        if gpos.container != Container::Space && !self.is_occupied(gpos) {
+            return None;
+        }
+        self.cells.iter().find(|cell| cell.gpos() == gpos)
     }
 
-    fn check_pos(&self, gpos: GlobalPos) -> Result<(), String> {
-        if gpos.block_id == usize::MAX {
-            Ok(())
-        } else {
-            let block = self.cells[gpos.block_id].block().unwrap();
-            if !block.in_bounds(gpos.pos) {
-                Err(format!("Invalid position {gpos:?}"))
-            } else if self.cell_at(gpos).is_some() {
-                Err(format!("Cell already exists at {gpos:?}"))
-            } else {
-                Ok(())
-            }
+    pub(super) fn check_pos(&self, gpos: GlobalPos) -> Result<(), String> {
+        match self.container_block(gpos) {
+            None => Ok(()),
+            Some(block) if !block.in_bounds(gpos.pos) => Err(format!("Invalid position {gpos:?}")),
+            Some(_) if self.is_occupied(gpos) => Err(format!("Cell already exists at {gpos:?}")),
+            Some(_) => Ok(()),
         }
     }
 
+    /// Returns whether the block is a plain wall when seen from outside, i.e.
+    /// it is filled, or has a border of walls and nothing inside. The result
+    /// is cached until a cell inside the block changes.
     pub fn is_block_trivial(&self, block: &Block) -> bool {
+        match self.trivial.get(block.id) {
+            Some(trivial) => *trivial.get_or_init(|| self.compute_block_trivial(block)),
+            None => self.compute_block_trivial(block),
+        }
+    }
+
+    fn compute_block_trivial(&self, block: &Block) -> bool {
         if block.filled {
             // filled blocks are trivial
             return true;
         }
 
+        // the border should be filled and the inside empty, which is
+        // cheap to check before looking at the cells
+        let occupancy = &self.occupancy[block.id];
         for x in 0..block.width {
             for y in 0..block.height {
-                let cell = self.cell_at(GlobalPos {
-                    block_id: block.id,
-                    pos: Pos(x, y),
-                });
-
-                if x == 0 || y == 0 || x == block.width - 1 || y == block.height - 1 {
-                    // the border should be filled with non-possessable walls
-                    if let Some(Cell::Wall(wall)) = cell {
-                        if wall.possessable {
-                            return false;
-                        }
-                    } else {
-                        return false;
-                    }
-                } else {
-                    // the inside should be empty
-                    if cell.is_some() {
-                        return false;
-                    }
+                let border = x == 0 || y == 0 || x == block.width - 1 || y == block.height - 1;
+                if occupancy.get(Pos(x, y)) != border {
+                    return false;
                 }
             }
         }
 
-        true
+        // the border should be made of non-possessable walls
+        self.cells.iter().all(|cell| match cell {
+            _ if !cell.gpos().is_in(block.id) => true,
+            Cell::Wall(wall) => !wall.possessable,
+            _ => false,
+        })
     }
 
     pub fn is_space(&self, block_id: usize) -> bool {
@@ -253,9 +683,34 @@ impl Game {
         }
     }
 
+    /// Returns the block the position is in, or `None` in space.
+    pub fn container_block(&self, gpos: GlobalPos) -> Option<&Block> {
+        self.cells.get(gpos.block_id()?)?.block()
+    }
+
+    /// Returns the block shown by nested views: the block containing the
+    /// first player, or the first non-trivial block if there is none.
+    pub fn view_block(&self) -> Option<&Block> {
+        self.player_ids
+            .first()
+            .and_then(|&id| self.container_block(self.cells[id].gpos()))
+            .or_else(|| {
+                self.cells
+                    .iter()
+                    .filter_map(|cell| cell.block())
+                    .find(|block| !self.is_block_trivial(block))
+            })
+    }
+
+    /// Returns whether the position is directly in the block of a space,
+    /// i.e. a cell there floats.
+    pub fn is_floating(&self, gpos: GlobalPos) -> bool {
+        gpos.block_id().is_some_and(|id| self.is_space(id))
+    }
+
     fn allocate_block_no(&self) -> BlockNo {
         let mut result = 0;
-        for cell in &self.cells {
+        for cell in self.cells.iter() {
             if let Cell::Block(block) = cell {
                 result = result.max(block.block_no.0 + 1);
             }
@@ -265,21 +720,26 @@ impl Game {
 
     pub(super) fn add_space(&mut self) -> usize {
         let id = self.cells.len();
-        self.cells.push(Cell::Block(Block {
+        let block_no = self.allocate_block_no();
+        let (width, height) = self.level.config.space_size;
+        self.push_cell(Cell::Block(Block {
             id,
             gpos: GlobalPos {
-                block_id: usize::MAX,
+                container: Container::Space,
                 pos: Pos(0, 0),
             },
-            block_no: self.allocate_block_no(),
-            width: 2 * Self::SPACE_SIZE + 1,
-            height: 2 * Self::SPACE_SIZE + 1,
+            block_no,
+            width,
+            height,
             hsv: Hsv::new(0.0, 0.0, 0.5),
+            zoom: 1.0,
             filled: false,
             space: true,
             possessable: false,
             fliph: false,
             inf_enter: None,
+            effect: SpecialEffect::None,
+            locked: false,
         }));
         id
     }
@@ -294,14 +754,14 @@ impl Game {
         if !block.can_exit() {
             return None;
         }
-        for cell in &self.cells {
-            if let Cell::Reference(reference) = cell {
-                if reference.exit && reference.target_no == block.block_no {
-                    return Some(reference.id);
-                }
-            }
+        if let Some(id) = self
+            .references_to(block.block_no)
+            .find(|reference| reference.exit)
+            .map(|reference| reference.id)
+        {
+            return Some(id);
         }
-        if block.gpos.block_id != usize::MAX {
+        if block.gpos.container != Container::Space {
             Some(block.id)
         } else {
             None
@@ -309,34 +769,144 @@ impl Game {
     }
 
     pub fn inf_exit_id_for(&self, block_no: BlockNo, degree: u32) -> Option<usize> {
-        for cell in &self.cells {
-            if let Cell::Reference(reference) = cell {
-                if reference.target_no == block_no && reference.inf_exit == Some(degree) {
-                    return Some(reference.id);
-                }
+        self.references_to(block_no)
+            .find(|reference| reference.inf_exit == Some(degree))
+            .map(|reference| reference.id)
+    }
+
+    pub fn inf_enter_id_for(&self, block: &Block, degree: u32) -> Option<usize> {
+        let ids = self.inf_enter_map.get(&block.block_no)?;
+        ids.iter()
+            .copied()
+            .find(|&id| self.cells[id].block().unwrap().inf_enter == Some((block.block_no, degree)))
+    }
+
+    /// Returns the references to the given block, in the order of their ids.
+    pub fn references_to(&self, block_no: BlockNo) -> impl Iterator<Item = &Reference> {
+        self.ref_map
+            .get(&block_no)
+            .into_iter()
+            .flatten()
+            .map(|&id| self.cells[id].reference().unwrap())
+    }
+
+    // records that the block is an infinite enter of another block
+    fn index_inf_enter(&mut self, id: usize, block_no: BlockNo) {
+        let ids = Arc::make_mut(&mut self.inf_enter_map)
+            .entry(block_no)
+            .or_default();
+        ids.insert(ids.partition_point(|&i| i < id), id);
+    }
+
+    /// Adds a cell to the game, and updates the occupied positions and the
+    /// indexes. Returns the id of the cell, which must be set to the number
+    /// of cells before.
+    pub(super) fn push_cell(&mut self, cell: Cell) -> usize {
+        let id = cell.id();
+        debug_assert_eq!(id, self.cells.len());
+
+        let occupancy = match &cell {
+            Cell::Block(block) => Occupancy::new(block.width, block.height),
+            _ => Occupancy::default(),
+        };
+        match &cell {
+            Cell::Reference(reference) => Arc::make_mut(&mut self.ref_map)
+                .entry(reference.target_no)
+                .or_default()
+                .push(id),
+            Cell::Block(Block {
+                inf_enter: Some((block_no, _)),
+                ..
+            }) => self.index_inf_enter(id, *block_no),
+            _ => (),
+        }
+
+        let gpos = cell.gpos();
+        self.cells_mut().push(cell);
+        Arc::make_mut(&mut self.occupancy).push(occupancy);
+        // the cache is missing after deserializing
+        Arc::make_mut(&mut self.trivial).resize_with(id + 1, OnceLock::new);
+        self.player_flags.push(false);
+        self.occupy(gpos, true);
+        id
+    }
+
+    /// Replaces all the cells, rebuilding the occupied positions and the
+    /// indexes.
+    pub(super) fn set_cells(&mut self, cells: impl IntoIterator<Item = Cell>) {
+        self.cells = Arc::default();
+        self.occupancy = Arc::default();
+        self.trivial = Arc::default();
+        self.ref_map = Arc::default();
+        self.inf_enter_map = Arc::default();
+        self.player_flags.clear();
+        for cell in cells {
+            self.push_cell(cell);
+        }
+        for &id in &self.player_ids {
+            if let Some(flag) = self.player_flags.get_mut(id) {
+                *flag = true;
             }
         }
-        None
+        // cells inside blocks added after them are only placed now
+        for id in 0..self.cells.len() {
+            self.occupy(self.cells[id].gpos(), true);
+        }
     }
 
-    pub fn inf_enter_id_for(&self, block: &Block, degree: u32) -> Option<usize> {
-        for cell in &self.cells {
-            if let Cell::Block(target) = cell {
-                if target.inf_enter == Some((block.block_no, degree)) {
-                    return Some(target.id);
+    /// Removes the cell added last, undoing `push_cell`.
+    pub(super) fn pop_cell(&mut self) -> Option<Cell> {
+        let cell = self.cells_mut().pop()?;
+        Arc::make_mut(&mut self.occupancy).pop();
+        Arc::make_mut(&mut self.trivial).truncate(self.cells.len());
+        self.player_flags.pop();
+        self.occupy(cell.gpos(), false);
+        match &cell {
+            Cell::Reference(reference) => {
+                let ref_map = Arc::make_mut(&mut self.ref_map);
+                if let Some(ids) = ref_map.get_mut(&reference.target_no) {
+                    ids.retain(|&id| id != reference.id);
+                    if ids.is_empty() {
+                        ref_map.remove(&reference.target_no);
+                    }
                 }
             }
+            Cell::Block(Block {
+                id,
+                inf_enter: Some((block_no, _)),
+                ..
+            }) => {
+                let inf_enter_map = Arc::make_mut(&mut self.inf_enter_map);
+                if let Some(ids) = inf_enter_map.get_mut(block_no) {
+                    ids.retain(|other| other != id);
+                    if ids.is_empty() {
+                        inf_enter_map.remove(block_no);
+                    }
+                }
+            }
+            _ => (),
         }
-        None
+        Some(cell)
     }
 
-    pub(super) fn add_inf_exit_for(&mut self, block_no: BlockNo, degree: u32) -> usize {
-        let gpos = GlobalPos {
-            block_id: self.add_space(),
-            pos: Self::SPACE_CENTER,
+    // Returns where a floating cell at the given coordinates is put: the
+    // center of a new space, or its coordinates in the shared space, which
+    // is created for the first cell.
+    fn floating_pos(&mut self, pos: Pos, shared_space: &mut Option<usize>) -> GlobalPos {
+        if !self.level.config.shared_space {
+            return GlobalPos::new(self.add_space(), self.level.config.space_center());
+        }
+        let block_id = match *shared_space {
+            Some(id) => id,
+            None => *shared_space.insert(self.add_space()),
         };
+        GlobalPos::new(block_id, pos)
+    }
+
+    pub(super) fn add_inf_exit_for(&mut self, block_no: BlockNo, degree: u32) -> usize {
+        let gpos = GlobalPos::new(self.add_space(), self.level.config.space_center());
         let id = self.cells.len();
-        self.cells.push(Cell::Reference(Reference {
+        self.push_cell(Cell::Reference(Reference {
             id,
             gpos,
             target_no: block_no,
@@ -344,33 +914,66 @@ impl Game {
             inf_exit: Some(degree),
             possessable: false,
             fliph: false,
+            rotation: 0,
+            effect: SpecialEffect::None,
         }));
         id
     }
 
     pub(super) fn add_inf_enter_for(&mut self, block_no: BlockNo, degree: u32) -> usize {
-        let gpos = GlobalPos {
-            block_id: self.add_space(),
-            pos: Self::SPACE_CENTER,
-        };
+        let gpos = GlobalPos::new(self.add_space(), self.level.config.space_center());
         let id = self.cells.len();
-        let block = self.block_by_no(block_no).unwrap();
-        self.cells.push(Cell::Block(Block {
+        let hsv = self.block_by_no(block_no).unwrap().hsv;
+        let new_block_no = self.allocate_block_no();
+        self.push_cell(Cell::Block(Block {
             id,
             gpos,
-            block_no: self.allocate_block_no(),
+            block_no: new_block_no,
             width: 5,
             height: 5,
-            hsv: block.hsv,
+            hsv,
+            zoom: 1.0,
             filled: false,
             space: false,
             possessable: false,
             fliph: false,
             inf_enter: Some((block_no, degree)),
+            effect: SpecialEffect::None,
+            locked: false,
         }));
         id
     }
 
+    /// Removes the cells synthesized for infinite exits and enters that are
+    /// no longer used, together with their spaces, and returns how many cells
+    /// were removed. A synthesized reference is unused if it is alone in its
+    /// space, and a synthesized block if it is also empty.
+    ///
+    /// Only cells at the end of `cells` are removed, so that the ids of the
+    /// other cells stay the same.
+    pub fn collect_synthesized(&mut self) -> usize {
+        let len = self.cells.len();
+        while self.cells.len() >= self.level.initial_cells + 2 {
+            let id = self.cells.len() - 1;
+            let space_id = id - 1;
+            let cell = &self.cells[id];
+            let unused = match cell {
+                Cell::Reference(reference) => reference.inf_exit.is_some(),
+                Cell::Block(block) => block.inf_enter.is_some() && self.occupancy[id].count() == 0,
+                Cell::Wall(_) => false,
+            } && self.is_space(space_id)
+                && cell.gpos().is_in(space_id)
+                && self.occupancy[space_id].count() == 1
+                && !self.is_player(id);
+            if !unused {
+                break;
+            }
+            self.pop_cell();
+            self.pop_cell();
+        }
+        len - self.cells.len()
+    }
+
     /// Parses a level file and returns a Game object.
     ///
     /// A level file is a text file containing two parts: Header and Objects,
@@ -382,27 +985,88 @@ impl Game {
     /// attempt_order push,enter,eat,possess (used in Priority area in-game with value "enter,eat,push,possess".)
     /// shed (enables Shed area behavior)
     /// inner_push (enables Inner Push area behavior)
+    /// rotation (enables the rotation field of references, see below)
+    /// gravity (cells fall down after each move)
+    /// pull (players drag the cell behind them along)
+    /// space_size 7 7 (width and height of the spaces of floating cells)
+    /// shared_space (floating cells share one space, see below)
     /// draw_style tui (Text graphics)
     /// draw_style grid (Like tui, but with blocks instead of text)
     /// draw_style oldstyle (Gallery area development graphics)
     /// custom_level_music -1 (-1 means no music)
     /// custom_level_palette -1 (-1 means no palette is applied)
+    /// title The Name of the Level
+    /// author Someone
+    /// par_moves 42 (number of moves of a good solution)
+    /// tags tutorial,recursion
+    /// property key value (custom property, stored in `LevelMeta::extra`)
     /// ```
     ///
     /// # Objects
     /// ```plain
-    /// Block x y id width height hue sat val zoomfactor fillwithwalls player possessable playerorder fliph floatinspace specialeffect
+    /// Block x y id width height hue sat val zoomfactor fillwithwalls player possessable playerorder fliph floatinspace specialeffect [locked]
     /// Ref x y id exitblock infexit infexitnum infenter infenternum infenterid player posssessable playerorder fliph floatinspace specialeffect
     /// Wall x y player possessable playerorder
-    /// Floor x y type
+    /// Floor x y type [argument]
     /// ```
+    ///
+    /// Floors of the types `Button` and `PlayerButton` are the goals, and
+    /// `OneWay <directions>` floors, e.g. `OneWay UR`, are gates that cells
+    /// can only move onto in the given directions. `Door <channel>` floors
+    /// can only be moved onto while a cell rests on a `Switch <channel>`
+    /// floor of the same channel. The other official types,
+    /// e.g. `Info <text>` or `Portal <level>`, are kept in `floors` but have
+    /// no effect.
+    ///
+    /// Objects are nested in the enclosing block with one tab per level, or
+    /// with spaces, where the first indented line sets the number of spaces
+    /// per level. Lines starting with `//` or `--` are comments, and
+    /// trailing whitespace is ignored. Lines may end with `\n`, `\r\n` or
+    /// `\r`, and a byte order mark at the start is skipped, as in files
+    /// saved by some editors.
+    ///
+    /// With the `rotation` header item, `Ref` lines may have a `rotation`
+    /// field after `specialeffect`: the number of quarter turns
+    /// counterclockwise (0 to 3) of the interior as seen through the
+    /// reference. Moves entering or exiting through the reference are turned
+    /// along with it.
+    ///
+    /// Cells floating in space are put at the center of a space of their own,
+    /// ignoring their coordinates. With the `shared_space` header item, they
+    /// are put in a single space instead, at their coordinates in it.
+    ///
+    /// The optional `locked` field of `Block` lines marks the block as locked
+    /// with a 1: it can't be pushed, pulled or eaten.
+    ///
+    /// `version 5` marks an extension of the format where blocks can be given
+    /// names in place of numbers, as in `Block x y @lobby ...`, and referred
+    /// to by them in `Ref x y @lobby ...` and the infinite enter field. Named
+    /// blocks are numbered after the largest numbered block, and the names
+    /// are kept in `LevelMeta::block_names`.
     pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let text = if text.contains('\r') {
+            Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+        } else {
+            Cow::Borrowed(text)
+        };
+
         let mut game = Game {
-            cells: Vec::new(),
-            goals: Vec::new(),
-            block_map: HashMap::new(),
+            level: Arc::new(Level {
+                goals: Vec::new(),
+                floors: Vec::new(),
+                config: GameConfig::default(),
+                meta: LevelMeta::default(),
+                initial_cells: 0,
+            }),
+            cells: Arc::new(Vec::new()),
+            block_map: Arc::new(HashMap::new()),
+            ref_map: Arc::new(HashMap::new()),
+            inf_enter_map: Arc::new(HashMap::new()),
+            occupancy: Arc::new(Vec::new()),
+            trivial: Arc::new(Vec::new()),
             player_ids: Vec::new(),
-            config: GameConfig::default(),
+            player_flags: Vec::new(),
         };
 
         // whether we're still reading the header
@@ -417,7 +1081,21 @@ impl Game {
         // (block_no, degree), target_no
         let mut inf_enter_record: Vec<((BlockNo, u32), BlockNo)> = Vec::new();
 
+        // number of spaces per level when the objects are indented with spaces
+        let mut indent_unit: Option<usize> = None;
+
+        // the space shared by floating cells, with `shared_space`
+        let mut shared_space: Option<usize> = None;
+
+        // named blocks get the numbers after the largest numbered block
+        let mut names = BlockNames::new(&text);
+
         let mut process = |line: &str| -> Result<(), String> {
+            let line = line.trim_end();
+            if line.trim_start().starts_with("//") || line.trim_start().starts_with("--") {
+                return Ok(());
+            }
+
             if line == "#" {
                 reading_header = false;
                 return Ok(());
@@ -429,15 +1107,14 @@ impl Game {
                     return Ok(());
                 }
                 match parts[0] {
-                    "version" => {
-                        let version = parts[1];
-                        if version != "4" {
-                            return Err(format!("Unsupported version: {version}"));
-                        }
-                    }
+                    "version" => match parts.get(1).copied().unwrap_or("") {
+                        "4" => (),
+                        "5" => names.enabled = true,
+                        version => return Err(format!("Unsupported version: {version}")),
+                    },
                     "attempt_order" => {
                         let mut attempt_order = Vec::new();
-                        for part in parts[1].split(',') {
+                        for part in parts.get(1).copied().unwrap_or("").split(',') {
                             match part {
                                 "push" => attempt_order.push(ActionType::Push),
                                 "enter" => attempt_order.push(ActionType::Enter),
@@ -446,13 +1123,65 @@ impl Game {
                                 _ => return Err(format!("Unknown attempt order {part}")),
                             }
                         }
-                        game.config.attempt_order = attempt_order;
+                        game.config_mut().attempt_order = attempt_order;
                     }
                     "shed" => {
-                        game.config.shed = true;
+                        game.config_mut().shed = true;
                     }
                     "inner_push" => {
-                        game.config.inner_push = true;
+                        game.config_mut().inner_push = true;
+                    }
+                    "rotation" => {
+                        game.config_mut().rotation = true;
+                    }
+                    "gravity" => {
+                        game.config_mut().gravity = true;
+                    }
+                    "pull" => {
+                        game.config_mut().pull = true;
+                    }
+                    "space_size" => {
+                        let width = parse_field::<i32>(&parts, 1)?;
+                        let height = parse_field::<i32>(&parts, 2)?;
+                        if width <= 0 || height <= 0 {
+                            return Err(format!("Invalid space size: {width}x{height}"));
+                        }
+                        game.config_mut().space_size = (width, height);
+                    }
+                    "shared_space" => {
+                        game.config_mut().shared_space = true;
+                    }
+                    "title" | "author" => {
+                        let value = line.trim_start()[parts[0].len()..].trim();
+                        let meta = game.meta_mut();
+                        let field = match parts[0] {
+                            "title" => &mut meta.title,
+                            _ => &mut meta.author,
+                        };
+                        *field = Some(value.to_string());
+                    }
+                    "par_moves" => {
+                        let par_moves = parse_field::<usize>(&parts, 1)?;
+                        game.meta_mut().par_moves = Some(par_moves);
+                    }
+                    "tags" => {
+                        let tags = parts[1..]
+                            .iter()
+                            .flat_map(|part| part.split(','))
+                            .filter(|tag| !tag.is_empty())
+                            .map(str::to_string);
+                        game.meta_mut().tags.extend(tags);
+                    }
+                    "property" => {
+                        let Some(key) = parts.get(1) else {
+                            return Err("Missing property key".to_string());
+                        };
+                        // the value is the rest of the line, which may contain spaces
+                        let rest = line.trim_start()["property".len()..].trim_start();
+                        let value = rest[key.len()..].trim();
+                        game.meta_mut()
+                            .extra
+                            .insert(key.to_string(), value.to_string());
                     }
                     _ => {}
                 }
@@ -464,16 +1193,34 @@ impl Game {
                 return Ok(());
             }
 
-            let depth = line.chars().take_while(|c| *c == '\t').count();
+            let tabs = line.chars().take_while(|c| *c == '\t').count();
+            let spaces = line.chars().take_while(|c| *c == ' ').count();
+            let depth = if spaces == 0 {
+                tabs
+            } else {
+                // the first indented line decides how many spaces make a level
+                let unit = *indent_unit.get_or_insert(spaces);
+                if spaces % unit != 0 {
+                    return Err(format!(
+                        "Indentation of {spaces} spaces is not a multiple of {unit}, as used above"
+                    ));
+                }
+                spaces / unit
+            };
+            if line[tabs.max(spaces)..].starts_with([' ', '\t']) {
+                return Err("Indentation mixes tabs and spaces".to_string());
+            }
             if depth > stack.len() {
-                return Err(format!("Invalid indentation {depth}"));
+                return Err(format!(
+                    "Indented {depth} levels, but only {} blocks are open",
+                    stack.len()
+                ));
             }
             stack.truncate(depth);
 
-            let parent_id = stack.last().map_or(usize::MAX, |id| *id);
-
-            // println!("{:3} | {}", lineno + 1, line);
-            // println!("depth = {}, parent_id = {}", depth, parent_id);
+            let container = stack
+                .last()
+                .map_or(Container::Space, |id| Container::Block(CellId(*id)));
 
             match parts[0] {
                 "Block" => {
@@ -484,63 +1231,66 @@ impl Game {
                         ));
                     }
 
-                    let x = parts[1].parse::<i32>().unwrap();
-                    let y = parts[2].parse::<i32>().unwrap();
-                    let block_no = BlockNo(parts[3].parse::<i32>().unwrap());
-                    let width = parts[4].parse::<i32>().unwrap();
-                    let height = parts[5].parse::<i32>().unwrap();
+                    let x = parse_field::<i32>(&parts, 1)?;
+                    let y = parse_field::<i32>(&parts, 2)?;
+                    let block_no = names.field(&parts, 3)?;
+                    let width = parse_field::<i32>(&parts, 4)?;
+                    let height = parse_field::<i32>(&parts, 5)?;
 
-                    let hue = parts[6].parse::<f64>().unwrap();
-                    let sat = parts[7].parse::<f64>().unwrap();
-                    let val = parts[8].parse::<f64>().unwrap();
+                    let hue = parse_field::<f64>(&parts, 6)?;
+                    let sat = parse_field::<f64>(&parts, 7)?;
+                    let val = parse_field::<f64>(&parts, 8)?;
+                    let zoom = parse_field::<f64>(&parts, 9)?;
 
                     let filled = parts[10] == "1";
                     let player_order = if parts[11] == "1" {
-                        Some(parts[13].parse::<i32>().unwrap())
+                        Some(parse_field::<i32>(&parts, 13)?)
                     } else {
                         None
                     };
                     let possessable = parts[12] == "1";
                     let fliph = parts[14] == "1";
                     let floating = parts[15] == "1";
+                    let effect = SpecialEffect::parse(parts[16])?;
+                    let locked = parts.get(17) == Some(&"1");
 
                     if !filled && (width <= 0 || height <= 0) {
-                        panic!("Invalid block size: {width}x{height}");
+                        return Err(format!("Invalid block size: {width}x{height}"));
                     }
 
                     let gpos = if floating {
-                        GlobalPos {
-                            block_id: game.add_space(),
-                            pos: Self::SPACE_CENTER,
-                        }
+                        game.floating_pos(Pos(x, y), &mut shared_space)
                     } else {
                         GlobalPos {
-                            block_id: parent_id,
+                            container,
                             pos: Pos(x, y),
                         }
                     };
                     game.check_pos(gpos)?;
 
                     let id = game.cells.len();
-                    game.cells.push(Cell::Block(Block {
+                    game.push_cell(Cell::Block(Block {
                         id,
                         gpos,
                         block_no,
                         width,
                         height,
                         hsv: Hsv::new(360.0 * hue, sat, val),
+                        zoom,
                         filled,
                         space: false,
                         possessable,
                         fliph,
                         inf_enter: None,
+                        effect,
+                        locked,
                     }));
 
                     if let Some(i) = player_order {
                         players.push((i, id));
                     }
 
-                    game.block_map.insert(block_no, id);
+                    Arc::make_mut(&mut game.block_map).insert(block_no, id);
 
                     stack.push(id);
                 }
@@ -553,47 +1303,54 @@ impl Game {
                         ));
                     }
 
-                    let x = parts[1].parse::<i32>().unwrap();
-                    let y = parts[2].parse::<i32>().unwrap();
-                    let target_no = BlockNo(parts[3].parse::<i32>().unwrap());
+                    let x = parse_field::<i32>(&parts, 1)?;
+                    let y = parse_field::<i32>(&parts, 2)?;
+                    let target_no = names.field(&parts, 3)?;
 
                     let mut exit = parts[4] == "1";
                     let mut inf_exit = None;
 
                     if parts[5] == "1" {
-                        let degree = parts[6].parse::<u32>().unwrap();
+                        let degree = parse_field::<u32>(&parts, 6)?;
                         exit = false; // inf exits don't serve as an exit
                         inf_exit = Some(degree);
                     } else if parts[7] == "1" {
-                        let degree = parts[8].parse::<u32>().unwrap();
-                        let block_no = BlockNo(parts[9].parse::<i32>().unwrap());
+                        let degree = parse_field::<u32>(&parts, 8)?;
+                        let block_no = names.field(&parts, 9)?;
                         inf_enter_record.push(((block_no, degree), target_no));
                     }
 
                     let player_order = if parts[10] == "1" {
-                        Some(parts[12].parse::<i32>().unwrap())
+                        Some(parse_field::<i32>(&parts, 12)?)
                     } else {
                         None
                     };
                     let possessable = parts[11] == "1";
                     let fliph = parts[13] == "1";
                     let floating = parts[14] == "1";
+                    let effect = SpecialEffect::parse(parts[15])?;
+                    // the rotation is an extra field, only read when enabled
+                    let rotation = if game.level.config.rotation && parts.len() > 16 {
+                        match parse_field::<u8>(&parts, 16)? {
+                            rotation @ 0..=3 => rotation,
+                            rotation => return Err(format!("Invalid rotation {rotation}")),
+                        }
+                    } else {
+                        0
+                    };
 
                     let gpos = if floating {
-                        GlobalPos {
-                            block_id: game.add_space(),
-                            pos: Self::SPACE_CENTER,
-                        }
+                        game.floating_pos(Pos(x, y), &mut shared_space)
                     } else {
                         GlobalPos {
-                            block_id: parent_id,
+                            container,
                             pos: Pos(x, y),
                         }
                     };
                     game.check_pos(gpos)?;
 
                     let id = game.cells.len();
-                    game.cells.push(Cell::Reference(Reference {
+                    game.push_cell(Cell::Reference(Reference {
                         id,
                         gpos,
                         target_no,
@@ -601,6 +1358,8 @@ impl Game {
                         inf_exit,
                         possessable,
                         fliph,
+                        rotation,
+                        effect,
                     }));
 
                     if let Some(i) = player_order {
@@ -616,28 +1375,28 @@ impl Game {
                         ));
                     }
 
-                    let x = parts[1].parse::<i32>().unwrap();
-                    let y = parts[2].parse::<i32>().unwrap();
+                    let x = parse_field::<i32>(&parts, 1)?;
+                    let y = parse_field::<i32>(&parts, 2)?;
 
                     let player_order = if parts[3] == "1" {
-                        Some(parts[5].parse::<i32>().unwrap())
+                        Some(parse_field::<i32>(&parts, 5)?)
                     } else {
                         None
                     };
                     let possessable = parts[4] == "1";
 
-                    if parent_id == usize::MAX {
+                    if container == Container::Space {
                         return Err("Wall outside of block".to_string());
                     }
 
                     let gpos = GlobalPos {
-                        block_id: parent_id,
+                        container,
                         pos: Pos(x, y),
                     };
                     game.check_pos(gpos)?;
 
                     let id = game.cells.len();
-                    game.cells.push(Cell::Wall(Wall {
+                    game.push_cell(Cell::Wall(Wall {
                         id,
                         gpos,
                         possessable,
@@ -657,22 +1416,20 @@ impl Game {
                         ));
                     }
 
-                    let x = parts[1].parse::<i32>().unwrap();
-                    let y = parts[2].parse::<i32>().unwrap();
+                    let x = parse_field::<i32>(&parts, 1)?;
+                    let y = parse_field::<i32>(&parts, 2)?;
 
-                    let player = match parts[3] {
-                        "Button" => false,
-                        "PlayerButton" => true,
-                        _ => return Err(format!("Unknown floor type {}", parts[3])),
+                    let kind = FloorKind::parse(&parts[3..])?;
+                    let gpos = GlobalPos {
+                        container,
+                        pos: Pos(x, y),
                     };
 
-                    game.goals.push(Goal {
-                        gpos: GlobalPos {
-                            block_id: parent_id,
-                            pos: Pos(x, y),
-                        },
-                        player,
-                    });
+                    let level = Arc::make_mut(&mut game.level);
+                    if let Some(player) = kind.goal() {
+                        level.goals.push(Goal { gpos, player });
+                    }
+                    level.floors.push(Floor { gpos, kind });
                 }
 
                 _ => return Err(format!("Unknown object type {}", parts[0])),
@@ -686,10 +1443,13 @@ impl Game {
         }
 
         // check if all block_no are valid
-        for cell in &game.cells {
+        for cell in game.cells.iter() {
             if let Cell::Reference(reference) = cell {
                 if !game.block_map.contains_key(&reference.target_no) {
-                    return Err(format!("Invalid reference target {}", reference.target_no));
+                    return Err(format!(
+                        "Invalid reference target {}",
+                        names.label(reference.target_no)
+                    ));
                 }
             }
         }
@@ -699,41 +1459,144 @@ impl Game {
             let block_id = *game
                 .block_map
                 .get(&target_no)
-                .ok_or_else(|| format!("Invalid inf enter target {target_no}"))?;
-            let block = game.cells[block_id].block_mut().unwrap();
+                .ok_or_else(|| format!("Invalid inf enter target {}", names.label(target_no)))?;
+            let block = game.cells_mut()[block_id].block_mut().unwrap();
             block.inf_enter = Some(inf_enter);
+            game.index_inf_enter(block_id, inf_enter.0);
         }
 
         // sort players by order
         players.sort_by_key(|(i, _)| *i);
-        game.player_ids.extend(players.iter().map(|(_, id)| *id));
+        let player_ids = players.iter().map(|(_, id)| *id).collect::<Vec<_>>();
+        game.set_player_ids(&player_ids);
+        Arc::make_mut(&mut game.level).initial_cells = game.cells.len();
+
+        game.meta_mut().block_names = names
+            .numbers
+            .into_iter()
+            .map(|(name, block_no)| (block_no, name))
+            .collect();
 
         Ok(game)
     }
 
-    pub fn play(&mut self, direction: Direction) {
-        use super::simulation::Simulator;
+    /// Plays one input: every player moves in the given direction in turn.
+    pub fn play(&mut self, direction: Direction) -> MoveOutcome {
         let mut simulator = Simulator::new(self);
-        simulator.play(direction);
+        simulator.play(direction)
+    }
+
+    /// Plays one input like `play`, reusing the buffers of an earlier call,
+    /// which makes it cheaper to play many moves e.g. in solvers. The outcome
+    /// is kept in the buffers until the next call.
+    pub fn play_with<'a>(
+        &mut self,
+        direction: Direction,
+        buffers: &'a mut SimulatorBuffers,
+    ) -> &'a MoveOutcome {
+        let mut outcome = buffers.take_outcome();
+        let mut simulator = Simulator::with_buffers(self, buffers);
+        simulator.play_into(direction, &mut outcome);
+        simulator.release(buffers);
+        buffers.put_outcome(outcome)
+    }
+
+    /// Returns the directions in which playing would change the state.
+    pub fn legal_moves(&self) -> Vec<Direction> {
+        Direction::ALL
+            .into_iter()
+            .filter(|direction| self.clone().play(*direction).changed())
+            .collect()
+    }
+
+    /// Returns the number of goals that are satisfied: block goals covered by
+    /// a non-player cell other than a wall, and player goals covered by a
+    /// player.
+    pub fn satisfied_goals(&self) -> usize {
+        self.level
+            .goals
+            .iter()
+            .filter(|goal| self.goal_satisfied(goal))
+            .count()
+    }
+
+    /// Whether the goal is covered by the right kind of cell, see
+    /// `satisfied_goals`.
+    pub fn goal_satisfied(&self, goal: &Goal) -> bool {
+        match self.cell_at(goal.gpos) {
+            Some(cell) => !cell.is_wall() && self.is_player(cell.id()) == goal.player,
+            None => false,
+        }
     }
 
     pub fn won(&self) -> bool {
-        for goal in &self.goals {
-            let cell = self.cell_at(goal.gpos);
-            if cell.is_none() {
-                return false;
-            }
+        !self.level.goals.is_empty() && self.satisfied_goals() == self.level.goals.len()
+    }
+}
 
-            let cell = cell.unwrap();
-            if cell.is_wall() {
-                return false;
-            }
+// the block names of a version 5 level file, and the numbers given to them
+struct BlockNames {
+    enabled: bool,
+    numbers: HashMap<String, BlockNo>,
+    next: BlockNo,
+}
 
-            let player = self.player_ids.contains(&cell.id());
-            if player != goal.player {
-                return false;
-            }
+impl BlockNames {
+    fn new(text: &str) -> Self {
+        let largest = text
+            .lines()
+            .filter_map(|line| {
+                let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
+                match parts[..] {
+                    ["Block", _, _, no, ..] => no.parse::<i32>().ok(),
+                    _ => None,
+                }
+            })
+            .max()
+            .unwrap_or(-1);
+        Self {
+            enabled: false,
+            numbers: HashMap::new(),
+            next: BlockNo(largest + 1),
+        }
+    }
+
+    // parses a field with a block number or `@name`, giving new names the
+    // next free number
+    fn field(&mut self, parts: &[&str], index: usize) -> Result<BlockNo, String> {
+        let Some(name) = parts[index].strip_prefix('@') else {
+            return parse_field::<i32>(parts, index).map(BlockNo);
+        };
+        if !self.enabled {
+            return Err(format!("Block name @{name} requires version 5"));
+        }
+        if name.is_empty() {
+            return Err(format!("Empty block name in field {index} of {}", parts[0]));
+        }
+
+        let next = &mut self.next;
+        let block_no = *self.numbers.entry(name.to_string()).or_insert_with(|| {
+            let block_no = *next;
+            next.0 += 1;
+            block_no
+        });
+        Ok(block_no)
+    }
+
+    fn label(&self, block_no: BlockNo) -> String {
+        match self.numbers.iter().find(|(_, no)| **no == block_no) {
+            Some((name, _)) => format!("@{name}"),
+            None => block_no.to_string(),
         }
-        !self.goals.is_empty()
     }
 }
+
+// parses a field of an object line, naming the field when it's invalid
+fn parse_field<T: std::str::FromStr>(parts: &[&str], index: usize) -> Result<T, String> {
+    parts[index].parse().map_err(|_| {
+        format!(
+            "Invalid value {} in field {index} of {}",
+            parts[index], parts[0]
+        )
+    })
+}
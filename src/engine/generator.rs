@@ -0,0 +1,940 @@
+use std::collections::HashSet;
+
+use super::difficulty::{mechanic_usage, MechanicUsage};
+use super::game::*;
+use super::solver::*;
+use super::template::Template;
+use super::utility::*;
+use super::validation::Severity;
+
+/// Number of levels generated for a daily puzzle before giving up.
+pub const DAILY_LEVELS: u64 = 10;
+
+/// The mechanics that generated levels may use, besides pushing filled
+/// blocks around.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mechanics {
+    /// The blocks are hollow, with walls inside, and can be entered. Goals
+    /// may be inside them.
+    pub enter: bool,
+    /// A reference to the room or to a hollow block is added, in the room
+    /// or inside the block it refers to, which leaving the block then goes
+    /// through infinitely.
+    pub references: bool,
+    /// A possessable block is added next to the blocks to push.
+    pub possession: bool,
+    /// The player has a goal of its own.
+    pub player_goal: bool,
+}
+
+impl Mechanics {
+    pub const NAMES: [&'static str; 4] = ["enter", "references", "possession", "player-goal"];
+
+    /// Parses a comma-separated list of the names in `NAMES`.
+    pub fn parse(list: &str) -> Result<Mechanics, String> {
+        let mut mechanics = Mechanics::default();
+        for name in list.split(',').filter(|name| !name.is_empty()) {
+            match name {
+                "enter" => mechanics.enter = true,
+                "references" => mechanics.references = true,
+                "possession" => mechanics.possession = true,
+                "player-goal" => mechanics.player_goal = true,
+                _ => {
+                    return Err(format!(
+                        "Unknown mechanic {name}, expected some of {}",
+                        Mechanics::NAMES.join(", ")
+                    ))
+                }
+            }
+        }
+        Ok(mechanics)
+    }
+}
+
+/// A mechanic that the solution of a generated level may use, see
+/// `Constraint`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mechanic {
+    Push,
+    Enter,
+    Exit,
+    Possession,
+    Flip,
+    InfEnter,
+    InfExit,
+}
+
+impl Mechanic {
+    pub const NAMES: [&'static str; 7] = [
+        "push",
+        "enter",
+        "exit",
+        "possession",
+        "flip",
+        "inf-enter",
+        "inf-exit",
+    ];
+    const ALL: [Mechanic; 7] = [
+        Mechanic::Push,
+        Mechanic::Enter,
+        Mechanic::Exit,
+        Mechanic::Possession,
+        Mechanic::Flip,
+        Mechanic::InfEnter,
+        Mechanic::InfExit,
+    ];
+
+    /// Returns the mechanic with the name, in `NAMES`.
+    pub fn from_name(name: &str) -> Result<Mechanic, String> {
+        let index = Mechanic::NAMES
+            .iter()
+            .position(|&known| known == name)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown mechanic {name}, expected one of {}",
+                    Mechanic::NAMES.join(", ")
+                )
+            })?;
+        Ok(Mechanic::ALL[index])
+    }
+
+    pub fn name(self) -> &'static str {
+        let index = Mechanic::ALL.iter().position(|&m| m == self).unwrap();
+        Mechanic::NAMES[index]
+    }
+
+    /// Returns how often the mechanic is used.
+    pub fn count(self, usage: &MechanicUsage) -> usize {
+        match self {
+            Mechanic::Push => usage.pushes,
+            Mechanic::Enter => usage.enters,
+            Mechanic::Exit => usage.exits,
+            Mechanic::Possession => usage.possessions,
+            Mechanic::Flip => usage.flips,
+            Mechanic::InfEnter => usage.inf_enters,
+            Mechanic::InfExit => usage.inf_exits,
+        }
+    }
+}
+
+/// A requirement on generated levels, e.g. for generating a pack with a
+/// theme. The mechanics used are found by playing the solution, so the
+/// constraints only reject levels; the `Mechanics` of the configuration
+/// should make levels meeting them possible.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Constraint {
+    /// The solution uses the mechanic at least once.
+    Require(Mechanic),
+    /// The solution never uses the mechanic.
+    Forbid(Mechanic),
+    /// The level has exactly this many players, which the generator places.
+    Players(usize),
+}
+
+impl Constraint {
+    /// Parses a comma-separated list of constraints: the name of a mechanic
+    /// in `Mechanic::NAMES` to require it, `no-` and the name to forbid it,
+    /// or `players=<n>`.
+    pub fn parse_list(list: &str) -> Result<Vec<Constraint>, String> {
+        list.split(',')
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                if let Some(players) = item.strip_prefix("players=") {
+                    let players = players
+                        .parse()
+                        .map_err(|_| format!("Invalid number of players {players}"))?;
+                    return Ok(Constraint::Players(players));
+                }
+                match item.strip_prefix("no-") {
+                    Some(name) => Ok(Constraint::Forbid(Mechanic::from_name(name)?)),
+                    None => Ok(Constraint::Require(Mechanic::from_name(item)?)),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns whether a level with the given number of players, whose
+    /// solution uses the mechanics as given, meets the constraint.
+    pub fn met(self, players: usize, usage: &MechanicUsage) -> bool {
+        match self {
+            Constraint::Require(mechanic) => mechanic.count(usage) > 0,
+            Constraint::Forbid(mechanic) => mechanic.count(usage) == 0,
+            Constraint::Players(count) => players == count,
+        }
+    }
+}
+
+/// How `generate` makes sure that levels can be won.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Method {
+    /// Places the cells and goals at random, and runs the solver on each
+    /// random level until it wins one.
+    #[default]
+    Random,
+    /// Starts from a solved level, with the blocks on their goals, and plays
+    /// up to the given number of random moves backwards: walking, pulling
+    /// cells, and leaving and entering blocks the other way around. Each
+    /// move is checked by playing it forwards, so the moves played forwards
+    /// solve the level by construction. They are rarely a shortest solution,
+    /// so the solver is still run for one, and the moves are only kept when
+    /// it runs out of budget.
+    Reverse { steps: usize },
+}
+
+#[derive(Clone, Debug)]
+pub struct GeneratorConfig {
+    /// Seed of the random choices. The same configuration always generates
+    /// the same level.
+    pub seed: u64,
+    /// Size of the room the level takes place in, including the walls
+    /// around it.
+    pub width: i32,
+    pub height: i32,
+    /// Number of blocks to push onto goals.
+    pub blocks: usize,
+    /// Number of walls placed in the room, besides the ones around it.
+    pub walls: usize,
+    pub mechanics: Mechanics,
+    pub method: Method,
+    /// Requirements that the levels must meet, besides being won.
+    pub constraints: Vec<Constraint>,
+    /// Levels solved in fewer moves are rejected as too easy.
+    pub min_moves: usize,
+    /// Number of random levels tried before giving up.
+    pub attempts: usize,
+    /// The search run on each random level, whose budget bounds the
+    /// difficulty of the levels found.
+    pub solver: SolverConfig,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            width: 7,
+            height: 7,
+            blocks: 2,
+            walls: 4,
+            mechanics: Mechanics::default(),
+            method: Method::Random,
+            constraints: Vec::new(),
+            min_moves: 6,
+            attempts: 1000,
+            solver: SolverConfig {
+                max_nodes: 20_000,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// A day of the proleptic Gregorian calendar, which the daily puzzle is
+/// keyed by.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Parses a date in the form `YYYY-MM-DD`.
+    pub fn parse(text: &str) -> Result<Date, String> {
+        let invalid = || format!("Invalid date {text}, expected YYYY-MM-DD");
+        let mut parts = text.splitn(3, '-');
+        let mut part = || parts.next().and_then(|part| part.parse().ok());
+        let (Some(year), Some(month), Some(day)) = (part(), part(), part()) else {
+            return Err(invalid());
+        };
+        let date = Date {
+            year: year as i32,
+            month,
+            day,
+        };
+        // days past the end of the month roll over into the next one
+        if month == 0 || day == 0 || Date::from_unix_days(date.unix_days()) != date {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+
+    /// Returns the date the number of days after 1970-01-01.
+    pub fn from_unix_days(days: i64) -> Date {
+        // see http://howardhinnant.github.io/date_algorithms.html
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Date {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// Returns the number of days after 1970-01-01.
+    pub fn unix_days(self) -> i64 {
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let month = i64::from(self.month);
+        let month = if month > 2 { month - 3 } else { month + 9 };
+        let day_of_year = (153 * month + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    /// The configuration of the daily puzzle of the date. The mechanics
+    /// change from day to day, and the levels are played backwards from a
+    /// solved level, so that they are generated quickly.
+    pub fn daily_config(self) -> GeneratorConfig {
+        let mechanics = match self.unix_days().rem_euclid(4) {
+            0 => Mechanics::default(),
+            1 => Mechanics {
+                enter: true,
+                ..Default::default()
+            },
+            2 => Mechanics {
+                possession: true,
+                player_goal: true,
+                ..Default::default()
+            },
+            _ => Mechanics {
+                enter: true,
+                player_goal: true,
+                ..Default::default()
+            },
+        };
+        GeneratorConfig {
+            seed: (self.year as u64) * 10_000 + u64::from(self.month) * 100 + u64::from(self.day),
+            walls: 5,
+            mechanics,
+            method: Method::Reverse { steps: 300 },
+            min_moves: 10,
+            ..Default::default()
+        }
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// A small random change to a level, made by `variants`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mutation {
+    /// A wall inside a block moves to an empty position of the same block.
+    MoveWall,
+    /// A goal moves to a position of the same block without a floor or a
+    /// wall.
+    MoveGoal,
+    /// A hollow block inside another block grows or shrinks by one cell in
+    /// width or height, keeping the cells inside it.
+    ResizeBlock,
+}
+
+impl Mutation {
+    pub const ALL: [Mutation; 3] = [
+        Mutation::MoveWall,
+        Mutation::MoveGoal,
+        Mutation::ResizeBlock,
+    ];
+}
+
+#[derive(Clone, Debug)]
+pub struct VariantConfig {
+    /// Seed of the random choices. The same configuration always makes the
+    /// same variants of a level.
+    pub seed: u64,
+    /// Number of variants to make.
+    pub count: usize,
+    /// Largest number of mutations made to the level for each variant.
+    pub mutations: usize,
+    /// Variants solved in fewer moves are rejected as too easy.
+    pub min_moves: usize,
+    /// Number of mutated levels tried before giving up.
+    pub attempts: usize,
+    /// The search run on each mutated level.
+    pub solver: SolverConfig,
+}
+
+impl Default for VariantConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            count: 5,
+            mutations: 2,
+            min_moves: 6,
+            attempts: 1000,
+            solver: SolverConfig {
+                max_nodes: 100_000,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl GeneratorConfig {
+    /// The number of players of the levels, 1 unless a constraint asks for
+    /// another number.
+    pub fn players(&self) -> usize {
+        self.constraints
+            .iter()
+            .rev()
+            .find_map(|constraint| match constraint {
+                Constraint::Players(count) => Some(*count),
+                _ => None,
+            })
+            .unwrap_or(1)
+    }
+}
+
+/// A generated level, with the solution found for it.
+#[derive(Clone, Debug)]
+pub struct GeneratedLevel {
+    pub game: Game,
+    pub solution: Vec<Direction>,
+    /// Number of random levels tried, including this one.
+    pub attempts: usize,
+}
+
+/// Generates the daily puzzle of the date, which is the same for everyone
+/// on that day, with the same version of the generator. The level is titled
+/// with the date, and its par is the number of moves of a shortest solution.
+///
+/// The generator keeps the moves played backwards when its search runs out
+/// of budget, so each level is searched again with a larger one, and another
+/// level is generated if that search doesn't finish, or finds a solution
+/// shorter than `min_moves`. Fails if none of `DAILY_LEVELS` levels is kept.
+pub fn daily(date: Date) -> Result<GeneratedLevel, String> {
+    let mut config = date.daily_config();
+    let seed = config.seed;
+    let mut attempts = 0;
+    for retry in 0..DAILY_LEVELS {
+        config.seed = seed ^ (retry << 32);
+        let mut generated = generate(&config)?;
+        attempts += generated.attempts;
+        let Some(solution) = solve(&generated.game, &SolverConfig::default()).solution else {
+            continue;
+        };
+        if solution.len() < config.min_moves {
+            continue;
+        }
+        generated.solution = solution;
+        generated.attempts = attempts;
+        let meta = generated.game.meta_mut();
+        meta.title = Some(format!("Daily puzzle {date}"));
+        meta.par_moves = Some(generated.solution.len());
+        return Ok(generated);
+    }
+    Err(format!(
+        "No daily puzzle with a shortest solution of at least {} moves found for {date}",
+        config.min_moves
+    ))
+}
+
+/// Makes up to `count` variants of a level, each with a few random
+/// mutations, which the solver wins in at least `min_moves` moves, e.g. for
+/// a practice set from a single good puzzle. Variants with errors found by
+/// `Game::validate`, and variants that are the same as the level or as
+/// another variant up to symmetry, see `Game::canonical_form`, are skipped.
+///
+/// Fails if no variant is found within the attempts; otherwise there may be
+/// fewer variants than asked for.
+pub fn variants(game: &Game, config: &VariantConfig) -> Result<Vec<GeneratedLevel>, String> {
+    let mut rng = Rng::new(config.seed);
+    let mut seen = HashSet::from([game.canonical_form()]);
+    let mut variants = Vec::new();
+    for attempt in 1..=config.attempts {
+        if variants.len() == config.count {
+            break;
+        }
+        let mut variant = game.clone();
+        for _ in 0..1 + rng.below(config.mutations.max(1)) {
+            let mutation = Mutation::ALL[rng.below(Mutation::ALL.len())];
+            // a mutation that can't be made leaves the level as it was
+            let _ = mutate(&mut variant, mutation, &mut rng);
+        }
+        if !seen.insert(variant.canonical_form()) || !valid(&variant) {
+            continue;
+        }
+        if let Some(solution) = solve(&variant, &config.solver).solution {
+            if solution.len() >= config.min_moves {
+                variants.push(GeneratedLevel {
+                    game: variant,
+                    solution,
+                    attempts: attempt,
+                });
+            }
+        }
+    }
+    if variants.is_empty() {
+        return Err(format!("No variant found in {} attempts", config.attempts));
+    }
+    Ok(variants)
+}
+
+/// Generates a level that can be won in at least `min_moves` moves, trying
+/// levels made with the method of the configuration until one meeting the
+/// constraints is found. Levels with errors found by `Game::validate` are
+/// skipped.
+pub fn generate(config: &GeneratorConfig) -> Result<GeneratedLevel, String> {
+    let room = Template::Room {
+        width: config.width,
+        height: config.height,
+    };
+    // fails early on sizes the room can't have
+    room.level()?;
+    if config.blocks == 0 && !config.mechanics.player_goal {
+        return Err("The level needs blocks or a player goal".to_string());
+    }
+    if config.players() == 0 {
+        return Err("The level needs a player".to_string());
+    }
+    for &constraint in &config.constraints {
+        if let Constraint::Require(mechanic) = constraint {
+            if config.constraints.contains(&Constraint::Forbid(mechanic)) {
+                return Err(format!(
+                    "The {} mechanic is both required and forbidden",
+                    mechanic.name()
+                ));
+            }
+        }
+    }
+
+    let mut rng = Rng::new(config.seed);
+    for attempt in 1..=config.attempts {
+        let found = match config.method {
+            Method::Random => random_level(config, &mut rng).and_then(|game| {
+                let solution = valid(&game).then(|| solve(&game, &config.solver).solution)??;
+                Some((game, solution))
+            }),
+            Method::Reverse { steps } => solved_level(config, &mut rng)
+                .and_then(|game| play_backwards(game, steps, &mut rng))
+                .filter(|(game, _)| valid(game))
+                .map(|(game, moves)| {
+                    let solution = solve(&game, &config.solver).solution.unwrap_or(moves);
+                    (game, solution)
+                }),
+        };
+        if let Some((game, solution)) = found {
+            let usage = mechanic_usage(&game, &solution);
+            let players = game.player_ids().len();
+            if solution.len() >= config.min_moves
+                && config
+                    .constraints
+                    .iter()
+                    .all(|constraint| constraint.met(players, &usage))
+            {
+                return Ok(GeneratedLevel {
+                    game,
+                    solution,
+                    attempts: attempt,
+                });
+            }
+        }
+    }
+    Err(format!(
+        "No level solved in at least {} moves meeting the constraints found in {} attempts",
+        config.min_moves, config.attempts
+    ))
+}
+
+// places the cells and goals of a level at random, or returns None if they
+// don't fit
+fn random_level(config: &GeneratorConfig, rng: &mut Rng) -> Option<Game> {
+    let mechanics = config.mechanics;
+    let mut game = Template::Room {
+        width: config.width,
+        height: config.height,
+    }
+    .level()
+    .ok()?;
+    let room = game.block_by_no(BlockNo(0))?.id;
+
+    for _ in 0..config.walls {
+        let gpos = rng.empty_pos(&game, room)?;
+        game.add_wall(Wall::new(gpos)).ok()?;
+    }
+    for _ in 0..config.players() {
+        game.add_player(rng.empty_pos(&game, room)?).ok()?;
+    }
+
+    let mut hollow = Vec::new();
+    for _ in 0..config.blocks {
+        let gpos = rng.empty_pos(&game, room)?;
+        let mut block = Block::new(gpos, game.next_block_no(), 1, 1, game.new_block_hsv());
+        if mechanics.enter {
+            block.width = 3 + rng.below(3) as i32;
+            block.height = 3 + rng.below(3) as i32;
+        } else {
+            block.filled = true;
+        }
+        let id = game.add_block(block).ok()?;
+        if mechanics.enter {
+            // a wall in some of the blocks, which rarely closes them off
+            if rng.below(2) == 0 {
+                game.add_wall(Wall::new(rng.empty_pos(&game, id)?)).ok()?;
+            }
+            hollow.push(id);
+        }
+    }
+    if mechanics.possession {
+        let gpos = rng.empty_pos(&game, room)?;
+        let mut block = Block::new(gpos, game.next_block_no(), 1, 1, game.new_block_hsv());
+        block.filled = true;
+        block.possessable = true;
+        game.add_block(block).ok()?;
+    }
+    if mechanics.references {
+        // the first reference to a block is its exit
+        let targets = [room]
+            .into_iter()
+            .chain(hollow.iter().copied())
+            .collect::<Vec<_>>();
+        let target_id = targets[rng.below(targets.len())];
+        let target_no = game.cells()[target_id].block()?.block_no;
+        let exit = game
+            .references_to(target_no)
+            .all(|reference| !reference.exit);
+        let container = if target_id != room && rng.below(2) == 0 {
+            target_id
+        } else {
+            room
+        };
+        game.add_reference(Reference {
+            id: 0,
+            gpos: rng.empty_pos(&game, container)?,
+            target_no,
+            exit,
+            inf_exit: None,
+            possessable: false,
+            fliph: false,
+            rotation: 0,
+            effect: SpecialEffect::None,
+        })
+        .ok()?;
+    }
+
+    // a goal for each block, in the room or inside the hollow blocks
+    let containers = [room].into_iter().chain(hollow).collect::<Vec<_>>();
+    for _ in 0..config.blocks {
+        let container = containers[rng.below(containers.len())];
+        game.add_goal(rng.empty_pos(&game, container)?, false)
+            .ok()?;
+    }
+    if mechanics.player_goal {
+        for _ in 0..config.players() {
+            let gpos = rng.empty_pos(&game, room)?;
+            game.add_goal(gpos, true).ok()?;
+        }
+    }
+
+    Some(game)
+}
+
+// a level with each block on a goal, and the player on its own goal if it
+// has one, for playing backwards
+fn solved_level(config: &GeneratorConfig, rng: &mut Rng) -> Option<Game> {
+    let mechanics = config.mechanics;
+    let mut game = Template::Room {
+        width: config.width,
+        height: config.height,
+    }
+    .level()
+    .ok()?;
+    let room = game.block_by_no(BlockNo(0))?.id;
+
+    for _ in 0..config.walls {
+        let gpos = rng.empty_pos(&game, room)?;
+        game.add_wall(Wall::new(gpos)).ok()?;
+    }
+    for _ in 0..config.blocks {
+        let gpos = rng.empty_pos(&game, room)?;
+        game.add_goal(gpos, false).ok()?;
+        let mut block = Block::new(gpos, game.next_block_no(), 1, 1, game.new_block_hsv());
+        if mechanics.enter {
+            block.width = 3 + rng.below(3) as i32;
+            block.height = 3 + rng.below(3) as i32;
+        } else {
+            block.filled = true;
+        }
+        game.add_block(block).ok()?;
+    }
+    for _ in 0..config.players() {
+        let gpos = rng.empty_pos(&game, room)?;
+        if mechanics.player_goal {
+            game.add_goal(gpos, true).ok()?;
+        }
+        game.add_player(gpos).ok()?;
+    }
+    if mechanics.possession {
+        let gpos = rng.empty_pos(&game, room)?;
+        let mut block = Block::new(gpos, game.next_block_no(), 1, 1, game.new_block_hsv());
+        block.filled = true;
+        block.possessable = true;
+        game.add_block(block).ok()?;
+    }
+    if mechanics.references {
+        let gpos = rng.empty_pos(&game, room)?;
+        game.add_reference(Reference {
+            id: 0,
+            gpos,
+            target_no: BlockNo(0),
+            exit: true,
+            inf_exit: None,
+            possessable: false,
+            fliph: false,
+            rotation: 0,
+            effect: SpecialEffect::None,
+        })
+        .ok()?;
+    }
+    Some(game)
+}
+
+// plays a solved level backwards, never returning to a state it was in, and
+// returns the level at the start and the moves that solve it
+fn play_backwards(mut game: Game, steps: usize, rng: &mut Rng) -> Option<(Game, Vec<Direction>)> {
+    let mut visited = HashSet::from([game.state_key()]);
+    let mut moves = Vec::new();
+    for _ in 0..steps {
+        let mut candidates = predecessors(&game)
+            .into_iter()
+            .filter(|(prev, _, _)| !visited.contains(&prev.state_key()))
+            .collect::<Vec<_>>();
+        // walking around alone would leave the blocks on their goals
+        if candidates.iter().any(|&(_, _, pulls)| pulls) && rng.below(2) == 0 {
+            candidates.retain(|&(_, _, pulls)| pulls);
+        }
+        if candidates.is_empty() {
+            break;
+        }
+        let (prev, direction, _) = candidates.swap_remove(rng.below(candidates.len()));
+        visited.insert(prev.state_key());
+        game = prev;
+        moves.push(direction);
+    }
+    moves.reverse();
+
+    // the level may be won on the way, by other cells on the goals
+    let mut state = game.clone();
+    let won_at = moves.iter().position(|&direction| {
+        state.play(direction);
+        state.won()
+    })?;
+    moves.truncate(won_at + 1);
+    (!game.won()).then_some((game, moves))
+}
+
+// the states that the move leads from to the state of the game, with
+// whether the move pushes a cell; every candidate is played forwards to
+// check that it really leads there
+fn predecessors(game: &Game) -> Vec<(Game, Direction, bool)> {
+    let mut candidates = Vec::new();
+    for direction in Direction::ALL {
+        // the ways all the players may have moved, combined
+        let mut options = vec![(Vec::new(), false)];
+        for &player in game.player_ids() {
+            let steps = steps_back(game, player, direction);
+            options = options
+                .iter()
+                .flat_map(|(cells, pulls): &(Vec<_>, bool)| {
+                    steps.iter().map(move |(step, pull)| {
+                        let mut cells = cells.clone();
+                        cells.extend_from_slice(step);
+                        (cells, *pulls || *pull)
+                    })
+                })
+                .collect();
+        }
+
+        for (cells, pulls) in options {
+            let mut prev = game.clone();
+            if cells
+                .iter()
+                .any(|&(id, gpos)| prev.move_cell(id, gpos).is_err())
+            {
+                continue;
+            }
+            let mut next = prev.clone();
+            next.play(direction);
+            if next.state_key() == game.state_key() {
+                candidates.push((prev, direction, pulls));
+            }
+        }
+    }
+    candidates
+}
+
+// the ways the player may have moved in the direction to where it is, as the
+// cells to move back, with whether a cell is pulled along; a player that
+// didn't move moves nothing back
+fn steps_back(
+    game: &Game,
+    player: usize,
+    direction: Direction,
+) -> Vec<(Vec<(usize, GlobalPos)>, bool)> {
+    let mut steps = vec![(Vec::new(), false)];
+    let gpos = game.cells()[player].gpos();
+    let Some(container) = gpos.block_id() else {
+        return steps;
+    };
+    let is_empty = |gpos: GlobalPos| {
+        let block = gpos.block_id().and_then(|id| game.cells()[id].block());
+        block.is_some_and(|block| block.in_bounds(gpos.pos)) && game.cell_at(gpos).is_none()
+    };
+    let back = GlobalPos::new(container, gpos.pos.towards(direction.opposite()));
+    let ahead = GlobalPos::new(container, gpos.pos.towards(direction));
+
+    // walking, and pulling the cell ahead along
+    if is_empty(back) {
+        steps.push((vec![(player, back)], false));
+        if let Some(cell) = game.cell_at(ahead).filter(|cell| !cell.is_wall()) {
+            steps.push((vec![(player, back), (cell.id(), gpos)], true));
+        }
+    }
+    // entering the block the player is in
+    let block = game.cells()[container].block().unwrap();
+    if let Some(outer) = block.gpos.block_id() {
+        let from = GlobalPos::new(outer, block.gpos.pos.towards(direction.opposite()));
+        if is_empty(from) {
+            steps.push((vec![(player, from)], false));
+        }
+    }
+    // leaving the block behind the player
+    if let Some(Cell::Block(inner)) = game.cell_at(back) {
+        let inner_id = inner.id;
+        let edge = (0..inner.width)
+            .flat_map(|x| (0..inner.height).map(move |y| Pos(x, y)))
+            .filter(|&pos| !inner.in_bounds(pos.towards(direction)))
+            .map(|pos| GlobalPos::new(inner_id, pos))
+            .filter(|&from| is_empty(from));
+        steps.extend(edge.map(|from| (vec![(player, from)], false)));
+    }
+    steps
+}
+
+// makes the mutation at a random place of the level
+fn mutate(game: &mut Game, mutation: Mutation, rng: &mut Rng) -> Result<(), String> {
+    match mutation {
+        Mutation::MoveWall => {
+            let walls = game
+                .cells()
+                .iter()
+                .filter(|cell| cell.is_wall() && cell.gpos().block_id().is_some())
+                .map(|cell| cell.id())
+                .collect::<Vec<_>>();
+            let &id = rng.pick(&walls).ok_or("No walls to move")?;
+            let block_id = game.cells()[id].gpos().block_id().unwrap();
+            let gpos = rng
+                .empty_pos(game, block_id)
+                .ok_or("No room for the wall")?;
+            game.move_cell(id, gpos)
+        }
+        Mutation::MoveGoal => {
+            let &goal = rng.pick(game.goals()).ok_or("No goals to move")?;
+            let block_id = goal.gpos.block_id().ok_or("Goal outside of block")?;
+            let free = positions(game, block_id)
+                .into_iter()
+                .filter(|&gpos| {
+                    game.floors().iter().all(|floor| floor.gpos != gpos)
+                        && !game.cell_at(gpos).is_some_and(|cell| cell.is_wall())
+                })
+                .collect::<Vec<_>>();
+            let &gpos = rng.pick(&free).ok_or("No room for the goal")?;
+            game.remove_floor(goal.gpos)?;
+            game.add_goal(gpos, goal.player)
+        }
+        Mutation::ResizeBlock => {
+            let blocks = game
+                .cells()
+                .iter()
+                .filter_map(|cell| cell.block())
+                .filter(|block| !block.filled && !block.space && block.inf_enter.is_none())
+                .filter(|block| block.gpos.block_id().is_some())
+                .map(|block| (block.id, block.width, block.height))
+                .collect::<Vec<_>>();
+            let &(id, mut width, mut height) = rng.pick(&blocks).ok_or("No blocks to resize")?;
+            let side = if rng.below(2) == 0 {
+                &mut width
+            } else {
+                &mut height
+            };
+            *side += if rng.below(2) == 0 { 1 } else { -1 };
+            game.resize_block(id, width, height)
+        }
+    }
+}
+
+// every position in the block
+fn positions(game: &Game, block_id: usize) -> Vec<GlobalPos> {
+    let Some(block) = game.cells()[block_id].block() else {
+        return Vec::new();
+    };
+    (0..block.width)
+        .flat_map(|x| (0..block.height).map(move |y| GlobalPos::new(block_id, Pos(x, y))))
+        .collect()
+}
+
+// whether the level has no errors found by the validation
+fn valid(game: &Game) -> bool {
+    game.validate()
+        .iter()
+        .all(|issue| issue.severity() != Severity::Error)
+}
+
+// a small pseudorandom number generator (SplitMix64), so that levels only
+// depend on the seed
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // a number below `n`, which must be positive
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    // one of the items, if there are any
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len()))
+    }
+
+    // a position in the block without a cell or floor, if there is one
+    fn empty_pos(&mut self, game: &Game, block_id: usize) -> Option<GlobalPos> {
+        let empty = positions(game, block_id)
+            .into_iter()
+            .filter(|&gpos| {
+                game.cell_at(gpos).is_none() && game.floors().iter().all(|floor| floor.gpos != gpos)
+            })
+            .collect::<Vec<_>>();
+        self.pick(&empty).copied()
+    }
+}
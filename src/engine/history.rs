@@ -0,0 +1,471 @@
+use super::delta::StateDelta;
+use super::game::*;
+use super::simulation::MoveOutcome;
+use super::utility::*;
+
+/// A linear history of moves played from an initial state.
+///
+/// Each move is one input transaction: a single direction, resolved for all
+/// players in turn by `Game::play`. Undo, redo and replays step over whole
+/// transactions, and inputs that change nothing are not recorded unless
+/// `set_record_noops` is enabled.
+///
+/// Instead of storing a copy of the game after every move, the timeline keeps
+/// a full copy (keyframe) every `interval` moves. Any earlier state can be
+/// reconstructed by replaying the moves after the nearest keyframe.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timeline {
+    // all moves played, including the ones after the cursor
+    moves: Vec<Direction>,
+
+    // keyframes[i] is the state after i * interval moves
+    keyframes: Vec<Game>,
+
+    interval: usize,
+
+    // number of moves applied to the current state
+    cursor: usize,
+
+    // cursors of the checkpoints, in increasing order
+    checkpoints: Vec<usize>,
+
+    // whether inputs that change nothing are recorded
+    record_noops: bool,
+
+    current: Game,
+}
+
+impl Timeline {
+    pub const DEFAULT_INTERVAL: usize = 32;
+
+    pub fn new(game: Game) -> Self {
+        Self::with_interval(game, Self::DEFAULT_INTERVAL)
+    }
+
+    /// Creates a timeline keeping a keyframe every `interval` moves.
+    pub fn with_interval(game: Game, interval: usize) -> Self {
+        assert!(interval > 0, "keyframe interval must be positive");
+        Timeline {
+            moves: Vec::new(),
+            keyframes: vec![game.clone()],
+            interval,
+            cursor: 0,
+            checkpoints: Vec::new(),
+            record_noops: false,
+            current: game,
+        }
+    }
+
+    /// Returns the total number of moves recorded, including the ones after
+    /// the cursor.
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Returns the number of moves applied to the current state.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn current(&self) -> &Game {
+        &self.current
+    }
+
+    pub fn initial(&self) -> &Game {
+        &self.keyframes[0]
+    }
+
+    pub fn moves(&self) -> &[Direction] {
+        &self.moves
+    }
+
+    pub fn record_noops(&self) -> bool {
+        self.record_noops
+    }
+
+    /// Sets whether inputs that change nothing are recorded, e.g. for keeping
+    /// the exact input sequence of a TAS.
+    pub fn set_record_noops(&mut self, record_noops: bool) {
+        self.record_noops = record_noops;
+    }
+
+    /// Plays a move from the current state, and returns its outcome.
+    ///
+    /// If the move changed nothing and no-ops are not recorded, the timeline
+    /// is left untouched. Otherwise, if the cursor is not at the end of the
+    /// timeline, the moves after the cursor are discarded first.
+    pub fn play(&mut self, direction: Direction) -> MoveOutcome {
+        let mut next = self.current.clone();
+        let outcome = next.play(direction);
+        if !outcome.changed() && !self.record_noops {
+            return outcome;
+        }
+
+        self.truncate();
+        self.current = next;
+        self.moves.push(direction);
+        self.cursor += 1;
+
+        if self.cursor.is_multiple_of(self.interval) {
+            self.keyframes.push(self.current.clone());
+        }
+        outcome
+    }
+
+    /// Steps back over the last move. Returns false if there is none.
+    pub fn undo(&mut self) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        self.seek(self.cursor - 1);
+        true
+    }
+
+    /// Replays the next undone move. Returns false if there is none.
+    pub fn redo(&mut self) -> bool {
+        if self.cursor == self.moves.len() {
+            return false;
+        }
+        self.seek(self.cursor + 1);
+        true
+    }
+
+    /// Marks the current state as a checkpoint, which `restart` returns to.
+    pub fn set_checkpoint(&mut self) {
+        if let Err(i) = self.checkpoints.binary_search(&self.cursor) {
+            self.checkpoints.insert(i, self.cursor);
+        }
+    }
+
+    /// Returns the cursor of the last checkpoint at or before the current
+    /// state.
+    pub fn last_checkpoint(&self) -> Option<usize> {
+        let count = self
+            .checkpoints
+            .partition_point(|&checkpoint| checkpoint <= self.cursor);
+        count.checked_sub(1).map(|i| self.checkpoints[i])
+    }
+
+    /// Seeks back to the last checkpoint at or before the current state, or
+    /// to the initial state if there is none. The moves after it can still be
+    /// redone.
+    pub fn restart(&mut self) -> &Game {
+        self.seek(self.last_checkpoint().unwrap_or(0))
+    }
+
+    /// Moves the cursor to the state after `n` moves, clamped to the length
+    /// of the timeline, and returns that state.
+    ///
+    /// Moving forwards replays from the current state when possible; other
+    /// seeks replay from the nearest keyframe.
+    pub fn seek(&mut self, n: usize) -> &Game {
+        let n = n.min(self.moves.len());
+
+        let keyframe = n / self.interval;
+        let mut from = keyframe * self.interval;
+        if n >= self.cursor && self.cursor >= from {
+            from = self.cursor;
+        } else {
+            self.current = self.keyframes[keyframe].clone();
+        }
+
+        for direction in &self.moves[from..n] {
+            self.current.play(*direction);
+        }
+        self.cursor = n;
+
+        &self.current
+    }
+
+    /// Returns a copy of the state after `n` moves, without moving the cursor.
+    pub fn state_at(&self, n: usize) -> Game {
+        let n = n.min(self.moves.len());
+        let keyframe = n / self.interval;
+
+        let mut game = self.keyframes[keyframe].clone();
+        for direction in &self.moves[keyframe * self.interval..n] {
+            game.play(*direction);
+        }
+        game
+    }
+
+    /// Creates a new timeline sharing the history up to the cursor, so that
+    /// different moves can be explored without affecting this timeline.
+    pub fn branch(&self) -> Timeline {
+        let mut timeline = self.clone();
+        timeline.truncate();
+        timeline
+    }
+
+    /// Discards the moves, keyframes and checkpoints after the cursor.
+    fn truncate(&mut self) {
+        self.moves.truncate(self.cursor);
+        self.keyframes.truncate(self.cursor / self.interval + 1);
+        self.checkpoints
+            .retain(|&checkpoint| checkpoint <= self.cursor);
+    }
+}
+
+/// A history of moves that keeps every line of play: undoing some moves and
+/// then playing a different one starts a new branch instead of discarding
+/// the undone moves.
+///
+/// Every node stores the changes made by its move as a `StateDelta`, which
+/// undo and redo revert and apply. Every `KEYFRAME_INTERVAL` moves deep, a
+/// node also keeps a full copy of the state, so that jumps only apply the
+/// deltas after the nearest copy.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UndoTree {
+    nodes: Vec<UndoNode>,
+    current: usize,
+    game: Game,
+    // whether inputs that change nothing are recorded
+    record_noops: bool,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct UndoNode {
+    parent: Option<usize>,
+    // the move leading to this node, None for the root
+    direction: Option<Direction>,
+    children: Vec<usize>,
+    // the child that redo goes to: the one visited last
+    redo: Option<usize>,
+    depth: usize,
+    // the changes from the parent state
+    delta: StateDelta,
+    keyframe: Option<Game>,
+}
+
+/// A chain of moves in an undo tree without branches, as shown by tree
+/// navigators.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UndoBranch {
+    /// The node the branch starts from, before its first move.
+    pub from: usize,
+    /// The last node of the branch.
+    pub tip: usize,
+    /// The number of moves before the branch.
+    pub depth: usize,
+    pub moves: Vec<Direction>,
+    /// The node after each move.
+    pub nodes: Vec<usize>,
+    /// The number of branches it forks from, zero for the first one.
+    pub level: usize,
+}
+
+impl UndoTree {
+    pub const ROOT: usize = 0;
+
+    pub const KEYFRAME_INTERVAL: usize = 32;
+
+    pub fn new(game: Game) -> Self {
+        Self {
+            nodes: vec![UndoNode {
+                parent: None,
+                direction: None,
+                children: Vec::new(),
+                redo: None,
+                depth: 0,
+                delta: StateDelta::default(),
+                keyframe: Some(game.clone()),
+            }],
+            current: Self::ROOT,
+            game,
+            record_noops: false,
+        }
+    }
+
+    /// Returns the number of states in the tree, including the initial one.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Always false, since the tree contains at least the initial state.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn current(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn current_node(&self) -> usize {
+        self.current
+    }
+
+    /// Returns the number of moves from the initial state to the current one.
+    pub fn depth(&self) -> usize {
+        self.nodes[self.current].depth
+    }
+
+    /// Returns the number of moves up to the end of the line that redo
+    /// follows from the current state.
+    pub fn redo_depth(&self) -> usize {
+        let mut node = self.current;
+        while let Some(next) = self.nodes[node].redo {
+            node = next;
+        }
+        self.nodes[node].depth
+    }
+
+    /// Sets whether inputs that change nothing are recorded.
+    pub fn set_record_noops(&mut self, record_noops: bool) {
+        self.record_noops = record_noops;
+    }
+
+    /// Returns the moves from the initial state to the current one.
+    pub fn moves(&self) -> Vec<Direction> {
+        let mut moves = Vec::with_capacity(self.depth());
+        let mut node = self.current;
+        while let Some(direction) = self.nodes[node].direction {
+            moves.push(direction);
+            node = self.nodes[node].parent.unwrap();
+        }
+        moves.reverse();
+        moves
+    }
+
+    /// Plays a move from the current state, and returns its outcome.
+    ///
+    /// If the same move was played from this state before, the existing node
+    /// is revisited. Otherwise a new node is added, branching off if other
+    /// moves were played from here.
+    pub fn play(&mut self, direction: Direction) -> MoveOutcome {
+        let mut next = self.game.clone();
+        let outcome = next.play(direction);
+        if !outcome.changed() && !self.record_noops {
+            return outcome;
+        }
+
+        let existing = self.nodes[self.current]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].direction == Some(direction));
+        let child = match existing {
+            Some(child) => child,
+            None => {
+                let child = self.nodes.len();
+                let depth = self.depth() + 1;
+                self.nodes.push(UndoNode {
+                    parent: Some(self.current),
+                    direction: Some(direction),
+                    children: Vec::new(),
+                    redo: None,
+                    depth,
+                    delta: self.game.diff(&next),
+                    keyframe: depth
+                        .is_multiple_of(Self::KEYFRAME_INTERVAL)
+                        .then(|| next.clone()),
+                });
+                self.nodes[self.current].children.push(child);
+                child
+            }
+        };
+        self.nodes[self.current].redo = Some(child);
+        self.current = child;
+        self.game = next;
+        outcome
+    }
+
+    /// Steps back to the parent state. Returns false at the initial state.
+    pub fn undo(&mut self) -> bool {
+        match self.nodes[self.current].parent {
+            Some(parent) => {
+                self.game.revert_delta(&self.nodes[self.current].delta);
+                self.current = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Steps forward along the branch visited last. Returns false if no
+    /// moves were played from the current state.
+    pub fn redo(&mut self) -> bool {
+        match self.nodes[self.current].redo {
+            Some(child) => {
+                self.current = child;
+                self.game.apply_delta(&self.nodes[child].delta);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Jumps to any node of the tree. Redo from its ancestors then follows
+    /// the path to it.
+    pub fn jump(&mut self, node: usize) -> &Game {
+        self.current = node;
+        let mut child = node;
+        while let Some(parent) = self.nodes[child].parent {
+            self.nodes[parent].redo = Some(child);
+            child = parent;
+        }
+
+        // replay the deltas after the nearest keyframe
+        let mut path = Vec::new();
+        let mut node = node;
+        while self.nodes[node].keyframe.is_none() {
+            path.push(node);
+            node = self.nodes[node].parent.unwrap();
+        }
+        self.game = self.nodes[node].keyframe.clone().unwrap();
+        for &node in path.iter().rev() {
+            self.game.apply_delta(&self.nodes[node].delta);
+        }
+        self.current()
+    }
+
+    /// Splits the tree into chains of moves without branches, in depth-first
+    /// order, with the branch visited last first among siblings.
+    pub fn branches(&self) -> Vec<UndoBranch> {
+        let mut branches = Vec::new();
+        self.collect_branches(Self::ROOT, 0, &mut branches);
+        branches
+    }
+
+    fn collect_branches(&self, from: usize, level: usize, branches: &mut Vec<UndoBranch>) {
+        for (i, first) in self.sorted_children(from).into_iter().enumerate() {
+            let level = level + (i > 0) as usize;
+            let mut moves = Vec::new();
+            let mut nodes = Vec::new();
+            let mut node = first;
+            loop {
+                moves.push(self.nodes[node].direction.unwrap());
+                nodes.push(node);
+                match self.nodes[node].children[..] {
+                    [only] => node = only,
+                    _ => break,
+                }
+            }
+
+            branches.push(UndoBranch {
+                from,
+                tip: node,
+                depth: self.nodes[from].depth,
+                moves,
+                nodes,
+                level,
+            });
+            self.collect_branches(node, level, branches);
+        }
+    }
+
+    fn sorted_children(&self, node: usize) -> Vec<usize> {
+        let mut children = self.nodes[node].children.clone();
+        if let Some(redo) = self.nodes[node].redo {
+            children.retain(|&child| child != redo);
+            children.insert(0, redo);
+        }
+        children
+    }
+}
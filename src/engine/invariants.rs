@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use super::game::*;
+use super::occupancy::Occupancy;
+use super::utility::*;
+
+/// A broken invariant of the internal state of a game, which means there is
+/// a bug in the simulator or in code that builds games.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// The cell at an index of `cells` has a different id.
+    WrongId { index: usize, id: usize },
+    /// A cell is in a container which is not a block, or a cell other than a
+    /// block is not in any block.
+    MissingContainer {
+        cell_id: usize,
+        container: Container,
+    },
+    /// A cell is outside the bounds of the block containing it.
+    OutOfBounds { cell_id: usize, gpos: GlobalPos },
+    /// More than one cell is at the same position.
+    SharedPosition {
+        gpos: GlobalPos,
+        cell_ids: Vec<usize>,
+    },
+    /// The block with the given number in `block_map` is missing or has
+    /// another number.
+    BlockMap { block_no: BlockNo, id: usize },
+    /// The references to a block in `ref_map` don't match the cells.
+    RefMap { block_no: BlockNo },
+    /// The infinite enters of a block in `inf_enter_map` don't match the
+    /// cells.
+    InfEnterMap { block_no: BlockNo },
+    /// A reference points to a block that doesn't exist.
+    MissingTarget {
+        reference_id: usize,
+        target_no: BlockNo,
+    },
+    /// A player id is not the id of a cell, or appears more than once.
+    InvalidPlayer { id: usize },
+    /// The player flags of the cells don't match the player ids.
+    PlayerFlags,
+    /// The occupied positions of a block don't match the cells in it.
+    Occupancy { block_id: usize },
+}
+
+impl std::fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvariantViolation::WrongId { index, id } => {
+                write!(f, "Cell at index {index} has id {id}")
+            }
+            InvariantViolation::MissingContainer { cell_id, container } => {
+                write!(
+                    f,
+                    "Cell {cell_id} is in {container:?}, which is not a block"
+                )
+            }
+            InvariantViolation::OutOfBounds { cell_id, gpos } => {
+                write!(f, "Cell {cell_id} at {gpos:?} is outside its block")
+            }
+            InvariantViolation::SharedPosition { gpos, cell_ids } => {
+                write!(f, "Cells {cell_ids:?} share the position {gpos:?}")
+            }
+            InvariantViolation::BlockMap { block_no, id } => {
+                write!(
+                    f,
+                    "Block {block_no} is mapped to cell {id}, which is not that block"
+                )
+            }
+            InvariantViolation::RefMap { block_no } => {
+                write!(
+                    f,
+                    "The indexed references to block {block_no} don't match the cells"
+                )
+            }
+            InvariantViolation::InfEnterMap { block_no } => write!(
+                f,
+                "The indexed infinite enters of block {block_no} don't match the cells"
+            ),
+            InvariantViolation::MissingTarget {
+                reference_id,
+                target_no,
+            } => write!(
+                f,
+                "Reference {reference_id} points to block {target_no}, which doesn't exist"
+            ),
+            InvariantViolation::InvalidPlayer { id } => {
+                write!(f, "Player {id} is not a cell or is listed twice")
+            }
+            InvariantViolation::PlayerFlags => {
+                write!(f, "The player flags don't match the player ids")
+            }
+            InvariantViolation::Occupancy { block_id } => write!(
+                f,
+                "The occupied positions of block {block_id} don't match its cells"
+            ),
+        }
+    }
+}
+
+impl Game {
+    /// Checks the internal state of the game for inconsistencies, e.g. after
+    /// every move in tests, and returns every violation found.
+    ///
+    /// This looks at all the cells and indexes, so it is too slow to call on
+    /// every move outside of tests and debugging. Debug builds with the
+    /// `check-invariants` feature check after every move played by the
+    /// simulator, and panic at the first violation.
+    pub fn check_invariants(&self) -> Vec<InvariantViolation> {
+        let mut violations = Vec::new();
+        let cells = &self.cells;
+
+        for (index, cell) in cells.iter().enumerate() {
+            if cell.id() != index {
+                violations.push(InvariantViolation::WrongId {
+                    index,
+                    id: cell.id(),
+                });
+            }
+        }
+
+        // positions in blocks, with the cells there
+        let mut positions = HashMap::<GlobalPos, Vec<usize>>::new();
+        let mut occupancy = cells
+            .iter()
+            .map(|cell| match cell {
+                Cell::Block(block) => Occupancy::new(block.width, block.height),
+                _ => Occupancy::default(),
+            })
+            .collect::<Vec<_>>();
+        for cell in cells.iter() {
+            let gpos = cell.gpos();
+            let Container::Block(CellId(block_id)) = gpos.container else {
+                if cell.block().is_none() {
+                    violations.push(InvariantViolation::MissingContainer {
+                        cell_id: cell.id(),
+                        container: gpos.container,
+                    });
+                }
+                continue;
+            };
+            let Some(block) = cells.get(block_id).and_then(Cell::block) else {
+                violations.push(InvariantViolation::MissingContainer {
+                    cell_id: cell.id(),
+                    container: gpos.container,
+                });
+                continue;
+            };
+            if !block.in_bounds(gpos.pos) {
+                violations.push(InvariantViolation::OutOfBounds {
+                    cell_id: cell.id(),
+                    gpos,
+                });
+            }
+            positions.entry(gpos).or_default().push(cell.id());
+            occupancy[block_id].set(gpos.pos, true);
+        }
+        let mut shared = positions
+            .into_iter()
+            .filter(|(_, cell_ids)| cell_ids.len() > 1)
+            .collect::<Vec<_>>();
+        shared.sort_by_key(|(_, cell_ids)| cell_ids[0]);
+        for (gpos, cell_ids) in shared {
+            violations.push(InvariantViolation::SharedPosition { gpos, cell_ids });
+        }
+
+        let mut block_map = self.block_map.iter().collect::<Vec<_>>();
+        block_map.sort();
+        for (&block_no, &id) in block_map {
+            if cells
+                .get(id)
+                .and_then(Cell::block)
+                .is_none_or(|block| block.block_no != block_no)
+            {
+                violations.push(InvariantViolation::BlockMap { block_no, id });
+            }
+        }
+
+        let mut ref_map = HashMap::<BlockNo, Vec<usize>>::new();
+        let mut inf_enter_map = HashMap::<BlockNo, Vec<usize>>::new();
+        for cell in cells.iter() {
+            match cell {
+                Cell::Reference(reference) => {
+                    ref_map
+                        .entry(reference.target_no)
+                        .or_default()
+                        .push(reference.id);
+                    if self.block_by_no(reference.target_no).is_none() {
+                        violations.push(InvariantViolation::MissingTarget {
+                            reference_id: reference.id,
+                            target_no: reference.target_no,
+                        });
+                    }
+                }
+                Cell::Block(Block {
+                    id,
+                    inf_enter: Some((block_no, _)),
+                    ..
+                }) => inf_enter_map.entry(*block_no).or_default().push(*id),
+                _ => (),
+            }
+        }
+        violations.extend(
+            mismatches(&self.ref_map, &ref_map)
+                .map(|block_no| InvariantViolation::RefMap { block_no }),
+        );
+        violations.extend(
+            mismatches(&self.inf_enter_map, &inf_enter_map)
+                .map(|block_no| InvariantViolation::InfEnterMap { block_no }),
+        );
+
+        let mut players = vec![false; cells.len()];
+        for &id in &self.player_ids {
+            match players.get_mut(id) {
+                Some(player) if !*player => *player = true,
+                _ => violations.push(InvariantViolation::InvalidPlayer { id }),
+            }
+        }
+        if self.player_flags != players {
+            violations.push(InvariantViolation::PlayerFlags);
+        }
+
+        for (block_id, expected) in occupancy.iter().enumerate() {
+            if cells[block_id].block().is_some() && self.occupancy.get(block_id) != Some(expected) {
+                violations.push(InvariantViolation::Occupancy { block_id });
+            }
+        }
+
+        violations
+    }
+}
+
+// the blocks whose ids differ between an index and the expected one, in order
+fn mismatches<'a>(
+    index: &'a HashMap<BlockNo, Vec<usize>>,
+    expected: &'a HashMap<BlockNo, Vec<usize>>,
+) -> impl Iterator<Item = BlockNo> + 'a {
+    let mut block_nos = index
+        .keys()
+        .chain(expected.keys())
+        .copied()
+        .collect::<Vec<_>>();
+    block_nos.sort();
+    block_nos.dedup();
+    block_nos
+        .into_iter()
+        .filter(|block_no| index.get(block_no) != expected.get(block_no))
+}
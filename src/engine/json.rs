@@ -0,0 +1,433 @@
+//! A JSON format for levels, for external tools that generate or analyze
+//! levels. It has the same content as the text format, but with named fields,
+//! and the objects inside a block are nested in its `children`.
+//!
+//! ```json
+//! {
+//!   "version": 4,
+//!   "objects": [
+//!     {
+//!       "type": "block", "x": -1, "y": -1, "id": 0, "width": 5, "height": 5,
+//!       "hue": 0.6, "sat": 0.8, "val": 1,
+//!       "children": [
+//!         { "type": "wall", "x": 0, "y": 0 },
+//!         { "type": "block", "x": 2, "y": 2, "id": 1, "width": 1, "height": 1, "hue": 0, "sat": 0, "val": 0.8, "filled": true, "player": 0 },
+//!         { "type": "floor", "x": 3, "y": 3, "kind": "PlayerButton" }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Fields left out take the default values, which are `false`, `0` or `null`
+//! except for `zoom` (1). `player` is the player order of a player, and `null`
+//! for other objects.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::game::*;
+use super::serialize::{flag, number};
+use super::utility::*;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonLevel {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attempt_order: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    shed: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    inner_push: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    rotation: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    gravity: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pull: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    space_size: Option<(i32, i32)>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    shared_space: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    par_moves: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    properties: BTreeMap<String, String>,
+    objects: Vec<JsonObject>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonObject {
+    Block {
+        #[serde(flatten)]
+        common: JsonCommon,
+        id: i32,
+        width: i32,
+        height: i32,
+        hue: f64,
+        sat: f64,
+        val: f64,
+        #[serde(default = "one", skip_serializing_if = "is_one")]
+        zoom: f64,
+        #[serde(default, skip_serializing_if = "is_default")]
+        filled: bool,
+        #[serde(default, skip_serializing_if = "is_default")]
+        locked: bool,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        children: Vec<JsonObject>,
+    },
+    #[serde(rename = "ref")]
+    Reference {
+        #[serde(flatten)]
+        common: JsonCommon,
+        id: i32,
+        #[serde(default, skip_serializing_if = "is_default")]
+        exit: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        inf_exit: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        inf_enter: Option<JsonInfEnter>,
+        #[serde(default, skip_serializing_if = "is_default")]
+        rotation: u8,
+    },
+    Wall {
+        x: i32,
+        y: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        player: Option<usize>,
+        #[serde(default, skip_serializing_if = "is_default")]
+        possessable: bool,
+    },
+    Floor {
+        x: i32,
+        y: i32,
+        kind: FloorKind,
+    },
+}
+
+// the fields shared by blocks and references
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonCommon {
+    x: i32,
+    y: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    player: Option<usize>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    possessable: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    fliph: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    float_in_space: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    special_effect: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonInfEnter {
+    id: i32,
+    degree: u32,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+fn is_one(value: &f64) -> bool {
+    *value == 1.0
+}
+
+impl Game {
+    /// Writes the current state of the game in the JSON format, which can be
+    /// read back with `Game::from_json`. The same cells as in `to_text` are
+    /// written.
+    pub fn to_json(&self) -> String {
+        let config = self.config();
+        let attempt_order =
+            (config.attempt_order != GameConfig::default().attempt_order).then(|| {
+                config
+                    .attempt_order
+                    .iter()
+                    .map(|action| format!("{action:?}").to_lowercase())
+                    .collect()
+            });
+        let level = JsonLevel {
+            version: 4,
+            attempt_order,
+            shed: config.shed,
+            inner_push: config.inner_push,
+            rotation: config.rotation,
+            gravity: config.gravity,
+            pull: config.pull,
+            space_size: (config.space_size != GameConfig::DEFAULT_SPACE_SIZE)
+                .then_some(config.space_size),
+            shared_space: config.shared_space,
+            title: self.meta().title.clone(),
+            author: self.meta().author.clone(),
+            par_moves: self.meta().par_moves,
+            tags: self.meta().tags.clone(),
+            properties: self.meta().extra.clone(),
+            objects: self.json_children(Container::Space),
+        };
+        serde_json::to_string_pretty(&level).unwrap()
+    }
+
+    /// Reads a level in the JSON format.
+    ///
+    /// The level is converted to the text format and parsed by `Game::parse`,
+    /// so that both formats are checked in the same way.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let level: JsonLevel = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        Game::parse(&level.to_text())
+    }
+
+    // collects the objects inside a block, or the top level objects in
+    // space, like `write_children`
+    fn json_children(&self, container: Container) -> Vec<JsonObject> {
+        let mut objects = Vec::new();
+
+        for cell in self.cells.iter() {
+            if cell.gpos().container != container {
+                continue;
+            }
+            if self.is_space(cell.id()) {
+                objects.extend(self.json_children(Container::Block(CellId(cell.id()))));
+                continue;
+            }
+            if self.is_synthesized(cell) {
+                continue;
+            }
+
+            let float_in_space = container.block_id().is_some_and(|id| self.is_space(id));
+            let Pos(x, y) = if float_in_space && !self.config().shared_space {
+                Pos(-1, -1)
+            } else {
+                cell.gpos().pos
+            };
+            let player = self.player_ids.iter().position(|id| *id == cell.id());
+            let common = JsonCommon {
+                x,
+                y,
+                player,
+                possessable: cell.possessable(),
+                fliph: cell.fliph(),
+                float_in_space,
+                special_effect: cell.effect().number(),
+            };
+
+            objects.push(match cell {
+                Cell::Wall(wall) => JsonObject::Wall {
+                    x,
+                    y,
+                    player,
+                    possessable: wall.possessable,
+                },
+                Cell::Block(block) => JsonObject::Block {
+                    common,
+                    id: block.block_no.0,
+                    width: block.width,
+                    height: block.height,
+                    hue: block.hsv.h / 360.0,
+                    sat: block.hsv.s,
+                    val: block.hsv.v,
+                    zoom: block.zoom,
+                    filled: block.filled,
+                    locked: block.locked,
+                    children: self.json_children(Container::Block(CellId(block.id))),
+                },
+                Cell::Reference(reference) => JsonObject::Reference {
+                    common,
+                    id: reference.target_no.0,
+                    exit: reference.exit,
+                    inf_exit: reference.inf_exit,
+                    inf_enter: self.inf_enter_of(reference).map(|(block_no, degree)| {
+                        JsonInfEnter {
+                            id: block_no.0,
+                            degree,
+                        }
+                    }),
+                    rotation: reference.rotation,
+                },
+            });
+        }
+
+        for floor in self.floors() {
+            if floor.gpos.container == container {
+                let Pos(x, y) = floor.gpos.pos;
+                objects.push(JsonObject::Floor {
+                    x,
+                    y,
+                    kind: floor.kind.clone(),
+                });
+            }
+        }
+
+        objects
+    }
+}
+
+impl JsonLevel {
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "version {}", self.version).unwrap();
+        if let Some(attempt_order) = &self.attempt_order {
+            writeln!(out, "attempt_order {}", attempt_order.join(",")).unwrap();
+        }
+        if self.shed {
+            writeln!(out, "shed").unwrap();
+        }
+        if self.inner_push {
+            writeln!(out, "inner_push").unwrap();
+        }
+        if self.rotation {
+            writeln!(out, "rotation").unwrap();
+        }
+        if self.gravity {
+            writeln!(out, "gravity").unwrap();
+        }
+        if self.pull {
+            writeln!(out, "pull").unwrap();
+        }
+        if let Some((width, height)) = self.space_size {
+            writeln!(out, "space_size {width} {height}").unwrap();
+        }
+        if self.shared_space {
+            writeln!(out, "shared_space").unwrap();
+        }
+        if let Some(title) = &self.title {
+            writeln!(out, "title {title}").unwrap();
+        }
+        if let Some(author) = &self.author {
+            writeln!(out, "author {author}").unwrap();
+        }
+        if let Some(par_moves) = self.par_moves {
+            writeln!(out, "par_moves {par_moves}").unwrap();
+        }
+        if !self.tags.is_empty() {
+            writeln!(out, "tags {}", self.tags.join(",")).unwrap();
+        }
+        for (key, value) in &self.properties {
+            writeln!(out, "property {key} {value}").unwrap();
+        }
+        writeln!(out, "#").unwrap();
+        for object in &self.objects {
+            object.write_text(&mut out, 0, self.rotation);
+        }
+        out
+    }
+}
+
+impl JsonObject {
+    // `rotation` tells whether the rotation field of references is written
+    fn write_text(&self, out: &mut String, depth: usize, rotation: bool) {
+        let indent = "\t".repeat(depth);
+        match self {
+            JsonObject::Block {
+                common,
+                id,
+                width,
+                height,
+                hue,
+                sat,
+                val,
+                zoom,
+                filled,
+                locked,
+                children,
+            } => {
+                let locked = if *locked { " 1" } else { "" };
+                writeln!(
+                    out,
+                    "{indent}Block {} {} {id} {width} {height} {} {} {} {} {} {}{locked}",
+                    common.x,
+                    common.y,
+                    number(*hue),
+                    number(*sat),
+                    number(*val),
+                    number(*zoom),
+                    flag(*filled),
+                    common.flags(),
+                )
+                .unwrap();
+                for child in children {
+                    child.write_text(out, depth + 1, rotation);
+                }
+            }
+            JsonObject::Reference {
+                common,
+                id,
+                exit,
+                inf_exit,
+                inf_enter,
+                rotation: turns,
+            } => {
+                let inf_exit = match inf_exit {
+                    Some(degree) => format!("1 {degree}"),
+                    None => "0 0".to_string(),
+                };
+                let inf_enter = match inf_enter {
+                    Some(JsonInfEnter { id, degree }) => format!("1 {degree} {id}"),
+                    None => "0 0 0".to_string(),
+                };
+                let turns = if rotation {
+                    format!(" {turns}")
+                } else {
+                    String::new()
+                };
+                writeln!(
+                    out,
+                    "{indent}Ref {} {} {id} {} {inf_exit} {inf_enter} {}{turns}",
+                    common.x,
+                    common.y,
+                    flag(*exit),
+                    common.flags(),
+                )
+                .unwrap();
+            }
+            JsonObject::Wall {
+                x,
+                y,
+                player,
+                possessable,
+            } => {
+                writeln!(
+                    out,
+                    "{indent}Wall {x} {y} {} {} {}",
+                    flag(player.is_some()),
+                    flag(*possessable),
+                    player.unwrap_or(0),
+                )
+                .unwrap();
+            }
+            JsonObject::Floor { x, y, kind } => {
+                writeln!(out, "{indent}Floor {x} {y} {kind}").unwrap();
+            }
+        }
+    }
+}
+
+impl JsonCommon {
+    // the fields from `player` to `specialeffect` of blocks and references
+    fn flags(&self) -> String {
+        format!(
+            "{} {} {} {} {} {}",
+            flag(self.player.is_some()),
+            flag(self.possessable),
+            self.player.unwrap_or(0),
+            flag(self.fliph),
+            flag(self.float_in_space),
+            self.special_effect,
+        )
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use super::game::*;
+use super::utility::*;
+
+/// The positions of a block that are occupied by cells, one bit per
+/// position, so that empty positions can be told apart without scanning all
+/// the cells.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Occupancy {
+    width: i32,
+    height: i32,
+    bits: Vec<u64>,
+}
+
+impl Occupancy {
+    pub fn new(width: i32, height: i32) -> Self {
+        let (width, height) = (width.max(0), height.max(0));
+        let len = (width as usize * height as usize).div_ceil(64);
+        Self {
+            width,
+            height,
+            bits: vec![0; len],
+        }
+    }
+
+    // the index of the bit of the position, if it is in bounds
+    fn index(&self, Pos(x, y): Pos) -> Option<usize> {
+        (x >= 0 && y >= 0 && x < self.width && y < self.height)
+            .then(|| (y * self.width + x) as usize)
+    }
+
+    /// Returns whether the position is occupied. Positions out of bounds are
+    /// never occupied.
+    pub fn get(&self, pos: Pos) -> bool {
+        self.index(pos)
+            .is_some_and(|i| self.bits[i / 64] & (1 << (i % 64)) != 0)
+    }
+
+    pub fn set(&mut self, pos: Pos, occupied: bool) {
+        if let Some(i) = self.index(pos) {
+            if occupied {
+                self.bits[i / 64] |= 1 << (i % 64);
+            } else {
+                self.bits[i / 64] &= !(1 << (i % 64));
+            }
+        }
+    }
+
+    /// Returns the number of occupied positions.
+    pub fn count(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+}
+
+impl Game {
+    /// Returns the occupied positions of the block with the given id.
+    pub fn occupancy(&self, block_id: usize) -> Option<&Occupancy> {
+        self.cells.get(block_id)?.block()?;
+        self.occupancy.get(block_id)
+    }
+
+    /// Returns whether a cell is at the given position. This doesn't need to
+    /// look at the cells, so it is cheaper than `cell_at`.
+    pub fn is_occupied(&self, gpos: GlobalPos) -> bool {
+        let Some(block_id) = gpos.block_id() else {
+            return self.cell_at(gpos).is_some();
+        };
+        self.occupancy
+            .get(block_id)
+            .is_some_and(|occupancy| occupancy.get(gpos.pos))
+    }
+
+    /// Marks the position as occupied or free, e.g. when a cell moves, and
+    /// clears the cached triviality of the block. Positions outside of
+    /// blocks, or in blocks that haven't been added yet, are ignored.
+    pub(super) fn occupy(&mut self, gpos: GlobalPos, occupied: bool) {
+        let Some(block_id) = gpos.block_id() else {
+            return;
+        };
+        if let Some(occupancy) = Arc::make_mut(&mut self.occupancy).get_mut(block_id) {
+            occupancy.set(gpos.pos, occupied);
+        }
+        if let Some(trivial) = Arc::make_mut(&mut self.trivial).get_mut(block_id) {
+            trivial.take();
+        }
+    }
+}
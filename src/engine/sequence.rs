@@ -0,0 +1,90 @@
+use super::game::*;
+use super::utility::*;
+
+/// The result of playing a movement sequence to the end.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SequenceOutcome {
+    /// Number of moves played.
+    pub steps: usize,
+    /// Number of moves after which the game was first won, if ever.
+    pub won_at: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SequenceError {
+    /// The character at the given index (in chars) is not a direction.
+    InvalidCharacter { index: usize, character: char },
+}
+
+impl SequenceOutcome {
+    /// Whether the game was won exactly after the last move, and not before.
+    pub fn won_at_end(&self) -> bool {
+        self.won_at == Some(self.steps) && self.steps > 0
+    }
+}
+
+impl std::fmt::Display for SequenceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SequenceError::InvalidCharacter { index, character } => {
+                write!(f, "invalid sequence character {character:?} at {index}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SequenceError {}
+
+/// Parses a movement sequence specified as `UDLR`, ignoring whitespace.
+pub fn parse_sequence(sequence: &str) -> Result<Vec<Direction>, SequenceError> {
+    sequence
+        .chars()
+        .enumerate()
+        .filter(|(_, c)| !c.is_whitespace())
+        .map(|(index, character)| {
+            Direction::from_char(character)
+                .ok_or(SequenceError::InvalidCharacter { index, character })
+        })
+        .collect()
+}
+
+/// Formats moves like the `.solution` files: groups of five moves separated
+/// by spaces, ten groups per line, ending with a newline.
+pub fn format_sequence(moves: &[Direction]) -> String {
+    let mut text = String::new();
+    for (i, direction) in moves.iter().enumerate() {
+        if i > 0 && i % 50 == 0 {
+            text.push('\n');
+        } else if i > 0 && i % 5 == 0 {
+            text.push(' ');
+        }
+        text.push(direction.to_char());
+    }
+    text.push('\n');
+    text
+}
+
+impl Game {
+    /// Plays a movement sequence specified as `UDLR`, ignoring whitespace.
+    ///
+    /// The whole sequence is validated before any move is played, so the game
+    /// is left untouched if an invalid character is found.
+    pub fn play_sequence(&mut self, sequence: &str) -> Result<SequenceOutcome, SequenceError> {
+        let directions = parse_sequence(sequence)?;
+
+        let mut won_at = if self.won() { Some(0) } else { None };
+        for (i, direction) in directions.iter().enumerate() {
+            self.play(*direction);
+            if won_at.is_none() && self.won() {
+                won_at = Some(i + 1);
+            }
+        }
+
+        Ok(SequenceOutcome {
+            steps: directions.len(),
+            won_at,
+        })
+    }
+}
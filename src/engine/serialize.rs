@@ -0,0 +1,222 @@
+use std::fmt::Write;
+
+use super::game::*;
+use super::utility::*;
+
+impl Game {
+    /// Writes the current state of the game as a level file, which can be
+    /// read back with `Game::parse`.
+    ///
+    /// Cells synthesized during play for infinite exits and enters are not
+    /// written, as they are recreated when needed. Header items that the
+    /// parser ignores, such as `draw_style`, are not written.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+
+        // block names need the extended format
+        let version = if self.meta().block_names.is_empty() {
+            4
+        } else {
+            5
+        };
+        writeln!(out, "version {version}").unwrap();
+        let config = self.config();
+        if config.attempt_order != GameConfig::default().attempt_order {
+            let names = config
+                .attempt_order
+                .iter()
+                .map(|action| match action {
+                    ActionType::Push => "push",
+                    ActionType::Enter => "enter",
+                    ActionType::Eat => "eat",
+                    ActionType::Possess => "possess",
+                })
+                .collect::<Vec<_>>();
+            writeln!(out, "attempt_order {}", names.join(",")).unwrap();
+        }
+        if config.shed {
+            writeln!(out, "shed").unwrap();
+        }
+        if config.inner_push {
+            writeln!(out, "inner_push").unwrap();
+        }
+        if config.rotation {
+            writeln!(out, "rotation").unwrap();
+        }
+        if config.gravity {
+            writeln!(out, "gravity").unwrap();
+        }
+        if config.pull {
+            writeln!(out, "pull").unwrap();
+        }
+        if config.space_size != GameConfig::DEFAULT_SPACE_SIZE {
+            let (width, height) = config.space_size;
+            writeln!(out, "space_size {width} {height}").unwrap();
+        }
+        if config.shared_space {
+            writeln!(out, "shared_space").unwrap();
+        }
+        let meta = self.meta();
+        if let Some(title) = &meta.title {
+            writeln!(out, "title {title}").unwrap();
+        }
+        if let Some(author) = &meta.author {
+            writeln!(out, "author {author}").unwrap();
+        }
+        if let Some(par_moves) = meta.par_moves {
+            writeln!(out, "par_moves {par_moves}").unwrap();
+        }
+        if !meta.tags.is_empty() {
+            writeln!(out, "tags {}", meta.tags.join(",")).unwrap();
+        }
+        for (key, value) in &meta.extra {
+            writeln!(out, "property {key} {value}").unwrap();
+        }
+        writeln!(out, "#").unwrap();
+
+        self.write_children(&mut out, Container::Space, 0);
+        out
+    }
+
+    // writes the objects inside a block, or the top level objects in space
+    fn write_children(&self, out: &mut String, container: Container, depth: usize) {
+        for cell in self.cells.iter() {
+            if cell.gpos().container != container {
+                continue;
+            }
+
+            // cells floating in space are written at the top level
+            if self.is_space(cell.id()) {
+                self.write_children(out, Container::Block(CellId(cell.id())), depth);
+                continue;
+            }
+            if self.is_synthesized(cell) {
+                continue;
+            }
+
+            let floating = container.block_id().is_some_and(|id| self.is_space(id));
+            let Pos(x, y) = if floating && !self.config().shared_space {
+                Pos(-1, -1)
+            } else {
+                cell.gpos().pos
+            };
+            let player_order = self.player_ids.iter().position(|id| *id == cell.id());
+            let player = flag(player_order.is_some());
+            let player_order = player_order.unwrap_or(0);
+            let indent = "\t".repeat(depth);
+
+            match cell {
+                Cell::Wall(wall) => {
+                    writeln!(
+                        out,
+                        "{indent}Wall {x} {y} {player} {} {player_order}",
+                        flag(wall.possessable),
+                    )
+                    .unwrap();
+                }
+
+                Cell::Block(block) => {
+                    // an extra field, only written for locked blocks
+                    let locked = if block.locked { " 1" } else { "" };
+                    writeln!(
+                        out,
+                        "{indent}Block {x} {y} {} {} {} {} {} {} {} {} {player} {} {player_order} {} {} {}{locked}",
+                        self.meta().block_label(block.block_no),
+                        block.width,
+                        block.height,
+                        number(block.hsv.h / 360.0),
+                        number(block.hsv.s),
+                        number(block.hsv.v),
+                        number(block.zoom),
+                        flag(block.filled),
+                        flag(block.possessable),
+                        flag(block.fliph),
+                        flag(floating),
+                        block.effect.number(),
+                    )
+                    .unwrap();
+                    self.write_children(out, Container::Block(CellId(block.id)), depth + 1);
+                }
+
+                Cell::Reference(reference) => {
+                    let (inf_exit, inf_exit_degree) = match reference.inf_exit {
+                        Some(degree) => (1, degree),
+                        None => (0, 0),
+                    };
+                    let (inf_enter, inf_enter_degree, inf_enter_no) = match self
+                        .inf_enter_of(reference)
+                    {
+                        Some((block_no, degree)) => (1, degree, self.meta().block_label(block_no)),
+                        None => (0, 0, "0".to_string()),
+                    };
+                    // an extra field, only written when enabled
+                    let rotation = if self.config().rotation {
+                        format!(" {}", reference.rotation)
+                    } else {
+                        String::new()
+                    };
+                    writeln!(
+                        out,
+                        "{indent}Ref {x} {y} {} {} {inf_exit} {inf_exit_degree} {inf_enter} {inf_enter_degree} {inf_enter_no} {player} {} {player_order} {} {} {}{rotation}",
+                        self.meta().block_label(reference.target_no),
+                        flag(reference.exit),
+                        flag(reference.possessable),
+                        flag(reference.fliph),
+                        flag(floating),
+                        reference.effect.number(),
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        // floors are written after the cells of their block
+        for floor in self.floors() {
+            if floor.gpos.container == container {
+                let Pos(x, y) = floor.gpos.pos;
+                writeln!(out, "{}Floor {x} {y} {}", "\t".repeat(depth), floor.kind).unwrap();
+            }
+        }
+    }
+
+    // whether the cell was created during play rather than read from the
+    // level file
+    pub(super) fn is_synthesized(&self, cell: &Cell) -> bool {
+        match cell {
+            Cell::Wall(_) => false,
+            Cell::Block(block) => self.block_by_no(block.block_no).is_none(),
+            Cell::Reference(reference) => {
+                reference.inf_exit.is_some() && self.is_floating(reference.gpos)
+            }
+        }
+    }
+
+    // the infinite enter recorded on the target of the reference, which is
+    // written on the first reference to it
+    pub(super) fn inf_enter_of(&self, reference: &Reference) -> Option<(BlockNo, u32)> {
+        let target = self.block_by_no(reference.target_no)?;
+        let first = self
+            .references_to(reference.target_no)
+            .find(|other| !self.is_synthesized(&self.cells[other.id]))?;
+        if first.id == reference.id {
+            target.inf_enter
+        } else {
+            None
+        }
+    }
+}
+
+pub(super) fn flag(value: bool) -> u8 {
+    value as u8
+}
+
+// formats a number with at most 6 decimal places, without trailing zeros
+pub(super) fn number(value: f64) -> String {
+    let text = format!("{value:.6}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        "0".to_string()
+    } else {
+        text.to_string()
+    }
+}
@@ -1,4 +1,5 @@
 use super::game::*;
+use super::trace::{SimCall, SimTrace, TraceEvent, TraceKind};
 use super::utility::*;
 
 pub struct Simulator<'a> {
@@ -21,28 +22,219 @@ pub struct Simulator<'a> {
 
     // stack for transfer cache
     transfer_stack: Vec<TransferCache>,
+
+    // transfer caches that are no longer used, kept for their capacity
+    spare_caches: Vec<TransferCache>,
+
+    // reasons why interactions failed during the current player's move
+    failures: Vec<MoveFailure>,
+
+    // remaining number of steps for the current player's move
+    fuel: u32,
+
+    // whether the cells are falling after the players moved, with gravity
+    falling: bool,
+
+    // decisions made so far, if they are being recorded
+    trace: Option<Vec<TraceEvent>>,
+
+    // depth of the next recorded decision
+    trace_depth: usize,
+
+    // index in the trace of the innermost decision being made
+    trace_current: Option<usize>,
+
+    // receives the calls made by the simulator, for debugging
+    sink: Option<&'a mut dyn SimTrace>,
+}
+
+/// Buffers of the simulator that are kept between moves by
+/// `Game::play_with`, so that playing moves doesn't allocate once the
+/// buffers have grown large enough.
+#[derive(Default)]
+pub struct SimulatorBuffers {
+    move_stack: Vec<MoveState>,
+    transfer_stack: Vec<TransferCache>,
+    spare_caches: Vec<TransferCache>,
+    failures: Vec<MoveFailure>,
+    outcome: MoveOutcome,
+}
+
+/// The result of playing a move.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MoveOutcome {
+    /// Cells whose position or fliph state changed, in the order they were
+    /// applied.
+    pub moved: Vec<usize>,
+    /// Possessions that happened, as (previous player, new player).
+    pub possessed: Vec<(usize, usize)>,
+    /// Reasons why the moves of the players that didn't move failed, in the
+    /// order they were encountered.
+    pub failures: Vec<MoveFailure>,
+}
+
+/// The reason why an attempted interaction failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MoveFailure {
+    /// A wall can't be pushed.
+    Wall { block_no: BlockNo, pos: Pos },
+    /// The cell is not inside any block, so it can't move.
+    Immovable { cell_id: usize },
+    /// The block can't be exited, e.g. it's a top-level block.
+    NoExit { block_no: BlockNo },
+    /// The block can't be entered.
+    EnterRefused {
+        block_no: BlockNo,
+        reason: EnterRefusal,
+    },
+    /// The cell is already moving in another way.
+    Cycle { cell_id: usize },
+    /// The cell can't be possessed.
+    NotPossessable { cell_id: usize },
+    /// The cell is locked, so it can't be moved by others.
+    Locked { cell_id: usize },
+    /// A one-way gate doesn't allow moving onto it in this direction.
+    OneWay { block_no: BlockNo, pos: Pos },
+    /// The door is closed.
+    Door { block_no: BlockNo, pos: Pos },
+    /// The move took more steps than allowed by `GameConfig::fuel`.
+    /// `depth` is the number of cells scheduled to move at that point.
+    OutOfFuel { budget: u32, depth: usize },
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-struct MoveState {
-    cell_id: usize,
-    direction: Direction,
-    gpos: GlobalPos,
-    fliph: bool,
+pub enum EnterRefusal {
+    /// The block is filled with walls.
+    Filled,
+    /// Infinite exits can't be entered.
+    InfExit,
+    /// Cells floating in space can't be entered.
+    InSpace,
+    /// The block is moving at the same time.
+    Moving,
+}
+
+impl SimulatorBuffers {
+    // takes the outcome of the last move, emptied for the next one
+    pub(super) fn take_outcome(&mut self) -> MoveOutcome {
+        let mut outcome = std::mem::take(&mut self.outcome);
+        outcome.clear();
+        outcome
+    }
+
+    pub(super) fn put_outcome(&mut self, outcome: MoveOutcome) -> &MoveOutcome {
+        self.outcome = outcome;
+        &self.outcome
+    }
+}
+
+impl MoveOutcome {
+    // empties the outcome, keeping the capacity
+    fn clear(&mut self) {
+        self.moved.clear();
+        self.possessed.clear();
+        self.failures.clear();
+    }
+
+    /// Whether the move changed the state of the game.
+    pub fn changed(&self) -> bool {
+        !self.moved.is_empty() || !self.possessed.is_empty()
+    }
+
+    /// Returns the failure that best explains why nothing happened, which is
+    /// the first failure encountered, usually caused by the highest priority
+    /// action. Running out of fuel takes precedence over anything else.
+    pub fn explanation(&self) -> Option<&MoveFailure> {
+        self.failures
+            .iter()
+            .find(|failure| matches!(failure, MoveFailure::OutOfFuel { .. }))
+            .or(self.failures.first())
+    }
+
+    pub fn out_of_fuel(&self) -> bool {
+        self.failures
+            .iter()
+            .any(|failure| matches!(failure, MoveFailure::OutOfFuel { .. }))
+    }
+}
+
+impl std::fmt::Display for MoveFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveFailure::Wall {
+                block_no,
+                pos: Pos(x, y),
+            } => write!(f, "blocked by a wall at ({x}, {y}) in block {block_no}"),
+            MoveFailure::Immovable { cell_id } => {
+                write!(f, "cell {cell_id} is not inside any block")
+            }
+            MoveFailure::NoExit { block_no } => write!(f, "block {block_no} has no exit"),
+            MoveFailure::EnterRefused { block_no, reason } => {
+                let reason = match reason {
+                    EnterRefusal::Filled => "it is filled",
+                    EnterRefusal::InfExit => "it is an infinite exit",
+                    EnterRefusal::InSpace => "it is floating in space",
+                    EnterRefusal::Moving => "it is moving",
+                };
+                write!(f, "cannot enter block {block_no}: {reason}")
+            }
+            MoveFailure::Cycle { cell_id } => {
+                write!(f, "cell {cell_id} is already moving in another direction")
+            }
+            MoveFailure::NotPossessable { cell_id } => {
+                write!(f, "cell {cell_id} cannot be possessed")
+            }
+            MoveFailure::Locked { cell_id } => {
+                write!(f, "cell {cell_id} is locked")
+            }
+            MoveFailure::OneWay {
+                block_no,
+                pos: Pos(x, y),
+            } => write!(
+                f,
+                "blocked by a one-way gate at ({x}, {y}) in block {block_no}"
+            ),
+            MoveFailure::Door {
+                block_no,
+                pos: Pos(x, y),
+            } => write!(
+                f,
+                "blocked by a closed door at ({x}, {y}) in block {block_no}"
+            ),
+            MoveFailure::OutOfFuel { budget, depth } => write!(
+                f,
+                "gave up after {budget} steps with {depth} cells scheduled to move"
+            ),
+        }
+    }
+}
+
+/// A cell scheduled to move, as it is being moved by the simulator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MoveState {
+    pub cell_id: usize,
+    pub direction: Direction,
+    pub gpos: GlobalPos,
+    pub fliph: bool,
 }
 
-#[derive(Default)]
 struct TransferCache {
     exit: Vec<TransferState>,
     enter: Vec<TransferState>,
 }
 
-type TransferPoint = num_rational::Rational32;
+/// Where a cell crosses the side of a block when it exits or enters it, as
+/// a fraction of the side, between 0 and 1.
+pub type TransferPoint = num_rational::Rational32;
 // (context_no, direction)
 type ExitKey = (BlockNo, Direction);
 // (block_no, direction, enter_point)
 type EnterKey = (BlockNo, Direction, TransferPoint);
 
+/// Maximum number of times the cells fall by one position after a move, with
+/// gravity. This stops cells that fall through a loop of blocks forever.
+const MAX_FALL_ROUNDS: usize = 1024;
+
 const MIDDLE_POINT: TransferPoint = TransferPoint::new_raw(1, 2);
 const ONE_POINT: TransferPoint = TransferPoint::new_raw(1, 1);
 
@@ -80,7 +272,7 @@ impl MoveState {
     }
 
     fn apply(self, game: &mut Game) {
-        match &mut game.cells[self.cell_id] {
+        match &mut game.cells_mut()[self.cell_id] {
             Cell::Wall(wall) => {
                 wall.gpos = self.gpos;
                 wall.fliph = self.fliph;
@@ -97,6 +289,17 @@ impl MoveState {
     }
 }
 
+// Caches are reused for different moves, so each of them has room for a few
+// states from the start, which is enough for typical moves.
+impl Default for TransferCache {
+    fn default() -> Self {
+        Self {
+            exit: Vec::with_capacity(4),
+            enter: Vec::with_capacity(4),
+        }
+    }
+}
+
 impl TransferCache {
     fn clear(&mut self) {
         self.exit.clear();
@@ -123,33 +326,200 @@ impl TransferCache {
     }
 }
 
+impl<'a> Simulator<'a> {
+    /// Sends the calls made by the following moves to the sink.
+    pub(super) fn set_sink(&mut self, sink: &'a mut dyn SimTrace) {
+        self.sink = Some(sink);
+    }
+}
+
 impl Simulator<'_> {
-    pub fn new(game: &mut Game) -> Simulator {
+    pub fn new(game: &mut Game) -> Simulator<'_> {
+        Simulator::with_buffers(game, &mut SimulatorBuffers::default())
+    }
+
+    /// Creates a simulator using the given buffers, which should be given
+    /// back with `release` afterwards.
+    pub fn with_buffers<'a>(game: &'a mut Game, buffers: &mut SimulatorBuffers) -> Simulator<'a> {
+        let mut transfer_cache = buffers.spare_caches.pop().unwrap_or_default();
+        transfer_cache.clear();
         Simulator {
             game,
             player_index: 0,
-            move_stack: Vec::new(),
+            move_stack: std::mem::take(&mut buffers.move_stack),
             move_index: 0,
-            transfer_cache: Default::default(),
-            transfer_stack: Vec::new(),
+            transfer_cache,
+            transfer_stack: std::mem::take(&mut buffers.transfer_stack),
+            spare_caches: std::mem::take(&mut buffers.spare_caches),
+            failures: std::mem::take(&mut buffers.failures),
+            fuel: 0,
+            falling: false,
+            trace: None,
+            trace_depth: 0,
+            trace_current: None,
+            sink: None,
         }
     }
 
-    pub fn play(&mut self, direction: Direction) {
+    /// Starts recording the decisions made by the following moves.
+    pub(super) fn record_trace(&mut self) {
+        self.trace = Some(Vec::new());
+        self.trace_depth = 0;
+    }
+
+    /// Returns the decisions recorded since `record_trace`.
+    pub(super) fn take_trace(&mut self) -> Vec<TraceEvent> {
+        self.trace.take().unwrap_or_default()
+    }
+
+    /// Gives the buffers back, to be used by the next simulator.
+    pub fn release(mut self, buffers: &mut SimulatorBuffers) {
+        self.reset();
+        self.spare_caches.push(self.transfer_cache);
+        buffers.move_stack = self.move_stack;
+        buffers.transfer_stack = self.transfer_stack;
+        buffers.spare_caches = self.spare_caches;
+        buffers.failures = self.failures;
+    }
+
+    pub fn play(&mut self, direction: Direction) -> MoveOutcome {
+        let mut outcome = MoveOutcome::default();
+        self.play_into(direction, &mut outcome);
+        outcome
+    }
+
+    /// Plays a move like `play`, adding the results to the given outcome.
+    pub fn play_into(&mut self, direction: Direction, outcome: &mut MoveOutcome) {
         for i in 0..self.game.player_ids.len() {
             self.player_index = i;
-            if self.try_move(self.game.player_ids[i], direction) {
-                for state in &self.move_stack[self.move_index..] {
-                    state.apply(self.game);
+            self.fuel = self.game.level.config.fuel.unwrap_or(u32::MAX);
+            let player_id = self.game.player_ids[i];
+            let from = self.game.cells[player_id].gpos();
+
+            if self.try_move(player_id, direction) {
+                self.apply_moves(outcome);
+                if self.game.player_ids[i] != player_id {
+                    outcome.possessed.push((player_id, self.game.player_ids[i]));
                 }
+            } else {
+                outcome.failures.append(&mut self.failures);
+            }
+            self.reset();
+
+            if self.game.level.config.pull && self.game.cells[player_id].gpos() != from {
+                self.pull(from, direction, outcome);
+            }
+        }
+
+        if self.game.level.config.gravity {
+            self.settle(outcome);
+        }
+
+        // the cells synthesized for this move may not be needed anymore
+        if self.game.collect_synthesized() > 0 {
+            let len = self.game.cells.len();
+            outcome.moved.retain(|&id| id < len);
+        }
+
+        #[cfg(all(debug_assertions, feature = "check-invariants"))]
+        if let Some(violation) = self.game.check_invariants().first() {
+            panic!("Broken invariant after playing {direction:?}: {violation}");
+        }
+    }
+
+    /// Applies the moves that can be made to the game, and records the cells
+    /// that changed.
+    fn apply_moves(&mut self, outcome: &mut MoveOutcome) {
+        // free all the old positions first, as the cells may move into each
+        // other's positions
+        for state in &self.move_stack[self.move_index..] {
+            let gpos = self.game.cells[state.cell_id].gpos();
+            self.game.occupy(gpos, false);
+        }
+        for state in &self.move_stack[self.move_index..] {
+            let cell = &self.game.cells[state.cell_id];
+            if cell.gpos() != state.gpos || cell.fliph() != state.fliph {
+                outcome.moved.push(state.cell_id);
             }
-            self.move_stack.clear();
-            self.move_index = 0;
-            self.transfer_stack.clear();
-            self.transfer_cache.clear();
+            state.apply(self.game);
+            self.game.occupy(state.gpos, true);
         }
     }
 
+    /// Clears the state of the last move.
+    fn reset(&mut self) {
+        self.move_stack.clear();
+        self.move_index = 0;
+        self.spare_caches
+            .extend(self.transfer_stack.drain(..).map(|mut cache| {
+                cache.clear();
+                cache
+            }));
+        self.transfer_cache.clear();
+        self.failures.clear();
+    }
+
+    /// Drags the cell behind the position a player moved away from along in
+    /// the same direction, if there is one and it isn't a wall. Nothing
+    /// happens if the cell can't move.
+    fn pull(&mut self, from: GlobalPos, direction: Direction, outcome: &mut MoveOutcome) {
+        let behind = GlobalPos {
+            container: from.container,
+            pos: from.pos.towards(direction.opposite()),
+        };
+        let Some(cell) = self.game.cell_at(behind) else {
+            return;
+        };
+        if cell.is_wall() {
+            return;
+        }
+
+        let cell_id = cell.id();
+        self.fuel = self.game.level.config.fuel.unwrap_or(u32::MAX);
+        let pull = TraceKind::Pull { cell_id, direction };
+        if self.traced(pull, |simulator| simulator.try_move(cell_id, direction)) {
+            self.apply_moves(outcome);
+        }
+        self.reset();
+    }
+
+    /// Lets the cells fall down one position at a time until none can fall
+    /// any further, or `MAX_FALL_ROUNDS` rounds have passed.
+    ///
+    /// A cell falls if the position below it is empty, or if it can enter the
+    /// cell there. Cells at the bottom of a block fall out of it. Walls and
+    /// cells floating in space don't fall.
+    fn settle(&mut self, outcome: &mut MoveOutcome) {
+        self.falling = true;
+        for _ in 0..MAX_FALL_ROUNDS {
+            let mut fell = false;
+            for cell_id in 0..self.game.cells.len() {
+                let cell = &self.game.cells[cell_id];
+                let gpos = cell.gpos();
+                if cell.is_wall()
+                    || gpos.container == Container::Space
+                    || self.game.is_floating(gpos)
+                {
+                    continue;
+                }
+
+                self.fuel = self.game.level.config.fuel.unwrap_or(u32::MAX);
+                let fall = TraceKind::Fall { cell_id };
+                if self.traced(fall, |simulator| {
+                    simulator.try_move(cell_id, Direction::Down)
+                }) {
+                    self.apply_moves(outcome);
+                    fell = true;
+                }
+                self.reset();
+            }
+            if !fell {
+                break;
+            }
+        }
+        self.falling = false;
+    }
+
     /// Checks whether the given cell is already in the move stack, that is, a
     /// cycle exists.
     ///
@@ -175,6 +545,28 @@ impl Simulator<'_> {
         }
     }
 
+    /// Consumes one step of fuel. Returns false if the fuel has run out, in
+    /// which case the current move must fail.
+    fn consume_fuel(&mut self) -> bool {
+        if let Some(fuel) = self.fuel.checked_sub(1) {
+            self.fuel = fuel;
+            return true;
+        }
+        // every step after running out fails the same way, which is only
+        // reported once
+        let reported = self
+            .failures
+            .iter()
+            .any(|failure| matches!(failure, MoveFailure::OutOfFuel { .. }));
+        if !reported {
+            self.fail(MoveFailure::OutOfFuel {
+                budget: self.game.level.config.fuel.unwrap_or(u32::MAX),
+                depth: self.move_stack.len(),
+            });
+        }
+        false
+    }
+
     /// Starts a new move and push it to the move stack. Also pushes the old
     /// transfer cache to the transfer stack.
     ///
@@ -182,23 +574,87 @@ impl Simulator<'_> {
     fn push_move(&mut self, cell_id: usize, direction: Direction) -> MoveState {
         let current = MoveState::new(&self.game.cells[cell_id], direction);
         self.move_stack.push(current);
+        let cache = self.spare_caches.pop().unwrap_or_default();
         self.transfer_stack
-            .push(std::mem::take(&mut self.transfer_cache));
+            .push(std::mem::replace(&mut self.transfer_cache, cache));
         current
     }
 
     /// Pops the last move from the move stack, restoring the transfer cache.
     fn pop_move(&mut self) {
         self.move_stack.pop();
-        self.transfer_cache = self.transfer_stack.pop().unwrap();
+        let mut cache =
+            std::mem::replace(&mut self.transfer_cache, self.transfer_stack.pop().unwrap());
+        cache.clear();
+        self.spare_caches.push(cache);
+    }
+
+    /// Makes a decision with the given function, recording it in the trace
+    /// if there is one.
+    fn traced(&mut self, kind: TraceKind, decide: impl FnOnce(&mut Self) -> bool) -> bool {
+        let Some(trace) = &mut self.trace else {
+            return decide(self);
+        };
+        let index = trace.len();
+        trace.push(TraceEvent {
+            depth: self.trace_depth,
+            kind,
+            success: false,
+            failure: None,
+        });
+
+        self.trace_depth += 1;
+        let parent = self.trace_current.replace(index);
+        let success = decide(self);
+        self.trace_current = parent;
+        self.trace_depth -= 1;
+        if let Some(trace) = &mut self.trace {
+            trace[index].success = success;
+        }
+        success
+    }
+
+    /// Sends a call to the sink, if there is one, at the depth of the move
+    /// stack.
+    fn log(&mut self, call: impl FnOnce() -> SimCall) {
+        if let Some(sink) = &mut self.sink {
+            sink.call(self.move_stack.len(), &call());
+        }
+    }
+
+    /// Records why an interaction failed, also in the decision being made if
+    /// the decisions are recorded.
+    fn fail(&mut self, failure: MoveFailure) {
+        if let (Some(trace), Some(index)) = (&mut self.trace, self.trace_current) {
+            trace[index].failure.get_or_insert_with(|| failure.clone());
+        }
+        self.failures.push(failure);
     }
 
     /// Attempts to move the given cell towards the given direction.
     ///
     /// Returns true if the movement was successful.
     fn try_move(&mut self, cell_id: usize, direction: Direction) -> bool {
-        // print!("{}", "  ".repeat(self.move_stack.len()));
-        // println!("try_move: {:?} {:?}", cell_id, direction);
+        self.traced(TraceKind::Move { cell_id, direction }, |simulator| {
+            simulator.move_cell(cell_id, direction)
+        })
+    }
+
+    fn move_cell(&mut self, cell_id: usize, direction: Direction) -> bool {
+        self.log(|| SimCall::Move { cell_id, direction });
+
+        if !self.consume_fuel() {
+            return false;
+        }
+
+        // locked cells only move by themselves, as the player
+        let player_id = self.game.player_ids.get(self.player_index);
+        if self.game.cells[cell_id].locked()
+            && (self.falling || !self.move_stack.is_empty() || player_id != Some(&cell_id))
+        {
+            self.fail(MoveFailure::Locked { cell_id });
+            return false;
+        }
 
         match self.check_cycle(cell_id, direction) {
             Some(Ok(i)) => {
@@ -207,27 +663,38 @@ impl Simulator<'_> {
                 self.move_index = i;
                 return true;
             }
-            Some(Err(())) => return false,
+            Some(Err(())) => {
+                self.fail(MoveFailure::Cycle { cell_id });
+                return false;
+            }
             None => (),
         }
 
         let current = self.push_move(cell_id, direction);
-        if current.gpos.block_id != usize::MAX && self.try_exit(current, MIDDLE_POINT) {
-            true
-        } else {
-            self.pop_move();
-            false
+        if current.gpos.container == Container::Space {
+            self.fail(MoveFailure::Immovable { cell_id });
+        } else if self.try_exit(current, MIDDLE_POINT) {
+            return true;
         }
+
+        self.pop_move();
+        false
     }
 
     fn try_exit(&mut self, mut current: MoveState, mut exit_point: TransferPoint) -> bool {
-        // print!("{}", "  ".repeat(self.move_stack.len()));
-        // println!("try_exit: {:?} {:?}", current, exit_point);
+        self.log(|| SimCall::Exit {
+            state: current,
+            point: exit_point,
+        });
+
+        if !self.consume_fuel() {
+            return false;
+        }
 
         // first, try to move the cell in the given direction
         current.gpos.pos.go(current.direction);
 
-        let block: &Block = self.game.cells[current.gpos.block_id].block().unwrap();
+        let block: &Block = self.game.container_block(current.gpos).unwrap();
         // if the new position is still in the same block, we're done
         if block.in_bounds(current.gpos.pos) {
             return self.try_interact_pos(current, exit_point);
@@ -237,6 +704,9 @@ impl Simulator<'_> {
         // first, check if the block can be exited
         let exit_id = self.game.exit_id_for(block);
         if exit_id.is_none() {
+            self.fail(MoveFailure::NoExit {
+                block_no: block.block_no,
+            });
             return false;
         }
         let mut exit = &self.game.cells[exit_id.unwrap()];
@@ -292,14 +762,21 @@ impl Simulator<'_> {
             };
             current.fliph = !current.fliph;
         }
+        // then turn it if the exit is rotated
+        turn_out(&mut current.direction, &mut exit_point, exit.rotation());
 
         // try again from the new exit
         current.gpos = exit.gpos();
-        if self.try_exit(current, exit_point) {
+        let kind = TraceKind::Exit {
+            cell_id: current.cell_id,
+            exit_id,
+        };
+        if self.traced(kind, |simulator| simulator.try_exit(current, exit_point)) {
             return true;
         }
 
-        if self.game.config.shed {
+        // falling cells don't push their blocks up
+        if self.game.level.config.shed && !self.falling {
             self.move_stack.last_mut().unwrap().update(current);
 
             if self.try_move(exit_id, current.direction.opposite()) {
@@ -314,8 +791,20 @@ impl Simulator<'_> {
     ///
     /// Returns true if the occupation was successful.
     fn try_interact_pos(&mut self, current: MoveState, point: TransferPoint) -> bool {
-        // print!("{}", "  ".repeat(self.move_stack.len()));
-        // println!("try_interact_pos: {:?} {:?}", current, point);
+        self.log(|| SimCall::InteractPos {
+            state: current,
+            point,
+        });
+
+        if let Some(floor) = self.game.blocking_floor(current.gpos, current.direction) {
+            let block_no = self.game.container_block(current.gpos).unwrap().block_no;
+            let pos = current.gpos.pos;
+            self.fail(match floor.kind {
+                FloorKind::Door(_) => MoveFailure::Door { block_no, pos },
+                _ => MoveFailure::OneWay { block_no, pos },
+            });
+            return false;
+        }
 
         if let Some(target) = self.game.cell_at(current.gpos) {
             // some cell exists at the target position
@@ -335,29 +824,46 @@ impl Simulator<'_> {
     ///
     /// Returns true if the interaction was successful.
     fn try_interact(&mut self, current: MoveState, target_id: usize, point: TransferPoint) -> bool {
-        self.game
-            .config
-            .attempt_order
-            .clone()
-            .iter()
-            .any(|action_type| match action_type {
-                ActionType::Push => self.try_push(current, target_id),
+        // indexed, since the simulator is borrowed mutably by the actions
+        (0..self.game.level.config.attempt_order.len()).any(|i| {
+            let action_type = self.game.level.config.attempt_order[i];
+            // falling cells are held up by the cells below them, unless they
+            // can enter them
+            if self.falling && action_type != ActionType::Enter {
+                return false;
+            }
+            let attempt = TraceKind::Attempt {
+                action: action_type,
+                cell_id: current.cell_id,
+                target_id,
+            };
+            self.traced(attempt, |simulator| match action_type {
+                ActionType::Push => simulator.try_push(current, target_id),
                 ActionType::Enter => {
-                    let moving = &self.move_stack[self.move_index..];
+                    let moving = &simulator.move_stack[simulator.move_index..];
                     if moving.iter().any(|s| s.cell_id == target_id) {
                         // entering a moving cell is not allowed
+                        if let Some(block_no) = simulator.game.cells[target_id].target_no() {
+                            simulator.fail(MoveFailure::EnterRefused {
+                                block_no,
+                                reason: EnterRefusal::Moving,
+                            });
+                        }
                         return false;
                     }
-                    self.try_enter(current, target_id, point)
+                    simulator.try_enter(current, target_id, point)
                 }
-                ActionType::Eat => self.try_eat(current, target_id),
-                ActionType::Possess => self.try_possess(current.cell_id, target_id),
+                ActionType::Eat => simulator.try_eat(current, target_id),
+                ActionType::Possess => simulator.try_possess(current.cell_id, target_id),
             })
+        })
     }
 
     fn try_push(&mut self, current: MoveState, target_id: usize) -> bool {
-        // print!("{}", "  ".repeat(self.move_stack.len()));
-        // println!("try_push: {:?} {:?}", current, target_id);
+        self.log(|| SimCall::Push {
+            state: current,
+            target_id,
+        });
 
         // move the pusher to the new position
         self.move_stack.last_mut().unwrap().update(current);
@@ -372,9 +878,9 @@ impl Simulator<'_> {
                 return true;
             }
 
-            if self.game.config.inner_push {
+            if self.game.level.config.inner_push {
                 // try to move the parent block of the wall
-                let parent = self.game.cells[target.gpos().block_id].block().unwrap();
+                let parent = self.game.container_block(target.gpos()).unwrap();
                 if let Some(exit_id) = self.game.exit_id_for(parent) {
                     // even if the inner push succeeds, previous movements cannot be made
                     let old_move_index = self.move_index;
@@ -390,6 +896,7 @@ impl Simulator<'_> {
                             _ => (),
                         };
                     }
+                    let direction = turn_direction_out(direction, exit.rotation());
 
                     if self.try_move(exit_id, direction) {
                         return true;
@@ -399,6 +906,12 @@ impl Simulator<'_> {
                     self.move_index = old_move_index;
                 }
             }
+
+            let gpos = self.game.cells[target_id].gpos();
+            self.fail(MoveFailure::Wall {
+                block_no: self.game.container_block(gpos).unwrap().block_no,
+                pos: gpos.pos,
+            });
             return false;
         }
 
@@ -412,31 +925,51 @@ impl Simulator<'_> {
         target_id: usize,
         mut enter_point: TransferPoint,
     ) -> bool {
-        // print!("{}", "  ".repeat(self.move_stack.len()));
-        // println!("try_enter: {:?} {:?} {:?}", current, target_id, enter_point);
+        self.log(|| SimCall::Enter {
+            state: current,
+            target_id,
+            point: enter_point,
+        });
+
+        if !self.consume_fuel() {
+            return false;
+        }
 
         let target = &self.game.cells[target_id];
-        let mut block = match &target {
+        let (mut block, refusal) = match &target {
             Cell::Wall(_) => return false,
             Cell::Block(block) => {
-                if self.game.is_space(block.gpos.block_id) {
-                    return false;
-                }
-                block
+                let refusal = if self.game.is_floating(block.gpos) {
+                    Some(EnterRefusal::InSpace)
+                } else {
+                    None
+                };
+                (block, refusal)
             }
             Cell::Reference(reference) => {
-                if !reference.can_enter() || self.game.is_space(reference.gpos.block_id) {
-                    return false;
-                }
-                self.game.block_by_no(reference.target_no).unwrap()
+                let refusal = if !reference.can_enter() {
+                    Some(EnterRefusal::InfExit)
+                } else if self.game.is_floating(reference.gpos) {
+                    Some(EnterRefusal::InSpace)
+                } else {
+                    None
+                };
+                (self.game.block_by_no(reference.target_no).unwrap(), refusal)
             }
         };
 
-        if !block.can_enter() {
+        let refusal = refusal.or((!block.can_enter()).then_some(EnterRefusal::Filled));
+        if let Some(reason) = refusal {
+            self.fail(MoveFailure::EnterRefused {
+                block_no: block.block_no,
+                reason,
+            });
             return false;
         }
 
-        // flip the direction if necessary
+        // undo the rotation of the target first, then flip the direction if
+        // necessary
+        turn_in(&mut current.direction, &mut enter_point, target.rotation());
         if target.fliph() {
             match current.direction {
                 Direction::Left => current.direction = Direction::Right,
@@ -488,31 +1021,38 @@ impl Simulator<'_> {
         };
 
         // determine the enter pos
-        current.gpos = GlobalPos {
-            block_id: block.id,
-            pos: match current.direction {
+        current.gpos = GlobalPos::new(
+            block.id,
+            match current.direction {
                 Direction::Up => Pos(enter_coord(block.width), 0),
                 Direction::Down => Pos(enter_coord(block.width), block.height - 1),
                 Direction::Left => Pos(block.width - 1, enter_coord(block.height)),
                 Direction::Right => Pos(0, enter_coord(block.height)),
             },
-        };
+        );
 
         // try to interact with the enter pos
         self.try_interact_pos(current, enter_point)
     }
 
     fn try_eat(&mut self, current: MoveState, target_id: usize) -> bool {
-        // print!("{}", "  ".repeat(self.move_stack.len()));
-        // println!("try_eat: {:?} {:?}", current, target_id);
+        self.log(|| SimCall::Eat {
+            state: current,
+            target_id,
+        });
 
         let target = &self.game.cells[target_id];
         if target.is_wall() {
             return false;
         }
+        if target.locked() {
+            self.fail(MoveFailure::Locked { cell_id: target_id });
+            return false;
+        }
 
         // cycles are not allowed in eat
         if self.move_stack.iter().any(|s| s.cell_id == target_id) {
+            self.fail(MoveFailure::Cycle { cell_id: target_id });
             return false;
         }
 
@@ -560,19 +1100,55 @@ impl Simulator<'_> {
     }
 
     fn try_possess(&mut self, source_id: usize, target_id: usize) -> bool {
+        self.log(|| SimCall::Possess {
+            cell_id: source_id,
+            target_id,
+        });
+
         // only the current player can possess
         if source_id != self.game.player_ids[self.player_index] {
             return false;
         }
 
         let target = &self.game.cells[target_id];
-        if target.possessable() && !self.game.player_ids.contains(&target_id) {
-            self.game.player_ids[self.player_index] = target_id;
+        if target.possessable() && !self.game.is_player(target_id) {
+            self.game.replace_player(self.player_index, target_id);
             // no cells can be moved
             self.move_index = self.move_stack.len();
             return true;
         }
 
+        self.fail(MoveFailure::NotPossessable { cell_id: target_id });
         false
     }
 }
+
+// Turns a move leaving the interior of a reference rotated by the given
+// number of quarter turns into the direction and transfer point outside of
+// it. The transfer point is measured along the x or y axis, so it is
+// mirrored when a turn maps that axis onto the opposite one.
+fn turn_out(direction: &mut Direction, point: &mut TransferPoint, rotation: u8) {
+    for _ in 0..rotation {
+        if matches!(direction, Direction::Left | Direction::Right) {
+            *point = ONE_POINT - *point;
+        }
+        *direction = direction.turn_left();
+    }
+}
+
+// Turns only the direction of a move leaving a rotated reference, like
+// `turn_out`, for moves that don't transfer at a point.
+fn turn_direction_out(direction: Direction, rotation: u8) -> Direction {
+    (0..rotation).fold(direction, |direction, _| direction.turn_left())
+}
+
+// Turns a move entering a rotated reference into the direction and transfer
+// point inside of it, undoing `turn_out`.
+fn turn_in(direction: &mut Direction, point: &mut TransferPoint, rotation: u8) {
+    for _ in 0..rotation {
+        if matches!(direction, Direction::Up | Direction::Down) {
+            *point = ONE_POINT - *point;
+        }
+        *direction = direction.turn_right();
+    }
+}
@@ -0,0 +1,495 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::game::*;
+use super::simulation::SimulatorBuffers;
+use super::state::StateKey;
+use super::utility::*;
+
+/// Estimates the number of moves needed to win from a state. Lower is better.
+pub type Heuristic = fn(&Game) -> u32;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Breadth-first search, which finds a shortest solution.
+    Bfs,
+    /// A* search, ordering states by moves taken plus the heuristic.
+    AStar,
+    /// Greedy best-first search, ordering states by the heuristic only.
+    Greedy,
+}
+
+#[derive(Clone, Debug)]
+pub struct SolverConfig {
+    pub strategy: Strategy,
+    pub heuristic: Heuristic,
+    /// Maximum number of states to expand before giving up.
+    pub max_nodes: usize,
+    /// Number of worker threads. With more than one thread, the order in
+    /// which states are expanded is only approximate, so BFS may not find a
+    /// shortest solution.
+    pub threads: usize,
+}
+
+/// The result of a search.
+#[derive(Clone, Debug)]
+pub struct SolveReport {
+    pub solution: Option<Vec<Direction>>,
+    /// Number of states expanded.
+    pub nodes: usize,
+    /// Whether every reachable state has been explored. If so, a missing
+    /// solution means that the level is unsolvable.
+    pub exhausted: bool,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::Bfs,
+            heuristic: goal_distance,
+            max_nodes: 1_000_000,
+            threads: 1,
+        }
+    }
+}
+
+// a search node, linked to its parent for reconstructing the solution
+struct Node {
+    parent: usize,
+    direction: Direction,
+    depth: u32,
+}
+
+// an entry of the priority queue used by A* and greedy search
+struct Entry {
+    priority: u32,
+    node: usize,
+    game: Game,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        (self.priority, self.node) == (other.priority, other.node)
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // ties are broken by insertion order
+        (self.priority, self.node).cmp(&(other.priority, other.node))
+    }
+}
+
+/// Searches for a sequence of moves that wins the game. States which are
+/// provably dead ends are not expanded.
+pub fn solve(game: &Game, config: &SolverConfig) -> SolveReport {
+    if config.threads > 1 {
+        return solve_parallel(game, config);
+    }
+
+    if game.won() {
+        return SolveReport {
+            solution: Some(Vec::new()),
+            nodes: 0,
+            exhausted: false,
+        };
+    }
+
+    let mut nodes = vec![Node {
+        parent: usize::MAX,
+        direction: Direction::Up,
+        depth: 0,
+    }];
+    let mut visited = HashSet::new();
+    visited.insert(game.state_key());
+
+    let mut bfs_queue = VecDeque::new();
+    let mut heap = BinaryHeap::new();
+    match config.strategy {
+        Strategy::Bfs => bfs_queue.push_back((0, game.clone())),
+        _ => heap.push(Reverse(Entry {
+            priority: 0,
+            node: 0,
+            game: game.clone(),
+        })),
+    }
+
+    let mut buffers = SimulatorBuffers::default();
+    let mut expanded = 0;
+    while expanded < config.max_nodes {
+        let (index, state) = match config.strategy {
+            Strategy::Bfs => match bfs_queue.pop_front() {
+                Some(item) => item,
+                None => break,
+            },
+            _ => match heap.pop() {
+                Some(Reverse(entry)) => (entry.node, entry.game),
+                None => break,
+            },
+        };
+        expanded += 1;
+
+        for direction in Direction::ALL {
+            let mut next = state.clone();
+            if !next.play_with(direction, &mut buffers).changed()
+                || !visited.insert(next.state_key())
+            {
+                continue;
+            }
+            if next.is_dead_end() {
+                continue;
+            }
+
+            let depth = nodes[index].depth + 1;
+            nodes.push(Node {
+                parent: index,
+                direction,
+                depth,
+            });
+            let node = nodes.len() - 1;
+
+            if next.won() {
+                return SolveReport {
+                    solution: Some(reconstruct(&nodes, node)),
+                    nodes: expanded,
+                    exhausted: false,
+                };
+            }
+
+            match config.strategy {
+                Strategy::Bfs => bfs_queue.push_back((node, next)),
+                Strategy::AStar => heap.push(Reverse(Entry {
+                    priority: depth + (config.heuristic)(&next),
+                    node,
+                    game: next,
+                })),
+                Strategy::Greedy => heap.push(Reverse(Entry {
+                    priority: (config.heuristic)(&next),
+                    node,
+                    game: next,
+                })),
+            }
+        }
+    }
+
+    SolveReport {
+        solution: None,
+        nodes: expanded,
+        exhausted: bfs_queue.is_empty() && heap.is_empty(),
+    }
+}
+
+// the moves leading to a state in the parallel search, shared between the
+// descendants of the state
+struct Path {
+    parent: Option<Arc<Path>>,
+    direction: Direction,
+}
+
+struct Task {
+    priority: u32,
+    // global insertion order, for breaking ties
+    order: usize,
+    depth: u32,
+    path: Option<Arc<Path>>,
+    game: Game,
+}
+
+impl PartialEq for Task {
+    fn eq(&self, other: &Self) -> bool {
+        (self.priority, self.order) == (other.priority, other.order)
+    }
+}
+
+impl Eq for Task {}
+
+impl PartialOrd for Task {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Task {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.priority, self.order).cmp(&(other.priority, other.order))
+    }
+}
+
+// state shared between the workers of a parallel search
+struct Shared<'a> {
+    config: &'a SolverConfig,
+    // one queue per worker; idle workers steal from the others
+    queues: Vec<Mutex<BinaryHeap<Reverse<Task>>>>,
+    // the visited set, split into shards to reduce contention
+    visited: Vec<Mutex<HashSet<StateKey>>>,
+    // number of tasks queued or being expanded
+    pending: AtomicUsize,
+    order: AtomicUsize,
+    expanded: AtomicUsize,
+    stop: AtomicBool,
+    solution: Mutex<Option<Vec<Direction>>>,
+}
+
+const VISITED_SHARDS: usize = 64;
+
+impl Shared<'_> {
+    fn visit(&self, key: StateKey) -> bool {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let shard = hasher.finish() as usize % VISITED_SHARDS;
+        self.visited[shard].lock().unwrap().insert(key)
+    }
+
+    fn push(&self, worker: usize, depth: u32, path: Option<Arc<Path>>, game: Game) {
+        let priority = match self.config.strategy {
+            Strategy::Bfs => depth,
+            Strategy::AStar => depth + (self.config.heuristic)(&game),
+            Strategy::Greedy => (self.config.heuristic)(&game),
+        };
+        let task = Task {
+            priority,
+            order: self.order.fetch_add(1, Ordering::Relaxed),
+            depth,
+            path,
+            game,
+        };
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        self.queues[worker].lock().unwrap().push(Reverse(task));
+    }
+
+    // takes a task from the worker's own queue, or steals one from others
+    fn pop(&self, worker: usize) -> Option<Task> {
+        let count = self.queues.len();
+        (0..count).find_map(|i| {
+            let mut queue = self.queues[(worker + i) % count].lock().unwrap();
+            queue.pop().map(|Reverse(task)| task)
+        })
+    }
+
+    fn work(&self, worker: usize) {
+        let mut buffers = SimulatorBuffers::default();
+        while !self.stop.load(Ordering::SeqCst) {
+            let Some(task) = self.pop(worker) else {
+                if self.pending.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+                std::thread::yield_now();
+                continue;
+            };
+
+            if self.expanded.fetch_add(1, Ordering::SeqCst) >= self.config.max_nodes {
+                self.stop.store(true, Ordering::SeqCst);
+                break;
+            }
+            self.expand(worker, task, &mut buffers);
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    fn expand(&self, worker: usize, task: Task, buffers: &mut SimulatorBuffers) {
+        let path = task.path;
+        for direction in Direction::ALL {
+            let mut next = task.game.clone();
+            if !next.play_with(direction, buffers).changed() || !self.visit(next.state_key()) {
+                continue;
+            }
+            if next.is_dead_end() {
+                continue;
+            }
+
+            let path = Path {
+                parent: path.clone(),
+                direction,
+            };
+            if next.won() {
+                let mut solution = self.solution.lock().unwrap();
+                if solution.is_none() {
+                    *solution = Some(unwind(&path));
+                }
+                self.stop.store(true, Ordering::SeqCst);
+                return;
+            }
+
+            self.push(worker, task.depth + 1, Some(Arc::new(path)), next);
+        }
+    }
+}
+
+fn unwind(mut path: &Path) -> Vec<Direction> {
+    let mut moves = vec![path.direction];
+    while let Some(parent) = &path.parent {
+        moves.push(parent.direction);
+        path = parent;
+    }
+    moves.reverse();
+    moves
+}
+
+fn solve_parallel(game: &Game, config: &SolverConfig) -> SolveReport {
+    if game.won() {
+        return SolveReport {
+            solution: Some(Vec::new()),
+            nodes: 0,
+            exhausted: false,
+        };
+    }
+
+    let shared = Shared {
+        config,
+        queues: (0..config.threads).map(|_| Default::default()).collect(),
+        visited: (0..VISITED_SHARDS).map(|_| Default::default()).collect(),
+        pending: AtomicUsize::new(0),
+        order: AtomicUsize::new(0),
+        expanded: AtomicUsize::new(0),
+        stop: AtomicBool::new(false),
+        solution: Mutex::new(None),
+    };
+    shared.visit(game.state_key());
+    shared.push(0, 0, None, game.clone());
+
+    std::thread::scope(|scope| {
+        for worker in 0..config.threads {
+            let shared = &shared;
+            scope.spawn(move || shared.work(worker));
+        }
+    });
+
+    let solution = shared.solution.into_inner().unwrap();
+    let exhausted = solution.is_none() && shared.pending.load(Ordering::SeqCst) == 0;
+    SolveReport {
+        solution,
+        nodes: shared.expanded.load(Ordering::SeqCst).min(config.max_nodes),
+        exhausted,
+    }
+}
+
+/// Remembers the results of previous searches on a level, so that later
+/// queries from any state along a known solution are answered immediately.
+#[derive(Clone, Debug, Default)]
+pub struct SolutionTable {
+    // the remaining moves from a state, or None if it is known unsolvable
+    entries: HashMap<StateKey, Option<Vec<Direction>>>,
+}
+
+impl SolutionTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Looks up the state in the table, searching for a solution only if the
+    /// state hasn't been solved before.
+    pub fn solve(&mut self, game: &Game, config: &SolverConfig) -> SolveReport {
+        if let Some(entry) = self.entries.get(&game.state_key()) {
+            return SolveReport {
+                solution: entry.clone(),
+                nodes: 0,
+                exhausted: entry.is_none(),
+            };
+        }
+
+        let report = solve(game, config);
+        if let Some(solution) = &report.solution {
+            // every state along the solution is solved by the rest of it
+            let mut state = game.clone();
+            for (i, &direction) in solution.iter().enumerate() {
+                self.entries
+                    .insert(state.state_key(), Some(solution[i..].to_vec()));
+                state.play(direction);
+            }
+        } else if report.exhausted {
+            self.entries.insert(game.state_key(), None);
+        }
+        report
+    }
+}
+
+fn reconstruct(nodes: &[Node], mut index: usize) -> Vec<Direction> {
+    let mut moves = Vec::new();
+    while index != 0 {
+        moves.push(nodes[index].direction);
+        index = nodes[index].parent;
+    }
+    moves.reverse();
+    moves
+}
+
+/// The default heuristic: the sum, over all goals, of the distance from the
+/// goal to the nearest cell that can satisfy it.
+///
+/// Within the same block, the distance is the Manhattan distance. Otherwise,
+/// it is one more than the number of levels of nesting between the blocks.
+pub fn goal_distance(game: &Game) -> u32 {
+    let mut total = 0;
+
+    for goal in game.goals() {
+        let nearest = game
+            .cells()
+            .iter()
+            .filter(|cell| {
+                !cell.is_wall()
+                    && !game.is_space(cell.id())
+                    && game.is_player(cell.id()) == goal.player
+            })
+            .map(|cell| position_distance(game, cell.gpos(), goal.gpos))
+            .min();
+
+        // unsatisfiable goals are left to the search
+        total += nearest.unwrap_or(0);
+    }
+
+    total
+}
+
+fn position_distance(game: &Game, from: GlobalPos, to: GlobalPos) -> u32 {
+    if from.container == to.container {
+        return from.pos.0.abs_diff(to.pos.0) + from.pos.1.abs_diff(to.pos.1);
+    }
+
+    let from_chain = container_chain(game, from.container);
+    let to_chain = container_chain(game, to.container);
+    match from_chain
+        .iter()
+        .enumerate()
+        .find_map(|(i, id)| to_chain.iter().position(|other| other == id).map(|j| i + j))
+    {
+        Some(nesting) => 1 + nesting as u32,
+        None => 1 + (from_chain.len() + to_chain.len()) as u32,
+    }
+}
+
+/// Returns the block and all blocks containing it, from the inside out.
+fn container_chain(game: &Game, mut container: Container) -> Vec<usize> {
+    let mut chain = Vec::new();
+    while let Some(block_id) = container.block_id() {
+        if chain.contains(&block_id) {
+            break;
+        }
+        chain.push(block_id);
+        container = game.cells()[block_id].gpos().container;
+    }
+    chain
+}
@@ -0,0 +1,232 @@
+use super::game::*;
+use super::sequence::*;
+
+/// An executable specification of a rule, consisting of a level and a list of
+/// moves and expectations.
+///
+/// A spec file is a text file with one instruction per line:
+///
+/// ```plain
+/// # Comment lines at the top describe the rule.
+/// play RRU               (plays a movement sequence)
+/// expect player at 0 2 2 (the first player is at pos (2, 2) in block 0)
+/// expect player 1 at space 3 3 fliph
+/// expect block 1 at 0 3 2
+/// expect unchanged       (the last play didn't change anything)
+/// expect won
+/// expect not won
+/// ```
+///
+/// Positions are given as `<container> <x> <y> [fliph]`, where `container` is
+/// the number of the block containing the cell, or `space` for a floating cell.
+#[derive(Clone, Debug)]
+pub struct Spec {
+    pub description: String,
+    pub steps: Vec<SpecStep>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SpecStep {
+    /// The line number in the spec file, starting from 1.
+    pub line: usize,
+    pub text: String,
+    pub kind: SpecStepKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum SpecStepKind {
+    Play(String),
+    Expect(Expectation),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expectation {
+    PlayerAt(usize, SpecLocation),
+    BlockAt(BlockNo, SpecLocation),
+    Unchanged,
+    Won(bool),
+}
+
+/// The location of a cell as written in a spec.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpecLocation {
+    /// `None` for a floating cell in space.
+    pub container: Option<BlockNo>,
+    pub x: i32,
+    pub y: i32,
+    pub fliph: bool,
+}
+
+/// The result of checking a single expectation.
+#[derive(Clone, Debug)]
+pub struct SpecResult {
+    pub line: usize,
+    pub text: String,
+    /// `None` if the expectation holds, otherwise what was actually observed.
+    pub failure: Option<String>,
+}
+
+impl std::fmt::Display for SpecLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.container {
+            Some(block_no) => write!(f, "{block_no}")?,
+            None => write!(f, "space")?,
+        }
+        write!(f, " {} {}", self.x, self.y)?;
+        if self.fliph {
+            write!(f, " fliph")?;
+        }
+        Ok(())
+    }
+}
+
+impl Spec {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut description = Vec::new();
+        let mut steps = Vec::new();
+
+        for (lineno, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if let Some(comment) = line.strip_prefix('#') {
+                if steps.is_empty() {
+                    description.push(comment.trim());
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            let kind =
+                Self::parse_step(line).map_err(|e| format!("{}\n{} | {}", e, lineno + 1, line))?;
+            steps.push(SpecStep {
+                line: lineno + 1,
+                text: line.to_string(),
+                kind,
+            });
+        }
+
+        Ok(Spec {
+            description: description.join("\n").trim().to_string(),
+            steps,
+        })
+    }
+
+    fn parse_step(line: &str) -> Result<SpecStepKind, String> {
+        let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
+        match parts[..] {
+            ["play", sequence] => {
+                parse_sequence(sequence).map_err(|e| e.to_string())?;
+                Ok(SpecStepKind::Play(sequence.to_string()))
+            }
+            ["expect", "won"] => Ok(SpecStepKind::Expect(Expectation::Won(true))),
+            ["expect", "not", "won"] => Ok(SpecStepKind::Expect(Expectation::Won(false))),
+            ["expect", "unchanged"] => Ok(SpecStepKind::Expect(Expectation::Unchanged)),
+            ["expect", "player", "at", ref location @ ..] => Ok(SpecStepKind::Expect(
+                Expectation::PlayerAt(0, Self::parse_location(location)?),
+            )),
+            ["expect", "player", index, "at", ref location @ ..] => {
+                let index = index
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid player index {index}"))?;
+                Ok(SpecStepKind::Expect(Expectation::PlayerAt(
+                    index,
+                    Self::parse_location(location)?,
+                )))
+            }
+            ["expect", "block", block_no, "at", ref location @ ..] => {
+                let block_no = block_no
+                    .parse::<i32>()
+                    .map_err(|_| format!("Invalid block number {block_no}"))?;
+                Ok(SpecStepKind::Expect(Expectation::BlockAt(
+                    BlockNo(block_no),
+                    Self::parse_location(location)?,
+                )))
+            }
+            _ => Err("Unknown spec instruction".to_string()),
+        }
+    }
+
+    fn parse_location(parts: &[&str]) -> Result<SpecLocation, String> {
+        let (fliph, parts) = match parts {
+            [rest @ .., "fliph"] => (true, rest),
+            _ => (false, parts),
+        };
+        let [container, x, y] = parts else {
+            return Err("Expected location <container> <x> <y> [fliph]".to_string());
+        };
+
+        let parse = |s: &str| s.parse::<i32>().map_err(|_| format!("Invalid number {s}"));
+        let container = match *container {
+            "space" => None,
+            no => Some(BlockNo(parse(no)?)),
+        };
+
+        Ok(SpecLocation {
+            container,
+            x: parse(x)?,
+            y: parse(y)?,
+            fliph,
+        })
+    }
+
+    /// Runs the spec against the given level, returning the result of each
+    /// expectation in order.
+    pub fn run(&self, mut game: Game) -> Vec<SpecResult> {
+        let mut results = Vec::new();
+        let mut previous = format!("{:?}", game.cells());
+
+        for step in &self.steps {
+            let expectation = match &step.kind {
+                SpecStepKind::Play(sequence) => {
+                    previous = format!("{:?}", game.cells());
+                    // the sequence has been validated when parsing
+                    game.play_sequence(sequence).unwrap();
+                    continue;
+                }
+                SpecStepKind::Expect(expectation) => expectation,
+            };
+
+            let failure = match expectation {
+                Expectation::PlayerAt(index, location) => match game.player_ids().get(*index) {
+                    Some(&id) => Self::check_location(&game, &game.cells()[id], location),
+                    None => Some(format!("there is no player {index}")),
+                },
+                Expectation::BlockAt(block_no, location) => match game.block_by_no(*block_no) {
+                    Some(block) => Self::check_location(&game, &game.cells()[block.id], location),
+                    None => Some(format!("there is no block {block_no}")),
+                },
+                Expectation::Unchanged => {
+                    (format!("{:?}", game.cells()) != previous).then(|| "changed".to_string())
+                }
+                Expectation::Won(won) => {
+                    (game.won() != *won).then(|| if *won { "not won" } else { "won" }.to_string())
+                }
+            };
+
+            results.push(SpecResult {
+                line: step.line,
+                text: step.text.clone(),
+                failure,
+            });
+        }
+
+        results
+    }
+
+    fn check_location(game: &Game, cell: &Cell, expected: &SpecLocation) -> Option<String> {
+        let gpos = cell.gpos();
+        let container = match game.container_block(gpos) {
+            Some(block) if !block.space => Some(block.block_no),
+            _ => None,
+        };
+        let actual = SpecLocation {
+            container,
+            x: gpos.pos.0,
+            y: gpos.pos.1,
+            fliph: cell.fliph(),
+        };
+
+        (actual != *expected).then(|| format!("at {actual}"))
+    }
+}
@@ -0,0 +1,256 @@
+use super::game::*;
+use super::utility::*;
+use std::sync::Arc;
+
+/// A canonical description of the dynamic state of a game: positions, fliph
+/// flags and players.
+///
+/// Two games with the same level have equal keys if and only if they are in
+/// the same state, regardless of the order of `cells` or the ids assigned to
+/// synthesized cells.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StateKey(Vec<CellEntry>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct CellEntry {
+    identity: CellIdentity,
+    container: ContainerKey,
+    pos: (i32, i32),
+    fliph: bool,
+    // index in the player order
+    player: Option<usize>,
+}
+
+/// Identifies a cell independently of its id. Walls and references with the
+/// same properties are interchangeable.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CellIdentity {
+    Wall {
+        possessable: bool,
+    },
+    Block(BlockNo),
+    /// A synthesized block for the infinite enter of the given block.
+    InfEnter(BlockNo, u32),
+    Reference {
+        target_no: BlockNo,
+        exit: bool,
+        inf_exit: Option<u32>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum ContainerKey {
+    Root,
+    Space,
+    Block(CellIdentity),
+}
+
+impl Game {
+    /// Returns the identity of a cell, which is stable across states.
+    pub fn cell_identity(&self, cell: &Cell) -> CellIdentity {
+        match cell {
+            Cell::Wall(wall) => CellIdentity::Wall {
+                possessable: wall.possessable,
+            },
+            Cell::Block(block) => match block.inf_enter {
+                Some((block_no, degree)) => CellIdentity::InfEnter(block_no, degree),
+                None => CellIdentity::Block(block.block_no),
+            },
+            Cell::Reference(reference) => CellIdentity::Reference {
+                target_no: reference.target_no,
+                exit: reference.exit,
+                inf_exit: reference.inf_exit,
+            },
+        }
+    }
+
+    /// Returns the canonical key of the current state, for deduplicating
+    /// states e.g. in solvers.
+    pub fn state_key(&self) -> StateKey {
+        let mut entries = self
+            .cells
+            .iter()
+            .filter(|cell| !self.is_space(cell.id()))
+            .map(|cell| {
+                let gpos = cell.gpos();
+                let container = match gpos.block_id().map(|id| &self.cells[id]) {
+                    None => ContainerKey::Root,
+                    Some(Cell::Block(block)) if block.space => ContainerKey::Space,
+                    Some(parent) => ContainerKey::Block(self.cell_identity(parent)),
+                };
+
+                CellEntry {
+                    identity: self.cell_identity(cell),
+                    container,
+                    pos: (gpos.pos.0, gpos.pos.1),
+                    fliph: cell.fliph(),
+                    player: self.player_ids.iter().position(|id| *id == cell.id()),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_unstable();
+        StateKey(entries)
+    }
+}
+
+impl Game {
+    /// Describes the current state as text, for golden files in regression
+    /// tests: the satisfied goals, the players, and a line per cell with its
+    /// id, kind, container and position, e.g. `5 Ref 1 exit in 0 at 3 4
+    /// player`.
+    ///
+    /// Unlike `to_text`, synthesized cells and spaces are included, and cells
+    /// are listed by id, so that the text changes whenever the simulator
+    /// reaches the state differently. Walls that can't be players are left
+    /// out, as they never move.
+    pub fn snapshot_text(&self) -> String {
+        let mut text = format!("goals {}/{}", self.satisfied_goals(), self.goals().len());
+        if self.won() {
+            text += " won";
+        }
+        text += "\nplayers";
+        for id in &self.player_ids {
+            text += &format!(" {id}");
+        }
+        text += "\n";
+
+        let meta = self.meta();
+        for cell in self.cells.iter() {
+            let kind = match cell {
+                Cell::Wall(wall) if !wall.possessable && !self.is_player(wall.id) => continue,
+                Cell::Wall(_) => "Wall".to_string(),
+                Cell::Block(block) if block.space => "Space".to_string(),
+                Cell::Block(block) => match block.inf_enter {
+                    Some((block_no, degree)) => {
+                        format!("InfEnter {} {degree}", meta.block_label(block_no))
+                    }
+                    None => format!("Block {}", meta.block_label(block.block_no)),
+                },
+                Cell::Reference(reference) => {
+                    let mut kind = format!("Ref {}", meta.block_label(reference.target_no));
+                    if reference.exit {
+                        kind += " exit";
+                    }
+                    if let Some(degree) = reference.inf_exit {
+                        kind += &format!(" infexit {degree}");
+                    }
+                    if reference.rotation != 0 {
+                        kind += &format!(" rotation {}", reference.rotation);
+                    }
+                    kind
+                }
+            };
+            let gpos = cell.gpos();
+            let container = gpos
+                .block_id()
+                .map_or("space".to_string(), |id| id.to_string());
+            let Pos(x, y) = gpos.pos;
+            text += &format!("{} {kind} in {container} at {x} {y}", cell.id());
+            if cell.fliph() {
+                text += " fliph";
+            }
+            if self.is_player(cell.id()) {
+                text += " player";
+            }
+            text += "\n";
+        }
+        text
+    }
+}
+
+/// An immutable snapshot of a game state.
+///
+/// Taking a snapshot only clones a few reference-counted pointers, so it is
+/// cheap enough to do after every move. Snapshots are `Send + Sync` and can be
+/// handed to renderers or solvers on other threads while the original game
+/// keeps playing; the cells are only copied when the game is next modified.
+#[derive(Clone, Debug)]
+pub struct StateSnapshot {
+    game: Game,
+}
+
+impl StateSnapshot {
+    /// Returns a game starting from this state, which can be played
+    /// independently.
+    pub fn to_game(&self) -> Game {
+        self.game.clone()
+    }
+}
+
+impl std::ops::Deref for StateSnapshot {
+    type Target = Game;
+
+    fn deref(&self) -> &Game {
+        &self.game
+    }
+}
+
+impl Game {
+    /// Takes a read-only snapshot of the current state.
+    pub fn snapshot(&self) -> Arc<StateSnapshot> {
+        Arc::new(StateSnapshot { game: self.clone() })
+    }
+}
+
+/// A compact snapshot of a game state for solvers that keep many states.
+///
+/// The cells are stored in chunks, which are shared with the parent state
+/// when none of their cells changed, so that packing the state after a move
+/// usually copies a single chunk. Unlike `StateSnapshot`, the static parts of
+/// the game are not kept, so a packed state can only be restored into a game
+/// of the same level.
+#[derive(Clone, Debug)]
+pub struct PackedState {
+    chunks: Vec<Arc<[Cell]>>,
+    player_ids: Vec<usize>,
+}
+
+impl PackedState {
+    pub const CHUNK_SIZE: usize = 16;
+
+    /// Returns the number of chunks shared with another packed state.
+    pub fn shared_chunks(&self, other: &PackedState) -> usize {
+        self.chunks
+            .iter()
+            .zip(&other.chunks)
+            .filter(|(a, b)| Arc::ptr_eq(a, b))
+            .count()
+    }
+}
+
+impl Game {
+    /// Packs the current state, sharing the chunks of cells that didn't
+    /// change with the state it was reached from, if given.
+    pub fn pack(&self, parent: Option<&PackedState>) -> PackedState {
+        let chunks = self
+            .cells
+            .chunks(PackedState::CHUNK_SIZE)
+            .enumerate()
+            .map(|(i, cells)| {
+                let same = |chunk: &&Arc<[Cell]>| {
+                    chunk.len() == cells.len()
+                        && chunk
+                            .iter()
+                            .zip(cells)
+                            .all(|(a, b)| a.gpos() == b.gpos() && a.fliph() == b.fliph())
+                };
+                match parent.and_then(|parent| parent.chunks.get(i)).filter(same) {
+                    Some(chunk) => chunk.clone(),
+                    None => cells.into(),
+                }
+            })
+            .collect();
+
+        PackedState {
+            chunks,
+            player_ids: self.player_ids.clone(),
+        }
+    }
+
+    /// Restores a state packed from a game of the same level.
+    pub fn restore(&mut self, state: &PackedState) {
+        self.set_cells(state.chunks.iter().flat_map(|chunk| chunk.iter().cloned()));
+        self.set_player_ids(&state.player_ids);
+    }
+}
@@ -0,0 +1,253 @@
+use std::fmt::Write;
+
+use super::game::*;
+use super::utility::*;
+
+/// The graph of how the blocks of a game are connected, for understanding
+/// and debugging deeply recursive levels. Nodes are blocks, and edges tell
+/// which blocks contain which blocks and references, where cells exiting a
+/// block end up, and which blocks are infinite exits or enters. The blocks
+/// of spaces are not included; cells floating in them are treated like
+/// top-level cells.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructureGraph {
+    pub nodes: Vec<StructureNode>,
+    pub edges: Vec<StructureEdge>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructureNode {
+    pub block_no: BlockNo,
+    /// How the block is written in level files, e.g. `@name`.
+    pub label: String,
+    pub width: i32,
+    pub height: i32,
+    /// Whether the block is not inside any block, or floating in space.
+    pub top_level: bool,
+    pub filled: bool,
+    pub player: bool,
+}
+
+/// An edge between two blocks. `None` stands for the space outside of all
+/// blocks, including spaces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructureEdge {
+    pub from: Option<BlockNo>,
+    pub to: BlockNo,
+    pub kind: EdgeKind,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdgeKind {
+    /// `from` contains the block `to`.
+    Contains,
+    /// `from` contains a reference to `to`.
+    Reference,
+    /// `from` contains an infinite exit of `to` with the given degree.
+    InfExit { degree: u32 },
+    /// `from` is the infinite enter of `to` with the given degree.
+    InfEnter { degree: u32 },
+    /// Cells exiting `to` come out in `from`, through its exit reference.
+    /// Blocks that are exited through themselves, into the block containing
+    /// them, have no exit edge.
+    Exit,
+}
+
+impl EdgeKind {
+    fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Contains => "contains",
+            EdgeKind::Reference => "reference",
+            EdgeKind::InfExit { .. } => "inf_exit",
+            EdgeKind::InfEnter { .. } => "inf_enter",
+            EdgeKind::Exit => "exit",
+        }
+    }
+
+    fn degree(self) -> Option<u32> {
+        match self {
+            EdgeKind::InfExit { degree } | EdgeKind::InfEnter { degree } => Some(degree),
+            _ => None,
+        }
+    }
+}
+
+impl Game {
+    /// Returns the graph of how the blocks are connected in the current
+    /// state, with the nodes and the edges of each kind in the order of the
+    /// cells.
+    pub fn structure_graph(&self) -> StructureGraph {
+        let container_no = |gpos: GlobalPos| {
+            gpos.block_id()
+                .and_then(|id| self.cells[id].block())
+                .filter(|block| !block.space)
+                .map(|block| block.block_no)
+        };
+
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        let mut exits = Vec::new();
+        for cell in self.cells.iter() {
+            match cell {
+                Cell::Wall(_) => (),
+                Cell::Block(block) if block.space => (),
+                Cell::Block(block) => {
+                    nodes.push(StructureNode {
+                        block_no: block.block_no,
+                        label: self.meta().block_label(block.block_no),
+                        width: block.width,
+                        height: block.height,
+                        top_level: container_no(block.gpos).is_none(),
+                        filled: block.filled,
+                        player: self.is_player(block.id),
+                    });
+                    edges.push(StructureEdge {
+                        from: container_no(block.gpos),
+                        to: block.block_no,
+                        kind: EdgeKind::Contains,
+                    });
+                    if let Some((target_no, degree)) = block.inf_enter {
+                        edges.push(StructureEdge {
+                            from: Some(block.block_no),
+                            to: target_no,
+                            kind: EdgeKind::InfEnter { degree },
+                        });
+                    }
+                    if let Some(exit_id) = self
+                        .exit_id_for(block)
+                        .filter(|&exit_id| exit_id != block.id)
+                    {
+                        exits.push(StructureEdge {
+                            from: container_no(self.cells[exit_id].gpos()),
+                            to: block.block_no,
+                            kind: EdgeKind::Exit,
+                        });
+                    }
+                }
+                Cell::Reference(reference) => edges.push(StructureEdge {
+                    from: container_no(reference.gpos),
+                    to: reference.target_no,
+                    kind: match reference.inf_exit {
+                        Some(degree) => EdgeKind::InfExit { degree },
+                        None => EdgeKind::Reference,
+                    },
+                }),
+            }
+        }
+        edges.append(&mut exits);
+
+        StructureGraph { nodes, edges }
+    }
+}
+
+impl StructureGraph {
+    /// Formats the graph in the DOT language of Graphviz, e.g. for
+    /// `dot -Tsvg`. Edges are drawn from the outer block to the inner one,
+    /// so exit edges point against the direction cells move in.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph structure {{").unwrap();
+        if self.edges.iter().any(|edge| edge.from.is_none()) {
+            writeln!(out, "  space [shape=point];").unwrap();
+        }
+        for node in &self.nodes {
+            let mut attributes = format!(
+                "label=\"{}\\n{}x{}\"",
+                escape(&node.label),
+                node.width,
+                node.height
+            );
+            if node.filled {
+                attributes += ", style=filled";
+            }
+            if node.player {
+                attributes += ", penwidth=3";
+            }
+            writeln!(out, "  {} [{attributes}];", node_id(Some(node.block_no))).unwrap();
+        }
+        for edge in &self.edges {
+            let attributes = match edge.kind {
+                EdgeKind::Contains => String::new(),
+                EdgeKind::Reference => " [style=dashed]".to_string(),
+                EdgeKind::InfExit { degree } => {
+                    format!(" [style=dashed, color=red, label=\"inf exit {degree}\"]")
+                }
+                EdgeKind::InfEnter { degree } => {
+                    format!(" [style=dotted, color=blue, label=\"inf enter {degree}\"]")
+                }
+                EdgeKind::Exit => " [style=dotted, label=\"exit\"]".to_string(),
+            };
+            writeln!(
+                out,
+                "  {} -> {}{attributes};",
+                node_id(edge.from),
+                node_id(Some(edge.to))
+            )
+            .unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+        out
+    }
+
+    /// Formats the graph as a JSON object with `nodes` and `edges`, where
+    /// `from` is `null` for the space outside of all blocks.
+    pub fn to_json(&self) -> String {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| {
+                format!(
+                    "{{\"block\": {}, \"label\": \"{}\", \"width\": {}, \"height\": {}, \"top_level\": {}, \"filled\": {}, \"player\": {}}}",
+                    node.block_no,
+                    escape(&node.label),
+                    node.width,
+                    node.height,
+                    node.top_level,
+                    node.filled,
+                    node.player
+                )
+            })
+            .collect::<Vec<_>>();
+        let edges = self
+            .edges
+            .iter()
+            .map(|edge| {
+                let mut json = format!(
+                    "{{\"from\": {}, \"to\": {}, \"kind\": \"{}\"",
+                    edge.from
+                        .map_or("null".to_string(), |from| from.to_string()),
+                    edge.to,
+                    edge.kind.name()
+                );
+                if let Some(degree) = edge.kind.degree() {
+                    write!(json, ", \"degree\": {degree}").unwrap();
+                }
+                json + "}"
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"nodes\": [{}], \"edges\": [{}]}}",
+            nodes.join(", "),
+            edges.join(", ")
+        )
+    }
+}
+
+// the DOT identifier of a block, or of the space outside of all blocks;
+// block numbers may be negative, which identifiers can't contain
+fn node_id(block_no: Option<BlockNo>) -> String {
+    match block_no {
+        Some(BlockNo(no)) if no < 0 => format!("bm{}", -no),
+        Some(BlockNo(no)) => format!("b{no}"),
+        None => "space".to_string(),
+    }
+}
+
+// escapes a string for a quoted DOT or JSON string
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,180 @@
+use color_space::Hsv;
+
+use super::game::*;
+use super::utility::*;
+
+// the color of the players of the vanilla levels
+const PLAYER_HSV: Hsv = Hsv {
+    h: 324.0,
+    s: 1.0,
+    v: 0.7,
+};
+
+/// A common starting structure for a level, or for a part of one, built
+/// with the editing methods of `Game`. The blocks other than the players get
+/// hues distinct from the blocks already in the level.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Template {
+    /// An empty block of the given size, with walls along its edges.
+    Room { width: i32, height: i32 },
+    /// A block of the given size with a player, next to a smaller block
+    /// holding the exit reference to the outer one, so that leaving the
+    /// outer block comes back out of the reference inside the smaller one.
+    Nested { width: i32, height: i32 },
+    /// A room of the given size with a player, a possessable block and a
+    /// possessable wall around a player goal.
+    Possession { width: i32, height: i32 },
+}
+
+impl Template {
+    pub const NAMES: [&'static str; 3] = ["room", "nested", "possession"];
+
+    /// Returns the template with the name, in `NAMES`, of the given size.
+    pub fn from_name(name: &str, width: i32, height: i32) -> Result<Template, String> {
+        match name {
+            "room" => Ok(Template::Room { width, height }),
+            "nested" => Ok(Template::Nested { width, height }),
+            "possession" => Ok(Template::Possession { width, height }),
+            _ => Err(format!(
+                "Unknown template {name}, expected one of {}",
+                Template::NAMES.join(", ")
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Template::Room { .. } => "room",
+            Template::Nested { .. } => "nested",
+            Template::Possession { .. } => "possession",
+        }
+    }
+
+    /// Returns the size of the outer block.
+    pub fn size(self) -> (i32, i32) {
+        match self {
+            Template::Room { width, height }
+            | Template::Nested { width, height }
+            | Template::Possession { width, height } => (width, height),
+        }
+    }
+
+    // the smallest size that fits the cells of the template
+    fn min_size(self) -> i32 {
+        match self {
+            Template::Room { .. } | Template::Nested { .. } => 3,
+            Template::Possession { .. } => 5,
+        }
+    }
+
+    /// Creates a level with nothing but the template.
+    pub fn level(self) -> Result<Game, String> {
+        let mut game = Game::parse("version 4\n#\n")?;
+        let gpos = GlobalPos {
+            container: Container::Space,
+            pos: Pos(-1, -1),
+        };
+        game.add_template(self, gpos)?;
+        Ok(game)
+    }
+}
+
+impl Game {
+    /// Adds the structure of a template with its outer block at the
+    /// position, which may be in space or inside another block, and returns
+    /// the id of the outer block. The blocks get numbers after the largest
+    /// one in the level, and the players of the template come after the
+    /// players of the level.
+    ///
+    /// Fails without changing anything if the template is smaller than its
+    /// cells need, or the position is taken.
+    pub fn add_template(&mut self, template: Template, gpos: GlobalPos) -> Result<usize, String> {
+        let (width, height) = template.size();
+        let min_size = template.min_size();
+        if width < min_size || height < min_size {
+            return Err(format!(
+                "The {} template needs at least {min_size}x{min_size} cells, not {width}x{height}",
+                template.name()
+            ));
+        }
+        let (cx, cy) = (width / 2, height / 2);
+
+        let outer_no = self.next_block_no();
+        let hsv = self.new_block_hsv();
+        let outer = self.add_block(Block::new(gpos, outer_no, width, height, hsv))?;
+        let at = |x, y| GlobalPos::new(outer, Pos(x, y));
+        if matches!(
+            template,
+            Template::Room { .. } | Template::Possession { .. }
+        ) {
+            for x in 0..width {
+                for y in 0..height {
+                    if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                        self.add_wall(Wall::new(at(x, y)))?;
+                    }
+                }
+            }
+        }
+
+        match template {
+            Template::Room { .. } => (),
+            Template::Nested { .. } => {
+                self.add_player(at(cx - 1, cy))?;
+                let inner_no = self.next_block_no();
+                let hsv = self.new_block_hsv();
+                let inner = self.add_block(Block::new(at(cx + 1, cy), inner_no, 5, 5, hsv))?;
+                self.add_reference(Reference {
+                    id: 0,
+                    gpos: GlobalPos::new(inner, Pos(2, 2)),
+                    target_no: outer_no,
+                    exit: true,
+                    inf_exit: None,
+                    possessable: false,
+                    fliph: false,
+                    rotation: 0,
+                    effect: SpecialEffect::None,
+                })?;
+            }
+            Template::Possession { .. } => {
+                self.add_player(at(cx - 1, cy))?;
+                let hsv = self.new_block_hsv();
+                let mut other = Block::new(at(cx + 1, cy), self.next_block_no(), 1, 1, hsv);
+                other.filled = true;
+                other.possessable = true;
+                self.add_block(other)?;
+                self.add_wall(Wall {
+                    possessable: true,
+                    ..Wall::new(at(cx, cy + 1))
+                })?;
+                self.add_goal(at(cx, cy - 1), true)?;
+            }
+        }
+        Ok(outer)
+    }
+
+    // adds a filled block that is a player
+    pub(super) fn add_player(&mut self, gpos: GlobalPos) -> Result<usize, String> {
+        let mut player = Block::new(gpos, self.next_block_no(), 1, 1, PLAYER_HSV);
+        player.filled = true;
+        player.possessable = true;
+        let id = self.add_block(player)?;
+        self.set_player(id, true)?;
+        Ok(id)
+    }
+
+    pub(super) fn new_block_hsv(&self) -> Hsv {
+        Hsv::new(self.distinct_hue(), 0.8, 1.0)
+    }
+
+    // the number after the largest block number
+    pub(super) fn next_block_no(&self) -> BlockNo {
+        let max = self
+            .cells()
+            .iter()
+            .filter_map(|cell| cell.block())
+            .map(|block| block.block_no.0)
+            .max();
+        BlockNo(max.map_or(0, |max| max + 1))
+    }
+}
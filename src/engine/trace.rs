@@ -0,0 +1,436 @@
+use std::io::Write;
+
+use super::game::*;
+use super::simulation::{MoveFailure, MoveOutcome, MoveState, Simulator, TransferPoint};
+use super::utility::*;
+
+/// A decision the simulator made while playing a move.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TraceKind {
+    /// The cell tries to move in the direction.
+    Move {
+        cell_id: usize,
+        direction: Direction,
+    },
+    /// The cell tries to leave its block through the exit cell, which is the
+    /// block itself, a reference to it, or an infinite exit.
+    Exit { cell_id: usize, exit_id: usize },
+    /// The cell tries to push, enter, eat or possess the target cell.
+    Attempt {
+        action: ActionType,
+        cell_id: usize,
+        target_id: usize,
+    },
+    /// The cell is pulled along by a player that moved away from it, with
+    /// the `pull` rule extension.
+    Pull {
+        cell_id: usize,
+        direction: Direction,
+    },
+    /// The cell tries to fall down after the players moved, with the
+    /// `gravity` rule extension.
+    Fall { cell_id: usize },
+}
+
+/// A decision and whether it succeeded. The decisions made while trying it
+/// follow it in the trace, one level deeper.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub depth: usize,
+    pub kind: TraceKind,
+    pub success: bool,
+    /// Why the decision itself failed, if it failed for a reason other than
+    /// the failure of the decisions made while trying it, e.g. a push
+    /// blocked by a wall.
+    pub failure: Option<MoveFailure>,
+}
+
+/// A decision with the decisions made while trying it, e.g. the tree of
+/// interactions that were attempted for a move that failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecisionTree {
+    pub kind: TraceKind,
+    pub success: bool,
+    pub failure: Option<MoveFailure>,
+    pub children: Vec<DecisionTree>,
+}
+
+/// A call of the simulator, with the state of the moving cell as it is
+/// passed to the call. Unlike decisions, calls are made for every step of a
+/// move, e.g. each position checked inside a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimCall {
+    /// `try_move`: the cell starts moving in the direction.
+    Move {
+        cell_id: usize,
+        direction: Direction,
+    },
+    /// `try_exit`: the cell steps forward, and exits its block if it is at
+    /// the edge, crossing it at the point.
+    Exit {
+        state: MoveState,
+        point: TransferPoint,
+    },
+    /// `try_interact_pos`: the cell tries to take the position of the state.
+    InteractPos {
+        state: MoveState,
+        point: TransferPoint,
+    },
+    /// `try_push`: the cell pushes the target.
+    Push { state: MoveState, target_id: usize },
+    /// `try_enter`: the cell enters the target at the point.
+    Enter {
+        state: MoveState,
+        target_id: usize,
+        point: TransferPoint,
+    },
+    /// `try_eat`: the cell eats the target.
+    Eat { state: MoveState, target_id: usize },
+    /// `try_possess`: the player possesses the target.
+    Possess { cell_id: usize, target_id: usize },
+}
+
+/// Receives the calls made by the simulator while playing, see
+/// `Game::play_logged`. `depth` is the number of cells scheduled to move
+/// when the call is made.
+pub trait SimTrace {
+    fn call(&mut self, depth: usize, call: &SimCall);
+}
+
+/// Collects the calls with their depths, e.g. for tests.
+impl SimTrace for Vec<(usize, SimCall)> {
+    fn call(&mut self, depth: usize, call: &SimCall) {
+        self.push((depth, call.clone()));
+    }
+}
+
+/// Writes each call as a line indented by its depth, e.g. to stderr for
+/// debugging, or to a buffer shown in the TUI.
+pub struct SimLog<W: Write>(pub W);
+
+impl<W: Write> SimTrace for SimLog<W> {
+    fn call(&mut self, depth: usize, call: &SimCall) {
+        // the log is best effort, so write errors are ignored
+        let _ = writeln!(self.0, "{}{call}", "  ".repeat(depth));
+    }
+}
+
+/// A recording of the decisions made while playing a sequence of moves,
+/// which can be compared with the current simulator by `diff_trace`.
+///
+/// In the text format, each move starts with a line `step <n> <direction>`,
+/// followed by a line for each decision, indented by its depth and ending
+/// with `ok` or `fail`, e.g. `  push 3 5 ok`. Failures with a reason of
+/// their own are followed by it, e.g. `  push 3 5 fail: blocked by a wall at
+/// (4, 2) in block 0`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    /// The moves played, with the decisions made during each of them.
+    pub steps: Vec<(Direction, Vec<TraceEvent>)>,
+}
+
+/// Where a recorded trace and the current simulator first disagree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceMismatch {
+    /// The line of the recording that differs, counting from 1.
+    pub line: usize,
+    /// The move being played at that line, counting from 1.
+    pub step: usize,
+    /// The line in the recording, or `None` past its end.
+    pub expected: Option<String>,
+    /// The line the simulator produced, or `None` past its end.
+    pub actual: Option<String>,
+}
+
+impl std::fmt::Display for TraceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceKind::Move { cell_id, direction } => {
+                write!(f, "move {cell_id} {}", direction.to_char())
+            }
+            TraceKind::Exit { cell_id, exit_id } => write!(f, "exit {cell_id} {exit_id}"),
+            TraceKind::Attempt {
+                action,
+                cell_id,
+                target_id,
+            } => {
+                let action = match action {
+                    ActionType::Push => "push",
+                    ActionType::Enter => "enter",
+                    ActionType::Eat => "eat",
+                    ActionType::Possess => "possess",
+                };
+                write!(f, "{action} {cell_id} {target_id}")
+            }
+            TraceKind::Pull { cell_id, direction } => {
+                write!(f, "pull {cell_id} {}", direction.to_char())
+            }
+            TraceKind::Fall { cell_id } => write!(f, "fall {cell_id}"),
+        }
+    }
+}
+
+impl std::fmt::Display for SimCall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // e.g. `3 R at (1, 2) in 0`
+        let state = |state: &MoveState| {
+            let Pos(x, y) = state.gpos.pos;
+            let container = match state.gpos.block_id() {
+                Some(block_id) => block_id.to_string(),
+                None => "space".to_string(),
+            };
+            let fliph = if state.fliph { " fliph" } else { "" };
+            format!(
+                "{} {} at ({x}, {y}) in {container}{fliph}",
+                state.cell_id,
+                state.direction.to_char()
+            )
+        };
+        match self {
+            SimCall::Move { cell_id, direction } => {
+                write!(f, "try_move: {cell_id} {}", direction.to_char())
+            }
+            SimCall::Exit { state: s, point } => {
+                write!(f, "try_exit: {} point {point}", state(s))
+            }
+            SimCall::InteractPos { state: s, point } => {
+                write!(f, "try_interact_pos: {} point {point}", state(s))
+            }
+            SimCall::Push {
+                state: s,
+                target_id,
+            } => write!(f, "try_push: {} target {target_id}", state(s)),
+            SimCall::Enter {
+                state: s,
+                target_id,
+                point,
+            } => write!(
+                f,
+                "try_enter: {} target {target_id} point {point}",
+                state(s)
+            ),
+            SimCall::Eat {
+                state: s,
+                target_id,
+            } => write!(f, "try_eat: {} target {target_id}", state(s)),
+            SimCall::Possess { cell_id, target_id } => {
+                write!(f, "try_possess: {cell_id} target {target_id}")
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = if self.success { "ok" } else { "fail" };
+        write!(f, "{}{} {result}", "  ".repeat(self.depth), self.kind)?;
+        if let Some(failure) = &self.failure {
+            write!(f, ": {failure}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for DecisionTree {
+    /// Describes the decisions in words, one per line, indented below the
+    /// decision they were made for.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_lines(f, 0)
+    }
+}
+
+impl std::fmt::Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (direction, events)) in self.steps.iter().enumerate() {
+            writeln!(f, "step {} {}", i + 1, direction.to_char())?;
+            for event in events {
+                writeln!(f, "{event}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for TraceMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expected = self.expected.as_deref().unwrap_or("<end>");
+        let actual = self.actual.as_deref().unwrap_or("<end>");
+        write!(
+            f,
+            "line {} (step {}): expected `{expected}`, got `{actual}`",
+            self.line, self.step
+        )
+    }
+}
+
+impl Trace {
+    /// Plays the moves on a copy of the game, recording the decisions.
+    pub fn record(game: &Game, moves: &[Direction]) -> Trace {
+        let mut game = game.clone();
+        let steps = moves
+            .iter()
+            .map(|&direction| (direction, game.play_traced(direction).1))
+            .collect();
+        Trace { steps }
+    }
+
+    /// Returns the moves of a trace in the text format, from its `step`
+    /// lines.
+    pub fn parse_moves(text: &str) -> Result<Vec<Direction>, String> {
+        let mut moves = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let Some(step) = line.strip_prefix("step ") else {
+                continue;
+            };
+            let direction = step
+                .split_once(' ')
+                .and_then(|(_, direction)| direction.parse::<char>().ok())
+                .and_then(Direction::from_char)
+                .ok_or(format!("Invalid step at line {}", i + 1))?;
+            moves.push(direction);
+        }
+        Ok(moves)
+    }
+}
+
+impl DecisionTree {
+    /// Builds the trees of the decisions in a trace, one for each decision
+    /// at depth 0.
+    pub fn build(events: &[TraceEvent]) -> Vec<DecisionTree> {
+        let mut events = events.iter().peekable();
+        let mut trees = Vec::new();
+        while let Some(event) = events.next() {
+            trees.push(Self::build_from(event, &mut events));
+        }
+        trees
+    }
+
+    // builds the tree of the event, taking its descendants from the events
+    fn build_from<'a>(
+        event: &TraceEvent,
+        events: &mut std::iter::Peekable<impl Iterator<Item = &'a TraceEvent>>,
+    ) -> DecisionTree {
+        let mut children = Vec::new();
+        while let Some(child) = events.next_if(|child| child.depth > event.depth) {
+            children.push(Self::build_from(child, events));
+        }
+        DecisionTree {
+            kind: event.kind,
+            success: event.success,
+            failure: event.failure.clone(),
+            children,
+        }
+    }
+
+    fn write_lines(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        write!(f, "{}", "  ".repeat(depth))?;
+        match self.kind {
+            TraceKind::Move { cell_id, direction } => {
+                write!(f, "cell {cell_id} moves {}", direction_name(direction))?
+            }
+            TraceKind::Exit { cell_id, exit_id } => {
+                write!(f, "cell {cell_id} exits through cell {exit_id}")?
+            }
+            TraceKind::Attempt {
+                action,
+                cell_id,
+                target_id,
+            } => {
+                let action = match action {
+                    ActionType::Push => "pushes",
+                    ActionType::Enter => "enters",
+                    ActionType::Eat => "eats",
+                    ActionType::Possess => "possesses",
+                };
+                write!(f, "cell {cell_id} {action} cell {target_id}")?
+            }
+            TraceKind::Pull { cell_id, direction } => {
+                write!(f, "cell {cell_id} is pulled {}", direction_name(direction))?
+            }
+            TraceKind::Fall { cell_id } => write!(f, "cell {cell_id} falls")?,
+        }
+        match (&self.failure, self.success) {
+            (_, true) => writeln!(f)?,
+            (Some(failure), false) => writeln!(f, ": failed, {failure}")?,
+            (None, false) => writeln!(f, ": failed")?,
+        }
+        for child in &self.children {
+            child.write_lines(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Game {
+    /// Explains why playing the direction would fail for some of the
+    /// players, without changing the game: returns the trees of decisions
+    /// made for the players' moves that failed, with the reason of each
+    /// rejected interaction. There are no trees if every move would succeed.
+    pub fn explain_move(&self, direction: Direction) -> Vec<DecisionTree> {
+        let (_, events) = self.clone().play_traced(direction);
+        let mut trees = DecisionTree::build(&events);
+        trees.retain(|tree| matches!(tree.kind, TraceKind::Move { .. }) && !tree.success);
+        trees
+    }
+
+    /// Plays one input like `play`, sending every call the simulator makes
+    /// to the sink.
+    pub fn play_logged(&mut self, direction: Direction, sink: &mut dyn SimTrace) -> MoveOutcome {
+        let mut simulator = Simulator::new(self);
+        simulator.set_sink(sink);
+        simulator.play(direction)
+    }
+
+    /// Plays one input like `play`, also returning the decisions the
+    /// simulator made, in the order they were made.
+    pub fn play_traced(&mut self, direction: Direction) -> (MoveOutcome, Vec<TraceEvent>) {
+        let mut simulator = Simulator::new(self);
+        simulator.record_trace();
+        let outcome = simulator.play(direction);
+        (outcome, simulator.take_trace())
+    }
+}
+
+/// Replays the moves of a recorded trace in the text format on the game, and
+/// compares the decisions with the recording, e.g. to check that a refactor
+/// of the simulator doesn't change its behavior.
+///
+/// Returns the first line that differs, if any.
+pub fn diff_trace(game: &Game, recorded: &str) -> Result<Option<TraceMismatch>, String> {
+    let moves = Trace::parse_moves(recorded)?;
+    let actual = Trace::record(game, &moves).to_string();
+
+    let mut step = 0;
+    let mut expected_lines = recorded.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        let (expected, actual) = (expected_lines.next(), actual_lines.next());
+        if expected.is_none() && actual.is_none() {
+            break;
+        }
+        if expected
+            .or(actual)
+            .is_some_and(|line| line.starts_with("step "))
+        {
+            step += 1;
+        }
+        if expected != actual {
+            return Ok(Some(TraceMismatch {
+                line,
+                step,
+                expected: expected.map(str::to_string),
+                actual: actual.map(str::to_string),
+            }));
+        }
+    }
+    Ok(None)
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
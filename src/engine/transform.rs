@@ -0,0 +1,335 @@
+use std::sync::Arc;
+
+use super::game::*;
+use super::utility::*;
+
+/// A geometric transformation of the interior of a block, or of every block
+/// of a level, for reusing and remixing structures.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Transform {
+    /// Swaps left and right.
+    MirrorH,
+    /// Swaps up and down.
+    MirrorV,
+    /// Turns by the given number of quarter turns counterclockwise. The
+    /// width and height of the block are swapped by odd turns.
+    Rotate(u8),
+    /// Moves the cells and floors by the given offset.
+    Translate(i32, i32),
+    /// Keeps the area of the given size whose bottom left corner is at
+    /// `(x, y)`, which becomes the new interior of the block. Walls and
+    /// floors outside of it are removed.
+    Crop {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+}
+
+// a horizontal mirror, if `mirrored`, followed by a number of quarter turns
+// counterclockwise; this is also how a reference shows the interior of its
+// target, with `fliph` and `rotation`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Orientation {
+    turns: u8,
+    mirrored: bool,
+}
+
+impl Orientation {
+    // `self` applied after `other`
+    fn after(self, other: Orientation) -> Orientation {
+        let turns = if self.mirrored {
+            4 - other.turns
+        } else {
+            other.turns
+        };
+        Orientation {
+            turns: (self.turns + turns) % 4,
+            mirrored: self.mirrored != other.mirrored,
+        }
+    }
+
+    fn inverse(self) -> Orientation {
+        if self.mirrored {
+            // mirrors undo themselves, even when followed by turns
+            self
+        } else {
+            Orientation {
+                turns: (4 - self.turns) % 4,
+                mirrored: false,
+            }
+        }
+    }
+
+    fn direction(self, mut direction: Direction) -> Direction {
+        if self.mirrored && matches!(direction, Direction::Left | Direction::Right) {
+            direction = direction.opposite();
+        }
+        for _ in 0..self.turns {
+            direction = direction.turn_left();
+        }
+        direction
+    }
+
+    fn pos(self, Pos(mut x, mut y): Pos, mut width: i32, mut height: i32) -> Pos {
+        if self.mirrored {
+            x = width - 1 - x;
+        }
+        for _ in 0..self.turns {
+            (x, y) = (height - 1 - y, x);
+            (width, height) = (height, width);
+        }
+        Pos(x, y)
+    }
+
+    fn of(cell: &Cell) -> Orientation {
+        Orientation {
+            turns: cell.rotation(),
+            mirrored: cell.fliph(),
+        }
+    }
+
+    // sets how the cell shows its interior, if it can: only references turn,
+    // with the rotation extension
+    fn set(self, cell: &mut Cell, rotation: bool) -> bool {
+        if self.turns != 0 && !(rotation && matches!(cell, Cell::Reference(_))) {
+            return false;
+        }
+        match cell {
+            Cell::Wall(wall) => wall.fliph = self.mirrored,
+            Cell::Block(block) => block.fliph = self.mirrored,
+            Cell::Reference(reference) => {
+                reference.fliph = self.mirrored;
+                reference.rotation = self.turns;
+            }
+        }
+        true
+    }
+}
+
+impl Transform {
+    fn orientation(self) -> Option<Orientation> {
+        let (turns, mirrored) = match self {
+            Transform::MirrorH => (0, true),
+            // a vertical mirror is a horizontal one turned upside down
+            Transform::MirrorV => (2, true),
+            Transform::Rotate(turns) => (turns % 4, false),
+            Transform::Translate(..) | Transform::Crop { .. } => return None,
+        };
+        Some(Orientation { turns, mirrored })
+    }
+
+    // where a position in a block of the given size ends up
+    fn pos(self, pos: Pos, width: i32, height: i32) -> Pos {
+        let Pos(x, y) = pos;
+        match self {
+            Transform::Translate(dx, dy) => Pos(x + dx, y + dy),
+            Transform::Crop {
+                x: left, y: bottom, ..
+            } => Pos(x - left, y - bottom),
+            _ => self.orientation().unwrap().pos(pos, width, height),
+        }
+    }
+
+    // the size of a block of the given size after the transformation
+    fn size(self, width: i32, height: i32) -> (i32, i32) {
+        match self {
+            Transform::Rotate(turns) if turns % 2 == 1 => (height, width),
+            Transform::Crop { width, height, .. } => (width, height),
+            _ => (width, height),
+        }
+    }
+}
+
+impl Game {
+    /// Transforms the interior of a block: the cells and floors directly in
+    /// it move to their new positions, and one-way floors turn with them.
+    /// When mirroring or rotating, the cells inside also show their own
+    /// interiors transformed along with the block, by toggling `fliph` and,
+    /// for references with the rotation extension, turning them. Cells that
+    /// can't show the turn keep the orientation of their interiors.
+    ///
+    /// Cells keep their ids, except when walls are cropped away. Fails
+    /// without changing anything if a cell or floor would end up outside the
+    /// block.
+    pub fn transform_block(&mut self, id: usize, transform: Transform) -> Result<(), String> {
+        let Some(block) = self.cells.get(id).and_then(|cell| cell.block()) else {
+            return Err(format!("Cell {id} is not a block"));
+        };
+        let (width, height) = (block.width, block.height);
+        let (new_width, new_height) = transform.size(width, height);
+        if !block.filled && (new_width <= 0 || new_height <= 0) {
+            return Err(format!("Invalid block size: {new_width}x{new_height}"));
+        }
+        let cropping = matches!(transform, Transform::Crop { .. });
+        let in_bounds = |pos: Pos| {
+            let Pos(x, y) = transform.pos(pos, width, height);
+            x >= 0 && y >= 0 && x < new_width && y < new_height
+        };
+
+        let mut cropped = Vec::new();
+        for cell in self.cells.iter().filter(|cell| cell.gpos().is_in(id)) {
+            if in_bounds(cell.gpos().pos) {
+                continue;
+            }
+            if !(cropping && cell.is_wall()) {
+                return Err(format!(
+                    "Cell {} at {:?} would be outside the block",
+                    cell.id(),
+                    cell.gpos()
+                ));
+            }
+            cropped.push(cell.id());
+        }
+        if let Some(floor) = self
+            .floors()
+            .iter()
+            .find(|floor| !cropping && floor.gpos.is_in(id) && !in_bounds(floor.gpos.pos))
+        {
+            return Err(format!(
+                "Floor at {:?} would be outside the block",
+                floor.gpos
+            ));
+        }
+
+        // the walls are removed last first, so that the ids of the others
+        // stay the same
+        let mut id = id;
+        for &wall_id in cropped.iter().rev() {
+            self.remove_cell(wall_id)?;
+            if wall_id < id {
+                id -= 1;
+            }
+        }
+
+        let orientation = transform.orientation();
+        let rotation = self.config().rotation;
+        let mut cells = self.cells.as_ref().clone();
+        for cell in cells.iter_mut().filter(|cell| cell.gpos().is_in(id)) {
+            let gpos = GlobalPos::new(id, transform.pos(cell.gpos().pos, width, height));
+            set_gpos(cell, gpos);
+            if let Some(orientation) = orientation {
+                orientation.after(Orientation::of(cell)).set(cell, rotation);
+            }
+        }
+        let block = cells[id].block_mut().unwrap();
+        block.width = new_width;
+        block.height = new_height;
+
+        let level = Arc::make_mut(&mut self.level);
+        level
+            .goals
+            .retain(|goal| !goal.gpos.is_in(id) || in_bounds(goal.gpos.pos));
+        level
+            .floors
+            .retain(|floor| !floor.gpos.is_in(id) || in_bounds(floor.gpos.pos));
+        for goal in level.goals.iter_mut().filter(|goal| goal.gpos.is_in(id)) {
+            goal.gpos.pos = transform.pos(goal.gpos.pos, width, height);
+        }
+        for floor in level.floors.iter_mut().filter(|floor| floor.gpos.is_in(id)) {
+            floor.gpos.pos = transform.pos(floor.gpos.pos, width, height);
+            if let (Some(orientation), FloorKind::OneWay(directions)) =
+                (orientation, &mut floor.kind)
+            {
+                for direction in directions {
+                    *direction = orientation.direction(*direction);
+                }
+            }
+        }
+
+        self.set_cells(cells);
+        Ok(())
+    }
+
+    /// Mirrors or rotates every block of the level, including the spaces of
+    /// floating cells, so that the level plays the same with the moves
+    /// transformed likewise. Every cell inside a block keeps showing its
+    /// interior the same way relative to its surroundings, which may take
+    /// a different `fliph` and `rotation`.
+    ///
+    /// Moves into blocks of even size through the middle of an edge round to
+    /// one side, which the transformed level rounds to the other side, so
+    /// such levels may play differently.
+    ///
+    /// Fails without changing anything for translations and crops, if the
+    /// level has gravity and would be turned or mirrored vertically, or if a
+    /// flipped cell would have to show its interior turned without the
+    /// rotation extension.
+    pub fn transform_level(&mut self, transform: Transform) -> Result<(), String> {
+        let Some(orientation) = transform.orientation() else {
+            return Err("Only mirroring and rotating apply to the whole level".to_string());
+        };
+        if self.config().gravity && orientation.direction(Direction::Down) != Direction::Down {
+            return Err("Cells fall down with gravity, so the level can't be turned".to_string());
+        }
+
+        let sizes = self
+            .cells
+            .iter()
+            .map(|cell| {
+                cell.block()
+                    .map_or((0, 0), |block| (block.width, block.height))
+            })
+            .collect::<Vec<_>>();
+        let rotation = self.config().rotation;
+        let mut cells = self.cells.as_ref().clone();
+        for cell in &mut cells {
+            if let Some(block_id) = cell.gpos().block_id() {
+                let (width, height) = sizes[block_id];
+                let gpos =
+                    GlobalPos::new(block_id, orientation.pos(cell.gpos().pos, width, height));
+                set_gpos(cell, gpos);
+
+                // the orientation is seen through the transformed container,
+                // of the transformed interior
+                let shown = orientation
+                    .after(Orientation::of(cell))
+                    .after(orientation.inverse());
+                if !shown.set(cell, rotation) {
+                    return Err(format!(
+                        "Cell {} would have to be turned, which needs the rotation extension",
+                        cell.id()
+                    ));
+                }
+            }
+            if let Cell::Block(block) = cell {
+                (block.width, block.height) = transform.size(block.width, block.height);
+            }
+        }
+
+        let level = Arc::make_mut(&mut self.level);
+        let moved = |gpos: GlobalPos| match gpos.block_id() {
+            Some(block_id) => {
+                let (width, height) = sizes[block_id];
+                GlobalPos::new(block_id, orientation.pos(gpos.pos, width, height))
+            }
+            None => gpos,
+        };
+        for goal in &mut level.goals {
+            goal.gpos = moved(goal.gpos);
+        }
+        for floor in &mut level.floors {
+            floor.gpos = moved(floor.gpos);
+            if let FloorKind::OneWay(directions) = &mut floor.kind {
+                for direction in directions {
+                    *direction = orientation.direction(*direction);
+                }
+            }
+        }
+        let (width, height) = level.config.space_size;
+        level.config.space_size = transform.size(width, height);
+
+        self.set_cells(cells);
+        Ok(())
+    }
+}
+
+fn set_gpos(cell: &mut Cell, gpos: GlobalPos) {
+    match cell {
+        Cell::Wall(wall) => wall.gpos = gpos,
+        Cell::Block(block) => block.gpos = gpos,
+        Cell::Reference(reference) => reference.gpos = gpos,
+    }
+}
@@ -1,13 +1,33 @@
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pos(pub i32, pub i32);
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The id of a cell, which is its index in `Game::cells`. It is only used
+/// for the block of a `Container`, so that a position can't be taken for
+/// being in space by mistake; the other APIs take and return cell ids as
+/// plain indices, which `From` converts to and from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CellId(pub usize);
+
+/// What a position is in: the block with the given id, or nothing, which is
+/// only the case for top-level blocks, including the blocks of spaces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Container {
+    Space,
+    Block(CellId),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalPos {
-    pub block_id: usize,
+    pub container: Container,
     pub pos: Pos,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Direction {
     Up,
     Down,
@@ -15,6 +35,54 @@ pub enum Direction {
     Right,
 }
 
+impl CellId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl From<usize> for CellId {
+    fn from(id: usize) -> Self {
+        CellId(id)
+    }
+}
+
+impl From<CellId> for usize {
+    fn from(id: CellId) -> Self {
+        id.0
+    }
+}
+
+impl Container {
+    /// Returns the id of the block, or `None` in space.
+    pub fn block_id(self) -> Option<usize> {
+        match self {
+            Container::Space => None,
+            Container::Block(id) => Some(id.0),
+        }
+    }
+}
+
+impl GlobalPos {
+    /// Returns the position in the block with the given id.
+    pub fn new(block_id: usize, pos: Pos) -> Self {
+        GlobalPos {
+            container: Container::Block(CellId(block_id)),
+            pos,
+        }
+    }
+
+    /// Returns the id of the block the position is in, or `None` in space.
+    pub fn block_id(&self) -> Option<usize> {
+        self.container.block_id()
+    }
+
+    /// Whether the position is in the block with the given id.
+    pub fn is_in(&self, block_id: usize) -> bool {
+        self.block_id() == Some(block_id)
+    }
+}
+
 impl Pos {
     pub fn go(&mut self, direction: Direction) {
         match direction {
@@ -32,6 +100,48 @@ impl Pos {
 }
 
 impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    /// Parses a direction from one of the characters `UDLR`.
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'U' => Some(Direction::Up),
+            'D' => Some(Direction::Down),
+            'L' => Some(Direction::Left),
+            'R' => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        match self {
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        }
+    }
+
+    /// Returns the direction after a quarter turn counterclockwise.
+    pub fn turn_left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    /// Returns the direction after a quarter turn clockwise.
+    pub fn turn_right(self) -> Self {
+        self.turn_left().opposite()
+    }
+
     pub fn opposite(&self) -> Self {
         match self {
             Direction::Up => Direction::Down,
@@ -41,3 +151,20 @@ impl Direction {
         }
     }
 }
+
+/// (De)serializes an `Hsv` color as a `(h, s, v)` tuple, since `color_space`
+/// doesn't support serde.
+#[cfg(feature = "serde")]
+pub(crate) mod hsv_serde {
+    use color_space::Hsv;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(hsv: &Hsv, serializer: S) -> Result<S::Ok, S::Error> {
+        (hsv.h, hsv.s, hsv.v).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Hsv, D::Error> {
+        let (h, s, v) = <(f64, f64, f64)>::deserialize(deserializer)?;
+        Ok(Hsv::new(h, s, v))
+    }
+}
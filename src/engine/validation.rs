@@ -0,0 +1,318 @@
+use super::game::*;
+use super::utility::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem in a level that the parser accepts but is likely a mistake.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LevelIssue {
+    /// A reference points to a block that has no interior to enter.
+    ReferenceToSolidBlock {
+        reference_id: usize,
+        target_no: BlockNo,
+    },
+    /// More than one reference is marked as the exit of the same block.
+    MultipleExits {
+        target_no: BlockNo,
+        reference_ids: Vec<usize>,
+    },
+    /// A top-level block has references, but none of them is its exit, so
+    /// that cells can't leave it, which is rarely intended.
+    MissingExit {
+        target_no: BlockNo,
+        reference_ids: Vec<usize>,
+    },
+    /// A player is surrounded by walls it can neither push nor possess.
+    UnreachablePlayer { cell_id: usize },
+    /// A goal is not located inside the bounds of a block.
+    GoalOutsideBlock { gpos: GlobalPos },
+    /// A block is inside itself, directly or through the blocks containing
+    /// it, which happens while playing but can't be written to a level file.
+    BlockInsideItself { block_id: usize },
+    /// More than one cell occupies the same position.
+    OverlappingCells {
+        gpos: GlobalPos,
+        cell_ids: Vec<usize>,
+    },
+    /// The level has goals, but no players to reach them.
+    NoPlayers,
+    /// There are more player goals than players and possessable cells.
+    TooFewPlayers { goals: usize, players: usize },
+    /// There are more block goals than cells that can be moved onto them.
+    TooFewCells { goals: usize, cells: usize },
+}
+
+impl LevelIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            LevelIssue::ReferenceToSolidBlock { .. } => Severity::Warning,
+            LevelIssue::MultipleExits { .. } => Severity::Warning,
+            LevelIssue::MissingExit { .. } => Severity::Warning,
+            LevelIssue::UnreachablePlayer { .. } => Severity::Warning,
+            LevelIssue::GoalOutsideBlock { .. } => Severity::Error,
+            LevelIssue::BlockInsideItself { .. } => Severity::Error,
+            LevelIssue::OverlappingCells { .. } => Severity::Error,
+            LevelIssue::NoPlayers => Severity::Error,
+            LevelIssue::TooFewPlayers { .. } => Severity::Error,
+            LevelIssue::TooFewCells { .. } => Severity::Error,
+        }
+    }
+
+    /// Returns the positions of the cells and goals the issue is about, e.g.
+    /// to highlight them in an editor. Issues about the whole level have
+    /// none.
+    pub fn positions(&self, game: &Game) -> Vec<GlobalPos> {
+        let cell_ids = match self {
+            LevelIssue::ReferenceToSolidBlock { reference_id, .. } => vec![*reference_id],
+            LevelIssue::MultipleExits { reference_ids, .. }
+            | LevelIssue::MissingExit { reference_ids, .. } => reference_ids.clone(),
+            LevelIssue::UnreachablePlayer { cell_id } => vec![*cell_id],
+            LevelIssue::BlockInsideItself { block_id } => vec![*block_id],
+            LevelIssue::GoalOutsideBlock { gpos } => return vec![*gpos],
+            LevelIssue::OverlappingCells { gpos, .. } => return vec![*gpos],
+            LevelIssue::NoPlayers
+            | LevelIssue::TooFewPlayers { .. }
+            | LevelIssue::TooFewCells { .. } => Vec::new(),
+        };
+        cell_ids
+            .into_iter()
+            .filter_map(|id| game.cells().get(id))
+            .map(|cell| cell.gpos())
+            .collect()
+    }
+}
+
+impl std::fmt::Display for LevelIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelIssue::ReferenceToSolidBlock {
+                reference_id,
+                target_no,
+            } => write!(
+                f,
+                "Reference {reference_id} points to block {target_no} which has no interior"
+            ),
+            LevelIssue::MultipleExits {
+                target_no,
+                reference_ids,
+            } => write!(
+                f,
+                "Block {target_no} has multiple exit references {reference_ids:?}"
+            ),
+            LevelIssue::MissingExit {
+                target_no,
+                reference_ids,
+            } => write!(
+                f,
+                "Block {target_no} has references {reference_ids:?} but no exit, cells can't leave it"
+            ),
+            LevelIssue::UnreachablePlayer { cell_id } => {
+                write!(
+                    f,
+                    "Player {cell_id} is enclosed by walls and can never move"
+                )
+            }
+            LevelIssue::GoalOutsideBlock { gpos } => {
+                write!(f, "Goal at {gpos:?} is not inside any block")
+            }
+            LevelIssue::BlockInsideItself { block_id } => {
+                write!(f, "Block {block_id} is inside itself")
+            }
+            LevelIssue::OverlappingCells { gpos, cell_ids } => {
+                write!(f, "Cells {cell_ids:?} overlap at {gpos:?}")
+            }
+            LevelIssue::NoPlayers => write!(f, "Level has goals but no players"),
+            LevelIssue::TooFewPlayers { goals, players } => write!(
+                f,
+                "Level has {goals} player goals but only {players} players and possessable cells"
+            ),
+            LevelIssue::TooFewCells { goals, cells } => write!(
+                f,
+                "Level has {goals} block goals but only {cells} cells that can be moved onto them"
+            ),
+        }
+    }
+}
+
+impl Game {
+    /// Parses a level like `Game::parse`, and also returns the issues found
+    /// by `validate`, so that broken levels can be flagged when loading them.
+    pub fn parse_with_warnings(text: &str) -> Result<(Game, Vec<LevelIssue>), String> {
+        let game = Game::parse(text)?;
+        let issues = game.validate();
+        Ok((game, issues))
+    }
+
+    /// Checks the level for problems that the parser doesn't reject.
+    ///
+    /// Returns the issues found, sorted by severity (errors first).
+    pub fn validate(&self) -> Vec<LevelIssue> {
+        let mut issues = Vec::new();
+
+        self.validate_references(&mut issues);
+        self.validate_players(&mut issues);
+        self.validate_goals(&mut issues);
+        self.validate_positions(&mut issues);
+
+        issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity()));
+        issues
+    }
+
+    fn validate_references(&self, issues: &mut Vec<LevelIssue>) {
+        let mut exits: Vec<(BlockNo, Vec<usize>)> = Vec::new();
+        let mut referenced: Vec<(BlockNo, Vec<usize>)> = Vec::new();
+
+        for reference in self.cells.iter().filter_map(|cell| cell.reference()) {
+            let target_no = reference.target_no;
+            if let Some(target) = self.block_by_no(target_no) {
+                if !target.can_enter() {
+                    issues.push(LevelIssue::ReferenceToSolidBlock {
+                        reference_id: reference.id,
+                        target_no,
+                    });
+                }
+            }
+
+            let found = if reference.exit {
+                &mut exits
+            } else {
+                &mut referenced
+            };
+            match found.iter_mut().find(|(no, _)| *no == target_no) {
+                Some((_, ids)) => ids.push(reference.id),
+                None => found.push((target_no, vec![reference.id])),
+            }
+        }
+
+        // blocks inside other blocks are left into their container, even
+        // without an exit
+        for (target_no, reference_ids) in referenced {
+            let top_level = self
+                .block_by_no(target_no)
+                .is_some_and(|block| block.can_exit() && block.gpos.container == Container::Space);
+            if top_level && !exits.iter().any(|(no, _)| *no == target_no) {
+                issues.push(LevelIssue::MissingExit {
+                    target_no,
+                    reference_ids,
+                });
+            }
+        }
+
+        for (target_no, reference_ids) in exits {
+            if reference_ids.len() > 1 {
+                issues.push(LevelIssue::MultipleExits {
+                    target_no,
+                    reference_ids,
+                });
+            }
+        }
+    }
+
+    fn validate_players(&self, issues: &mut Vec<LevelIssue>) {
+        if self.player_ids.is_empty() && !self.level.goals.is_empty() {
+            issues.push(LevelIssue::NoPlayers);
+        }
+
+        if self.level.config.inner_push {
+            // pushing a wall may move its parent block
+            return;
+        }
+
+        for &cell_id in &self.player_ids {
+            let gpos = self.cells[cell_id].gpos();
+            let Some(parent) = self.container_block(gpos) else {
+                continue;
+            };
+
+            let enclosed = Direction::ALL.into_iter().all(|direction| {
+                let pos = gpos.pos.towards(direction);
+                parent.in_bounds(pos)
+                    && matches!(
+                        self.cell_at(GlobalPos::new(parent.id, pos)),
+                        Some(Cell::Wall(wall)) if !wall.possessable
+                    )
+            });
+
+            if enclosed {
+                issues.push(LevelIssue::UnreachablePlayer { cell_id });
+            }
+        }
+    }
+
+    fn validate_goals(&self, issues: &mut Vec<LevelIssue>) {
+        let player_goals = self.level.goals.iter().filter(|goal| goal.player).count();
+        let players = self
+            .cells
+            .iter()
+            .filter(|cell| cell.possessable() || self.is_player(cell.id()))
+            .count();
+        if player_goals > players && !self.player_ids.is_empty() {
+            issues.push(LevelIssue::TooFewPlayers {
+                goals: player_goals,
+                players,
+            });
+        }
+
+        // any cell that isn't a wall or a top-level block may end up on a goal
+        let block_goals = self.level.goals.len() - player_goals;
+        let cells = self
+            .cells
+            .iter()
+            .filter(|cell| {
+                !cell.is_wall()
+                    && cell.gpos().container != Container::Space
+                    && !self.is_space(cell.id())
+            })
+            .count();
+        if block_goals > cells {
+            issues.push(LevelIssue::TooFewCells {
+                goals: block_goals,
+                cells,
+            });
+        }
+
+        for goal in &self.level.goals {
+            let inside = match self.container_block(goal.gpos) {
+                Some(block) => block.can_enter() && block.in_bounds(goal.gpos.pos),
+                None => false,
+            };
+            if !inside {
+                issues.push(LevelIssue::GoalOutsideBlock { gpos: goal.gpos });
+            }
+        }
+    }
+
+    fn validate_positions(&self, issues: &mut Vec<LevelIssue>) {
+        let mut occupied: Vec<(GlobalPos, Vec<usize>)> = Vec::new();
+
+        for cell in self.cells.iter() {
+            let gpos = cell.gpos();
+            if gpos.container == Container::Space {
+                // top-level blocks don't occupy a position
+                continue;
+            }
+            match occupied.iter_mut().find(|(p, _)| *p == gpos) {
+                Some((_, ids)) => ids.push(cell.id()),
+                None => occupied.push((gpos, vec![cell.id()])),
+            }
+        }
+
+        for (gpos, cell_ids) in occupied {
+            if cell_ids.len() > 1 {
+                issues.push(LevelIssue::OverlappingCells { gpos, cell_ids });
+            }
+        }
+
+        for cell in self.cells.iter().filter(|cell| cell.block().is_some()) {
+            if self.is_within(cell.gpos(), cell.id()) {
+                issues.push(LevelIssue::BlockInsideItself {
+                    block_id: cell.id(),
+                });
+            }
+        }
+    }
+}
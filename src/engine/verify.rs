@@ -0,0 +1,98 @@
+use super::game::*;
+use super::sequence::*;
+
+/// The result of a solution that wins the level exactly at its last move.
+#[derive(Clone, Debug)]
+pub struct VerifyReport {
+    /// Number of moves of the solution.
+    pub steps: usize,
+    /// Number of moves that changed nothing, e.g. walking into a wall.
+    pub noops: usize,
+    /// The state after the last move.
+    pub final_state: Game,
+}
+
+/// Why a solution doesn't solve a level.
+#[derive(Clone, Debug)]
+pub enum VerifyError {
+    /// The solution is not a valid movement sequence.
+    InvalidSequence(SequenceError),
+    /// The level is won before any move is played, so it can't be solved.
+    AlreadyWon,
+    /// The level is won after `step` moves, but the solution goes on.
+    WonEarly { step: usize, steps: usize },
+    /// The level is not won at the end of the solution.
+    NotWon {
+        steps: usize,
+        satisfied_goals: usize,
+        goals: usize,
+        final_state: Box<Game>,
+    },
+}
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::InvalidSequence(e) => write!(f, "{e}"),
+            VerifyError::AlreadyWon => write!(f, "the level is won before any move"),
+            VerifyError::WonEarly { step, steps } => {
+                write!(f, "should not win now after {step} of {steps} steps")
+            }
+            VerifyError::NotWon {
+                steps,
+                satisfied_goals,
+                goals,
+                ..
+            } => write!(
+                f,
+                "should win now after {steps} steps, but only {satisfied_goals}/{goals} goals are satisfied"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl From<SequenceError> for VerifyError {
+    fn from(e: SequenceError) -> Self {
+        VerifyError::InvalidSequence(e)
+    }
+}
+
+/// Checks that a movement sequence, e.g. from a `.solution` file, solves the
+/// level: played from the given state, it must win exactly after the last
+/// move, and not before.
+pub fn verify_solution(game: &Game, moves: &str) -> Result<VerifyReport, VerifyError> {
+    let moves = parse_sequence(moves)?;
+    if game.won() {
+        return Err(VerifyError::AlreadyWon);
+    }
+
+    let mut game = game.clone();
+    let mut noops = 0;
+    for (i, direction) in moves.iter().enumerate() {
+        if !game.play(*direction).changed() {
+            noops += 1;
+        }
+        if game.won() && i + 1 < moves.len() {
+            return Err(VerifyError::WonEarly {
+                step: i + 1,
+                steps: moves.len(),
+            });
+        }
+    }
+
+    if !game.won() {
+        return Err(VerifyError::NotWon {
+            steps: moves.len(),
+            satisfied_goals: game.satisfied_goals(),
+            goals: game.goals().len(),
+            final_state: Box::new(game),
+        });
+    }
+    Ok(VerifyReport {
+        steps: moves.len(),
+        noops,
+        final_state: game,
+    })
+}
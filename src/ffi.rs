@@ -0,0 +1,150 @@
+//! A small C ABI for embedding the engine in other languages (`cdylib`
+//! feature). The declarations are in `include/parabox.h`.
+//!
+//! Games are passed around as opaque pointers which are owned by the caller
+//! and freed with `parabox_game_free`. Strings and byte buffers returned by
+//! these functions are owned by the caller as well, and freed with
+//! `parabox_string_free` and `parabox_bytes_free`.
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use crate::engine::*;
+
+// hands a string over to the caller, who frees it with `parabox_string_free`
+fn into_c_string(text: String) -> *mut c_char {
+    // C strings can't contain nul characters
+    CString::new(text.replace('\0', "")).unwrap().into_raw()
+}
+
+/// Parses a level from a nul-terminated string in the level file format.
+///
+/// Returns null if the level is invalid. If `error` is not null, it is set to
+/// the error message in that case, and to null otherwise.
+///
+/// # Safety
+///
+/// `text` must be a valid nul-terminated string, and `error` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn parabox_game_load(
+    text: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut Game {
+    let result = match CStr::from_ptr(text).to_str() {
+        Ok(text) => Game::parse(text),
+        Err(e) => Err(e.to_string()),
+    };
+    let (game, message) = match result {
+        Ok(game) => (Box::into_raw(Box::new(game)), ptr::null_mut()),
+        Err(e) => (ptr::null_mut(), into_c_string(e)),
+    };
+    if error.is_null() {
+        if !message.is_null() {
+            drop(CString::from_raw(message));
+        }
+    } else {
+        *error = message;
+    }
+    game
+}
+
+/// Returns a copy of a game, which can be played independently.
+///
+/// # Safety
+///
+/// `game` must be a valid game handle.
+#[no_mangle]
+pub unsafe extern "C" fn parabox_game_clone(game: *const Game) -> *mut Game {
+    Box::into_raw(Box::new((*game).clone()))
+}
+
+/// Frees a game. Does nothing if `game` is null.
+///
+/// # Safety
+///
+/// `game` must be null or a game handle that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn parabox_game_free(game: *mut Game) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Plays one input, given as one of the characters `U`, `D`, `L` and `R`.
+///
+/// Returns 1 if the state changed, 0 if it didn't, and -1 if the direction is
+/// invalid.
+///
+/// # Safety
+///
+/// `game` must be a valid game handle.
+#[no_mangle]
+pub unsafe extern "C" fn parabox_game_play(game: *mut Game, direction: c_char) -> i32 {
+    let Some(direction) = Direction::from_char(direction as u8 as char) else {
+        return -1;
+    };
+    (*game).play(direction).changed() as i32
+}
+
+/// Returns whether the level is solved.
+///
+/// # Safety
+///
+/// `game` must be a valid game handle.
+#[no_mangle]
+pub unsafe extern "C" fn parabox_game_won(game: *const Game) -> bool {
+    (*game).won()
+}
+
+/// Writes the current state in the level file format, as a nul-terminated
+/// string to be freed with `parabox_string_free`.
+///
+/// # Safety
+///
+/// `game` must be a valid game handle.
+#[no_mangle]
+pub unsafe extern "C" fn parabox_game_to_text(game: *const Game) -> *mut c_char {
+    into_c_string((*game).to_text())
+}
+
+/// Encodes the current state with `Game::encode_state`, e.g. for
+/// transposition tables. The length is written to `len`, and the buffer is
+/// to be freed with `parabox_bytes_free`.
+///
+/// # Safety
+///
+/// `game` must be a valid game handle, and `len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn parabox_game_encode_state(game: *const Game, len: *mut usize) -> *mut u8 {
+    let bytes = (*game).encode_state().into_boxed_slice();
+    *len = bytes.len();
+    Box::into_raw(bytes) as *mut u8
+}
+
+/// Frees a string returned by this library. Does nothing if `text` is null.
+///
+/// # Safety
+///
+/// `text` must be null or a string returned by this library that hasn't been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn parabox_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// Frees a buffer returned by `parabox_game_encode_state`. Does nothing if
+/// `bytes` is null.
+///
+/// # Safety
+///
+/// `bytes` must be null or a buffer returned by this library that hasn't
+/// been freed yet, with the length it was returned with.
+#[no_mangle]
+pub unsafe extern "C" fn parabox_bytes_free(bytes: *mut u8, len: usize) {
+    if !bytes.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(bytes, len)));
+    }
+}
@@ -1,8 +1,97 @@
+#[cfg(feature = "cdylib")]
+pub mod ffi;
+#[cfg(feature = "pyo3")]
+pub mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
 pub mod engine {
+    pub mod analysis;
+    pub mod batch;
+    pub mod canonical;
+    pub mod capabilities;
+    pub mod colors;
+    pub mod compose;
+    pub mod custom_levels;
+    pub mod deadlock;
+    pub mod delta;
+    pub mod difficulty;
+    pub mod edit;
+    pub mod encoding;
+    pub mod env;
     pub mod game;
+    pub mod generator;
+    pub mod history;
+    pub mod invariants;
+    #[cfg(feature = "json")]
+    pub mod json;
+    pub mod occupancy;
+    pub mod sequence;
+    pub mod serialize;
     pub mod simulation;
+    pub mod solver;
+    pub mod spec;
+    pub mod state;
+    pub mod structure;
+    pub mod template;
+    pub mod trace;
+    pub mod transform;
     pub mod utility;
+    pub mod validation;
+    pub mod verify;
 
+    pub use canonical::CanonicalLevel;
+    pub use capabilities::{capabilities, Capabilities};
+    pub use colors::{distinct_hue, hue_distance, MIN_HUE_DISTANCE};
+    pub use compose::Embedding;
+    pub use delta::StateDelta;
+    pub use edit::{EditHistory, Renumbering};
+    pub use encoding::StateSchema;
     pub use game::*;
+    pub use history::*;
+    pub use invariants::InvariantViolation;
+    pub use occupancy::Occupancy;
+    pub use sequence::*;
+    pub use simulation::{
+        EnterRefusal, MoveFailure, MoveOutcome, MoveState, SimulatorBuffers, TransferPoint,
+    };
+    pub use state::*;
+    pub use structure::{EdgeKind, StructureEdge, StructureGraph, StructureNode};
+    pub use template::Template;
+    pub use trace::{
+        diff_trace, DecisionTree, SimCall, SimLog, SimTrace, Trace, TraceEvent, TraceKind,
+        TraceMismatch,
+    };
+    pub use transform::Transform;
     pub use utility::*;
+    pub use validation::*;
+    pub use verify::{verify_solution, VerifyError, VerifyReport};
+}
+
+pub mod render {
+    pub mod ascii;
+    pub mod glyph;
+    #[cfg(feature = "png")]
+    pub mod kitty;
+    pub mod layout;
+    #[cfg(feature = "png")]
+    pub mod raster;
+    pub mod renderer;
+    pub mod svg;
+    #[cfg(feature = "text-ui")]
+    pub mod terminal;
+    pub mod theme;
+
+    pub use ascii::*;
+    pub use glyph::*;
+    #[cfg(feature = "png")]
+    pub use kitty::*;
+    pub use layout::*;
+    #[cfg(feature = "png")]
+    pub use raster::*;
+    pub use renderer::*;
+    pub use svg::*;
+    #[cfg(feature = "text-ui")]
+    pub use terminal::*;
+    pub use theme::*;
 }
@@ -1,221 +1,759 @@
-use color_space::{Hsv, ToRgb};
-use crossterm::{
-    cursor, event,
-    style::{self, Stylize},
-    terminal, QueueableCommand,
-};
-use parabox::engine::*;
+use crossterm::{cursor, event, style, terminal, ExecutableCommand, QueueableCommand};
+use parabox::engine::{solver::*, *};
+#[cfg(feature = "png")]
+use parabox::render::{kitty_supported, PngRenderer};
+use parabox::render::{Renderer, TerminalRenderer, Theme};
 use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+mod clipboard;
+mod daily;
+mod editor;
+mod menu;
+mod navigator;
+mod spectate;
+
+mod cli {
+    pub mod analyze;
+    pub mod capabilities;
+    pub mod check;
+    pub mod export;
+    pub mod generate;
+    pub mod graph;
+    pub mod replay;
+    #[cfg(feature = "json")]
+    pub mod serve;
+    pub mod solve;
+    pub mod solverd;
+    pub mod spec;
+    pub mod thumbnails;
+    pub mod variants;
+}
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
-    let text = std::fs::read_to_string(&args[1]).unwrap();
-
-    let mut history = vec![Game::parse(&text).unwrap()];
-
-    // execute the startup sequence
-    if let Some(sequence) = args.get(2) {
-        let game = history.last_mut().unwrap();
-        for c in sequence.chars() {
-            let direction = match c {
-                'U' => Direction::Up,
-                'D' => Direction::Down,
-                'L' => Direction::Left,
-                'R' => Direction::Right,
-                ' ' => continue,
-                _ => panic!("invalid sequence character: {c}"),
-            };
-            game.play(direction);
-        }
+
+    let result = match args.get(1).map(String::as_str) {
+        Some("analyze") => cli::analyze::run(&args[2..]),
+        Some("capabilities") => cli::capabilities::run(&args[2..]),
+        Some("check") => cli::check::run(&args[2..]),
+        Some("daily") => daily::run(&args[2..]),
+        Some("edit") => editor::run(&args[2..]),
+        Some("export") => cli::export::run(&args[2..]),
+        Some("generate") => cli::generate::run(&args[2..]),
+        Some("graph") => cli::graph::run(&args[2..]),
+        Some("replay") => cli::replay::run(&args[2..]),
+        #[cfg(feature = "json")]
+        Some("serve") => cli::serve::run(&args[2..]),
+        #[cfg(not(feature = "json"))]
+        Some("serve") => Err("parabox serve needs the json feature".to_string()),
+        Some("solve") => cli::solve::run(&args[2..]),
+        Some("solverd") => cli::solverd::run(&args[2..]),
+        Some("spec") => cli::spec::run(&args[2..]),
+        Some("thumbnails") => cli::thumbnails::run(&args[2..]),
+        Some("variants") => cli::variants::run(&args[2..]),
+        _ => play(&args),
+    };
+
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+/// Options of the game given on the command line.
+struct PlayOptions {
+    // whether moves that change nothing are kept in the history
+    record_noops: bool,
+    // whether to draw the board as an image when the terminal supports it
+    graphics: bool,
+    theme: Theme,
+    // whether to write the moves to the `.solution` file of the level when
+    // winning, and whether to replace an existing one
+    record_solution: bool,
+    overwrite_solution: bool,
+    // whether to watch the level file and offer to reload it when it changes
+    watch: bool,
+    // whether to pick from the custom levels of the installed game instead
+    custom_levels: bool,
+    // where the states are sent with `--spectate`
+    spectators: Option<spectate::Spectators>,
+}
+
+fn play(args: &[String]) -> Result<(), String> {
+    let options = PlayOptions {
+        record_noops: args.iter().any(|arg| arg == "--record-noops"),
+        graphics: args.iter().any(|arg| arg == "--graphics"),
+        record_solution: args
+            .iter()
+            .any(|arg| arg == "--record-solution" || arg == "--overwrite-solution"),
+        overwrite_solution: args.iter().any(|arg| arg == "--overwrite-solution"),
+        watch: args.iter().any(|arg| arg == "--watch"),
+        custom_levels: args.iter().any(|arg| arg == "--custom-levels"),
+        spectators: match args.iter().find_map(|arg| arg.strip_prefix("--spectate=")) {
+            Some(port) => {
+                let port = port.parse().map_err(|_| format!("Invalid port {port}"))?;
+                Some(spectate::Spectators::listen(port)?)
+            }
+            None => None,
+        },
+        // a theme preset, or the path to a theme config
+        theme: match args.iter().find_map(|arg| arg.strip_prefix("--theme=")) {
+            Some(name) => match Theme::preset(name) {
+                Some(theme) => theme,
+                None => Theme::load(Path::new(name))?,
+            },
+            None => Theme::default(),
+        },
+    };
+    let args = args
+        .iter()
+        .filter(|arg| !arg.starts_with("--"))
+        .collect::<Vec<_>>();
+
+    // pick from the custom levels of an installed copy of the game
+    if options.custom_levels {
+        let Some(dir) = custom_levels::find_custom_level_dir() else {
+            return Err(format!(
+                "No custom levels of Patrick's Parabox found, set {} to the directory",
+                custom_levels::CUSTOM_LEVELS_VAR
+            ));
+        };
+        return menu::run(&dir, &options);
+    }
+    let Some(path) = args.get(1) else {
+        return Err("usage: parabox <puzzle or directory> [sequence] [options]".to_string());
+    };
+
+    // pick levels from a menu when given a directory
+    if Path::new(path).is_dir() {
+        return menu::run(Path::new(path), &options);
     }
 
+    play_level(
+        Path::new(path),
+        args.get(2).map(|sequence| sequence.as_str()),
+        &options,
+    )?;
+    Ok(())
+}
+
+/// Plays a level until it is won or the player quits, and returns the number
+/// of moves it was won in, counting the startup sequence, if it was won.
+fn play_level(
+    path: &Path,
+    sequence: Option<&str>,
+    options: &PlayOptions,
+) -> Result<Option<usize>, String> {
+    // execute the startup sequence, which is kept for exporting the moves
+    let mut startup = parse_sequence(sequence.unwrap_or("")).map_err(|e| e.to_string())?;
+    let (game, issues) = load_level(path, &startup)?;
+    let mut modified = modified_time(path);
+    // levels pasted from the clipboard are played from a file of their own
+    let mut path = path.to_path_buf();
+
+    let mut history = UndoTree::new(game);
+    history.set_record_noops(options.record_noops);
+
     let stdout = std::io::stdout();
-    let mut writer = BufWriter::new(stdout);
-    render(history.last().unwrap(), &mut writer, true).unwrap();
+    let mut renderer = TerminalRenderer::new(BufWriter::new(stdout));
+    renderer.options.theme = options.theme;
+    let mut warnings = issues
+        .iter()
+        .map(|issue| format!("Warning: {issue}"))
+        .collect::<Vec<_>>();
+    if options.graphics && !enable_graphics(&mut renderer) {
+        warnings.insert(
+            0,
+            "Graphics are not supported, falling back to text".to_string(),
+        );
+    }
+    // the title is shown in the window title, and below the board until the
+    // first key
+    let heading = heading(history.current().meta());
+    if let Some(title) = &history.current().meta().title {
+        renderer.out_mut().queue(terminal::SetTitle(title)).unwrap();
+    }
+    let mut stats = Stats::default();
+    renderer.render(history.current()).unwrap();
+    if let Some(spectators) = &options.spectators {
+        spectators.broadcast(history.current(), history.depth());
+    }
+    print_status(renderer.out_mut(), &status(&history, &stats)).unwrap();
 
     let mut repaint = true;
+    // whether the calls made by the simulator are shown after each move
+    let mut sim_log = false;
+    let mut won = false;
+
+    // solutions found for hints, reused while following them
+    let mut hints = SolutionTable::new();
+    // the direction of the last move, if it failed, for explaining it
+    let mut failed = None;
+
+    // flag likely mistakes in the level before playing
+    if let Some(warning) = warnings.first() {
+        let mut message = warning.clone();
+        if warnings.len() > 1 {
+            message += &format!(" (and {} more)", warnings.len() - 1);
+        }
+        print_message(renderer.out_mut(), &message).unwrap();
+    } else if let Some(heading) = &heading {
+        print_message(renderer.out_mut(), heading).unwrap();
+    }
+
+    // keys are read as they are pressed, and clicking a cell inspects it
+    let capture = MouseCapture::enable().map_err(|e| e.to_string())?;
 
     loop {
-        let event = event::read();
-        if let event::Event::Key(event) = event.unwrap() {
+        if options.watch && !event::poll(WATCH_INTERVAL).unwrap() {
+            let time = modified_time(&path);
+            if time != modified {
+                modified = time;
+                print_message(
+                    renderer.out_mut(),
+                    "Level file changed: press L to reload and replay the moves, or Shift+L to reload from the start",
+                )
+                .unwrap();
+            }
+            continue;
+        }
+        let event = event::read().unwrap();
+        if let event::Event::Mouse(mouse) = event {
+            if mouse.kind == event::MouseEventKind::Down(event::MouseButton::Left) {
+                let game = history.current();
+                let message = renderer
+                    .gpos_at(game, mouse.column, mouse.row)
+                    .map(|gpos| inspect(game, gpos))
+                    .unwrap_or_default();
+                print_message(renderer.out_mut(), &message).unwrap();
+            }
+            continue;
+        }
+        if let event::Event::Key(event) = event {
             if event.kind == event::KeyEventKind::Press {
+                // explanation of why the last move failed
+                let mut message = None;
+                // what the move changed, to only redraw the affected blocks
+                let mut moved = None;
+                let last_failed = failed.take();
+                // the calls made by the simulator for the move
+                let mut log = None;
+
                 let mut play = |direction: Direction| {
-                    let mut game = history.last().unwrap().clone();
-                    game.play(direction);
-                    history.push(game);
+                    if sim_log {
+                        let mut calls = SimLog(Vec::new());
+                        history.current().clone().play_logged(direction, &mut calls);
+                        log = Some(String::from_utf8_lossy(&calls.0).into_owned());
+                    }
+                    let outcome = history.play(direction);
+                    let mut message = None;
+                    if !outcome.changed() {
+                        message = outcome
+                            .explanation()
+                            .map(|failure| format!("Can't move: {failure} (press ? for details)"));
+                        failed = Some(direction);
+                    }
+                    moved = Some(outcome);
+                    message
                 };
 
                 match event.code {
-                    event::KeyCode::Char('w') => play(Direction::Up),
-                    event::KeyCode::Char('a') => play(Direction::Left),
-                    event::KeyCode::Char('s') => play(Direction::Down),
-                    event::KeyCode::Char('d') => play(Direction::Right),
-                    event::KeyCode::Char('r') => history.push(history.first().unwrap().clone()),
+                    event::KeyCode::Char('w') => message = play(Direction::Up),
+                    event::KeyCode::Char('a') => message = play(Direction::Left),
+                    event::KeyCode::Char('s') => message = play(Direction::Down),
+                    event::KeyCode::Char('d') => message = play(Direction::Right),
+                    event::KeyCode::Char('r') => {
+                        history.jump(UndoTree::ROOT);
+                    }
                     event::KeyCode::Char('z') => {
-                        if history.len() > 1 {
-                            history.pop();
+                        if history.undo() {
+                            stats.undos += 1;
                         }
                     }
+                    event::KeyCode::Char('y') => {
+                        history.redo();
+                    }
+                    event::KeyCode::Char('[') => {
+                        for _ in 0..STEP {
+                            history.undo();
+                        }
+                    }
+                    event::KeyCode::Char(']') => {
+                        for _ in 0..STEP {
+                            history.redo();
+                        }
+                    }
+                    event::KeyCode::Home => {
+                        history.jump(UndoTree::ROOT);
+                    }
+                    event::KeyCode::End => while history.redo() {},
                     event::KeyCode::Char('p') => {
-                        debug(history.last().unwrap());
+                        debug(history.current());
+                        continue;
+                    }
+                    event::KeyCode::Char('?') => {
+                        failed = last_failed;
+                        match last_failed {
+                            Some(direction) => explain(history.current(), direction),
+                            None => print_message(renderer.out_mut(), "The last move didn't fail")
+                                .unwrap(),
+                        }
                         continue;
                     }
                     event::KeyCode::Char('e') => repaint = !repaint,
+                    event::KeyCode::Char('g') => {
+                        sim_log = !sim_log;
+                        message = Some(
+                            if sim_log {
+                                "Showing the simulator calls of each move"
+                            } else {
+                                "Hiding the simulator calls"
+                            }
+                            .to_string(),
+                        );
+                    }
+                    event::KeyCode::Char('v') => {
+                        renderer.options.preview = !renderer.options.preview
+                    }
+                    event::KeyCode::Char('n') => {
+                        renderer.options.depth = if renderer.options.depth == 0 { 2 } else { 0 };
+                        renderer.clear();
+                    }
+                    event::KeyCode::Char('h') => {
+                        message = Some(hint(history.current(), &mut hints))
+                    }
+                    event::KeyCode::Up => renderer.scroll(0, -4),
+                    event::KeyCode::Down => renderer.scroll(0, 4),
+                    event::KeyCode::Left => renderer.scroll(-8, 0),
+                    event::KeyCode::Right => renderer.scroll(8, 0),
+                    event::KeyCode::Char('x') => {
+                        let mut moves = startup.clone();
+                        moves.extend(history.moves());
+                        message = Some(export_moves(&path, &moves));
+                    }
+                    event::KeyCode::Char(key @ ('l' | 'L')) => {
+                        modified = modified_time(&path);
+                        message = Some(match load_level(&path, &startup) {
+                            Ok((game, issues)) => {
+                                let moves = if key == 'l' { history.moves() } else { vec![] };
+                                history = UndoTree::new(game);
+                                history.set_record_noops(options.record_noops);
+                                for &direction in &moves {
+                                    history.play(direction);
+                                }
+                                hints = SolutionTable::new();
+                                renderer.clear();
+                                let mut message =
+                                    format!("Reloaded, replayed {} moves", moves.len());
+                                if let Some(issue) = issues.first() {
+                                    message += &format!(" | Warning: {issue}");
+                                }
+                                message
+                            }
+                            Err(e) => format!("Can't reload: {e}"),
+                        });
+                    }
+                    event::KeyCode::Char(key @ ('c' | 'C')) => {
+                        // the current state, or the level file
+                        let text = if key == 'c' {
+                            Ok(history.current().to_text())
+                        } else {
+                            std::fs::read_to_string(&path).map_err(|e| e.to_string())
+                        };
+                        message = Some(match text.and_then(|text| clipboard::copy(&text)) {
+                            Ok(()) if key == 'c' => "Copied the current state".to_string(),
+                            Ok(()) => "Copied the level".to_string(),
+                            Err(e) => format!("Can't copy: {e}"),
+                        });
+                    }
+                    event::KeyCode::Char('V') => {
+                        let pasted = paste_level().and_then(|pasted| {
+                            let (game, issues) = load_level(&pasted, &[])?;
+                            Ok((pasted, game, issues))
+                        });
+                        message = Some(match pasted {
+                            Ok((pasted, game, issues)) => {
+                                path = pasted;
+                                modified = modified_time(&path);
+                                startup.clear();
+                                history = UndoTree::new(game);
+                                history.set_record_noops(options.record_noops);
+                                hints = SolutionTable::new();
+                                renderer.clear();
+                                let mut message =
+                                    format!("Pasted level saved to {}", path.display());
+                                if let Some(issue) = issues.first() {
+                                    message += &format!(" | Warning: {issue}");
+                                }
+                                message
+                            }
+                            Err(e) => format!("Can't paste: {e}"),
+                        });
+                    }
+                    event::KeyCode::Char('t') => {
+                        navigator::run(&mut history, renderer.out_mut()).unwrap();
+                        renderer.clear();
+                    }
                     event::KeyCode::Char('q') => break,
                     _ => continue,
                 }
 
-                let game = history.last().unwrap();
+                let game = history.current();
+                if let Some(spectators) = &options.spectators {
+                    spectators.broadcast(game, history.depth());
+                }
                 if repaint {
-                    render(game, &mut writer, false).unwrap();
+                    match &moved {
+                        Some(outcome) => renderer.render_move(game, outcome).unwrap(),
+                        None => renderer.render(game).unwrap(),
+                    }
+                    print_status(renderer.out_mut(), &status(&history, &stats)).unwrap();
+                    print_message(renderer.out_mut(), message.as_deref().unwrap_or("")).unwrap();
+                    if let Some(log) = &log {
+                        print_log(renderer.out_mut(), log).unwrap();
+                    }
                 }
                 if game.won() {
-                    println!("You won!");
+                    won = true;
                     break;
                 }
             }
         }
     }
-}
 
-fn debug(game: &Game) {
-    for cell in game.cells() {
-        println!("{cell:?}");
+    drop(capture);
+    if !won {
+        return Ok(None);
+    }
+    println!("You won!");
+    let mut moves = startup.clone();
+    moves.extend(history.moves());
+    if options.record_solution {
+        println!(
+            "{}",
+            record_solution(&path, &moves, options.overwrite_solution)
+        );
     }
+    Ok(Some(moves.len()))
 }
 
-fn color_from_hsv(hsv: Hsv) -> style::Color {
-    let rgb = hsv.to_rgb();
-    style::Color::Rgb {
-        r: rgb.r as u8,
-        g: rgb.g as u8,
-        b: rgb.b as u8,
+/// Puts the terminal in raw mode and captures the mouse, until dropped.
+struct MouseCapture;
+
+impl MouseCapture {
+    fn enable() -> std::io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        std::io::stdout().execute(event::EnableMouseCapture)?;
+        Ok(Self)
     }
 }
 
-fn block_no_to_char(block_no: BlockNo) -> char {
-    "0123456789ABCDEF"
-        .chars()
-        .nth(block_no.0 as usize)
-        .unwrap_or('G')
+impl Drop for MouseCapture {
+    fn drop(&mut self) {
+        let _ = std::io::stdout().execute(event::DisableMouseCapture);
+        let _ = terminal::disable_raw_mode();
+    }
 }
 
-fn render(game: &Game, out: &mut impl Write, clear: bool) -> crossterm::Result<()> {
-    if clear {
-        out.queue(terminal::Clear(terminal::ClearType::All))?;
+/// Describes the cell or empty position at the given position, for the
+/// cell inspector.
+fn inspect(game: &Game, gpos: GlobalPos) -> String {
+    let parent = match game.container_block(gpos) {
+        Some(block) => format!("block {}", block.block_no),
+        None => "space".to_string(),
+    };
+    let Pos(x, y) = gpos.pos;
+    let goal = match game.goals().iter().find(|goal| goal.gpos == gpos) {
+        Some(goal) if goal.player => ", on a player goal",
+        Some(_) => ", on a block goal",
+        None => "",
+    };
+
+    let Some(cell) = game.cell_at(gpos) else {
+        return format!("Empty at ({x}, {y}) in {parent}{goal}");
+    };
+    let mut flags = Vec::new();
+    if game.is_player(cell.id()) {
+        flags.push("player".to_string());
+    }
+    let kind = match cell {
+        Cell::Wall(wall) => {
+            if wall.possessable {
+                flags.push("possessable".to_string());
+            }
+            "Wall".to_string()
+        }
+        Cell::Block(block) => {
+            for (set, flag) in [
+                (block.filled, "filled"),
+                (block.space, "space"),
+                (block.possessable, "possessable"),
+            ] {
+                if set {
+                    flags.push(flag.to_string());
+                }
+            }
+            if let Some((block_no, degree)) = block.inf_enter {
+                flags.push(format!("infenter {degree} from {block_no}"));
+            }
+            format!(
+                "Block {} ({}x{})",
+                block.block_no, block.width, block.height
+            )
+        }
+        Cell::Reference(reference) => {
+            if reference.exit {
+                flags.push("exit".to_string());
+            }
+            if reference.possessable {
+                flags.push("possessable".to_string());
+            }
+            if let Some(degree) = reference.inf_exit {
+                flags.push(format!("infexit {degree}"));
+            }
+            format!("Reference to {}", reference.target_no)
+        }
+    };
+    if cell.fliph() {
+        flags.push("fliph".to_string());
     }
 
-    const WIDTH: u16 = 19;
-    const HEIGHT: u16 = 16;
-    const COLUMNS: u16 = 8;
-    let mut counter = 0u16;
+    format!(
+        "Cell {}: {} at ({x}, {y}) in {parent}{goal}{}",
+        cell.id(),
+        kind,
+        if flags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", flags.join(", "))
+        }
+    )
+}
 
-    for block in game.cells().iter().filter_map(|cell| cell.block()) {
-        if game.is_block_trivial(block) {
-            continue;
+/// Searches for a solution from the current state with a bounded budget, and
+/// describes the next move.
+fn hint(game: &Game, hints: &mut SolutionTable) -> String {
+    let config = SolverConfig {
+        strategy: Strategy::AStar,
+        max_nodes: 50_000,
+        ..Default::default()
+    };
+    let report = hints.solve(game, &config);
+    match report.solution {
+        Some(solution) => match solution.first() {
+            Some(direction) => format!("Hint: move {direction:?} ({} moves to go)", solution.len()),
+            None => "Hint: the level is already won".to_string(),
+        },
+        None if report.exhausted => {
+            "Hint: the level can't be won from here, try undoing".to_string()
         }
+        None => "Hint: no solution found nearby".to_string(),
+    }
+}
 
-        let area_x = WIDTH * (counter % COLUMNS);
-        let area_y = HEIGHT * (counter / COLUMNS);
-        let padding_x = (WIDTH - block.width as u16) / 2;
-        let padding_y = (HEIGHT - 1 - block.height as u16) / 2;
-        let offset_x = area_x + padding_x;
-        let offset_y = area_y + padding_y;
-
-        counter += 1;
-
-        let color = color_from_hsv(block.hsv);
-        let title = format!("[{}]", block_no_to_char(block.block_no));
-
-        out.queue(cursor::MoveTo(
-            area_x + (WIDTH - title.len() as u16) / 2,
-            offset_y,
-        ))?
-        .queue(style::PrintStyledContent(title.with(color)))?;
-
-        for y in (0..block.height).rev() {
-            out.queue(cursor::MoveTo(
-                offset_x,
-                offset_y + (block.height - y) as u16,
-            ))?;
-
-            for x in 0..block.width {
-                let gpos = GlobalPos {
-                    block_id: block.id,
-                    pos: Pos(x, y),
-                };
+/// Prints a one-line message below the board, replacing the previous one.
+/// Draws the board as an image if the terminal supports it.
+#[cfg(feature = "png")]
+fn enable_graphics(renderer: &mut TerminalRenderer<impl Write>) -> bool {
+    if kitty_supported() {
+        renderer.graphics = Some(PngRenderer {
+            theme: renderer.options.theme,
+            ..Default::default()
+        });
+    }
+    renderer.graphics.is_some()
+}
 
-                let mut color = color;
-                let mut inverted = false;
-                let mut underlined = false;
-                let mark = if let Some(cell) = game.cell_at(gpos) {
-                    match &cell {
-                        Cell::Wall(_) => '#',
-                        Cell::Block(block) => {
-                            color = color_from_hsv(block.hsv);
-
-                            if block.fliph {
-                                underlined = true;
-                            }
+#[cfg(not(feature = "png"))]
+fn enable_graphics(_renderer: &mut TerminalRenderer<impl Write>) -> bool {
+    false
+}
 
-                            if game.player_ids().contains(&block.id) {
-                                'p'
-                            } else if game.is_block_trivial(block) {
-                                'b'
-                            } else {
-                                if let Some(exit_id) = game.exit_id_for(block) {
-                                    inverted = exit_id != block.id;
-                                }
-                                block_no_to_char(block.block_no)
-                            }
-                        }
-                        Cell::Reference(reference) => {
-                            let target_no = reference.target_no;
-                            let target = game.block_by_no(target_no).unwrap();
-                            color = color_from_hsv(target.hsv);
+/// Writes the moves played so far next to the level, with the extension
+/// `.moves`, and describes the result.
+fn export_moves(level: &Path, moves: &[Direction]) -> String {
+    let path = level.with_extension("moves");
+    let sequence = moves
+        .iter()
+        .map(|direction| direction.to_char())
+        .collect::<String>();
+    match std::fs::write(&path, format_sequence(moves)) {
+        Ok(()) => format!(
+            "{} moves written to {}: {}",
+            moves.len(),
+            path.display(),
+            sequence
+        ),
+        Err(e) => format!("Failed to write {}: {} ({})", path.display(), e, sequence),
+    }
+}
 
-                            if reference.fliph {
-                                underlined = true;
-                            }
+/// Writes the moves that won the level to its `.solution` file, unless the
+/// file exists and `overwrite` is not set, and describes the result.
+fn record_solution(level: &Path, moves: &[Direction], overwrite: bool) -> String {
+    let path = level.with_extension("solution");
+    if path.exists() && !overwrite {
+        return format!(
+            "Not overwriting {} (use --overwrite-solution)",
+            path.display()
+        );
+    }
+    match std::fs::write(&path, format_sequence(moves)) {
+        Ok(()) => format!("Solution written to {}", path.display()),
+        Err(e) => format!("Failed to write {}: {}", path.display(), e),
+    }
+}
 
-                            if let Some(degree) = reference.inf_exit {
-                                "IJKLMN".chars().nth(degree as usize).unwrap_or('O')
-                            } else {
-                                inverted = !reference.exit;
-                                block_no_to_char(target_no)
-                            }
-                        }
-                    }
-                } else {
-                    match game.goals().iter().find(|goal| goal.gpos == gpos) {
-                        Some(goal) => {
-                            color = style::Color::White;
-                            if goal.player {
-                                '='
-                            } else {
-                                '_'
-                            }
-                        }
-                        None => {
-                            color = style::Color::Grey;
-                            '.'
-                        }
-                    }
-                };
+/// Reads and parses a level, and plays the startup sequence on it.
+fn load_level(path: &Path, startup: &[Direction]) -> Result<(Game, Vec<LevelIssue>), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let (mut game, issues) = Game::parse_with_warnings(&text)?;
+    for &direction in startup {
+        game.play(direction);
+    }
+    Ok((game, issues))
+}
 
-                let mut content = mark.with(color);
-                if inverted {
-                    content = content.negative();
-                }
-                if underlined {
-                    content = content.underlined();
-                }
-                out.queue(style::PrintStyledContent(content))?;
+/// Writes the level on the clipboard to a new file in the temporary
+/// directory, so that it can be reloaded and its moves exported like other
+/// levels, and returns the path of the file.
+fn paste_level() -> Result<PathBuf, String> {
+    let text = clipboard::paste()?;
+    Game::parse(&text)?;
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    let path = std::env::temp_dir().join(format!("parabox-pasted-{time}.txt"));
+    std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+fn modified_time(path: &Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+/// How often the level file is checked for changes with `--watch`.
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Number of moves stepped over by `[` and `]`.
+const STEP: usize = 10;
+
+/// Maximum number of lines of the simulator log shown after a move.
+const LOG_LINES: usize = 30;
+
+/// Counters shown in the status bar.
+#[derive(Debug, Default)]
+struct Stats {
+    undos: usize,
+}
+
+/// Describes the progress of the game, and which cells are the players.
+fn status(history: &UndoTree, stats: &Stats) -> String {
+    let game = history.current();
+    let players = game
+        .player_ids()
+        .iter()
+        .map(|&id| {
+            let cell = &game.cells()[id];
+            let name = match cell {
+                Cell::Wall(_) => "wall".to_string(),
+                Cell::Block(block) => format!("block {}", block.block_no),
+                Cell::Reference(reference) => format!("reference to {}", reference.target_no),
+            };
+            match game.container_block(cell.gpos()) {
+                Some(parent) => format!("{name} in block {}", parent.block_no),
+                None => name,
             }
-        }
+        })
+        .collect::<Vec<_>>();
+
+    // moves that can be redone are shown after a slash
+    let mut moves = history.depth().to_string();
+    if history.redo_depth() > history.depth() {
+        moves += &format!("/{}", history.redo_depth());
+    }
+    if let Some(par_moves) = game.meta().par_moves {
+        moves += &format!(" (par {par_moves})");
+    }
+
+    format!(
+        "Moves: {} | Undos: {} | Player: {} | Goals: {}/{}",
+        moves,
+        stats.undos,
+        if players.is_empty() {
+            "none".to_string()
+        } else {
+            players.join(", ")
+        },
+        game.satisfied_goals(),
+        game.goals().len()
+    )
+}
+
+/// Describes a level by its title, author and tags, if it has any.
+fn heading(meta: &LevelMeta) -> Option<String> {
+    let mut heading = meta.title.clone()?;
+    if let Some(author) = &meta.author {
+        heading += &format!(" by {author}");
     }
+    if !meta.tags.is_empty() {
+        heading += &format!(" [{}]", meta.tags.join(", "));
+    }
+    Some(heading)
+}
+
+/// Prints a line and leaves the cursor at the start of the next one.
+fn print_status(out: &mut impl Write, status: &str) -> crossterm::Result<()> {
+    out.queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+        .queue(style::Print(status))?
+        .queue(cursor::MoveToNextLine(1))?;
+    out.flush()
+}
 
-    let row_count = (counter + COLUMNS - 1) / COLUMNS;
-    out.queue(cursor::MoveTo(0, HEIGHT * row_count))?;
+fn print_message(out: &mut impl Write, message: &str) -> crossterm::Result<()> {
+    out.queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+        .queue(style::Print(message))?
+        .queue(cursor::MoveToColumn(0))?;
+    out.flush()
+}
+
+/// Prints the lines of a log below the message, at most `LOG_LINES` of them,
+/// and clears the rest of the screen.
+fn print_log(out: &mut impl Write, log: &str) -> crossterm::Result<()> {
+    out.queue(cursor::MoveToNextLine(1))?;
+    let lines = log.lines().collect::<Vec<_>>();
+    for line in lines.iter().take(LOG_LINES) {
+        out.queue(terminal::Clear(terminal::ClearType::CurrentLine))?
+            .queue(style::Print(line))?
+            .queue(cursor::MoveToNextLine(1))?;
+    }
+    if lines.len() > LOG_LINES {
+        out.queue(style::Print(format!(
+            "... {} more",
+            lines.len() - LOG_LINES
+        )))?;
+    }
+    out.queue(terminal::Clear(terminal::ClearType::FromCursorDown))?;
     out.flush()
 }
+
+/// Prints the interactions attempted for the moves of the players that fail
+/// in the direction, and why they failed.
+fn explain(game: &Game, direction: Direction) {
+    for tree in game.explain_move(direction) {
+        // lines have to be returned explicitly in raw mode
+        for line in tree.to_string().lines() {
+            print!("{line}\r\n");
+        }
+    }
+}
+
+fn debug(game: &Game) {
+    for cell in game.cells() {
+        // lines have to be returned explicitly in raw mode
+        print!("{cell:?}\r\n");
+    }
+}
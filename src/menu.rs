@@ -0,0 +1,231 @@
+use crossterm::{cursor, event, style, terminal, QueueableCommand};
+use parabox::engine::batch::find_levels;
+use parabox::engine::Game;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{stdout, Write};
+use std::path::Path;
+
+use crate::{heading, play_level, PlayOptions};
+
+/// The file in a level directory listing the levels completed so far, one
+/// relative path per line.
+const PROGRESS_FILE: &str = ".parabox-progress";
+
+/// The state of the level select menu.
+struct Menu {
+    // paths of the levels relative to the directory
+    levels: Vec<String>,
+    // titles and par moves of the levels, which are searched as well
+    details: Vec<String>,
+    completed: BTreeSet<String>,
+    // text typed after `/`, and whether it is being edited
+    search: String,
+    searching: bool,
+    // index of the selected level among the matching ones
+    selected: usize,
+    message: String,
+}
+
+impl Menu {
+    fn matching(&self) -> Vec<&String> {
+        let search = self.search.to_lowercase();
+        self.levels
+            .iter()
+            .zip(&self.details)
+            .filter(|(level, details)| {
+                level.to_lowercase().contains(&search) || details.to_lowercase().contains(&search)
+            })
+            .map(|(level, _)| level)
+            .collect()
+    }
+
+    fn draw(&self, dir: &Path, out: &mut impl Write) -> std::io::Result<()> {
+        let (_, rows) = terminal::size()?;
+        let matching = self.matching();
+
+        out.queue(terminal::Clear(terminal::ClearType::All))?
+            .queue(cursor::MoveTo(0, 0))?
+            .queue(style::Print(format!(
+                "{} ({}/{} completed)",
+                dir.display(),
+                self.completed.len(),
+                self.levels.len()
+            )))?
+            .queue(cursor::MoveTo(0, 1))?
+            .queue(style::Print(if self.searching || !self.search.is_empty() {
+                format!("/{}", self.search)
+            } else {
+                "Arrows: select, Enter: play, /: search, Q: quit".to_string()
+            }))?;
+
+        // keep the selection in the middle of the list
+        let height = rows.saturating_sub(4).max(1) as usize;
+        let first = self
+            .selected
+            .saturating_sub(height / 2)
+            .min(matching.len().saturating_sub(height));
+        for (i, level) in matching.iter().enumerate().skip(first).take(height) {
+            let marker = if self.completed.contains(*level) {
+                'x'
+            } else {
+                ' '
+            };
+            let pointer = if i == self.selected { '>' } else { ' ' };
+            let index = self
+                .levels
+                .iter()
+                .position(|other| other == *level)
+                .unwrap();
+            out.queue(cursor::MoveTo(0, (i - first) as u16 + 3))?
+                .queue(style::Print(format!(
+                    "{pointer} [{marker}] {level:<24} {}",
+                    self.details[index]
+                )))?;
+        }
+
+        out.queue(cursor::MoveTo(0, rows.saturating_sub(1)))?
+            .queue(style::Print(&self.message))?;
+        out.flush()
+    }
+
+    /// Handles a key, and returns the level to play if one was picked.
+    fn key(&mut self, code: event::KeyCode) -> Option<String> {
+        let count = self.matching().len();
+        match code {
+            event::KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            event::KeyCode::Down => {
+                self.selected = (self.selected + 1).min(count.saturating_sub(1))
+            }
+            event::KeyCode::PageUp => self.selected = self.selected.saturating_sub(10),
+            event::KeyCode::PageDown => {
+                self.selected = (self.selected + 10).min(count.saturating_sub(1))
+            }
+            event::KeyCode::Enter => {
+                self.searching = false;
+                return self
+                    .matching()
+                    .get(self.selected)
+                    .map(|level| level.to_string());
+            }
+            event::KeyCode::Esc => {
+                self.searching = false;
+                self.search.clear();
+            }
+            event::KeyCode::Backspace if self.searching => {
+                self.search.pop();
+                self.selected = 0;
+            }
+            event::KeyCode::Char(c) if self.searching => {
+                self.search.push(c);
+                self.selected = 0;
+            }
+            event::KeyCode::Char('/') => {
+                self.searching = true;
+                self.search.clear();
+            }
+            _ => (),
+        }
+        None
+    }
+}
+
+/// Shows the levels under `dir`, and plays the picked ones until the player
+/// quits the menu.
+pub fn run(dir: &Path, options: &PlayOptions) -> Result<(), String> {
+    let levels = find_levels(dir)?
+        .iter()
+        .map(|path| path.strip_prefix(dir).unwrap().display().to_string())
+        .collect::<Vec<_>>();
+    if levels.is_empty() {
+        return Err(format!("No levels found in {}", dir.display()));
+    }
+
+    // levels that fail to load are still listed, to show the error when
+    // picked
+    let details = levels
+        .iter()
+        .map(|level| {
+            let text = fs::read_to_string(dir.join(level)).unwrap_or_default();
+            let Ok(game) = Game::parse(&text) else {
+                return String::new();
+            };
+            let meta = game.meta();
+            let mut details = heading(meta).unwrap_or_default();
+            if let Some(par_moves) = meta.par_moves {
+                details += &format!(" (par {par_moves})");
+            }
+            details
+        })
+        .collect();
+
+    let progress = dir.join(PROGRESS_FILE);
+    let mut menu = Menu {
+        completed: fs::read_to_string(&progress)
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| levels.iter().any(|level| level == line))
+            .map(str::to_string)
+            .collect(),
+        levels,
+        details,
+        search: String::new(),
+        searching: false,
+        selected: 0,
+        message: String::new(),
+    };
+
+    loop {
+        terminal::enable_raw_mode().map_err(|e| e.to_string())?;
+        let picked = pick(dir, &mut menu).map_err(|e| e.to_string());
+        terminal::disable_raw_mode().map_err(|e| e.to_string())?;
+        let Some(level) = picked? else {
+            return Ok(());
+        };
+
+        stdout()
+            .queue(terminal::Clear(terminal::ClearType::All))
+            .and_then(|out| out.flush())
+            .map_err(|e| e.to_string())?;
+
+        menu.message = match play_level(&dir.join(&level), None, options) {
+            Ok(Some(_)) => {
+                menu.completed.insert(level.clone());
+                save_progress(&progress, &menu.completed);
+                format!("Completed {level}")
+            }
+            Ok(None) => String::new(),
+            Err(e) => format!("{level}: {}", e.lines().next().unwrap_or("")),
+        };
+    }
+}
+
+// waits for a level to be picked, or returns None if the menu is quit
+fn pick(dir: &Path, menu: &mut Menu) -> std::io::Result<Option<String>> {
+    let mut out = stdout();
+    loop {
+        menu.draw(dir, &mut out)?;
+
+        let event::Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != event::KeyEventKind::Press {
+            continue;
+        }
+        if key.code == event::KeyCode::Char('q') && !menu.searching {
+            return Ok(None);
+        }
+        if let Some(level) = menu.key(key.code) {
+            return Ok(Some(level));
+        }
+    }
+}
+
+// progress is a convenience, so failing to save it is not an error
+fn save_progress(path: &Path, completed: &BTreeSet<String>) {
+    let text = completed
+        .iter()
+        .map(|level| format!("{level}\n"))
+        .collect::<String>();
+    let _ = fs::write(path, text);
+}
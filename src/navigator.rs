@@ -0,0 +1,78 @@
+use crossterm::{cursor, event, style, terminal, QueueableCommand};
+use parabox::engine::*;
+use std::io::Write;
+
+/// Shows the branches of the undo tree, one line each, and jumps to the tip
+/// of the picked one. Up and Down select a branch, Enter jumps to it, and Esc
+/// or T goes back to the game.
+///
+/// Expects the terminal to be in raw mode.
+pub fn run(history: &mut UndoTree, out: &mut impl Write) -> std::io::Result<()> {
+    let branches = history.branches();
+    if branches.is_empty() {
+        return Ok(());
+    }
+    let current = history.current_node();
+    let mut selected = branches
+        .iter()
+        .position(|branch| branch.nodes.contains(&current))
+        .unwrap_or(0);
+
+    loop {
+        let (columns, rows) = terminal::size()?;
+        out.queue(terminal::Clear(terminal::ClearType::All))?
+            .queue(cursor::MoveTo(0, 0))?
+            .queue(style::Print(
+                "Undo tree (Up/Down: select, Enter: jump to the end of a branch, Esc: back)",
+            ))?;
+
+        let height = rows.saturating_sub(2).max(1) as usize;
+        let first = selected
+            .saturating_sub(height / 2)
+            .min(branches.len().saturating_sub(height));
+        for (i, branch) in branches.iter().enumerate().skip(first).take(height) {
+            let moves = branch
+                .moves
+                .iter()
+                .map(|direction| direction.to_char())
+                .collect::<String>();
+            let line = format!(
+                "{} {}{}-{}: {}{}",
+                if i == selected { '>' } else { ' ' },
+                "  ".repeat(branch.level),
+                branch.depth + 1,
+                branch.depth + branch.moves.len(),
+                moves,
+                if branch.nodes.contains(&current) {
+                    " *"
+                } else {
+                    ""
+                },
+            );
+            let line = line.chars().take(columns as usize).collect::<String>();
+            out.queue(cursor::MoveTo(0, (i - first) as u16 + 2))?
+                .queue(style::Print(line))?;
+        }
+        out.flush()?;
+
+        let event::Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != event::KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            event::KeyCode::Up => selected = selected.saturating_sub(1),
+            event::KeyCode::Down => selected = (selected + 1).min(branches.len() - 1),
+            event::KeyCode::Enter => {
+                history.jump(branches[selected].tip);
+                break;
+            }
+            event::KeyCode::Esc | event::KeyCode::Char('t') => break,
+            _ => (),
+        }
+    }
+
+    out.queue(terminal::Clear(terminal::ClearType::All))?;
+    out.flush()
+}
@@ -0,0 +1,134 @@
+//! Python bindings (`pyo3` feature), for scripting the engine e.g. from
+//! notebooks. The module is named `parabox`, and can be built with maturin.
+
+// the methods generated by pyo3 convert errors into themselves
+#![allow(clippy::useless_conversion)]
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::engine;
+use crate::render::render_ascii;
+
+/// A direction of movement.
+#[pyclass(name = "Direction", eq, eq_int, frozen)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PyDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl From<PyDirection> for engine::Direction {
+    fn from(direction: PyDirection) -> Self {
+        match direction {
+            PyDirection::Up => engine::Direction::Up,
+            PyDirection::Down => engine::Direction::Down,
+            PyDirection::Left => engine::Direction::Left,
+            PyDirection::Right => engine::Direction::Right,
+        }
+    }
+}
+
+impl From<engine::Direction> for PyDirection {
+    fn from(direction: engine::Direction) -> Self {
+        match direction {
+            engine::Direction::Up => PyDirection::Up,
+            engine::Direction::Down => PyDirection::Down,
+            engine::Direction::Left => PyDirection::Left,
+            engine::Direction::Right => PyDirection::Right,
+        }
+    }
+}
+
+#[pymethods]
+impl PyDirection {
+    /// Parses a direction from one of the characters `UDLR`.
+    #[staticmethod]
+    fn from_char(c: char) -> PyResult<Self> {
+        engine::Direction::from_char(c)
+            .map(Self::from)
+            .ok_or_else(|| PyValueError::new_err(format!("Invalid direction {c:?}")))
+    }
+
+    /// Formats the direction as one of the characters `UDLR`.
+    fn __str__(&self) -> String {
+        engine::Direction::from(*self).to_char().to_string()
+    }
+}
+
+/// A level and its current state.
+#[pyclass(name = "Game")]
+#[derive(Clone)]
+pub struct PyGame {
+    game: engine::Game,
+}
+
+#[pymethods]
+impl PyGame {
+    /// Parses a level in the level file format.
+    #[new]
+    fn new(text: &str) -> PyResult<Self> {
+        let game = engine::Game::parse(text).map_err(PyValueError::new_err)?;
+        Ok(Self { game })
+    }
+
+    /// Plays one input. Returns whether the state changed.
+    fn play(&mut self, direction: PyDirection) -> bool {
+        self.game.play(direction.into()).changed()
+    }
+
+    /// Plays a sequence of moves such as `"RRUL"`. Returns the number of
+    /// moves played.
+    fn play_sequence(&mut self, moves: &str) -> PyResult<usize> {
+        self.game
+            .play_sequence(moves)
+            .map(|outcome| outcome.steps)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    fn won(&self) -> bool {
+        self.game.won()
+    }
+
+    /// Returns the directions in which playing would change the state.
+    fn legal_moves(&self) -> Vec<PyDirection> {
+        self.game
+            .legal_moves()
+            .into_iter()
+            .map(PyDirection::from)
+            .collect()
+    }
+
+    /// Writes the current state in the level file format.
+    fn to_text(&self) -> String {
+        self.game.to_text()
+    }
+
+    /// Encodes the current state compactly, e.g. as a dictionary key.
+    fn encode_state<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.game.encode_state())
+    }
+
+    /// Returns a copy that can be played independently.
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    fn __str__(&self) -> String {
+        render_ascii(&self.game)
+    }
+}
+
+#[pymodule]
+fn parabox(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyDirection>()?;
+    module.add_class::<PyGame>()?;
+    Ok(())
+}
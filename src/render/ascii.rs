@@ -0,0 +1,77 @@
+use std::convert::Infallible;
+
+use super::glyph::*;
+use super::renderer::Renderer;
+use crate::engine::*;
+
+/// Draws every non-trivial block as a plain text panel, using the same glyphs
+/// as the terminal renderer but without colors or styles.
+///
+/// Each panel starts with the block number in brackets, and panels are
+/// separated by empty lines. With a nonzero `options.depth`, a single panel
+/// shows the block containing the player with nested interiors instead.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AsciiRenderer {
+    pub options: GlyphOptions,
+}
+
+impl AsciiRenderer {
+    /// The largest width or height of a nested view, in characters.
+    pub const MAX_NESTED_SIZE: usize = 64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Renderer for AsciiRenderer {
+    type Output = String;
+    type Error = Infallible;
+
+    fn render(&mut self, game: &Game) -> Result<String, Infallible> {
+        let mut text = String::new();
+
+        if self.options.depth > 0 {
+            if let Some(block) = game.view_block() {
+                let size = nested_cell_size(game, self.options.depth);
+                // keep the view readable when the nesting is deep
+                let width = (block.width as usize * size).min(Self::MAX_NESTED_SIZE);
+                let height = (block.height as usize * size).min(Self::MAX_NESTED_SIZE);
+
+                text.push_str(&format!("[{}]\n", block_no_to_char(block.block_no)));
+                for row in nested_glyphs(game, block, width, height, self.options) {
+                    text.extend(row.iter().map(|glyph| glyph.mark));
+                    text.push('\n');
+                }
+            }
+            return Ok(text);
+        }
+
+        for block in game.cells().iter().filter_map(|cell| cell.block()) {
+            if game.is_block_trivial(block) {
+                continue;
+            }
+
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&format!("[{}]\n", block_no_to_char(block.block_no)));
+
+            for y in (0..block.height).rev() {
+                for x in 0..block.width {
+                    let gpos = GlobalPos::new(block.id, Pos(x, y));
+                    text.push(glyph_at(game, gpos, self.options).mark);
+                }
+                text.push('\n');
+            }
+        }
+
+        Ok(text)
+    }
+}
+
+/// Renders the game as plain text with the default options.
+pub fn render_ascii(game: &Game) -> String {
+    let Ok(text) = AsciiRenderer::new().render(game);
+    text
+}
@@ -0,0 +1,322 @@
+use color_space::Hsv;
+
+use super::theme::Theme;
+use crate::engine::*;
+
+/// The visual representation of a single position in a block.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Glyph {
+    pub mark: char,
+    pub color: GlyphColor,
+    /// Clones (references that are not the exit of their block) are drawn
+    /// inverted.
+    pub inverted: bool,
+    /// Flipped cells are drawn underlined.
+    pub underlined: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GlyphColor {
+    /// The color of a block, or of the block containing a wall.
+    Block(Hsv),
+    Goal,
+    Empty,
+}
+
+/// Options shared by the renderers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GlyphOptions {
+    /// Draw references as a summary of the contents of their target instead
+    /// of its number.
+    pub preview: bool,
+    /// Draw the block containing the player as a single view, with the
+    /// interiors of blocks and references drawn inside their cells down to
+    /// this many levels. Zero draws every block as a flat panel.
+    pub depth: u32,
+    /// The marks and colors to draw with.
+    pub theme: Theme,
+}
+
+pub fn block_no_to_char(block_no: BlockNo) -> char {
+    "0123456789ABCDEF"
+        .chars()
+        .nth(block_no.0 as usize)
+        .unwrap_or('G')
+}
+
+/// Determines how to draw the given position.
+pub fn glyph_at(game: &Game, gpos: GlobalPos, options: GlyphOptions) -> Glyph {
+    let mut inverted = false;
+    let mut underlined = false;
+    let mut color = match game.container_block(gpos) {
+        Some(block) => GlyphColor::Block(block.hsv),
+        None => GlyphColor::Empty,
+    };
+
+    let theme = &options.theme;
+    let mark = if let Some(cell) = game.cell_at(gpos) {
+        match &cell {
+            Cell::Wall(_) => theme.wall,
+            Cell::Block(block) => {
+                color = GlyphColor::Block(block.hsv);
+
+                if block.fliph {
+                    underlined = true;
+                }
+
+                if game.is_player(block.id) {
+                    theme.player
+                } else if game.is_block_trivial(block) {
+                    theme.solid_block
+                } else {
+                    if let Some(exit_id) = game.exit_id_for(block) {
+                        inverted = exit_id != block.id;
+                    }
+                    block_no_to_char(block.block_no)
+                }
+            }
+            Cell::Reference(reference) => {
+                let target_no = reference.target_no;
+                let target = game.block_by_no(target_no).unwrap();
+                color = GlyphColor::Block(target.hsv);
+
+                if reference.fliph {
+                    underlined = true;
+                }
+
+                if let Some(degree) = reference.inf_exit {
+                    "IJKLMN".chars().nth(degree as usize).unwrap_or('O')
+                } else {
+                    inverted = !reference.exit;
+                    if options.preview {
+                        preview_char(game, target)
+                    } else {
+                        block_no_to_char(target_no)
+                    }
+                }
+            }
+        }
+    } else {
+        let floor = game.floors().iter().find(|floor| floor.gpos == gpos);
+        match (
+            game.goals().iter().find(|goal| goal.gpos == gpos),
+            floor.map(|floor| &floor.kind),
+        ) {
+            (Some(goal), _) => {
+                color = GlyphColor::Goal;
+                if goal.player {
+                    theme.player_goal
+                } else {
+                    theme.block_goal
+                }
+            }
+            (None, Some(FloorKind::OneWay(directions))) => {
+                color = GlyphColor::Empty;
+                match directions[..] {
+                    [Direction::Up] => '^',
+                    [Direction::Down] => 'v',
+                    [Direction::Left] => '<',
+                    [Direction::Right] => '>',
+                    _ => '+',
+                }
+            }
+            (None, Some(FloorKind::Switch(_))) => {
+                color = GlyphColor::Goal;
+                'o'
+            }
+            (None, Some(FloorKind::Door(channel))) => {
+                color = GlyphColor::Empty;
+                if game.is_door_open(*channel) {
+                    ':'
+                } else {
+                    '|'
+                }
+            }
+            (None, _) => {
+                color = GlyphColor::Empty;
+                theme.empty
+            }
+        }
+    };
+
+    Glyph {
+        mark,
+        color,
+        inverted,
+        underlined,
+    }
+}
+
+/// Returns the number of glyphs per cell of the view block needed to draw
+/// every block interior down to `depth` levels.
+pub fn nested_cell_size(game: &Game, depth: u32) -> usize {
+    let largest = game
+        .cells()
+        .iter()
+        .filter_map(|cell| cell.block())
+        .map(|block| block.width.max(block.height) as usize)
+        .max()
+        .unwrap_or(1);
+    largest.saturating_pow(depth).max(1)
+}
+
+/// Draws a block into a canvas of `width` × `height` glyphs, with the top row
+/// first, the way the original game presents recursion.
+///
+/// Each position of the block covers an equal part of the canvas. Blocks and
+/// references are drawn with the interior of their block inside their part,
+/// down to `options.depth` levels or until a part is smaller than the block
+/// it shows; other positions repeat their glyph over their part. Interiors of
+/// clones are inverted, and those of flipped cells are mirrored.
+pub fn nested_glyphs(
+    game: &Game,
+    block: &Block,
+    width: usize,
+    height: usize,
+    options: GlyphOptions,
+) -> Vec<Vec<Glyph>> {
+    let empty = Glyph {
+        mark: ' ',
+        color: GlyphColor::Empty,
+        inverted: false,
+        underlined: false,
+    };
+    let mut canvas = vec![vec![empty; width]; height];
+    let area = Area {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+    draw_nested(
+        game,
+        block,
+        area,
+        options,
+        options.depth,
+        &mut canvas,
+        false,
+    );
+    canvas
+}
+
+// a part of the canvas of a nested view
+#[derive(Copy, Clone, Debug)]
+struct Area {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+fn draw_nested(
+    game: &Game,
+    block: &Block,
+    area: Area,
+    options: GlyphOptions,
+    depth: u32,
+    canvas: &mut [Vec<Glyph>],
+    flip: bool,
+) {
+    let (block_width, block_height) = (block.width as usize, block.height as usize);
+
+    for y in 0..block_height {
+        for x in 0..block_width {
+            // parts are spread evenly, and the top row is the highest y
+            let column = if flip { block_width - 1 - x } else { x };
+            let row = block_height - 1 - y;
+            let part = Area {
+                x: area.x + area.width * column / block_width,
+                y: area.y + area.height * row / block_height,
+                width: area.width * (column + 1) / block_width - area.width * column / block_width,
+                height: area.height * (row + 1) / block_height - area.height * row / block_height,
+            };
+            let gpos = GlobalPos::new(block.id, Pos(x as i32, y as i32));
+
+            let glyph = glyph_at(game, gpos, options);
+            let interior = match game.cell_at(gpos) {
+                // players keep their mark
+                Some(Cell::Block(inner))
+                    if !game.is_block_trivial(inner) && !game.is_player(inner.id) =>
+                {
+                    Some(inner)
+                }
+                Some(Cell::Reference(reference)) if reference.inf_exit.is_none() => {
+                    game.block_by_no(reference.target_no)
+                }
+                _ => None,
+            }
+            .filter(|inner| {
+                depth > 0
+                    && !inner.filled
+                    && part.width >= inner.width as usize
+                    && part.height >= inner.height as usize
+            });
+
+            match interior {
+                Some(inner) => {
+                    draw_nested(
+                        game,
+                        inner,
+                        part,
+                        options,
+                        depth - 1,
+                        canvas,
+                        flip ^ glyph.underlined,
+                    );
+                    if glyph.inverted {
+                        for row in &mut canvas[part.y..part.y + part.height] {
+                            for cell in &mut row[part.x..part.x + part.width] {
+                                cell.inverted = !cell.inverted;
+                            }
+                        }
+                    }
+                }
+                None => {
+                    for row in &mut canvas[part.y..part.y + part.height] {
+                        row[part.x..part.x + part.width].fill(glyph);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Summarizes the contents of a block in a single character, with one
+/// quadrant of the character for each quadrant of the block. A quadrant is
+/// shown if at least half of it is occupied.
+pub fn preview_char(game: &Game, block: &Block) -> char {
+    // the middle row or column of odd-sized blocks belongs to both halves
+    let halves = |v: i32, size: i32| [2 * v < size, 2 * v + 1 >= size];
+
+    let mut occupied = [0; 4];
+    let mut area = [0; 4];
+    for y in 0..block.height {
+        for x in 0..block.width {
+            let gpos = GlobalPos::new(block.id, Pos(x, y));
+            let filled = block.filled || game.is_occupied(gpos);
+
+            // quadrants are ordered top left, top right, bottom left, bottom right
+            for (i, top) in halves(block.height - 1 - y, block.height)
+                .iter()
+                .enumerate()
+            {
+                for (j, left) in halves(x, block.width).iter().enumerate() {
+                    if *top && *left {
+                        area[2 * i + j] += 1;
+                        if filled {
+                            occupied[2 * i + j] += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let mask = (0..4)
+        .filter(|&i| area[i] > 0 && 2 * occupied[i] >= area[i])
+        .fold(0, |mask, i| mask | 1 << i);
+    [
+        '·', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+    ][mask]
+}
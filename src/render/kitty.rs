@@ -0,0 +1,60 @@
+use std::env;
+
+/// Returns whether the terminal likely supports the kitty graphics protocol,
+/// judging by the environment variables set by the terminals that do.
+pub fn kitty_supported() -> bool {
+    let var = |name: &str| env::var(name).unwrap_or_default();
+    !var("KITTY_WINDOW_ID").is_empty()
+        || var("TERM") == "xterm-kitty"
+        || var("TERM") == "xterm-ghostty"
+        || var("TERM_PROGRAM") == "WezTerm"
+}
+
+/// Encodes a PNG image as kitty graphics protocol escape sequences that
+/// display it at the cursor, scaled to `columns` × `rows` cells.
+///
+/// The image replaces any image previously displayed this way, and the
+/// cursor is not moved.
+pub fn kitty_image(png: &[u8], columns: u16, rows: u16) -> String {
+    const CHUNK: usize = 4096;
+
+    let data = base64(png);
+    let mut out = String::new();
+    let mut chunks = data.as_bytes().chunks(CHUNK).peekable();
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        let more = chunks.peek().is_some() as u8;
+        out.push_str("\x1b_G");
+        if std::mem::take(&mut first) {
+            // quiet, so that the terminal doesn't answer on stdin
+            out.push_str(&format!("a=T,f=100,i=1,q=2,C=1,c={columns},r={rows},"));
+        }
+        out.push_str(&format!("m={more};"));
+        // base64 is plain ASCII
+        out.push_str(std::str::from_utf8(chunk).unwrap());
+        out.push_str("\x1b\\");
+    }
+    out
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,90 @@
+use super::glyph::block_no_to_char;
+use crate::engine::*;
+
+/// The area of a panel in a layout, in characters. A panel holds the title
+/// of a block on its first line and the block below it, centered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Panel {
+    pub block_id: usize,
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+/// Panels of every non-trivial block, sized to their block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Layout {
+    pub panels: Vec<Panel>,
+    /// The size of the area covered by all panels.
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Layout {
+    /// Lays out the panels left to right in rows no wider than `max_width`
+    /// when possible. Panels wider than that get a row of their own.
+    pub fn new(game: &Game, max_width: u16) -> Self {
+        let mut layout = Layout::default();
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+
+        for block in game.cells().iter().filter_map(|cell| cell.block()) {
+            if game.is_block_trivial(block) {
+                continue;
+            }
+
+            // one column and one row of spacing around the block
+            let title = format!("[{}]", block_no_to_char(block.block_no));
+            let width = (block.width as u16).max(title.len() as u16) + 2;
+            let height = block.height as u16 + 2;
+
+            if x > 0 && x + width > max_width {
+                x = 0;
+                y += row_height;
+                row_height = 0;
+            }
+            layout.panels.push(Panel {
+                block_id: block.id,
+                x,
+                y,
+                width,
+                height,
+            });
+            x += width;
+            row_height = row_height.max(height);
+            layout.width = layout.width.max(x);
+        }
+        layout.height = y + row_height;
+
+        layout
+    }
+}
+
+/// The visible part of a layout larger than the screen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Viewport {
+    /// The position of the top left corner of the screen in the layout.
+    pub x: u16,
+    pub y: u16,
+    /// The size of the screen.
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Viewport {
+    /// Returns the screen position of a layout position, if it is visible.
+    pub fn to_screen(&self, x: u16, y: u16) -> Option<(u16, u16)> {
+        let (x, y) = (x.checked_sub(self.x)?, y.checked_sub(self.y)?);
+        (x < self.width && y < self.height).then_some((x, y))
+    }
+
+    /// Moves the viewport, keeping it inside the layout where possible.
+    pub fn scroll(&mut self, dx: i32, dy: i32, layout: &Layout) {
+        let clamp = |pos: u16, delta: i32, size: u16, screen: u16| {
+            let max = size.saturating_sub(screen) as i32;
+            (pos as i32 + delta).clamp(0, max) as u16
+        };
+        self.x = clamp(self.x, dx, layout.width, self.width);
+        self.y = clamp(self.y, dy, layout.height, self.height);
+    }
+}
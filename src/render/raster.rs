@@ -0,0 +1,296 @@
+use color_space::Hsv;
+
+use super::renderer::Renderer;
+use super::theme::{Color, Theme};
+use crate::engine::*;
+
+/// Rasterizes every non-trivial block as a panel of a PNG image.
+///
+/// Each panel draws the cells of a block `cell_size` pixels wide. Blocks and
+/// references inside it are drawn with their own contents scaled down into
+/// their cell, up to `depth` levels deep; deeper cells are filled with the
+/// color of their block.
+#[derive(Copy, Clone, Debug)]
+pub struct PngRenderer {
+    /// Size of a cell of a panel in pixels.
+    pub cell_size: u32,
+    /// Number of nested levels of block interiors to draw inside a panel.
+    pub depth: u32,
+    /// Number of panels per row.
+    pub columns: usize,
+    /// The colors of blocks and goals.
+    pub theme: Theme,
+}
+
+impl Default for PngRenderer {
+    fn default() -> Self {
+        Self {
+            cell_size: 32,
+            depth: 1,
+            columns: 4,
+            theme: Theme::default(),
+        }
+    }
+}
+
+/// An RGBA image, with rows from top to bottom.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+type Rgba = [u8; 4];
+
+const BACKGROUND: Rgba = [0, 0, 0, 255];
+const WHITE: Rgba = [255, 255, 255, 255];
+const MARGIN: u32 = 8;
+
+// the area of a cell in the image
+#[derive(Copy, Clone, Debug)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+impl Image {
+    fn new(width: u32, height: u32) -> Self {
+        let pixels = BACKGROUND.repeat((width * height) as usize);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Encodes the image as PNG.
+    pub fn encode(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(data)
+    }
+
+    fn fill(&mut self, rect: Rect, color: Rgba) {
+        let x0 = (rect.x.round() as u32).min(self.width);
+        let y0 = (rect.y.round() as u32).min(self.height);
+        let x1 = ((rect.x + rect.w).round() as u32).min(self.width);
+        let y1 = ((rect.y + rect.h).round() as u32).min(self.height);
+        for y in y0..y1 {
+            let row = (y * self.width) as usize * 4;
+            for x in x0..x1 {
+                let i = row + x as usize * 4;
+                self.pixels[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn outline(&mut self, rect: Rect, width: f64, color: Rgba) {
+        let Rect { x, y, w, h } = rect;
+        self.fill(Rect { x, y, w, h: width }, color);
+        self.fill(
+            Rect {
+                x,
+                y: y + h - width,
+                w,
+                h: width,
+            },
+            color,
+        );
+        self.fill(Rect { x, y, w: width, h }, color);
+        self.fill(
+            Rect {
+                x: x + w - width,
+                y,
+                w: width,
+                h,
+            },
+            color,
+        );
+    }
+
+    fn eyes(&mut self, rect: Rect, color: Rgba) {
+        let size = (rect.w.min(rect.h) * 0.12).max(1.0);
+        for dx in [0.35, 0.65] {
+            self.fill(
+                Rect {
+                    x: rect.x + rect.w * dx - size / 2.0,
+                    y: rect.y + rect.h * 0.4 - size / 2.0,
+                    w: size,
+                    h: size,
+                },
+                color,
+            );
+        }
+    }
+}
+
+impl Renderer for PngRenderer {
+    type Output = Vec<u8>;
+    type Error = png::EncodingError;
+
+    fn render(&mut self, game: &Game) -> Result<Vec<u8>, png::EncodingError> {
+        self.rasterize(game).encode()
+    }
+}
+
+impl PngRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn block_rgba(&self, hsv: Hsv) -> Rgba {
+        rgba(self.theme.block_color(hsv))
+    }
+
+    /// Draws the game into an image without encoding it.
+    pub fn rasterize(&self, game: &Game) -> Image {
+        let blocks = game
+            .cells()
+            .iter()
+            .filter_map(|cell| cell.block())
+            .filter(|block| !game.is_block_trivial(block))
+            .collect::<Vec<_>>();
+
+        let columns = self.columns.max(1);
+        let rows = blocks.len().div_ceil(columns);
+        let panel = blocks
+            .iter()
+            .map(|block| block.width.max(block.height) as u32 * self.cell_size)
+            .max()
+            .unwrap_or(0)
+            + MARGIN;
+        let mut image = Image::new(
+            panel * blocks.len().min(columns) as u32 + MARGIN,
+            panel * rows as u32 + MARGIN,
+        );
+
+        for (i, block) in blocks.iter().enumerate() {
+            let rect = Rect {
+                x: (panel * (i % columns) as u32 + MARGIN) as f64,
+                y: (panel * (i / columns) as u32 + MARGIN) as f64,
+                w: (block.width as u32 * self.cell_size) as f64,
+                h: (block.height as u32 * self.cell_size) as f64,
+            };
+            self.draw_interior(&mut image, game, block, rect, 0);
+        }
+
+        image
+    }
+
+    // draws the floor, goals and cells of a block inside the area
+    fn draw_interior(&self, image: &mut Image, game: &Game, block: &Block, rect: Rect, depth: u32) {
+        if block.filled {
+            image.fill(rect, self.block_rgba(block.hsv));
+            return;
+        }
+        // nested interiors are shrunk by the zoom factor, inside a frame of
+        // the block's color
+        let rect = if depth > 0 && block.nested_scale() < 1.0 {
+            image.fill(rect, self.block_rgba(block.hsv));
+            let margin = (1.0 - block.nested_scale()) / 2.0;
+            Rect {
+                x: rect.x + rect.w * margin,
+                y: rect.y + rect.h * margin,
+                w: rect.w * block.nested_scale(),
+                h: rect.h * block.nested_scale(),
+            }
+        } else {
+            rect
+        };
+        image.fill(
+            rect,
+            self.block_rgba(Hsv::new(block.hsv.h, block.hsv.s, block.hsv.v * 0.5)),
+        );
+
+        let cell_w = rect.w / block.width as f64;
+        let cell_h = rect.h / block.height as f64;
+        let area = |Pos(x, y): Pos| Rect {
+            x: rect.x + cell_w * x as f64,
+            // positions are y-up
+            y: rect.y + cell_h * (block.height - 1 - y) as f64,
+            w: cell_w,
+            h: cell_h,
+        };
+        let line = (cell_w.min(cell_h) * 0.06).max(1.0);
+
+        for goal in game.goals() {
+            if goal.gpos.is_in(block.id) {
+                let area = area(goal.gpos.pos);
+                let color = rgba(self.theme.goal_color);
+                image.outline(area, line, color);
+                if goal.player {
+                    image.eyes(area, color);
+                }
+            }
+        }
+
+        for cell in game.cells() {
+            if cell.gpos().is_in(block.id) {
+                self.draw_cell(image, game, cell, area(cell.gpos().pos), depth + 1);
+            }
+        }
+
+        if depth > 0 {
+            image.outline(rect, line, BACKGROUND);
+        }
+    }
+
+    fn draw_cell(&self, image: &mut Image, game: &Game, cell: &Cell, rect: Rect, depth: u32) {
+        match cell {
+            Cell::Wall(_) => {
+                let parent = game.container_block(cell.gpos()).unwrap();
+                image.fill(rect, self.block_rgba(parent.hsv));
+            }
+            Cell::Block(block) => {
+                if depth <= self.depth && !game.is_block_trivial(block) {
+                    self.draw_interior(image, game, block, rect, depth);
+                } else {
+                    image.fill(rect, self.block_rgba(block.hsv));
+                }
+            }
+            Cell::Reference(reference) => {
+                let target = game.block_by_no(reference.target_no).unwrap();
+                if depth <= self.depth {
+                    self.draw_interior(image, game, target, rect, depth);
+                } else {
+                    image.fill(rect, self.block_rgba(target.hsv));
+                }
+                if !reference.exit {
+                    // clones are marked with a white frame
+                    image.outline(rect, (rect.w * 0.06).max(1.0), WHITE);
+                }
+            }
+        }
+
+        if game.is_player(cell.id()) {
+            image.eyes(rect, BACKGROUND);
+        }
+        if cell.fliph() {
+            let size = rect.w.min(rect.h) * 0.2;
+            image.fill(
+                Rect {
+                    x: rect.x + rect.w - size,
+                    y: rect.y,
+                    w: size,
+                    h: size,
+                },
+                WHITE,
+            );
+        }
+    }
+}
+
+/// Renders the game as a PNG image with the default options.
+pub fn render_png(game: &Game) -> Result<Vec<u8>, png::EncodingError> {
+    PngRenderer::new().render(game)
+}
+
+fn rgba(color: Color) -> Rgba {
+    [color.r, color.g, color.b, 255]
+}
@@ -0,0 +1,11 @@
+use crate::engine::Game;
+
+/// A frontend that draws game states.
+pub trait Renderer {
+    /// What a render produces, e.g. `()` for renderers drawing to a terminal,
+    /// or a `String` for renderers producing documents.
+    type Output;
+    type Error;
+
+    fn render(&mut self, game: &Game) -> Result<Self::Output, Self::Error>;
+}
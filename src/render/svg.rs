@@ -0,0 +1,265 @@
+use color_space::Hsv;
+use std::convert::Infallible;
+use std::fmt::Write;
+
+use super::glyph::block_no_to_char;
+use super::renderer::Renderer;
+use super::theme::Theme;
+use crate::engine::*;
+
+/// Draws every non-trivial block as a panel of an SVG document.
+///
+/// Blocks and references inside a panel are drawn with their own contents
+/// up to `max_depth` levels deep, like in the original game. Players are
+/// drawn with eyes, goals as outlines, clones at reduced opacity, and flipped
+/// cells with a triangle marker in their corner.
+#[derive(Copy, Clone, Debug)]
+pub struct SvgRenderer {
+    /// Size of a panel in pixels.
+    pub panel_size: f64,
+    /// Number of panels per row.
+    pub columns: usize,
+    /// Maximum depth of nested blocks to draw inside a panel.
+    pub max_depth: u32,
+    /// The colors of blocks and goals.
+    pub theme: Theme,
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self {
+            panel_size: 160.0,
+            columns: 4,
+            max_depth: 3,
+            theme: Theme::default(),
+        }
+    }
+}
+
+// the area of a cell in the document
+#[derive(Copy, Clone, Debug)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+const TITLE_HEIGHT: f64 = 20.0;
+const MARGIN: f64 = 10.0;
+
+impl Renderer for SvgRenderer {
+    type Output = String;
+    type Error = Infallible;
+
+    fn render(&mut self, game: &Game) -> Result<String, Infallible> {
+        let blocks = game
+            .cells()
+            .iter()
+            .filter_map(|cell| cell.block())
+            .filter(|block| !game.is_block_trivial(block))
+            .collect::<Vec<_>>();
+
+        let columns = self.columns.max(1);
+        let rows = blocks.len().div_ceil(columns);
+        let panel_w = self.panel_size + 2.0 * MARGIN;
+        let panel_h = self.panel_size + TITLE_HEIGHT + 2.0 * MARGIN;
+        let width = panel_w * blocks.len().min(columns) as f64;
+        let height = panel_h * rows as f64;
+
+        let mut out = String::new();
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        )
+        .unwrap();
+
+        for (i, block) in blocks.iter().enumerate() {
+            let x = panel_w * (i % columns) as f64 + MARGIN;
+            let y = panel_h * (i / columns) as f64 + MARGIN;
+            writeln!(
+                out,
+                r#"<text x="{}" y="{}" text-anchor="middle" font-family="monospace" font-size="14" fill="{}">[{}]</text>"#,
+                x + self.panel_size / 2.0,
+                y + 14.0,
+                self.color(block.hsv),
+                block_no_to_char(block.block_no),
+            )
+            .unwrap();
+
+            // keep the cells square
+            let cell = self.panel_size / block.width.max(block.height) as f64;
+            let w = cell * block.width as f64;
+            let h = cell * block.height as f64;
+            let rect = Rect {
+                x: x + (self.panel_size - w) / 2.0,
+                y: y + TITLE_HEIGHT + (self.panel_size - h) / 2.0,
+                w,
+                h,
+            };
+            self.draw_interior(&mut out, game, block, rect, 0);
+        }
+
+        writeln!(out, "</svg>").unwrap();
+        Ok(out)
+    }
+}
+
+impl SvgRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn color(&self, hsv: Hsv) -> String {
+        self.theme.block_color(hsv).to_hex()
+    }
+
+    // draws the floor, goals and cells of a block inside the area
+    fn draw_interior(&self, out: &mut String, game: &Game, block: &Block, rect: Rect, depth: u32) {
+        if block.filled {
+            draw_rect(out, rect, &self.color(block.hsv), "");
+            return;
+        }
+        // nested interiors are shrunk by the zoom factor, inside a frame of
+        // the block's color
+        let rect = if depth > 0 && block.nested_scale() < 1.0 {
+            draw_rect(out, rect, &self.color(block.hsv), "");
+            inset(rect, (1.0 - block.nested_scale()) / 2.0)
+        } else {
+            rect
+        };
+        let floor = Hsv::new(block.hsv.h, block.hsv.s, block.hsv.v * 0.5);
+        draw_rect(out, rect, &self.color(floor), "");
+
+        let cell_w = rect.w / block.width as f64;
+        let cell_h = rect.h / block.height as f64;
+        let area = |Pos(x, y): Pos| Rect {
+            x: rect.x + cell_w * x as f64,
+            // positions are y-up
+            y: rect.y + cell_h * (block.height - 1 - y) as f64,
+            w: cell_w,
+            h: cell_h,
+        };
+
+        for goal in game.goals() {
+            if goal.gpos.is_in(block.id) {
+                let area = inset(area(goal.gpos.pos), 0.15);
+                let color = self.theme.goal_color.to_hex();
+                let stroke = format!(r#" stroke="{color}" stroke-width="1.5" fill-opacity="0""#);
+                draw_rect(out, area, &color, &stroke);
+                if goal.player {
+                    draw_eyes(out, area, &color);
+                }
+            }
+        }
+
+        for cell in game.cells() {
+            if cell.gpos().is_in(block.id) {
+                self.draw_cell(out, game, cell, area(cell.gpos().pos), depth + 1);
+            }
+        }
+
+        // outline of the block
+        let stroke = r#" stroke="black" stroke-width="1" fill-opacity="0""#;
+        draw_rect(out, rect, "black", stroke);
+    }
+
+    fn draw_cell(&self, out: &mut String, game: &Game, cell: &Cell, rect: Rect, depth: u32) {
+        let player = game.is_player(cell.id());
+
+        match cell {
+            Cell::Wall(_) => {
+                let parent = game.container_block(cell.gpos()).unwrap();
+                draw_rect(out, rect, &self.color(parent.hsv), "");
+            }
+            Cell::Block(block) => {
+                if depth < self.max_depth && !game.is_block_trivial(block) {
+                    self.draw_interior(out, game, block, rect, depth);
+                } else {
+                    draw_rect(out, rect, &self.color(block.hsv), "");
+                }
+            }
+            Cell::Reference(reference) => {
+                let target = game.block_by_no(reference.target_no).unwrap();
+                let clone = !reference.exit;
+                if clone {
+                    writeln!(out, r#"<g opacity="0.6">"#).unwrap();
+                }
+                if depth < self.max_depth {
+                    self.draw_interior(out, game, target, rect, depth);
+                } else {
+                    draw_rect(out, rect, &self.color(target.hsv), "");
+                }
+                if reference.inf_exit.is_some() {
+                    writeln!(
+                        out,
+                        r#"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="central" font-size="{}" fill="white">∞</text>"#,
+                        rect.x + rect.w / 2.0,
+                        rect.y + rect.h / 2.0,
+                        rect.h * 0.6,
+                    )
+                    .unwrap();
+                }
+                if clone {
+                    writeln!(out, "</g>").unwrap();
+                }
+            }
+        }
+
+        if player {
+            draw_eyes(out, rect, "black");
+        }
+        if cell.fliph() {
+            // a triangle in the top right corner
+            let size = rect.w.min(rect.h) * 0.3;
+            writeln!(
+                out,
+                r#"<polygon points="{},{} {},{} {},{}" fill="white" stroke="black" stroke-width="0.5"/>"#,
+                rect.x + rect.w - size,
+                rect.y,
+                rect.x + rect.w,
+                rect.y,
+                rect.x + rect.w,
+                rect.y + size,
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// Renders the game as an SVG document with the default options.
+pub fn render_svg(game: &Game) -> String {
+    let Ok(svg) = SvgRenderer::new().render(game);
+    svg
+}
+
+fn inset(rect: Rect, ratio: f64) -> Rect {
+    Rect {
+        x: rect.x + rect.w * ratio,
+        y: rect.y + rect.h * ratio,
+        w: rect.w * (1.0 - 2.0 * ratio),
+        h: rect.h * (1.0 - 2.0 * ratio),
+    }
+}
+
+fn draw_rect(out: &mut String, rect: Rect, fill: &str, extra: &str) {
+    writeln!(
+        out,
+        r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{fill}"{extra}/>"#,
+        rect.x, rect.y, rect.w, rect.h,
+    )
+    .unwrap();
+}
+
+fn draw_eyes(out: &mut String, rect: Rect, fill: &str) {
+    let r = rect.w.min(rect.h) * 0.08;
+    for dx in [0.35, 0.65] {
+        writeln!(
+            out,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="{r:.2}" fill="{fill}"/>"#,
+            rect.x + rect.w * dx,
+            rect.y + rect.h * 0.4,
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,382 @@
+use color_space::{Hsv, ToRgb};
+use crossterm::{
+    cursor,
+    style::{self, Stylize},
+    terminal, QueueableCommand,
+};
+use std::io::Write;
+
+use super::glyph::*;
+#[cfg(feature = "png")]
+use super::kitty::*;
+use super::layout::*;
+#[cfg(feature = "png")]
+use super::raster::PngRenderer;
+use super::renderer::Renderer;
+use super::theme::Theme;
+use crate::engine::*;
+
+/// Draws every non-trivial block in panels on a terminal, or a single nested
+/// view of the block containing the player if `options.depth` is nonzero.
+///
+/// Panels are sized to their block and wrapped to the width of the terminal.
+/// Layouts that don't fit on the screen can be scrolled with `scroll`.
+///
+/// With the `png` feature, setting `graphics` draws the panels as an image
+/// through the kitty graphics protocol instead, with block interiors drawn
+/// inside their cells. Check `kitty_supported` first, and keep the text
+/// panels as a fallback otherwise.
+///
+/// The renderer remembers what is on the screen, and only writes the
+/// characters that changed since the last frame, so that redrawing doesn't
+/// flicker. `render_move` also skips the blocks a move didn't touch.
+pub struct TerminalRenderer<W: Write> {
+    out: W,
+    pub options: GlyphOptions,
+    #[cfg(feature = "png")]
+    pub graphics: Option<PngRenderer>,
+    // whether the screen is cleared before the next render
+    clear: bool,
+    // the panels of the last render, and the part of them shown
+    layout: Layout,
+    viewport: Viewport,
+    // the glyphs on the screen row by row, or None if unknown
+    screen: Vec<Option<Glyph>>,
+    screen_size: (u16, u16),
+    // the parent block of every cell at the last render
+    parents: Vec<Container>,
+}
+
+impl<W: Write> TerminalRenderer<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            options: GlyphOptions::default(),
+            #[cfg(feature = "png")]
+            graphics: None,
+            clear: true,
+            layout: Layout::default(),
+            viewport: Viewport::default(),
+            screen: Vec::new(),
+            screen_size: (0, 0),
+            parents: Vec::new(),
+        }
+    }
+
+    /// Clears the whole screen before the next render.
+    pub fn clear(&mut self) {
+        self.clear = true;
+    }
+
+    /// Scrolls the panels by the given number of columns and rows, within
+    /// the layout of the last render. The screen is redrawn on the next
+    /// render.
+    pub fn scroll(&mut self, dx: i32, dy: i32) {
+        self.viewport.scroll(dx, dy, &self.layout);
+        self.clear = true;
+    }
+
+    /// Returns the part of the panels shown by the last render.
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
+    /// Returns the position drawn at the given screen column and row by the
+    /// last render, e.g. for inspecting the cell under the mouse. Nested
+    /// views only map to positions of the block shown, and images drawn
+    /// with graphics are not mapped.
+    pub fn gpos_at(&self, game: &Game, column: u16, row: u16) -> Option<GlobalPos> {
+        #[cfg(feature = "png")]
+        if self.graphics.is_some() {
+            return None;
+        }
+
+        let (x, y) = (column + self.viewport.x, row + self.viewport.y);
+        let panel = *self.layout.panels.iter().find(|panel| {
+            (panel.x..panel.x + panel.width).contains(&x)
+                && (panel.y..panel.y + panel.height).contains(&y)
+        })?;
+        let block = game.cells().get(panel.block_id)?.block()?;
+        let (width, height) = (block.width as u16, block.height as u16);
+
+        let pos = if self.options.depth > 0 {
+            // parts of the canvas below the title are spread evenly
+            let (canvas_width, canvas_height) = (panel.width, panel.height - 1);
+            let (x, y) = (x, y.checked_sub(1)?);
+            let column = (0..width).find(|&c| x < canvas_width * (c + 1) / width)?;
+            let row = (0..height).find(|&r| y < canvas_height * (r + 1) / height)?;
+            Pos(column as i32, (height - 1 - row) as i32)
+        } else {
+            let offset_x = panel.x + (panel.width - width) / 2;
+            let column = x.checked_sub(offset_x).filter(|&c| c < width)?;
+            let row = y.checked_sub(panel.y + 1).filter(|&r| r < height)?;
+            Pos(column as i32, (height - 1 - row) as i32)
+        };
+        Some(GlobalPos::new(block.id, pos))
+    }
+
+    /// Returns the underlying writer, e.g. for printing messages below the
+    /// board. The cursor is left on the line after the board.
+    pub fn out_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    /// Draws the state after a move, redrawing only the panels of blocks
+    /// whose cells moved or changed players, and the panels previewing them.
+    /// Falls back to a full render if the layout changed.
+    pub fn render_move(&mut self, game: &Game, outcome: &MoveOutcome) -> std::io::Result<()> {
+        let size = terminal::size()?;
+        #[cfg(feature = "png")]
+        if self.graphics.is_some() {
+            return self.render(game);
+        }
+        if self.options.depth > 0
+            || self.clear
+            || size != self.screen_size
+            || self.parents.len() != game.cells().len()
+            || Layout::new(game, size.0) != self.layout
+        {
+            return self.render(game);
+        }
+
+        let mut dirty = Vec::new();
+        let cells = outcome
+            .moved
+            .iter()
+            .chain(outcome.possessed.iter().flat_map(|(from, to)| [from, to]));
+        for &id in cells {
+            dirty.extend(self.parents[id].block_id());
+            dirty.extend(game.cells()[id].gpos().block_id());
+        }
+        if self.options.preview {
+            for reference in game.cells().iter().filter_map(|cell| cell.reference()) {
+                let target = game.block_by_no(reference.target_no).map(|block| block.id);
+                if target.is_some_and(|id| dirty.contains(&id)) {
+                    dirty.extend(reference.gpos.block_id());
+                }
+            }
+        }
+
+        for panel in self.layout.panels.clone() {
+            if dirty.contains(&panel.block_id) {
+                self.draw_panel(game, panel)?;
+            }
+        }
+        self.finish(game)
+    }
+
+    // clears the screen if requested or if its size changed, and updates the
+    // layout and the viewport
+    fn begin(&mut self, layout: Layout) -> std::io::Result<()> {
+        let (columns, rows) = terminal::size()?;
+        if std::mem::take(&mut self.clear)
+            || (columns, rows) != self.screen_size
+            || layout != self.layout
+        {
+            // stale glyphs outside of the new panels would stay otherwise
+            self.out.queue(terminal::Clear(terminal::ClearType::All))?;
+            self.screen = vec![None; columns as usize * rows as usize];
+            self.screen_size = (columns, rows);
+        }
+
+        self.layout = layout;
+        // two lines are left for messages
+        self.viewport.width = columns;
+        self.viewport.height = rows.saturating_sub(2);
+        self.viewport.scroll(0, 0, &self.layout);
+        Ok(())
+    }
+
+    // records the parents of the cells and leaves the cursor below the board
+    fn finish(&mut self, game: &Game) -> std::io::Result<()> {
+        self.parents = game
+            .cells()
+            .iter()
+            .map(|cell| cell.gpos().container)
+            .collect();
+
+        let height = self.layout.height.saturating_sub(self.viewport.y);
+        self.out
+            .queue(cursor::MoveTo(0, height.min(self.viewport.height)))?;
+        self.out.flush()
+    }
+
+    // writes a glyph at a layout position, unless it is already on the
+    // screen or outside of the viewport
+    fn put(&mut self, x: u16, y: u16, glyph: Glyph) -> std::io::Result<()> {
+        let Some((x, y)) = self.viewport.to_screen(x, y) else {
+            return Ok(());
+        };
+        let Some(slot) = self
+            .screen
+            .get_mut(y as usize * self.screen_size.0 as usize + x as usize)
+        else {
+            return Ok(());
+        };
+
+        if *slot != Some(glyph) {
+            *slot = Some(glyph);
+            self.out
+                .queue(cursor::MoveTo(x, y))?
+                .queue(style::PrintStyledContent(styled(
+                    glyph,
+                    &self.options.theme,
+                )))?;
+        }
+        Ok(())
+    }
+
+    fn put_title(&mut self, panel: Panel, block: &Block) -> std::io::Result<()> {
+        let title = format!("[{}]", block_no_to_char(block.block_no));
+        let x = panel.x + panel.width.saturating_sub(title.len() as u16) / 2;
+        for (i, mark) in title.chars().enumerate() {
+            let glyph = Glyph {
+                mark,
+                color: GlyphColor::Block(block.hsv),
+                inverted: false,
+                underlined: false,
+            };
+            self.put(x + i as u16, panel.y, glyph)?;
+        }
+        Ok(())
+    }
+
+    fn draw_panel(&mut self, game: &Game, panel: Panel) -> std::io::Result<()> {
+        let block = game.cells()[panel.block_id].block().unwrap();
+        self.put_title(panel, block)?;
+
+        let offset_x = panel.x + (panel.width - block.width as u16) / 2;
+        for y in 0..block.height {
+            for x in 0..block.width {
+                let gpos = GlobalPos::new(block.id, Pos(x, y));
+                let glyph = glyph_at(game, gpos, self.options);
+                self.put(
+                    offset_x + x as u16,
+                    panel.y + (block.height - y) as u16,
+                    glyph,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "png")]
+    fn render_graphics(&mut self, game: &Game, renderer: PngRenderer) -> std::io::Result<()> {
+        let image = renderer.rasterize(game);
+        let png = image.encode().map_err(std::io::Error::other)?;
+
+        // fit the image into the screen, assuming cells twice as tall as
+        // they are wide, and leave two lines for messages
+        let (columns, rows) = terminal::size()?;
+        let rows = rows.saturating_sub(2).max(1);
+        let aspect = image.width as f64 / image.height.max(1) as f64;
+        let (columns, rows) = if rows as f64 * aspect * 2.0 <= columns as f64 {
+            ((rows as f64 * aspect * 2.0).ceil() as u16, rows)
+        } else {
+            (columns, (columns as f64 / aspect / 2.0).ceil() as u16)
+        };
+
+        // the image replaces the previous one, but text panels have to be
+        // cleared, and redrawn from scratch when graphics are turned off
+        if std::mem::take(&mut self.clear) || !self.screen.is_empty() {
+            self.out.queue(terminal::Clear(terminal::ClearType::All))?;
+            self.screen.clear();
+            self.screen_size = (0, 0);
+        }
+        self.out
+            .queue(cursor::MoveTo(0, 0))?
+            .queue(style::Print(kitty_image(&png, columns, rows)))?
+            .queue(cursor::MoveTo(0, rows))?;
+        self.out.flush()
+    }
+
+    fn render_nested(&mut self, game: &Game) -> std::io::Result<()> {
+        let Some(block) = game.view_block() else {
+            self.begin(Layout::default())?;
+            return self.finish(game);
+        };
+
+        // characters are about twice as tall as they are wide, and three
+        // lines are left for the title and messages
+        let (columns, rows) = terminal::size()?;
+        let size = nested_cell_size(game, self.options.depth);
+        let height = (block.height as usize * size).min(rows.saturating_sub(3) as usize);
+        let width = (block.width as usize * size * 2).min(columns as usize);
+
+        // the view is a single panel, so that switching to another block
+        // clears the screen
+        let panel = Panel {
+            block_id: block.id,
+            x: 0,
+            y: 0,
+            width: width as u16,
+            height: height as u16 + 1,
+        };
+        self.begin(Layout {
+            panels: vec![panel],
+            width: panel.width,
+            height: panel.height,
+        })?;
+        self.put_title(panel, block)?;
+
+        for (y, row) in nested_glyphs(game, block, width, height, self.options)
+            .into_iter()
+            .enumerate()
+        {
+            for (x, glyph) in row.into_iter().enumerate() {
+                self.put(x as u16, y as u16 + 1, glyph)?;
+            }
+        }
+        self.finish(game)
+    }
+}
+
+impl<W: Write> Renderer for TerminalRenderer<W> {
+    type Output = ();
+    type Error = std::io::Error;
+
+    fn render(&mut self, game: &Game) -> std::io::Result<()> {
+        #[cfg(feature = "png")]
+        if let Some(renderer) = self.graphics {
+            return self.render_graphics(game, renderer);
+        }
+
+        if self.options.depth > 0 {
+            return self.render_nested(game);
+        }
+
+        let (columns, _) = terminal::size()?;
+        self.begin(Layout::new(game, columns))?;
+        for panel in self.layout.panels.clone() {
+            self.draw_panel(game, panel)?;
+        }
+        self.finish(game)
+    }
+}
+
+/// Styles a glyph with the colors of the theme, as it is drawn on the
+/// terminal.
+pub fn styled(glyph: Glyph, theme: &Theme) -> style::StyledContent<char> {
+    let color = theme.glyph_color(glyph.color);
+    let mut content = glyph.mark.with(style::Color::Rgb {
+        r: color.r,
+        g: color.g,
+        b: color.b,
+    });
+    if glyph.inverted {
+        content = content.negative();
+    }
+    if glyph.underlined {
+        content = content.underlined();
+    }
+    content
+}
+
+pub fn color_from_hsv(hsv: Hsv) -> style::Color {
+    let rgb = hsv.to_rgb();
+    style::Color::Rgb {
+        r: rgb.r as u8,
+        g: rgb.g as u8,
+        b: rgb.b as u8,
+    }
+}
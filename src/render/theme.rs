@@ -0,0 +1,241 @@
+use color_space::{Hsv, ToRgb};
+use std::path::Path;
+
+use super::glyph::GlyphColor;
+
+/// An RGB color with 8 bits per channel.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::new(255, 255, 255);
+    pub const GREY: Color = Color::new(192, 192, 192);
+
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Parses a color written as `rrggbb` in hexadecimal, with an optional
+    /// leading `#`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let hex = text.strip_prefix('#').unwrap_or(text);
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| format!("Invalid color {text}"))
+        };
+        if hex.len() != 6 {
+            return Err(format!("Invalid color {text}"));
+        }
+        Ok(Color::new(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// Formats the color as `#rrggbb`.
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    fn scale(self, factor: f64) -> Self {
+        let channel = |c: u8| (c as f64 * factor.clamp(0.0, 1.0)) as u8;
+        Color::new(channel(self.r), channel(self.g), channel(self.b))
+    }
+}
+
+/// How blocks are colored.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlockColors {
+    /// The HSV colors given by the level.
+    #[default]
+    Level,
+    /// One color for each sixth of the hue circle, starting from red, scaled
+    /// by the brightness of the level's color. Colors without saturation
+    /// stay grey, so that the blocks of a level keep their grouping.
+    Palette([Color; 6]),
+    /// The same color for every block.
+    Mono(Color),
+}
+
+/// The marks and colors used to draw a level.
+///
+/// Themes can be loaded from config files with `key = value` lines, e.g.
+///
+/// ```text
+/// # start from a preset, then override single items
+/// preset = color-blind
+/// wall = #
+/// goal_color = ffffff
+/// block_colors = palette d55e00 f0e442 009e73 56b4e9 0072b2 cc79a7
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub wall: char,
+    pub empty: char,
+    pub player: char,
+    pub solid_block: char,
+    pub player_goal: char,
+    pub block_goal: char,
+    pub goal_color: Color,
+    pub empty_color: Color,
+    pub block_colors: BlockColors,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            wall: '#',
+            empty: '.',
+            player: 'p',
+            solid_block: 'b',
+            player_goal: '=',
+            block_goal: '_',
+            goal_color: Color::WHITE,
+            empty_color: Color::GREY,
+            block_colors: BlockColors::Level,
+        }
+    }
+}
+
+impl Theme {
+    /// Names of the presets accepted by `preset`.
+    pub const PRESETS: [&'static str; 3] = ["default", "color-blind", "monochrome"];
+
+    /// A preset replacing the level's colors with the Okabe-Ito palette,
+    /// which stays distinguishable with the common kinds of color blindness.
+    pub fn color_blind() -> Self {
+        Self {
+            block_colors: BlockColors::Palette([
+                Color::new(0xd5, 0x5e, 0x00),
+                Color::new(0xf0, 0xe4, 0x42),
+                Color::new(0x00, 0x9e, 0x73),
+                Color::new(0x56, 0xb4, 0xe9),
+                Color::new(0x00, 0x72, 0xb2),
+                Color::new(0xcc, 0x79, 0xa7),
+            ]),
+            ..Self::default()
+        }
+    }
+
+    /// A preset without colors, telling blocks apart by their marks only.
+    pub fn monochrome() -> Self {
+        Self {
+            empty_color: Color::WHITE,
+            block_colors: BlockColors::Mono(Color::WHITE),
+            ..Self::default()
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "color-blind" => Some(Self::color_blind()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// Parses a theme config. Items not set in the config are taken from
+    /// the preset, or from the default theme.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut theme = Self::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let context = |e: String| format!("Line {}: {}", i + 1, e);
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| context(format!("Expected key = value, found {line}")))?;
+            theme.set(key, value).map_err(context)?;
+        }
+
+        Ok(theme)
+    }
+
+    /// Loads a theme from a config file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&text)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let mark = || {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(format!(
+                    "Expected a single character for {key}, found {value}"
+                )),
+            }
+        };
+
+        match key {
+            "preset" => {
+                *self = Self::preset(value).ok_or_else(|| format!("Unknown preset {value}"))?
+            }
+            "wall" => self.wall = mark()?,
+            "empty" => self.empty = mark()?,
+            "player" => self.player = mark()?,
+            "solid_block" => self.solid_block = mark()?,
+            "player_goal" => self.player_goal = mark()?,
+            "block_goal" => self.block_goal = mark()?,
+            "goal_color" => self.goal_color = Color::parse(value)?,
+            "empty_color" => self.empty_color = Color::parse(value)?,
+            "block_colors" => {
+                let mut words = value.split_whitespace();
+                let colors = |words: std::str::SplitWhitespace| {
+                    words.map(Color::parse).collect::<Result<Vec<_>, _>>()
+                };
+                self.block_colors = match words.next() {
+                    Some("level") => BlockColors::Level,
+                    Some("mono") => match colors(words)?[..] {
+                        [color] => BlockColors::Mono(color),
+                        _ => return Err("Expected one color after mono".to_string()),
+                    },
+                    Some("palette") => BlockColors::Palette(
+                        colors(words)?
+                            .try_into()
+                            .map_err(|_| "Expected six colors after palette".to_string())?,
+                    ),
+                    _ => return Err(format!("Unknown block colors {value}")),
+                };
+            }
+            _ => return Err(format!("Unknown theme item {key}")),
+        }
+        Ok(())
+    }
+
+    /// Returns the color of a block with the given level color.
+    pub fn block_color(&self, hsv: Hsv) -> Color {
+        match self.block_colors {
+            BlockColors::Level => {
+                let rgb = hsv.to_rgb();
+                Color::new(rgb.r as u8, rgb.g as u8, rgb.b as u8)
+            }
+            BlockColors::Palette(palette) => {
+                if hsv.s < 0.2 {
+                    let grey = (hsv.v.clamp(0.0, 1.0) * 255.0) as u8;
+                    return Color::new(grey, grey, grey);
+                }
+                let sector = (hsv.h.rem_euclid(360.0) / 60.0).round() as usize % 6;
+                palette[sector].scale(hsv.v)
+            }
+            BlockColors::Mono(color) => color,
+        }
+    }
+
+    pub fn glyph_color(&self, color: GlyphColor) -> Color {
+        match color {
+            GlyphColor::Block(hsv) => self.block_color(hsv),
+            GlyphColor::Goal => self.goal_color,
+            GlyphColor::Empty => self.empty_color,
+        }
+    }
+}
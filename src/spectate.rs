@@ -0,0 +1,80 @@
+use parabox::engine::*;
+use std::{
+    io::Write,
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+/// How long a write to a spectator may block before the spectator is
+/// dropped, so that slow connections don't hold up the game.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Broadcasts the states of a live session to spectators connected over
+/// TCP, e.g. for remote viewers or stream overlays.
+///
+/// Each state is sent as a frame: a line `state <moves> <goals>/<total>`,
+/// followed by `won` when the level is won, then the state in the level file
+/// format, and an empty line. Spectators get the current state when they
+/// connect, and a frame for every change after that.
+pub struct Spectators {
+    shared: Arc<Mutex<Shared>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    clients: Vec<TcpStream>,
+    // the last frame, for spectators that connect later
+    frame: String,
+}
+
+impl Spectators {
+    /// Accepts spectators on `127.0.0.1` at the given port in the background.
+    pub fn listen(port: u16) -> Result<Self, String> {
+        let listener = TcpListener::bind(("127.0.0.1", port))
+            .map_err(|e| format!("Can't listen for spectators on port {port}: {e}"))?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+
+        let accepting = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                let mut shared = accepting.lock().unwrap();
+                if stream.write_all(shared.frame.as_bytes()).is_ok() {
+                    shared.clients.push(stream);
+                }
+            }
+        });
+
+        Ok(Self { shared })
+    }
+
+    /// Sends the state to every spectator if it changed since the last call,
+    /// and drops the spectators that disconnected.
+    pub fn broadcast(&self, game: &Game, moves: usize) {
+        let mut frame = format!(
+            "state {moves} {}/{}",
+            game.satisfied_goals(),
+            game.goals().len()
+        );
+        if game.won() {
+            frame += " won";
+        }
+        frame += "\n";
+        frame += &game.to_text();
+        frame += "\n";
+
+        let mut shared = self.shared.lock().unwrap();
+        if shared.frame == frame {
+            return;
+        }
+        shared
+            .clients
+            .retain_mut(|client| client.write_all(frame.as_bytes()).is_ok());
+        shared.frame = frame;
+    }
+}
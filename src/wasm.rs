@@ -0,0 +1,85 @@
+//! JavaScript bindings for WebAssembly (`wasm` feature), so that a browser
+//! frontend can run the same rules. Build with `wasm-pack build --features
+//! wasm --no-default-features`.
+//!
+//! Directions are passed as strings of the characters `UDLR`, like the
+//! `.solution` files.
+
+use wasm_bindgen::prelude::*;
+
+use crate::engine::*;
+use crate::render::{render_ascii, render_svg};
+
+/// A level and its current state.
+#[wasm_bindgen(js_name = Game)]
+#[derive(Clone)]
+pub struct WasmGame {
+    game: Game,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    /// Parses a level in the level file format.
+    #[wasm_bindgen(constructor)]
+    pub fn new(text: &str) -> Result<WasmGame, JsError> {
+        let game = Game::parse(text).map_err(|e| JsError::new(&e))?;
+        Ok(Self { game })
+    }
+
+    /// Plays one input. Returns whether the state changed.
+    pub fn play(&mut self, direction: char) -> Result<bool, JsError> {
+        let direction = Direction::from_char(direction)
+            .ok_or_else(|| JsError::new(&format!("Invalid direction {direction:?}")))?;
+        Ok(self.game.play(direction).changed())
+    }
+
+    /// Plays a sequence of moves such as `"RRUL"`. Returns the number of
+    /// moves played.
+    #[wasm_bindgen(js_name = playSequence)]
+    pub fn play_sequence(&mut self, moves: &str) -> Result<usize, JsError> {
+        let outcome = self.game.play_sequence(moves)?;
+        Ok(outcome.steps)
+    }
+
+    pub fn won(&self) -> bool {
+        self.game.won()
+    }
+
+    /// Returns the directions in which playing would change the state.
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> String {
+        self.game
+            .legal_moves()
+            .into_iter()
+            .map(Direction::to_char)
+            .collect()
+    }
+
+    /// Writes the current state in the level file format.
+    #[wasm_bindgen(js_name = toText)]
+    pub fn to_text(&self) -> String {
+        self.game.to_text()
+    }
+
+    /// Encodes the current state compactly, as a `Uint8Array`.
+    #[wasm_bindgen(js_name = encodeState)]
+    pub fn encode_state(&self) -> Vec<u8> {
+        self.game.encode_state()
+    }
+
+    #[wasm_bindgen(js_name = renderAscii)]
+    pub fn render_ascii(&self) -> String {
+        render_ascii(&self.game)
+    }
+
+    #[wasm_bindgen(js_name = renderSvg)]
+    pub fn render_svg(&self) -> String {
+        render_svg(&self.game)
+    }
+
+    /// Returns a copy that can be played independently.
+    #[wasm_bindgen(js_name = clone)]
+    pub fn copy(&self) -> WasmGame {
+        self.clone()
+    }
+}
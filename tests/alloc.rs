@@ -0,0 +1,49 @@
+// checking the invariants after every move allocates
+#![cfg(not(feature = "check-invariants"))]
+
+use parabox::engine::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// counts the allocations, so that the hot path of the simulator can be
+// checked; the only test in this file, since tests run in parallel
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn test_play_without_allocations() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let solution = fs::read_to_string("levels/vanilla/enter.solution").unwrap();
+    let moves = parse_sequence(&solution).unwrap();
+    let mut buffers = SimulatorBuffers::default();
+
+    // the first pass lets the buffers grow
+    let mut game = Game::parse(&text).unwrap();
+    for direction in &moves {
+        game.play_with(*direction, &mut buffers);
+    }
+
+    let mut game = Game::parse(&text).unwrap();
+    let before = ALLOCATIONS.load(Ordering::SeqCst);
+    for direction in &moves {
+        game.play_with(*direction, &mut buffers);
+    }
+    assert_eq!(ALLOCATIONS.load(Ordering::SeqCst) - before, 0);
+    assert!(game.won());
+}
@@ -0,0 +1,149 @@
+use color_space::Hsv;
+use parabox::engine::{analysis::*, difficulty::*, solver::*, *};
+use std::fs;
+
+// a filled block
+fn block(gpos: GlobalPos, block_no: i32) -> Block {
+    Block {
+        filled: true,
+        ..Block::new(gpos, BlockNo(block_no), 1, 1, Hsv::new(0.0, 0.0, 0.8))
+    }
+}
+
+#[test]
+fn test_analysis() {
+    let text = fs::read_to_string("levels/vanilla/first_puzzle.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+
+    let analysis = analyze(&game, &AnalysisConfig::default());
+    assert_eq!(analysis.blocks, 3);
+    assert_eq!(analysis.references, 0);
+    assert_eq!(analysis.max_depth, 2);
+    assert!(analysis.exhausted);
+    assert!(analysis.branching_factor > 1.0 && analysis.branching_factor <= 4.0);
+
+    // the exploration is breadth-first, so it finds a shortest solution
+    let report = solve(&game, &SolverConfig::default());
+    assert_eq!(analysis.solution_moves, report.solution.map(|s| s.len()));
+
+    // with a small budget, the state count is only a lower bound
+    let bounded = analyze(&game, &AnalysisConfig { max_states: 10 });
+    assert!(!bounded.exhausted);
+    assert!(bounded.states > 10 && bounded.states < analysis.states);
+    assert!(bounded.to_string().contains("at least"));
+}
+
+#[test]
+fn test_reachability() {
+    // the player is walled off from the box goal and the other possessable
+    // block on the right
+    let text = "version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tWall 2 0 0 0 0
+\tWall 2 1 0 0 0
+\tWall 2 2 0 0 0
+\tWall 2 3 0 0 0
+\tWall 2 4 0 0 0
+\tFloor 0 3 PlayerButton
+\tFloor 3 0 Button
+\tBlock 0 0 1 1 1 0.9 1 0.7 1 1 1 1 0 0 0 0
+\tBlock 4 4 2 1 1 0.1 0.8 1 1 1 0 1 0 0 0 0
+";
+    let game = Game::parse(text).unwrap();
+    let analysis = analyze(&game, &AnalysisConfig::default());
+    assert!(analysis.exhausted);
+
+    let reachability = &analysis.reachability;
+    let root = game.block_by_no(BlockNo(0)).unwrap().id;
+    let mut positions = reachability
+        .player_positions
+        .iter()
+        .map(|gpos| {
+            assert!(gpos.is_in(root));
+            (gpos.pos.0, gpos.pos.1)
+        })
+        .collect::<Vec<_>>();
+    positions.sort();
+    let left = (0..2).flat_map(|x| (0..5).map(move |y| (x, y)));
+    assert_eq!(positions, left.collect::<Vec<_>>());
+
+    let goals = &reachability.unreachable_goals;
+    assert_eq!(goals.len(), 1);
+    assert!(!game.goals()[goals[0]].player);
+    assert_eq!(
+        reachability.unreachable_possessables,
+        vec![game.block_by_no(BlockNo(2)).unwrap().id]
+    );
+}
+
+#[test]
+fn test_difficulty() {
+    let text = fs::read_to_string("levels/vanilla/first_puzzle.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+    let config = SolverConfig::default();
+
+    let difficulty = estimate_difficulty(&game, &config);
+    assert_eq!(difficulty.moves, Some(14));
+    assert_eq!(
+        (
+            difficulty.possessions,
+            difficulty.flips,
+            difficulty.inf_transfers
+        ),
+        (0, 0, 0)
+    );
+    assert_eq!(difficulty.decoys, 0);
+
+    // a possessable wall in the corner that the solution leaves alone makes
+    // the level harder
+    let mut decoy = game.clone();
+    let root = decoy.block_by_no(BlockNo(0)).unwrap().id;
+    let mut corner = Wall::new(GlobalPos::new(root, Pos(1, 1)));
+    corner.possessable = true;
+    decoy.add_wall(corner).unwrap();
+    let harder = estimate_difficulty(&decoy, &config);
+    assert_eq!(harder.decoys, 1);
+    assert!(harder.score > difficulty.score);
+
+    // the player is stuck in a corner, and gets out by possessing the block
+    // it can't push into the wall
+    let mut possession = Template::Room {
+        width: 5,
+        height: 5,
+    }
+    .level()
+    .unwrap();
+    let root = possession.block_by_no(BlockNo(0)).unwrap().id;
+    let at = |x, y| GlobalPos::new(root, Pos(x, y));
+    for pos in [at(1, 2), at(3, 1)] {
+        possession.add_wall(Wall::new(pos)).unwrap();
+    }
+    let player = possession.add_block(block(at(1, 1), 1)).unwrap();
+    possession.set_player(player, true).unwrap();
+    let mut other = block(at(2, 1), 2);
+    other.possessable = true;
+    possession.add_block(other).unwrap();
+    possession.add_goal(at(2, 3), true).unwrap();
+    let difficulty = estimate_difficulty(&possession, &config);
+    assert_eq!(difficulty.possessions, 1);
+    assert!(difficulty.to_string().contains("1 possessions"));
+
+    // the vanilla solution exits blocks infinitely
+    let text = fs::read_to_string("levels/vanilla/inf_cannon_d.txt").unwrap();
+    let cannon = Game::parse(&text).unwrap();
+    let text = fs::read_to_string("levels/vanilla/inf_cannon_d.solution").unwrap();
+    let usage = mechanic_usage(&cannon, &parse_sequence(&text).unwrap());
+    assert_eq!((usage.inf_exits, usage.inf_enters), (2, 0));
+    assert!(usage.pushes > 0 && usage.enters > 0);
+
+    let bounded = estimate_difficulty(
+        &game,
+        &SolverConfig {
+            max_nodes: 10,
+            ..config
+        },
+    );
+    assert_eq!((bounded.moves, bounded.score), (None, None));
+    assert!(bounded.to_string().starts_with("no solution found"));
+}
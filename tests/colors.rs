@@ -0,0 +1,43 @@
+use parabox::engine::*;
+
+#[test]
+fn test_distinct_hue() {
+    assert_eq!(hue_distance(350.0, 10.0), 20.0);
+    assert_eq!(hue_distance(0.0, 180.0), 180.0);
+    assert_eq!(distinct_hue(&[0.0]), 180.0);
+    assert_eq!(distinct_hue(&[0.0, 90.0]), 225.0);
+    assert_eq!(distinct_hue(&[350.0, 30.0, 200.0]), 115.0);
+}
+
+#[test]
+fn test_assign_colors() {
+    // two gray blocks, and a block almost as red as the first one
+    let text = "version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tBlock 1 1 1 1 1 0 1 0.7 1 1 0 0 0 0 0 0
+\tBlock 2 1 2 1 1 0.02 0.9 0.6 1 1 0 0 0 0 0 0
+\tBlock 3 1 3 1 1 0 0 0.8 1 1 0 0 0 0 0 0
+";
+    let game = Game::parse(text).unwrap();
+    let hue = |game: &Game, no| game.block_by_no(BlockNo(no)).unwrap().hsv.h;
+
+    let mut colored = game.clone();
+    let recolored = colored.assign_colors(false);
+    assert_eq!(recolored.len(), 2);
+    assert_eq!(hue(&colored, 1), 0.0);
+    assert!(colored
+        .cells()
+        .iter()
+        .filter_map(|cell| cell.block())
+        .all(|block| block.hsv.s > 0.0));
+    assert!(hue_distance(hue(&colored, 0), hue(&colored, 3)) >= MIN_HUE_DISTANCE);
+
+    // the clashing block gets a new hue, but keeps its saturation and value
+    let mut recolored = game.clone();
+    assert_eq!(recolored.assign_colors(true).len(), 3);
+    let block = recolored.block_by_no(BlockNo(2)).unwrap();
+    assert!(hue_distance(block.hsv.h, 0.0) >= MIN_HUE_DISTANCE);
+    assert_eq!((block.hsv.s, block.hsv.v), (0.9, 0.6));
+    assert!(recolored.assign_colors(true).is_empty());
+}
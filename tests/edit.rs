@@ -0,0 +1,337 @@
+use color_space::Hsv;
+use parabox::engine::*;
+use std::fs;
+
+fn block(gpos: GlobalPos, block_no: i32, width: i32, height: i32) -> Block {
+    Block::new(
+        gpos,
+        BlockNo(block_no),
+        width,
+        height,
+        Hsv::new(0.0, 0.0, 0.8),
+    )
+}
+
+fn assert_consistent(game: &Game) {
+    assert_eq!(game.check_invariants(), vec![]);
+    // the level written back parses into the same level, though the cells
+    // may be numbered in another order
+    let text = game.to_text();
+    assert_eq!(Game::parse(&text).unwrap().to_text(), text);
+}
+
+#[test]
+fn test_edit() {
+    let mut game = Game::parse("version 4\n#\n").unwrap();
+    let space = GlobalPos {
+        container: Container::Space,
+        pos: Pos(-1, -1),
+    };
+    let root = game.add_block(block(space, 0, 5, 5)).unwrap();
+    let player = game
+        .add_block(block(GlobalPos::new(root, Pos(1, 2)), 1, 1, 1))
+        .unwrap();
+    let box_id = game
+        .add_block(block(GlobalPos::new(root, Pos(2, 2)), 2, 3, 3))
+        .unwrap();
+    let wall_id = game
+        .add_wall(Wall::new(GlobalPos::new(root, Pos(0, 0))))
+        .unwrap();
+    let reference = game
+        .add_reference(Reference {
+            id: 0,
+            gpos: GlobalPos::new(box_id, Pos(1, 1)),
+            target_no: BlockNo(0),
+            exit: true,
+            inf_exit: None,
+            possessable: false,
+            fliph: false,
+            rotation: 0,
+            effect: SpecialEffect::None,
+        })
+        .unwrap();
+    game.add_goal(GlobalPos::new(root, Pos(4, 4)), false)
+        .unwrap();
+    game.add_goal(GlobalPos::new(box_id, Pos(0, 0)), true)
+        .unwrap();
+    game.set_player(player, true).unwrap();
+    assert_eq!(game.player_ids(), &vec![player]);
+    assert_eq!(game.block_by_no(BlockNo(2)).unwrap().id, box_id);
+    assert_consistent(&game);
+
+    // conflicts are reported without changing the game
+    let before = game.snapshot_text();
+    assert!(game
+        .add_block(block(GlobalPos::new(root, Pos(3, 3)), 1, 1, 1))
+        .is_err());
+    assert!(game
+        .add_wall(Wall::new(GlobalPos::new(root, Pos(1, 2))))
+        .is_err());
+    assert!(game
+        .add_wall(Wall::new(GlobalPos::new(root, Pos(5, 0))))
+        .is_err());
+    assert!(game.add_wall(Wall::new(space)).is_err());
+    assert!(game
+        .add_goal(GlobalPos::new(root, Pos(4, 4)), true)
+        .is_err());
+    assert!(game
+        .move_cell(wall_id, GlobalPos::new(root, Pos(2, 2)))
+        .is_err());
+    assert!(game.remove_cell(root).is_err());
+    assert!(game.resize_block(box_id, 1, 1).is_err());
+    // blocks can't be moved inside themselves
+    assert!(game
+        .move_cell(box_id, GlobalPos::new(box_id, Pos(1, 1)))
+        .is_err());
+    assert!(game
+        .move_cell(root, GlobalPos::new(box_id, Pos(1, 1)))
+        .is_err());
+    assert_eq!(game.snapshot_text(), before);
+
+    game.move_cell(wall_id, GlobalPos::new(root, Pos(4, 0)))
+        .unwrap();
+    assert_eq!(
+        game.cell_at(GlobalPos::new(root, Pos(4, 0))).unwrap().id(),
+        wall_id
+    );
+    assert!(game.cell_at(GlobalPos::new(root, Pos(0, 0))).is_none());
+    game.resize_block(root, 6, 5).unwrap();
+    game.edit_cell(wall_id, |cell| {
+        if let Cell::Wall(wall) = cell {
+            wall.possessable = true;
+        }
+    })
+    .unwrap();
+    assert!(game.cells()[wall_id].possessable());
+    assert!(game
+        .edit_cell(box_id, |cell| cell.block_mut().unwrap().width = 1)
+        .is_err());
+    game.remove_floor(GlobalPos::new(root, Pos(4, 4))).unwrap();
+    assert!(game.remove_floor(GlobalPos::new(root, Pos(4, 4))).is_err());
+    game.add_goal(GlobalPos::new(root, Pos(4, 4)), false)
+        .unwrap();
+    game.add_wall(Wall::new(GlobalPos::new(root, Pos(5, 0))))
+        .unwrap();
+    assert_consistent(&game);
+
+    // removing the player block moves the later ids down, and the box can
+    // only be removed once its reference is gone, taking its goal with it
+    game.remove_cell(player).unwrap();
+    assert!(game.player_ids().is_empty());
+    assert_eq!(game.block_by_no(BlockNo(2)).unwrap().id, box_id - 1);
+    assert_consistent(&game);
+    assert!(game.remove_cell(box_id - 1).is_err());
+    game.remove_cell(reference - 1).unwrap();
+    game.remove_cell(box_id - 1).unwrap();
+    assert!(game.block_by_no(BlockNo(2)).is_none());
+    assert_eq!(game.goals().len(), 1);
+    assert_consistent(&game);
+}
+
+#[test]
+fn test_embed_level() {
+    // a hub with a player and one named block, and room for a puzzle
+    let hub_text = "version 5
+#
+Block -1 -1 @hub 9 9 0 0 0.8 1 0 0 0 0 0 0 0
+\tBlock 1 1 @box 1 1 0.1 1 0.7 1 1 0 0 0 0 0 0
+\tBlock 2 1 @you 1 1 0.9 1 0.7 1 1 1 1 0 0 0 0
+";
+    let mut hub = Game::parse(hub_text).unwrap();
+    let hub_id = hub.block_by_no(BlockNo(0)).unwrap().id;
+    let puzzle =
+        Game::parse(&fs::read_to_string("levels/vanilla/first_puzzle.txt").unwrap()).unwrap();
+    let players = hub.player_ids().len() + puzzle.player_ids().len();
+
+    let gpos = GlobalPos::new(hub_id, Pos(4, 4));
+    let embedding = hub.embed_level(&puzzle, gpos).unwrap();
+    assert_consistent(&hub);
+    assert_eq!(hub.cells()[embedding.root_id].gpos(), gpos);
+    assert_eq!(hub.player_ids().len(), players);
+    assert_eq!(hub.goals().len(), puzzle.goals().len());
+    // the blocks of the puzzle come after the ones of the hub
+    assert_eq!(embedding.block_nos[&BlockNo(0)], BlockNo(3));
+    assert_eq!(hub.block_by_no(BlockNo(3)).unwrap().id, embedding.root_id);
+    // the position is taken now
+    assert!(hub.embed_level(&puzzle, gpos).is_err());
+
+    // with the player of the hub gone, the solution of the puzzle wins the
+    // merged level
+    let player = hub.player_ids()[0];
+    hub.set_player(player, false).unwrap();
+    let solution = fs::read_to_string("levels/vanilla/first_puzzle.solution").unwrap();
+    verify_solution(&hub, &solution).unwrap();
+}
+
+#[test]
+fn test_renumber_blocks() {
+    let text = "version 5
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tBlock 1 1 @gone 1 1 0.1 1 0.7 1 1 0 0 0 0 0 0
+\tBlock 2 2 @inner 3 3 0.6 1 0.7 1 0 0 0 0 0 0 0
+\tRef 3 3 @inner 1 0 0 0 0 0 0 0 0 0 0 0
+";
+    let mut game = Game::parse(text).unwrap();
+    let named = |game: &Game, name: &str| {
+        let names = &game.meta().block_names;
+        names.iter().find(|(_, n)| *n == name).map(|(no, _)| *no)
+    };
+    let gone = named(&game, "gone").unwrap();
+    let inner = named(&game, "inner").unwrap();
+    game.remove_cell(game.block_by_no(gone).unwrap().id)
+        .unwrap();
+
+    let numbers = game.renumber_blocks(&Renumbering::Contiguous).unwrap();
+    assert_eq!(numbers[&inner], BlockNo(1));
+    assert_eq!(named(&game, "inner"), Some(BlockNo(1)));
+    assert!(game
+        .cells()
+        .iter()
+        .filter_map(|cell| cell.reference())
+        .all(|reference| reference.target_no == BlockNo(1)));
+    assert_consistent(&game);
+
+    // numbers given by a map can't clash with the other blocks
+    let clash = Renumbering::Map([(BlockNo(1), BlockNo(0))].into());
+    assert!(game.renumber_blocks(&clash).is_err());
+    let swap = Renumbering::Map([(BlockNo(0), BlockNo(1)), (BlockNo(1), BlockNo(0))].into());
+    game.renumber_blocks(&swap).unwrap();
+    assert_eq!(game.block_by_no(BlockNo(0)).unwrap().width, 3);
+    assert_consistent(&game);
+}
+
+#[test]
+fn test_edit_history() {
+    let text = "version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tWall 0 0 0 0 0
+";
+    let game = Game::parse(text).unwrap();
+    let root = game.block_by_no(BlockNo(0)).unwrap().id;
+    let mut history = EditHistory::new(game.clone());
+
+    let wall_id = history
+        .apply(|game| game.add_wall(Wall::new(GlobalPos::new(root, Pos(1, 0)))))
+        .unwrap();
+    history
+        .apply(|game| game.move_cell(wall_id, GlobalPos::new(root, Pos(2, 0))))
+        .unwrap();
+    let edited = history.current().snapshot_text();
+    // failed edits are rolled back and can't be undone
+    assert!(history
+        .apply(|game| game.add_wall(Wall::new(GlobalPos::new(root, Pos(2, 0)))))
+        .is_err());
+    assert_eq!(history.depth(), 2);
+
+    assert!(history.undo());
+    assert!(history.undo());
+    assert!(!history.undo());
+    assert_eq!(history.current().snapshot_text(), game.snapshot_text());
+    assert!(history.redo());
+    assert!(history.redo());
+    assert!(!history.redo());
+    assert_eq!(history.current().snapshot_text(), edited);
+
+    // a batch is undone as a whole, and rolled back entirely when it fails,
+    // e.g. because the result doesn't validate
+    history
+        .batch(|history| {
+            history.apply(|game| game.add_goal(GlobalPos::new(root, Pos(4, 4)), false))?;
+            history.apply(|game| game.resize_block(root, 6, 6))
+        })
+        .unwrap();
+    assert_eq!(history.depth(), 3);
+    let batched = history.current().snapshot_text();
+    let exit = |x, y| Reference {
+        id: 0,
+        gpos: GlobalPos::new(root, Pos(x, y)),
+        target_no: BlockNo(0),
+        exit: true,
+        inf_exit: None,
+        possessable: false,
+        fliph: false,
+        rotation: 0,
+        effect: SpecialEffect::None,
+    };
+    assert!(history
+        .batch(|history| {
+            history.apply(|game| game.remove_cell(wall_id))?;
+            history.apply(|game| game.add_reference(exit(1, 1)))?;
+            history.apply(|game| game.add_reference(exit(3, 3)))?;
+            match history
+                .current()
+                .validate()
+                .into_iter()
+                .find(|issue| matches!(issue, LevelIssue::MultipleExits { .. }))
+            {
+                Some(issue) => Err(issue.to_string()),
+                None => Ok(()),
+            }
+        })
+        .is_err());
+    assert_eq!(history.current().snapshot_text(), batched);
+    assert_eq!(history.depth(), 3);
+    history.undo();
+    assert_eq!(history.current().snapshot_text(), edited);
+    assert_eq!(history.redo_depth(), 1);
+
+    // a new edit drops the undone ones
+    history.apply(|game| game.remove_cell(wall_id)).unwrap();
+    assert_eq!(history.redo_depth(), 0);
+    assert_consistent(history.current());
+}
+
+#[test]
+fn test_templates() {
+    for name in Template::NAMES {
+        let template = Template::from_name(name, 7, 7).unwrap();
+        let game = template.level().unwrap();
+        assert_consistent(&game);
+        let players = if name == "room" { 0 } else { 1 };
+        assert_eq!(game.player_ids().len(), players, "{name}");
+        assert!(Template::from_name(name, 2, 2).unwrap().level().is_err());
+    }
+    assert!(Template::from_name("castle", 7, 7).is_err());
+
+    // leaving the outer block of the nested template comes out of its exit
+    // reference in the inner block
+    let game = Template::Nested {
+        width: 5,
+        height: 5,
+    }
+    .level()
+    .unwrap();
+    let outer = game.block_by_no(BlockNo(0)).unwrap().id;
+    let inner = game.block_by_no(BlockNo(2)).unwrap().id;
+    assert!(game
+        .cells()
+        .iter()
+        .filter_map(|cell| cell.reference())
+        .any(|reference| reference.exit && reference.gpos.is_in(inner)));
+    let player = game.player_ids()[0];
+    assert!(game.cells()[player].gpos().is_in(outer));
+
+    // templates can be placed inside other levels, with new block numbers
+    let mut game = Template::Room {
+        width: 9,
+        height: 9,
+    }
+    .level()
+    .unwrap();
+    let room = game.block_by_no(BlockNo(0)).unwrap().id;
+    let playground = Template::Possession {
+        width: 5,
+        height: 5,
+    };
+    let id = game
+        .add_template(playground, GlobalPos::new(room, Pos(4, 4)))
+        .unwrap();
+    assert_eq!(game.cells()[id].block().unwrap().block_no, BlockNo(1));
+    assert_eq!(game.goals().len(), 1);
+    assert!(game
+        .add_template(playground, GlobalPos::new(room, Pos(0, 0)))
+        .is_err());
+    assert_consistent(&game);
+}
@@ -0,0 +1,62 @@
+use parabox::engine::{env::*, *};
+use std::fs;
+
+#[test]
+fn test_environment() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let solution = fs::read_to_string("levels/vanilla/enter.solution").unwrap();
+    let game = Game::parse(&text).unwrap();
+    let unsatisfied = game.goals().len() - game.satisfied_goals();
+    let mut env = Environment::new(game, EnvConfig::default());
+
+    let Observation::Grid(grid) = env.reset() else {
+        panic!("expected a grid observation");
+    };
+    let channel = |name| {
+        GridObservation::CHANNELS
+            .iter()
+            .position(|c| *c == name)
+            .unwrap()
+    };
+    let count = |name| {
+        let channel = channel(name);
+        (0..grid.height)
+            .flat_map(|y| (0..grid.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| grid.get(channel, x, y) == 1.0)
+            .count()
+    };
+    assert_eq!(count("player"), 1);
+    assert!(count("inside") > 0);
+
+    let moves = parse_sequence(&solution).unwrap();
+    let mut total = 0.0;
+    for (i, direction) in moves.iter().enumerate() {
+        let step = env.step(*direction);
+        total += step.reward;
+        assert_eq!(step.done, i + 1 == moves.len());
+        assert!(!step.truncated);
+    }
+    let config = EnvConfig::default();
+    let expected = moves.len() as f64 * config.step_reward
+        + unsatisfied as f64 * config.goal_reward
+        + config.win_reward;
+    assert!((total - expected).abs() < 1e-9);
+
+    // encoded observations round trip through the schema
+    let mut env = Environment::new(
+        env.game().clone(),
+        EnvConfig {
+            observation: ObservationKind::Encoded,
+            max_steps: Some(1),
+            ..Default::default()
+        },
+    );
+    let step = env.step_action(0).unwrap();
+    assert!(step.truncated || step.done);
+    let Observation::Encoded(bytes) = step.observation else {
+        panic!("expected an encoded observation");
+    };
+    let decoded = env.game().state_schema().decode(&bytes).unwrap();
+    assert_eq!(decoded.state_key(), env.game().state_key());
+    assert!(env.step_action(Environment::ACTIONS).is_err());
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "cdylib")]
+
+use parabox::ffi::*;
+use std::ffi::{CStr, CString};
+use std::{fs, ptr};
+
+#[test]
+fn test_ffi() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let solution = fs::read_to_string("levels/vanilla/enter.solution").unwrap();
+    let text = CString::new(text).unwrap();
+
+    unsafe {
+        let mut error = ptr::null_mut();
+        let game = parabox_game_load(text.as_ptr(), &mut error);
+        assert!(!game.is_null() && error.is_null());
+
+        for c in solution.chars().filter(char::is_ascii_alphabetic) {
+            assert_ne!(parabox_game_play(game, c as _), -1);
+        }
+        assert!(parabox_game_won(game));
+        assert_eq!(parabox_game_play(game, b'X' as _), -1);
+
+        let copy = parabox_game_clone(game);
+        let saved = parabox_game_to_text(copy);
+        assert!(CStr::from_ptr(saved)
+            .to_str()
+            .unwrap()
+            .starts_with("version"));
+        parabox_string_free(saved);
+
+        let mut len = 0;
+        let bytes = parabox_game_encode_state(copy, &mut len);
+        assert_eq!(
+            std::slice::from_raw_parts(bytes, len),
+            (*game).encode_state()
+        );
+        parabox_bytes_free(bytes, len);
+        parabox_game_free(copy);
+        parabox_game_free(game);
+
+        let invalid = CString::new("version 4\n#\nWall 0 0 0 0 0\n").unwrap();
+        let game = parabox_game_load(invalid.as_ptr(), &mut error);
+        assert!(game.is_null());
+        assert!(CStr::from_ptr(error).to_str().unwrap().contains("outside"));
+        parabox_string_free(error);
+    }
+}
@@ -0,0 +1,172 @@
+use parabox::engine::difficulty::mechanic_usage;
+use parabox::engine::generator::*;
+use parabox::engine::solver::solve;
+use parabox::engine::*;
+
+#[test]
+fn test_generate() {
+    let config = GeneratorConfig {
+        seed: 7,
+        mechanics: Mechanics::parse("references,possession,player-goal").unwrap(),
+        ..Default::default()
+    };
+    let generated = generate(&config).unwrap();
+    let game = &generated.game;
+    assert_eq!(game.check_invariants(), vec![]);
+    assert!(generated.solution.len() >= config.min_moves);
+    verify_solution(game, &format_sequence(&generated.solution)).unwrap();
+
+    // the mechanics asked for are there
+    assert!(game.cells().iter().any(|cell| cell.reference().is_some()));
+    assert!(game
+        .cells()
+        .iter()
+        .any(|cell| cell.possessable() && !game.is_player(cell.id())));
+    assert!(game.goals().iter().any(|goal| goal.player));
+
+    // the seed decides the level
+    let again = generate(&config).unwrap();
+    assert_eq!(again.game.to_text(), game.to_text());
+    let other = generate(&GeneratorConfig { seed: 8, ..config }).unwrap();
+    assert_ne!(other.game.to_text(), game.to_text());
+
+    assert!(Mechanics::parse("gravity").is_err());
+    assert!(generate(&GeneratorConfig {
+        width: 2,
+        ..Default::default()
+    })
+    .is_err());
+}
+
+#[test]
+fn test_generate_reverse() {
+    let config = GeneratorConfig {
+        seed: 3,
+        mechanics: Mechanics::parse("enter,references,player-goal").unwrap(),
+        method: Method::Reverse { steps: 200 },
+        ..Default::default()
+    };
+    let generated = generate(&config).unwrap();
+    let game = &generated.game;
+    assert_eq!(game.check_invariants(), vec![]);
+    assert!(!game.won());
+    assert!(generated.solution.len() >= config.min_moves);
+    verify_solution(game, &format_sequence(&generated.solution)).unwrap();
+    // the moves played backwards are replaced by a shortest solution
+    let shortest = solve(game, &config.solver).solution.unwrap();
+    assert_eq!(generated.solution.len(), shortest.len());
+
+    let again = generate(&config).unwrap();
+    assert_eq!(again.game.to_text(), game.to_text());
+}
+
+#[test]
+fn test_variants() {
+    let text = std::fs::read_to_string("levels/vanilla/first_puzzle.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+    let config = VariantConfig {
+        count: 3,
+        ..Default::default()
+    };
+    let found = variants(&game, &config).unwrap();
+    assert_eq!(found.len(), 3);
+    for variant in &found {
+        assert_eq!(variant.game.check_invariants(), vec![]);
+        assert_ne!(variant.game.to_text(), game.to_text());
+        assert!(variant.solution.len() >= config.min_moves);
+        verify_solution(&variant.game, &format_sequence(&variant.solution)).unwrap();
+    }
+    assert_ne!(found[0].game.to_text(), found[1].game.to_text());
+
+    // a level without walls, goals or hollow blocks inside others can't be
+    // mutated
+    let empty = Game::parse("version 4\n#\nBlock -1 -1 0 3 3 0 0 0.8 1 0 0 0 0 0 0 0\n").unwrap();
+    assert!(variants(&empty, &config).is_err());
+}
+
+#[test]
+fn test_daily() {
+    let date = Date::parse("2024-02-29").unwrap();
+    assert_eq!(date.to_string(), "2024-02-29");
+    assert_eq!(Date::from_unix_days(date.unix_days()), date);
+    assert_eq!(Date::parse("1970-01-01").unwrap().unix_days(), 0);
+    assert_eq!(
+        Date::from_unix_days(19_782),
+        Date {
+            year: 2024,
+            month: 2,
+            day: 29
+        }
+    );
+    for invalid in ["2023-02-29", "2024-13-01", "2024-00-10", "2024-1", "today"] {
+        assert!(Date::parse(invalid).is_err());
+    }
+
+    // everyone gets the same puzzle on the same day
+    let generated = daily(date).unwrap();
+    assert_eq!(
+        daily(date).unwrap().game.to_text(),
+        generated.game.to_text()
+    );
+    let next = Date::from_unix_days(date.unix_days() + 1);
+    assert_ne!(
+        daily(next).unwrap().game.to_text(),
+        generated.game.to_text()
+    );
+
+    // the par is the length of a shortest solution, over days with each of
+    // the mechanics
+    for days in 0..8 {
+        let date = Date::from_unix_days(date.unix_days() + days);
+        let generated = daily(date).unwrap();
+        let game = &generated.game;
+        let shortest = solve(game, &Default::default()).solution.unwrap();
+        assert_eq!(game.meta().par_moves, Some(shortest.len()));
+        assert_eq!(generated.solution.len(), shortest.len());
+        assert!(shortest.len() >= date.daily_config().min_moves);
+        verify_solution(game, &format_sequence(&generated.solution)).unwrap();
+    }
+}
+
+#[test]
+fn test_constraints() {
+    let constraints = Constraint::parse_list("players=2,no-possession,push").unwrap();
+    assert_eq!(
+        constraints,
+        vec![
+            Constraint::Players(2),
+            Constraint::Forbid(Mechanic::Possession),
+            Constraint::Require(Mechanic::Push),
+        ]
+    );
+    assert!(Constraint::parse_list("no-gravity").is_err());
+
+    let config = GeneratorConfig {
+        mechanics: Mechanics::parse("player-goal").unwrap(),
+        method: Method::Reverse { steps: 300 },
+        constraints,
+        ..Default::default()
+    };
+    let generated = generate(&config).unwrap();
+    let game = &generated.game;
+    assert_eq!(game.player_ids().len(), 2);
+    assert_eq!(game.goals().iter().filter(|goal| goal.player).count(), 2);
+    verify_solution(game, &format_sequence(&generated.solution)).unwrap();
+    let usage = mechanic_usage(game, &generated.solution);
+    assert!(usage.pushes > 0);
+    assert_eq!(usage.possessions, 0);
+
+    let config = GeneratorConfig {
+        mechanics: Mechanics::parse("possession").unwrap(),
+        constraints: vec![Constraint::Require(Mechanic::Possession)],
+        ..Default::default()
+    };
+    let generated = generate(&config).unwrap();
+    assert!(mechanic_usage(&generated.game, &generated.solution).possessions > 0);
+
+    let contradicting = GeneratorConfig {
+        constraints: Constraint::parse_list("flip,no-flip").unwrap(),
+        ..Default::default()
+    };
+    assert!(generate(&contradicting).is_err());
+}
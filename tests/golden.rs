@@ -0,0 +1,68 @@
+use parabox::engine::*;
+use std::{fs, path::Path};
+
+// the states after playing the solutions, in `<pack>/<level>.snapshot`
+const GOLDEN_DIR: &str = "tests/golden";
+
+/// Compares the state after the solution of each level with its golden file,
+/// which catches changes of the simulator that still win the level but reach
+/// a different state. Run with `UPDATE_GOLDEN=1` to write the golden files
+/// after an intended change, and review the differences.
+#[test]
+fn test_golden_snapshots() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for entry in fs::read_dir("levels").unwrap() {
+        let pack = entry.unwrap().path();
+        if !pack.is_dir() {
+            continue;
+        }
+        let golden_dir = Path::new(GOLDEN_DIR).join(pack.file_name().unwrap());
+
+        let mut levels = fs::read_dir(&pack)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect::<Vec<_>>();
+        levels.sort();
+        for path in levels {
+            let Ok(solution) = fs::read_to_string(path.with_extension("solution")) else {
+                continue;
+            };
+            let mut game = Game::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+            game.play_sequence(&solution).unwrap();
+            let snapshot = game.snapshot_text();
+
+            let golden = golden_dir
+                .join(path.file_stem().unwrap())
+                .with_extension("snapshot");
+            if update {
+                fs::create_dir_all(&golden_dir).unwrap();
+                fs::write(&golden, snapshot).unwrap();
+            } else {
+                let expected = fs::read_to_string(&golden).unwrap_or_default();
+                if expected != snapshot {
+                    // the first line that differs, counting from 1
+                    let line = expected
+                        .lines()
+                        .zip(snapshot.lines())
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                        + 1;
+                    failures.push(format!("{} (line {line})", golden.display()));
+                }
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        for failure in &failures {
+            println!("[mismatch] {failure}");
+        }
+        panic!(
+            "{} states differ from their golden files (UPDATE_GOLDEN=1 rewrites them)",
+            failures.len()
+        );
+    }
+}
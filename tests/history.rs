@@ -0,0 +1,152 @@
+use parabox::engine::*;
+use std::fs;
+
+fn load(name: &str) -> (Game, Vec<Direction>) {
+    let text = fs::read_to_string(format!("levels/vanilla/{name}.txt")).unwrap();
+    let solution = fs::read_to_string(format!("levels/vanilla/{name}.solution")).unwrap();
+    (
+        Game::parse(&text).unwrap(),
+        parse_sequence(&solution).unwrap(),
+    )
+}
+
+fn state(game: &Game) -> String {
+    format!("{:?} {:?}", game.cells(), game.player_ids())
+}
+
+#[test]
+fn test_timeline_seek() {
+    let (game, moves) = load("enter");
+    let mut timeline = Timeline::with_interval(game.clone(), 4);
+    for direction in &moves {
+        timeline.play(*direction);
+    }
+    assert!(timeline.current().won());
+
+    for n in [0, 3, 4, 5, moves.len(), 9, 2, moves.len() + 10] {
+        let mut expected = game.clone();
+        for direction in &moves[..n.min(moves.len())] {
+            expected.play(*direction);
+        }
+        assert_eq!(state(timeline.seek(n)), state(&expected));
+        assert_eq!(state(&timeline.state_at(n)), state(&expected));
+    }
+}
+
+#[test]
+fn test_timeline_branch() {
+    let (game, moves) = load("enter");
+    let mut timeline = Timeline::with_interval(game, 4);
+    for direction in &moves {
+        timeline.play(*direction);
+    }
+
+    timeline.seek(6);
+    let mut branch = timeline.branch();
+    assert_eq!(branch.len(), 6);
+    assert_eq!(timeline.len(), moves.len());
+
+    branch.play(Direction::Down);
+    assert_eq!(branch.moves()[..6], moves[..6]);
+    assert_eq!(branch.len(), 7);
+    assert_eq!(timeline.len(), moves.len());
+}
+
+#[test]
+fn test_timeline_transactions() {
+    let (game, moves) = load("enter");
+    let mut timeline = Timeline::new(game);
+
+    // moving into the wall below the player changes nothing
+    let outcome = timeline.play(Direction::Down);
+    assert!(!outcome.changed());
+    assert!(timeline.is_empty());
+
+    for direction in &moves[..4] {
+        timeline.play(*direction);
+    }
+    timeline.set_checkpoint();
+    for direction in &moves[4..8] {
+        timeline.play(*direction);
+    }
+
+    let expected = state(&timeline.state_at(4));
+    assert_eq!(state(timeline.restart()), expected);
+    assert!(timeline.undo());
+    assert_eq!(timeline.cursor(), 3);
+    assert_eq!(state(timeline.restart()), state(&timeline.state_at(0)));
+    assert!(timeline.redo());
+    assert_eq!(timeline.len(), 8);
+
+    // no-ops can be kept, e.g. for TAS
+    timeline.set_record_noops(true);
+    timeline.play(Direction::Down);
+    assert_eq!(timeline.len(), 2);
+}
+
+#[test]
+fn test_undo_tree() {
+    let (game, moves) = load("enter");
+    let mut tree = UndoTree::new(game);
+    for direction in &moves[..6] {
+        tree.play(*direction);
+    }
+    assert_eq!(tree.depth(), 6);
+
+    // playing a different move after undoing keeps the old line
+    assert!(tree.undo() && tree.undo());
+    let branch_point = tree.current_node();
+    tree.play(Direction::Down);
+    assert_eq!(tree.depth(), 5);
+    assert_eq!(tree.len(), 8);
+
+    let branches = tree.branches();
+    assert_eq!(branches.len(), 3);
+    assert_eq!(branches[0].moves, moves[..4]);
+    // the branch visited last comes first
+    assert_eq!(branches[1].moves, [Direction::Down]);
+    assert_eq!((branches[2].from, branches[2].level), (branch_point, 1));
+
+    // jumping to the old line makes redo follow it
+    let tip = branches[2].tip;
+    assert_eq!(
+        state(tree.jump(tip)),
+        state(&tree.clone().current().clone())
+    );
+    assert_eq!(tree.moves(), moves[..6]);
+    tree.jump(UndoTree::ROOT);
+    assert_eq!(tree.redo_depth(), 6);
+    while tree.redo() {}
+    assert_eq!(tree.moves(), moves[..6]);
+
+    // replaying a move revisits its node
+    tree.undo();
+    tree.play(moves[5]);
+    assert_eq!(tree.len(), 8);
+}
+
+#[test]
+fn test_undo_tree_deltas() {
+    // long enough to pass a keyframe, and adds cells for infinite exits
+    let (game, moves) = load("1by1_inf");
+    let mut tree = UndoTree::new(game.clone());
+    let mut states = vec![state(&game)];
+    for direction in &moves {
+        if tree.play(*direction).changed() {
+            states.push(state(tree.current()));
+        }
+    }
+    assert!(states.len() > UndoTree::KEYFRAME_INTERVAL);
+
+    while tree.undo() {
+        assert_eq!(state(tree.current()), states[tree.depth()]);
+    }
+    assert_eq!(state(tree.current()), states[0]);
+    while tree.redo() {
+        assert_eq!(state(tree.current()), states[tree.depth()]);
+    }
+
+    let tip = tree.current_node();
+    tree.jump(UndoTree::ROOT);
+    assert_eq!(state(tree.jump(tip)), *states.last().unwrap());
+}
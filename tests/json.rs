@@ -0,0 +1,48 @@
+#![cfg(feature = "json")]
+
+use parabox::engine::*;
+use std::fs;
+
+#[test]
+fn test_json_round_trip() {
+    for entry in fs::read_dir("levels/vanilla").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+
+        let game = Game::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        let json = game.to_json();
+        let restored =
+            Game::from_json(&json).unwrap_or_else(|e| panic!("{}: {e}\n{json}", path.display()));
+        assert_eq!(restored.to_text(), game.to_text(), "{}", path.display());
+    }
+}
+
+#[test]
+fn test_json_schema() {
+    let json = r#"{
+        "version": 4,
+        "shed": true,
+        "objects": [
+            {
+                "type": "block", "x": -1, "y": -1, "id": 0, "width": 3, "height": 3,
+                "hue": 0.6, "sat": 0.8, "val": 1,
+                "children": [
+                    { "type": "wall", "x": 0, "y": 0, "player": 0 },
+                    { "type": "ref", "x": 1, "y": 1, "id": 0, "exit": true },
+                    { "type": "floor", "x": 2, "y": 2, "kind": "Button" },
+                    { "type": "floor", "x": 2, "y": 1, "kind": { "Info": "Hello" } }
+                ]
+            }
+        ]
+    }"#;
+    let game = Game::from_json(json).unwrap();
+    assert!(game.config().shed);
+    assert_eq!(game.player_ids(), &[1]);
+    assert_eq!(game.goals().len(), 1);
+    assert!(game.to_text().contains("\tFloor 2 1 Info Hello"));
+
+    let error = Game::from_json(r#"{ "version": 4, "objects": [{ "type": "wall" }] }"#);
+    assert!(error.unwrap_err().contains("missing field"));
+}
@@ -13,33 +13,10 @@ fn test_level(path: &std::path::PathBuf) -> Result<(), String> {
     }
 
     let text = fs::read_to_string(path).unwrap();
-    let mut game = Game::parse(&text).unwrap();
+    let game = Game::parse(&text).unwrap();
 
     let solution = fs::read_to_string(&solution_path).unwrap();
-
-    let mut steps = 0;
-
-    for c in solution.chars() {
-        let direction = match c {
-            'U' => Direction::Up,
-            'D' => Direction::Down,
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            ' ' | '\n' => continue,
-            _ => return Err(format!("invalid solution character: {c}")),
-        };
-
-        if game.won() {
-            return Err(format!("should not win now after {steps} steps"));
-        }
-
-        game.play(direction);
-        steps += 1;
-    }
-
-    if !game.won() {
-        return Err(format!("should win now after {steps} steps"));
-    }
+    verify_solution(&game, &solution).map_err(|e| e.to_string())?;
 
     Ok(())
 }
@@ -74,3 +51,34 @@ fn test_levels() {
         panic!("{} levels failed", results.len());
     }
 }
+
+#[test]
+fn test_verify_solution() {
+    let game = Game::parse(&fs::read_to_string("levels/vanilla/enter.txt").unwrap()).unwrap();
+    let solution = fs::read_to_string("levels/vanilla/enter.solution").unwrap();
+    let moves = parse_sequence(&solution).unwrap();
+    let sequence = moves.iter().map(|d| d.to_char()).collect::<String>();
+
+    let report = verify_solution(&game, &sequence).unwrap();
+    assert_eq!(report.steps, moves.len());
+    assert!(report.final_state.won());
+
+    match verify_solution(&game, &format!("{sequence}L")) {
+        Err(VerifyError::WonEarly { step, steps }) => {
+            assert_eq!((step, steps), (moves.len(), moves.len() + 1));
+        }
+        result => panic!("unexpected {result:?}"),
+    }
+    match verify_solution(&game, &sequence[..sequence.len() - 1]) {
+        Err(VerifyError::NotWon { final_state, .. }) => assert!(!final_state.won()),
+        result => panic!("unexpected {result:?}"),
+    }
+    assert!(matches!(
+        verify_solution(&game, "RX"),
+        Err(VerifyError::InvalidSequence(_))
+    ));
+    assert!(matches!(
+        verify_solution(&report.final_state, ""),
+        Err(VerifyError::AlreadyWon)
+    ));
+}
@@ -0,0 +1,161 @@
+use parabox::engine::*;
+use parabox::render::*;
+
+const LEVEL: &str = "\
+version 4
+#
+Block -1 -1 0 4 3 0 0 0.8 1 0 0 0 0 0 0 0
+\tWall 0 0 0 0 0
+\tBlock 1 1 1 1 1 0.6 0.8 1 1 1 1 0 0 0 0 0
+\tBlock 2 1 2 2 2 0.3 0.8 1 1 0 0 0 0 0 0 0
+\t\tWall 1 1 0 0 0
+\tRef 3 2 2 0 0 0 0 0 0 0 0 0 0 0 0
+\tFloor 3 0 Button
+";
+
+#[test]
+fn test_render_ascii() {
+    let game = Game::parse(LEVEL).unwrap();
+    assert_eq!(
+        render_ascii(&game),
+        "[0]\n...2\n.p2.\n#.._\n\n[2]\n.#\n..\n"
+    );
+
+    let mut renderer = AsciiRenderer {
+        options: GlyphOptions {
+            preview: true,
+            ..Default::default()
+        },
+    };
+    let text = renderer.render(&game).unwrap();
+    assert_eq!(text.lines().next_back(), Some(".."));
+    assert!(text.starts_with("[0]\n...▝\n"));
+}
+
+#[test]
+fn test_render_svg() {
+    let game = Game::parse(LEVEL).unwrap();
+    let svg = render_svg(&game);
+
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    // one panel for each non-trivial block
+    assert_eq!(svg.matches("<text").count(), 2);
+    // the player has eyes
+    assert_eq!(svg.matches("<circle").count(), 2);
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn test_render_png() {
+    let game = Game::parse(LEVEL).unwrap();
+    let renderer = PngRenderer {
+        cell_size: 10,
+        depth: 0,
+        ..Default::default()
+    };
+    let image = renderer.rasterize(&game);
+
+    // two panels of 4 cells, with margins
+    assert_eq!((image.width, image.height), (2 * 48 + 8, 48 + 8));
+    assert_eq!(
+        image.pixels.len(),
+        (image.width * image.height * 4) as usize
+    );
+
+    let data = PngRenderer::new().render(&game).unwrap();
+    assert!(data.starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn test_render_nested() {
+    let game = Game::parse(LEVEL).unwrap();
+    let mut renderer = AsciiRenderer::new();
+    renderer.options.depth = 1;
+
+    // block 2 and the reference to it show the wall inside, the player
+    // keeps its mark
+    assert_eq!(
+        renderer.render(&game).unwrap(),
+        "\
+[0]
+..............##
+..............##
+................
+................
+....pppp..##....
+....pppp..##....
+....pppp........
+....pppp........
+####........____
+####........____
+####........____
+####........____
+"
+    );
+}
+
+#[test]
+fn test_layout() {
+    let game = Game::parse(LEVEL).unwrap();
+
+    // the title is wider than block 2
+    let layout = Layout::new(&game, 80);
+    let sizes = layout
+        .panels
+        .iter()
+        .map(|panel| (panel.x, panel.y, panel.width, panel.height))
+        .collect::<Vec<_>>();
+    assert_eq!(sizes, [(0, 0, 6, 5), (6, 0, 5, 4)]);
+    assert_eq!((layout.width, layout.height), (11, 5));
+
+    // panels wrap when the screen is too narrow, and can be scrolled to
+    let layout = Layout::new(&game, 8);
+    assert_eq!((layout.width, layout.height), (6, 9));
+    let mut viewport = Viewport {
+        width: 8,
+        height: 4,
+        ..Default::default()
+    };
+    viewport.scroll(0, 100, &layout);
+    assert_eq!((viewport.x, viewport.y), (0, 5));
+    assert_eq!(viewport.to_screen(1, 6), Some((1, 1)));
+    assert_eq!(viewport.to_screen(1, 4), None);
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn test_kitty_image() {
+    let data = vec![0xAB; 5000];
+    let escapes = kitty_image(&data, 40, 20);
+
+    // the image is sent in chunks, with the options in the first one
+    assert_eq!(escapes.matches("\x1b_G").count(), 2);
+    assert!(escapes.starts_with("\x1b_Ga=T,f=100,i=1,q=2,C=1,c=40,r=20,m=1;q6urq6"));
+    assert!(escapes.ends_with("q6s=\x1b\\"));
+}
+
+#[test]
+fn test_theme() {
+    let theme = Theme::parse(
+        "\
+# walls are drawn differently
+preset = monochrome
+wall = X
+block_colors = palette d55e00 f0e442 009e73 56b4e9 0072b2 cc79a7
+",
+    )
+    .unwrap();
+    assert_eq!(theme.empty_color, Color::WHITE);
+    assert_eq!(
+        theme.block_color(color_space::Hsv::new(200.0, 0.8, 1.0)),
+        Color::new(0x56, 0xb4, 0xe9)
+    );
+    assert!(Theme::parse("wall = ##").is_err());
+    assert!(Theme::parse("block_colors = mono red").is_err());
+
+    let game = Game::parse(LEVEL).unwrap();
+    let mut renderer = AsciiRenderer::new();
+    renderer.options.theme = theme;
+    assert!(renderer.render(&game).unwrap().contains("\nX.._\n"));
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "serde")]
+
+use parabox::engine::*;
+use std::fs;
+
+#[test]
+fn test_serde_round_trip() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let mut game = Game::parse(&text).unwrap();
+    game.play_sequence("RUUUL").unwrap();
+
+    // compare as values, since the order of map entries is unspecified
+    let json = serde_json::to_string(&game).unwrap();
+    let mut restored: Game = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        serde_json::to_value(&restored).unwrap(),
+        serde_json::to_value(&game).unwrap()
+    );
+
+    game.play_sequence("URRRR").unwrap();
+    restored.play_sequence("URRRR").unwrap();
+    assert_eq!(
+        serde_json::to_value(&restored).unwrap(),
+        serde_json::to_value(&game).unwrap()
+    );
+}
+
+#[test]
+fn test_capabilities_json() {
+    let capabilities = capabilities();
+    let json: serde_json::Value = serde_json::from_str(&capabilities.to_json()).unwrap();
+    assert_eq!(json, serde_json::to_value(&capabilities).unwrap());
+    assert!(capabilities.features.contains(&"serde"));
+}
@@ -0,0 +1,241 @@
+use parabox::engine::*;
+use std::fs;
+
+#[test]
+fn test_round_trip() {
+    for entry in fs::read_dir("levels/vanilla").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+
+        let game = Game::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        let text = game.to_text();
+        let mut reparsed =
+            Game::parse(&text).unwrap_or_else(|e| panic!("{}: {e}\n{text}", path.display()));
+        assert_eq!(reparsed.state_key(), game.state_key(), "{}", path.display());
+        assert_eq!(reparsed.to_text(), text, "{}", path.display());
+
+        // the written level plays the same
+        if let Ok(solution) = fs::read_to_string(path.with_extension("solution")) {
+            let outcome = reparsed.play_sequence(&solution).unwrap();
+            assert!(outcome.won_at_end(), "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn test_properties() {
+    let text = "version 4\nproperty hint Push the box  right \nproperty music 3\n#\n";
+    let game = Game::parse(text).unwrap();
+    assert_eq!(game.meta().property("hint"), Some("Push the box  right"));
+    assert_eq!(game.meta().property("music"), Some("3"));
+
+    let reparsed = Game::parse(&game.to_text()).unwrap();
+    assert_eq!(reparsed.meta(), game.meta());
+}
+
+#[test]
+fn test_floors() {
+    let text = "\
+version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tWall 0 0 1 0 0
+\tFloor 1 1 Button
+\tFloor 2 2 Info Push the box
+\tFloor 3 3 Portal hub
+\tFloor 4 4 FastTravel
+";
+    let game = Game::parse(text).unwrap();
+    assert_eq!(game.goals().len(), 1);
+    assert_eq!(game.floors().len(), 4);
+    assert_eq!(
+        game.floors()[1].kind,
+        FloorKind::Info("Push the box".to_string())
+    );
+
+    let reparsed = Game::parse(&game.to_text()).unwrap();
+    assert_eq!(reparsed.floors(), game.floors());
+    assert!(Game::parse(&text.replace("FastTravel", "Teleport")).is_err());
+
+    // the directions of one-way gates may be spaced out
+    let one_way = FloorKind::OneWay(vec![Direction::Up, Direction::Right]);
+    assert_eq!(FloorKind::parse(&["OneWay", "UR"]), Ok(one_way.clone()));
+    assert_eq!(FloorKind::parse(&["OneWay", "U", "R"]), Ok(one_way.clone()));
+    let game = Game::parse(&text.replace("FastTravel", "OneWay U R")).unwrap();
+    assert_eq!(game.floors()[3].kind, one_way);
+    assert!(game.to_text().contains("Floor 4 4 OneWay UR"));
+    let error = FloorKind::parse(&["OneWay", "U", "X"]).unwrap_err();
+    assert_eq!(error, "Invalid direction X");
+    assert!(FloorKind::parse(&["OneWay"]).is_err());
+}
+
+#[test]
+fn test_zoom_and_effects() {
+    let text = "\
+version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tBlock 1 1 1 3 3 0.6 0.8 1 1.5 0 0 0 0 0 0 0
+\tRef 2 2 1 0 0 0 0 0 0 0 0 0 0 0 4
+";
+    let game = Game::parse(text).unwrap();
+    let block = game.block_by_no(BlockNo(1)).unwrap();
+    assert_eq!(block.zoom, 1.5);
+    assert!(game.to_text().contains("Block 1 1 1 3 3 0.6 0.8 1 1.5 0"));
+
+    let reference = game.cells().iter().find(|cell| cell.reference().is_some());
+    assert_eq!(reference.unwrap().effect(), SpecialEffect::Effect(4));
+    assert!(game.to_text().contains("Ref 2 2 1 0 0 0 0 0 0 0 0 0 0 0 4"));
+}
+
+#[test]
+fn test_tolerant_parsing() {
+    let text = "version 4 \n// a comment\n#\t\n-- the top block\nBlock -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0\n  Block 1 1 1 3 3 0.6 0.8 1 1 0 0 0 0 0 0 0  \n    Wall 0 0 1 0 0\n  Floor 3 3 Button\n";
+    let game = Game::parse(text).unwrap();
+    let tabs = game.to_text();
+    assert_eq!(Game::parse(&tabs).unwrap().to_text(), tabs);
+    assert!(tabs.contains("\t\tWall 0 0 1 0 0"));
+
+    // line endings and byte order marks of files saved on other systems
+    for text in [
+        text.replace('\n', "\r\n"),
+        text.replace('\n', "\r"),
+        format!("\u{feff}{text}"),
+    ] {
+        assert_eq!(Game::parse(&text).unwrap().to_text(), tabs);
+    }
+
+    let error = Game::parse(&text.replace("    Wall", "   Wall")).unwrap_err();
+    assert!(error.contains("not a multiple of 2"), "{error}");
+    let error = Game::parse(&text.replace("0.8 1 1", "0.8 x 1")).unwrap_err();
+    assert!(
+        error.starts_with("Invalid value x in field 8 of Block"),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_block_names() {
+    let text = "\
+version 5
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tRef 1 1 @lobby 1 0 0 0 0 0 0 0 0 0 0 0
+\tBlock 3 3 @lobby 3 3 0.6 0.8 1 1 0 0 0 0 0 0 0
+\t\tRef 1 1 0 0 0 0 1 1 @lobby 0 0 0 0 0 0
+";
+    let game = Game::parse(text).unwrap();
+    assert_eq!(game.block_by_no(BlockNo(1)).unwrap().width, 3);
+    let top = game.block_by_no(BlockNo(0)).unwrap();
+    assert_eq!(top.inf_enter, Some((BlockNo(1), 1)));
+    assert_eq!(game.meta().block_label(BlockNo(1)), "@lobby");
+    assert_eq!(game.to_text(), text);
+
+    let error = Game::parse(&text.replace("version 5", "version 4")).unwrap_err();
+    assert!(
+        error.starts_with("Block name @lobby requires version 5"),
+        "{error}"
+    );
+    let error = Game::parse(&text.replace("Ref 1 1 @lobby", "Ref 1 1 @hall")).unwrap_err();
+    assert!(
+        error.starts_with("Invalid reference target @hall"),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_level_meta() {
+    let text = "version 4\ntitle The Lobby\nauthor Someone Else\npar_moves 12\ntags tutorial, recursion\n#\n";
+    let game = Game::parse(text).unwrap();
+    let meta = game.meta();
+    assert_eq!(meta.title.as_deref(), Some("The Lobby"));
+    assert_eq!(meta.author.as_deref(), Some("Someone Else"));
+    assert_eq!(meta.par_moves, Some(12));
+    assert_eq!(meta.tags, ["tutorial", "recursion"]);
+
+    let reparsed = Game::parse(&game.to_text()).unwrap();
+    assert_eq!(reparsed.meta(), game.meta());
+}
+
+#[test]
+fn test_rotation() {
+    let text = fs::read_to_string("spec/rotation.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+    assert_eq!(game.to_text(), text);
+
+    // the field is ignored unless enabled
+    let game = Game::parse(&text.replace("rotation\n", "")).unwrap();
+    assert!(game.cells().iter().all(|cell| cell.rotation() == 0));
+}
+
+#[test]
+fn test_locked() {
+    let text = fs::read_to_string("spec/locked.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+    assert_eq!(game.cells().iter().filter(|cell| cell.locked()).count(), 2);
+    assert_eq!(game.to_text(), text);
+}
+
+#[test]
+fn test_shared_space() {
+    let text = "\
+version 4
+space_size 5 3
+shared_space
+#
+Block 1 1 0 3 3 0 0 0.8 1 0 1 0 0 0 1 0
+Block 3 1 1 1 1 0.6 0.8 1 1 1 0 0 0 0 1 0
+";
+    let game = Game::parse(text).unwrap();
+    let spaces = game.cells().iter().map(|cell| cell.gpos().block_id());
+    assert_eq!(spaces.collect::<Vec<_>>(), [None, Some(0), Some(0)]);
+    assert_eq!(game.cells()[0].block().unwrap().width, 5);
+    assert_eq!(game.cells()[2].gpos().pos, Pos(3, 1));
+    assert_eq!(game.to_text(), text);
+
+    // the cells must fit in the space
+    assert!(Game::parse(&text.replace("Block 3 1", "Block 5 1")).is_err());
+}
+
+#[test]
+fn test_custom_levels() {
+    let dir = std::env::temp_dir().join(format!("parabox-custom-{}", std::process::id()));
+    fs::create_dir_all(dir.join("pack")).unwrap();
+    let level =
+        "version 4\r\ncustom_level_music -1\r\n#\r\nBlock -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0\r\n";
+    fs::write(dir.join("a.txt"), level).unwrap();
+    fs::write(dir.join("pack/b.txt"), format!("\u{feff}{level}")).unwrap();
+    fs::write(dir.join("notes.txt"), "not a level").unwrap();
+
+    let levels = custom_levels::list_custom_levels(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(levels, [dir.join("a.txt"), dir.join("pack/b.txt")]);
+    assert!(custom_levels::is_level_text(level));
+}
+
+#[test]
+fn test_official_export() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+    let options = custom_levels::ExportOptions::default();
+    let official = custom_levels::to_official_text(&game, &options).unwrap();
+    assert!(official.starts_with("version 4\ncustom_level_music -1\ncustom_level_palette -1\n#\n"));
+    assert_eq!(
+        Game::parse(&official).unwrap().state_key(),
+        game.state_key()
+    );
+
+    let dir = std::env::temp_dir().join(format!("parabox-export-{}", std::process::id()));
+    let path = custom_levels::export_custom_level(&game, &dir, "enter", &options, false).unwrap();
+    let written = fs::read_to_string(&path).unwrap();
+    let again = custom_levels::export_custom_level(&game, &dir, "enter", &options, false);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(written, official);
+    assert!(again.is_err());
+
+    let gravity = Game::parse(&text.replacen("#\n", "gravity\n#\n", 1)).unwrap();
+    let error = custom_levels::to_official_text(&gravity, &options).unwrap_err();
+    assert!(error.contains("gravity"), "{error}");
+}
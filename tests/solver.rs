@@ -0,0 +1,166 @@
+use parabox::engine::{solver::*, *};
+use std::fs;
+
+#[test]
+fn test_solve() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+
+    let mut lengths = Vec::new();
+    for strategy in [Strategy::Bfs, Strategy::AStar, Strategy::Greedy] {
+        let config = SolverConfig {
+            strategy,
+            ..Default::default()
+        };
+        let report = solve(&game, &config);
+        let solution = report.solution.unwrap();
+        assert!(!report.exhausted);
+        assert!(verify_solution(&game, &format_sequence(&solution)).is_ok());
+        lengths.push(solution.len());
+    }
+    // breadth-first search finds a shortest solution
+    assert_eq!(lengths.iter().min(), Some(&lengths[0]));
+
+    // the search gives up after expanding `max_nodes` states
+    let config = SolverConfig {
+        max_nodes: 3,
+        ..Default::default()
+    };
+    let report = solve(&game, &config);
+    assert!(report.solution.is_none() && !report.exhausted);
+    assert_eq!(report.nodes, 3);
+
+    // the box is stuck in the corner, away from its goal
+    let text = "\
+version 4
+#
+Block -1 -1 0 5 5 0.6 0.8 1 1 0 0 0 0 0 0 0
+\tFloor 3 3 Button
+\tBlock 2 2 1 1 1 0.1 0.8 1 1 1 1 0 0 0 0 0
+\tBlock 0 0 2 1 1 0.3 0.8 1 1 1 0 0 0 0 0 0
+";
+    let game = Game::parse(text).unwrap();
+    for strategy in [Strategy::Bfs, Strategy::AStar, Strategy::Greedy] {
+        let config = SolverConfig {
+            strategy,
+            ..Default::default()
+        };
+        let report = solve(&game, &config);
+        assert!(report.solution.is_none() && report.exhausted);
+    }
+}
+
+#[test]
+fn test_parallel_solve() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+
+    for strategy in [Strategy::Bfs, Strategy::AStar, Strategy::Greedy] {
+        let config = SolverConfig {
+            strategy,
+            threads: 4,
+            ..Default::default()
+        };
+        let solution = solve(&game, &config).solution.unwrap();
+
+        let mut game = game.clone();
+        for direction in solution {
+            game.play(direction);
+        }
+        assert!(game.won());
+    }
+}
+
+#[test]
+fn test_solution_table() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+    let config = SolverConfig::default();
+
+    let mut table = SolutionTable::new();
+    let solution = table.solve(&game, &config).solution.unwrap();
+
+    // states along the solution are answered from the table
+    let mut state = game.clone();
+    state.play(solution[0]);
+    let report = table.solve(&state, &config);
+    assert_eq!(report.nodes, 0);
+    assert_eq!(report.solution.unwrap(), solution[1..]);
+}
+
+#[test]
+fn test_dead_ends() {
+    // no state along a solution is a dead end
+    for entry in fs::read_dir("levels/vanilla").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+        let Ok(solution) = fs::read_to_string(path.with_extension("solution")) else {
+            continue;
+        };
+        let mut game = Game::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        for direction in parse_sequence(&solution).unwrap() {
+            assert!(!game.is_dead_end(), "{}", path.display());
+            game.play(direction);
+        }
+    }
+
+    // a box stuck in a corner off the only goal
+    let text = "version 4\n#\nBlock -1 -1 0 4 4 0 0 0.8 1 0 0 0 0 0 0 0\n\tWall 0 0 0 0 0\n\tWall 1 0 0 0 0\n\tWall 0 1 0 0 0\n\tBlock 1 1 1 1 1 0.1 0.8 1 1 1 0 0 0 0 0 0\n\tBlock 3 3 2 1 1 0.6 0.8 1 1 1 1 0 0 0 0 0\n\tFloor 2 2 Button\n";
+    let game = Game::parse(text).unwrap();
+    assert!(game.is_dead_end());
+
+    // with gravity, a box in a corner of walls above and to the left falls
+    // once the box holding it up is pushed away
+    let text = "\
+version 4
+gravity
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tWall 0 1 0 0 0
+\tWall 1 2 0 0 0
+\tBlock 0 0 1 1 1 0.9 1 0.7 1 1 1 1 0 0 0 0
+\tBlock 1 1 2 1 1 0.1 0.8 1 1 1 0 0 0 0 0 0
+\tBlock 1 0 3 1 1 0.6 0.8 1 1 1 0 0 0 0 0 0
+\tFloor 1 0 Button
+\tFloor 3 0 Button
+";
+    let game = Game::parse(text).unwrap();
+    assert!(!game.is_dead_end());
+    let solution = solve(&game, &SolverConfig::default()).solution.unwrap();
+    assert_eq!(solution, [Direction::Right; 2]);
+}
+
+#[test]
+fn test_check_level() {
+    let config = batch::BatchConfig::default();
+
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    assert!(matches!(
+        batch::check_level(&text, &config),
+        batch::Verdict::Solved { moves: 28, .. }
+    ));
+
+    // the player is right next to the goal
+    let text = "version 4\n#\nBlock -1 -1 0 3 3 0 0 0.8 1 0 0 0 0 0 0 0\n\tBlock 0 0 1 1 1 0.6 0.8 1 1 1 1 0 0 0 0 0\n\tFloor 1 0 PlayerButton\n";
+    assert_eq!(
+        batch::check_level(text, &config),
+        batch::Verdict::Trivial { moves: 1 }
+    );
+
+    // walling off the goal while editing breaks the level
+    let mut game = Game::parse(text).unwrap();
+    let root = game.block_by_no(BlockNo(0)).unwrap().id;
+    game.add_wall(Wall {
+        id: 0,
+        gpos: GlobalPos::new(root, Pos(1, 0)),
+        possessable: false,
+        fliph: false,
+    })
+    .unwrap();
+    assert!(matches!(
+        batch::check_game(&game, &config),
+        batch::Verdict::Unsolvable { .. }
+    ));
+}
@@ -0,0 +1,35 @@
+use parabox::engine::{spec::Spec, Game};
+use std::{ffi::OsStr, fs};
+
+#[test]
+fn test_specs() {
+    let mut failures = Vec::new();
+
+    for entry in fs::read_dir("spec").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some(OsStr::new("spec")) {
+            continue;
+        }
+
+        let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+        let spec = Spec::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        let level = fs::read_to_string(path.with_extension("txt")).unwrap();
+        let game = Game::parse(&level).unwrap();
+
+        for result in spec.run(game) {
+            if let Some(actual) = result.failure {
+                failures.push(format!(
+                    "[{name}:{}] {} ({actual})",
+                    result.line, result.text
+                ));
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        for failure in &failures {
+            println!("{failure}");
+        }
+        panic!("{} expectations failed", failures.len());
+    }
+}
@@ -0,0 +1,312 @@
+use parabox::engine::*;
+use std::fs;
+
+#[test]
+fn test_state_key() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+
+    // moving away and back reaches the same state
+    let mut other = game.clone();
+    other.play_sequence("RL").unwrap();
+    assert_eq!(other.state_key(), game.state_key());
+
+    other.play(Direction::Right);
+    assert_ne!(other.state_key(), game.state_key());
+}
+
+#[test]
+fn test_snapshot() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let mut game = Game::parse(&text).unwrap();
+
+    let snapshot = game.snapshot();
+    let key = game.state_key();
+    game.play(Direction::Right);
+
+    // the snapshot is unaffected by later moves, even on another thread
+    let handle = std::thread::spawn(move || snapshot.state_key());
+    assert_eq!(handle.join().unwrap(), key);
+    assert_ne!(game.state_key(), key);
+}
+
+#[test]
+fn test_occupancy() {
+    for name in ["enter", "1by1_inf"] {
+        let text = fs::read_to_string(format!("levels/vanilla/{name}.txt")).unwrap();
+        let solution = fs::read_to_string(format!("levels/vanilla/{name}.solution")).unwrap();
+        let mut game = Game::parse(&text).unwrap();
+
+        for direction in parse_sequence(&solution).unwrap() {
+            game.play(direction);
+            for block in game.cells().iter().filter_map(|cell| cell.block()) {
+                let occupancy = game.occupancy(block.id).unwrap();
+                let cells = game
+                    .cells()
+                    .iter()
+                    .filter(|cell| cell.gpos().is_in(block.id));
+                assert_eq!(occupancy.count(), cells.clone().count(), "{name}");
+                assert!(cells.into_iter().all(|cell| occupancy.get(cell.gpos().pos)));
+            }
+
+            // cached triviality matches a game without the cache
+            let mut fresh = game.clone();
+            fresh.restore(&game.pack(None));
+            for block in game.cells().iter().filter_map(|cell| cell.block()) {
+                assert_eq!(game.is_block_trivial(block), fresh.is_block_trivial(block));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_packed_state() {
+    let text = fs::read_to_string("levels/vanilla/1by1_inf.txt").unwrap();
+    let solution = fs::read_to_string("levels/vanilla/1by1_inf.solution").unwrap();
+    let initial = Game::parse(&text).unwrap();
+
+    let mut game = initial.clone();
+    let mut states = vec![(game.pack(None), game.state_key())];
+    for direction in parse_sequence(&solution).unwrap() {
+        game.play(direction);
+        let packed = game.pack(Some(&states.last().unwrap().0));
+        states.push((packed, game.state_key()));
+    }
+
+    // unchanged chunks are shared with the previous state
+    let (first, second) = (&states[0].0, &states[1].0);
+    assert!(second.shared_chunks(first) > 0);
+
+    let mut restored = initial.clone();
+    for (packed, key) in states.iter().rev() {
+        restored.restore(packed);
+        assert_eq!(&restored.state_key(), key);
+    }
+    restored.restore(&states.last().unwrap().0);
+    assert!(restored.won());
+}
+
+#[test]
+fn test_is_player() {
+    let text = fs::read_to_string("levels/vanilla/possess_first.txt").unwrap();
+    let solution = fs::read_to_string("levels/vanilla/possess_first.solution").unwrap();
+    let mut game = Game::parse(&text).unwrap();
+
+    let check = |game: &Game| {
+        for cell in game.cells() {
+            assert_eq!(
+                game.is_player(cell.id()),
+                game.player_ids().contains(&cell.id())
+            );
+        }
+    };
+    let initial = game.clone();
+    for direction in parse_sequence(&solution).unwrap() {
+        let possessed = !game.play(direction).possessed.is_empty();
+        check(&game);
+        if possessed {
+            // restoring an earlier state moves the flags back
+            let mut restored = game.clone();
+            restored.restore(&initial.pack(None));
+            check(&restored);
+        }
+    }
+}
+
+#[test]
+fn test_encode_state() {
+    // the first level synthesizes an infinite exit
+    for name in ["iexit_just_push_out", "possess_first"] {
+        let text = fs::read_to_string(format!("levels/vanilla/{name}.txt")).unwrap();
+        let solution = fs::read_to_string(format!("levels/vanilla/{name}.solution")).unwrap();
+        let moves = parse_sequence(&solution).unwrap();
+        let mut game = Game::parse(&text).unwrap();
+
+        for (i, direction) in moves.iter().enumerate() {
+            game.play(*direction);
+            let encoded = game.encode_state();
+            assert!(encoded.len() < game.cells().len() * 3, "{name}");
+
+            // the schema can come from any state of the level
+            let mut decoded = game.state_schema().decode(&encoded).unwrap();
+            assert_eq!(decoded.state_key(), game.state_key(), "{name}");
+            assert_eq!(decoded.encode_state(), encoded);
+            if i + 1 == moves.len() {
+                assert!(decoded.won());
+            } else {
+                decoded.play(moves[i + 1]);
+            }
+        }
+    }
+
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let schema = Game::parse(&text).unwrap().state_schema();
+    assert!(schema.decode(&[]).is_err());
+}
+
+#[test]
+fn test_collect_synthesized() {
+    // pushing the box right makes the reference exit itself infinitely
+    let text = "\
+version 4
+#
+Block -1 -1 0 5 5 0.6 0.8 1 1 0 0 0 0 0 0 0
+\tRef 4 2 0 1 0 0 0 0 -1 0 0 0 0 0 0
+\tBlock 3 2 1 1 1 0.1 0.8 1 1 1 1 0 0 0 0 0
+";
+    let mut game = Game::parse(text).unwrap();
+    let cells = game.cells().len();
+
+    // the infinite exit is kept while the reference is next to it
+    let mut moved = game.clone();
+    moved.play(Direction::Right);
+    assert_eq!(moved.cells().len(), cells + 2);
+    assert_eq!(moved.collect_synthesized(), 0);
+
+    // running out of fuel after adding the infinite exit leaves it unused
+    game.config_mut().fuel = Some(5);
+    let outcome = game.play(Direction::Right);
+    assert!(outcome.out_of_fuel());
+    assert_eq!(game.cells().len(), cells);
+}
+
+#[test]
+fn test_fuel() {
+    let text = "\
+version 4
+#
+Block -1 -1 0 5 5 0.6 0.8 1 1 0 0 0 0 0 0 0
+\tBlock 1 2 1 1 1 0.1 0.8 1 1 1 1 0 0 0 0 0
+";
+    let game = Game::parse(text).unwrap();
+    let play = |fuel| {
+        let mut game = game.clone();
+        game.config_mut().fuel = Some(fuel);
+        game.play(Direction::Right)
+    };
+
+    // walking takes two steps, moving the player and leaving its position
+    assert_eq!(play(2).moved, [1]);
+    for (budget, depth) in [(0, 0), (1, 1)] {
+        let outcome = play(budget);
+        assert!(!outcome.changed());
+        assert_eq!(outcome.failures, [MoveFailure::OutOfFuel { budget, depth }]);
+    }
+}
+
+#[test]
+fn test_legal_moves() {
+    let text = fs::read_to_string("levels/vanilla/enter.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+
+    // the player starts with walls below and to the left
+    assert_eq!(game.legal_moves(), [Direction::Up, Direction::Right]);
+}
+
+#[test]
+fn test_invariants() {
+    for entry in fs::read_dir("levels/vanilla").unwrap() {
+        let path = entry.unwrap().path();
+        let Ok(solution) = fs::read_to_string(path.with_extension("solution")) else {
+            continue;
+        };
+        let mut game = Game::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(game.check_invariants(), [], "{}", path.display());
+        for (i, direction) in parse_sequence(&solution).unwrap().into_iter().enumerate() {
+            game.play(direction);
+            let violations = game.check_invariants();
+            assert!(
+                violations.is_empty(),
+                "{} after {} moves: {}",
+                path.display(),
+                i + 1,
+                violations[0]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_structure_graph() {
+    let text = fs::read_to_string("levels/vanilla/iienter.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+    let graph = game.structure_graph();
+
+    // one node per block, without the spaces the floating blocks are in
+    let mut block_nos = graph
+        .nodes
+        .iter()
+        .map(|node| node.block_no)
+        .collect::<Vec<_>>();
+    block_nos.sort();
+    assert_eq!(block_nos, (0..6).map(BlockNo).collect::<Vec<_>>());
+    let count = |kind: fn(EdgeKind) -> bool| graph.edges.iter().filter(|e| kind(e.kind)).count();
+    assert_eq!(count(|kind| kind == EdgeKind::Contains), 6);
+    assert_eq!(count(|kind| kind == EdgeKind::Reference), 7);
+    assert_eq!(count(|kind| matches!(kind, EdgeKind::InfEnter { .. })), 3);
+    assert!(graph.edges.contains(&StructureEdge {
+        from: Some(BlockNo(0)),
+        to: BlockNo(0),
+        kind: EdgeKind::Exit,
+    }));
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph structure {"));
+    assert!(dot.contains("b4 -> b0 [style=dotted, color=blue, label=\"inf enter 2\"];"));
+    let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+    assert_eq!(json["nodes"].as_array().unwrap().len(), graph.nodes.len());
+    assert_eq!(json["edges"].as_array().unwrap().len(), graph.edges.len());
+}
+
+#[test]
+fn test_move_outcome() {
+    let level = |cells: &str| {
+        let text = format!("version 4\n#\nBlock -1 -1 0 5 5 0.6 0.8 1 1 0 0 0 0 0 0 0\n{cells}");
+        Game::parse(&text).unwrap()
+    };
+    let player = "\tBlock 1 2 1 1 1 0.1 0.8 1 1 1 1 0 0 0 0 0\n";
+
+    let outcome = level(player).play(Direction::Right);
+    assert_eq!(outcome.moved, [1]);
+    assert!(outcome.possessed.is_empty() && outcome.failures.is_empty());
+
+    let outcome = level(&format!("{player}\tWall 2 2 0 0 0\n")).play(Direction::Right);
+    assert!(!outcome.changed());
+    assert_eq!(
+        outcome.explanation(),
+        Some(&MoveFailure::Wall {
+            block_no: BlockNo(0),
+            pos: Pos(2, 2)
+        })
+    );
+
+    let outcome = level("\tBlock 0 2 1 1 1 0.1 0.8 1 1 1 1 0 0 0 0 0\n").play(Direction::Left);
+    assert_eq!(
+        outcome.failures,
+        [MoveFailure::NoExit {
+            block_no: BlockNo(0)
+        }]
+    );
+
+    // a filled box against a wall can't be pushed or entered
+    let filled = "\tBlock 2 2 2 1 1 0.3 0.8 1 1 1 0 0 0 0 0 0\n\tWall 3 2 0 0 0\n";
+    let outcome = level(&format!("{player}{filled}")).play(Direction::Right);
+    assert!(!outcome.changed());
+    assert!(outcome.failures.contains(&MoveFailure::EnterRefused {
+        block_no: BlockNo(2),
+        reason: EnterRefusal::Filled
+    }));
+
+    // unless it can be possessed
+    let possessable = "\tBlock 2 2 2 1 1 0.3 0.8 1 1 1 0 1 0 0 0 0\n\tWall 3 2 0 0 0\n";
+    let outcome = level(&format!("{player}{possessable}")).play(Direction::Right);
+    assert_eq!(outcome.possessed, [(1, 2)]);
+    assert!(outcome.moved.is_empty() && outcome.failures.is_empty());
+
+    // a player that is blocked doesn't make a move of another player fail
+    let text = format!("{player}\tBlock 1 3 2 1 1 0.1 0.8 1 1 1 1 0 0 0 0 0\n\tWall 2 3 0 0 0\n");
+    let outcome = level(&text).play(Direction::Right);
+    assert!(outcome.changed());
+    assert_eq!(outcome.moved, [1]);
+    assert_eq!(outcome.failures.len(), 2);
+}
@@ -0,0 +1,233 @@
+use parabox::engine::*;
+use std::{fs, path::Path};
+
+// the decisions made while playing the solutions, in `<level>.trace`
+const TRACE_DIR: &str = "tests/traces";
+
+// vanilla levels whose solutions are recorded, covering each kind of decision
+const TRACED_LEVELS: &[&str] = &[
+    "first_puzzle",
+    "enter",
+    "hungry",
+    "possess_first",
+    "flip_first",
+    "shed_first",
+    "inpush_intro",
+    "vanilla_inf_exit",
+    "iienter",
+    "cycle_line",
+];
+
+/// Replays the recorded traces against the simulator and reports the first
+/// decision that differs for each level, which points at the exact push,
+/// enter, eat or possess attempt a refactor changed. Run with
+/// `UPDATE_TRACES=1` to record the traces again after an intended change.
+#[test]
+fn test_traces() {
+    let update = std::env::var_os("UPDATE_TRACES").is_some();
+    let mut failures = Vec::new();
+
+    for name in TRACED_LEVELS {
+        let level = Path::new("levels/vanilla").join(name);
+        let text = fs::read_to_string(level.with_extension("txt")).unwrap();
+        let game = Game::parse(&text).unwrap();
+        let path = Path::new(TRACE_DIR).join(name).with_extension("trace");
+
+        if update {
+            let solution = fs::read_to_string(level.with_extension("solution")).unwrap();
+            let moves = parse_sequence(&solution).unwrap();
+            fs::create_dir_all(TRACE_DIR).unwrap();
+            fs::write(&path, Trace::record(&game, &moves).to_string()).unwrap();
+        } else {
+            let recorded = fs::read_to_string(&path).unwrap();
+            if let Some(mismatch) = diff_trace(&game, &recorded).unwrap() {
+                failures.push(format!("{}: {mismatch}", path.display()));
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        for failure in &failures {
+            println!("[mismatch] {failure}");
+        }
+        panic!(
+            "{} traces differ from the simulator (UPDATE_TRACES=1 records them again)",
+            failures.len()
+        );
+    }
+}
+
+#[test]
+fn test_trace_events() {
+    let mut game =
+        Game::parse(&fs::read_to_string("levels/vanilla/first_puzzle.txt").unwrap()).unwrap();
+    let player = game.player_ids()[0];
+    let mut untraced = game.clone();
+    let (outcome, events) = game.play_traced(Direction::Right);
+    assert_eq!(outcome, untraced.play(Direction::Right));
+    assert_eq!(game.snapshot_text(), untraced.snapshot_text());
+
+    // the trace starts with the player's own move, and doesn't change the
+    // outcome of the move
+    assert_eq!(
+        events[0].kind,
+        TraceKind::Move {
+            cell_id: player,
+            direction: Direction::Right
+        }
+    );
+    assert_eq!(events[0].depth, 0);
+    assert_eq!(events[0].success, outcome.changed());
+    assert!(events.iter().skip(1).all(|event| event.depth > 0));
+
+    // a recording with a changed decision is reported at that line
+    let recorded = Trace::record(&game, &[Direction::Left, Direction::Up]).to_string();
+    assert_eq!(diff_trace(&game, &recorded).unwrap(), None);
+    let changed = recorded.replacen(" ok\n", " fail\n", 1);
+    let mismatch = diff_trace(&game, &changed).unwrap().unwrap();
+    let line = recorded
+        .lines()
+        .position(|line| line.ends_with(" ok"))
+        .unwrap()
+        + 1;
+    assert_eq!(mismatch.line, line);
+    assert_eq!(mismatch.step, 1);
+}
+
+#[test]
+fn test_explain_move() {
+    // the player stands left of a wall
+    let text = "version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tWall 2 0 0 0 0
+\tWall 2 1 0 0 0
+\tWall 2 2 0 0 0
+\tWall 2 3 0 0 0
+\tWall 2 4 0 0 0
+\tBlock 1 2 1 1 1 0.9 1 0.7 1 1 1 1 0 0 0 0
+";
+    let game = Game::parse(text).unwrap();
+    let player = game.player_ids()[0];
+    assert!(game.explain_move(Direction::Left).is_empty());
+
+    let trees = game.explain_move(Direction::Right);
+    assert_eq!(trees.len(), 1);
+    let tree = &trees[0];
+    assert!(!tree.success);
+    let wall = game.cell_at(GlobalPos::new(0, Pos(2, 2))).unwrap().id();
+    let attempts = tree
+        .children
+        .iter()
+        .map(|child| match child.kind {
+            TraceKind::Attempt {
+                action, target_id, ..
+            } => {
+                assert_eq!(target_id, wall);
+                assert!(!child.success);
+                (action, child.failure.clone())
+            }
+            kind => panic!("unexpected decision {kind}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        attempts,
+        vec![
+            (
+                ActionType::Push,
+                Some(MoveFailure::Wall {
+                    block_no: BlockNo(0),
+                    pos: Pos(2, 2)
+                })
+            ),
+            (ActionType::Enter, None),
+            (ActionType::Eat, None),
+            (
+                ActionType::Possess,
+                Some(MoveFailure::NotPossessable { cell_id: wall })
+            ),
+        ]
+    );
+    assert!(tree.to_string().starts_with(&format!(
+        "cell {player} moves right: failed\n  cell {player} pushes cell {wall}: failed, blocked by a wall at (2, 2) in block 0\n"
+    )));
+}
+
+#[test]
+fn test_explain_entering_moving_cell() {
+    // the player leaves the level to its right, into a flipped reference of
+    // it, which turns it back onto its own position
+    let text = "version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tRef 1 2 0 1 0 0 0 0 0 0 0 0 0 0 0
+\tRef 2 2 0 0 0 0 0 0 0 0 0 0 1 0 0
+\tWall 3 2 0 0 0
+\tBlock 4 2 1 1 1 0.9 1 0.7 1 1 1 1 0 0 0 0
+";
+    let game = Game::parse(text).unwrap();
+    let player = game.player_ids()[0];
+
+    let (outcome, trace) = game.clone().play_traced(Direction::Right);
+    assert!(!outcome.changed());
+    let refused = MoveFailure::EnterRefused {
+        block_no: BlockNo(1),
+        reason: EnterRefusal::Moving,
+    };
+    assert!(outcome.failures.contains(&refused));
+    let event = trace
+        .iter()
+        .find(|event| {
+            event.kind
+                == TraceKind::Attempt {
+                    action: ActionType::Enter,
+                    cell_id: player,
+                    target_id: player,
+                }
+        })
+        .unwrap();
+    assert!(!event.success);
+    assert_eq!(event.failure, Some(refused));
+
+    let trees = game.explain_move(Direction::Right);
+    assert!(trees[0].to_string().contains(&format!(
+        "cell {player} enters cell {player}: failed, cannot enter block 1: it is moving\n"
+    )));
+}
+
+#[test]
+fn test_sim_log() {
+    let text = fs::read_to_string("levels/vanilla/first_puzzle.txt").unwrap();
+    let mut game = Game::parse(&text).unwrap();
+    let player = game.player_ids()[0];
+    let mut untraced = game.clone();
+
+    let mut calls = Vec::new();
+    let outcome = game.play_logged(Direction::Right, &mut calls);
+    assert_eq!(outcome, untraced.play(Direction::Right));
+    assert_eq!(game.snapshot_text(), untraced.snapshot_text());
+
+    // the player starts moving before it is put on the move stack, then
+    // steps forward
+    assert_eq!(
+        calls[0],
+        (
+            0,
+            SimCall::Move {
+                cell_id: player,
+                direction: Direction::Right
+            }
+        )
+    );
+    assert!(matches!(
+        calls[1],
+        (1, SimCall::Exit { state, .. }) if state.cell_id == player
+    ));
+
+    let mut log = SimLog(Vec::new());
+    game.clone().play_logged(Direction::Left, &mut log);
+    let log = String::from_utf8(log.0).unwrap();
+    assert!(log.starts_with(&format!(
+        "try_move: {player} L\n  try_exit: {player} L at ("
+    )));
+}
@@ -0,0 +1,166 @@
+use parabox::engine::*;
+use std::{ffi::OsStr, fs};
+
+// the move that does in the transformed level what the given move did
+fn transform_move(transform: Transform, direction: Direction) -> Direction {
+    match (transform, direction) {
+        (Transform::MirrorH, Direction::Left | Direction::Right) => direction.opposite(),
+        (Transform::MirrorV, Direction::Up | Direction::Down) => direction.opposite(),
+        (Transform::Rotate(turns), _) => (0..turns).fold(direction, |d, _| d.turn_left()),
+        _ => direction,
+    }
+}
+
+// levels where cells enter blocks of even size through the middle of an
+// edge, which rounds to one side, so that they play differently when
+// transformed
+const ASYMMETRIC_LEVELS: &[&str] = &["2by2", "4by4_linear"];
+
+/// Transforms every vanilla level and plays its solution transformed
+/// likewise, which should still win it.
+#[test]
+fn test_transform_level() {
+    let transforms = [
+        Transform::MirrorH,
+        Transform::MirrorV,
+        Transform::Rotate(1),
+        Transform::Rotate(2),
+    ];
+    let mut failures = Vec::new();
+    let mut transformed = 0;
+
+    for entry in fs::read_dir("levels/vanilla").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some(OsStr::new("txt"))
+            || ASYMMETRIC_LEVELS.contains(&path.file_stem().unwrap().to_str().unwrap())
+        {
+            continue;
+        }
+        let Ok(solution) = fs::read_to_string(path.with_extension("solution")) else {
+            continue;
+        };
+        let game = Game::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        let moves = parse_sequence(&solution).unwrap();
+
+        for transform in transforms {
+            let mut game = game.clone();
+            if game.transform_level(transform).is_err() {
+                continue;
+            }
+            assert_eq!(game.check_invariants(), vec![]);
+            transformed += 1;
+
+            let moves = moves
+                .iter()
+                .map(|&direction| transform_move(transform, direction))
+                .collect::<Vec<_>>();
+            if let Err(e) = verify_solution(&game, &format_sequence(&moves)) {
+                failures.push(format!("{} {transform:?}: {e}", path.display()));
+            }
+        }
+    }
+
+    for failure in &failures {
+        println!("[failed] {failure}");
+    }
+    assert!(transformed > 0);
+    assert!(failures.is_empty(), "{} levels failed", failures.len());
+}
+
+#[test]
+fn test_transform_block() {
+    let text = "version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tWall 0 0 0 0 0
+\tWall 4 4 0 0 0
+\tBlock 1 2 1 3 3 0.9 1 0.7 1 0 0 0 0 0 0 0
+\tRef 3 2 1 1 0 0 0 0 0 0 0 0 1 0 0
+\tFloor 2 3 Button
+\tFloor 2 1 OneWay R
+";
+    let game = Game::parse(text).unwrap();
+    let root = game.block_by_no(BlockNo(0)).unwrap().id;
+    let cell_at = |game: &Game, x, y| game.cell_at(GlobalPos::new(root, Pos(x, y))).cloned();
+
+    // mirroring swaps the positions, flips the cells inside and turns the
+    // one-way floor around
+    let mut mirrored = game.clone();
+    mirrored.transform_block(root, Transform::MirrorH).unwrap();
+    assert_eq!(mirrored.check_invariants(), vec![]);
+    assert!(cell_at(&mirrored, 4, 0).is_some_and(|cell| cell.is_wall()));
+    let block = cell_at(&mirrored, 3, 2).unwrap();
+    assert!(block.block().is_some() && block.fliph());
+    let reference = cell_at(&mirrored, 1, 2).unwrap();
+    assert!(reference.reference().is_some() && !reference.fliph());
+    assert_eq!(mirrored.goals()[0].gpos.pos, Pos(2, 3));
+    assert!(mirrored
+        .floors()
+        .iter()
+        .any(|floor| floor.gpos.pos == Pos(2, 1)
+            && floor.kind == FloorKind::OneWay(vec![Direction::Left])));
+
+    // a quarter turn moves the goal to the left
+    let mut rotated = game.clone();
+    rotated.transform_block(root, Transform::Rotate(1)).unwrap();
+    assert_eq!(rotated.goals()[0].gpos.pos, Pos(1, 2));
+    assert!(cell_at(&rotated, 4, 0).is_some_and(|cell| cell.is_wall()));
+
+    // translating moves everything, unless something would fall off
+    let mut translated = game.clone();
+    assert!(translated
+        .transform_block(root, Transform::Translate(1, 0))
+        .is_err());
+    assert_eq!(translated.snapshot_text(), game.snapshot_text());
+
+    // cropping removes the walls outside, and shrinks the block
+    let mut cropped = game.clone();
+    cropped
+        .transform_block(
+            root,
+            Transform::Crop {
+                x: 1,
+                y: 1,
+                width: 3,
+                height: 3,
+            },
+        )
+        .unwrap();
+    assert_eq!(cropped.check_invariants(), vec![]);
+    assert!(cropped.cells().iter().all(|cell| !cell.is_wall()));
+    let root = cropped.block_by_no(BlockNo(0)).unwrap();
+    assert_eq!((root.width, root.height), (3, 3));
+    assert_eq!(cropped.goals()[0].gpos.pos, Pos(1, 2));
+    let text = cropped.to_text();
+    assert_eq!(Game::parse(&text).unwrap().to_text(), text);
+}
+
+/// Levels that only differ by orientation, colors and block numbers have the
+/// same canonical form, and a moved wall makes a near copy.
+#[test]
+fn test_canonical_form() {
+    let text = fs::read_to_string("levels/vanilla/first_puzzle.txt").unwrap();
+    let game = Game::parse(&text).unwrap();
+
+    let renumbered = text
+        .replace("Block 2 2 2 5 5 0.9", "Block 2 2 1 5 5 0.3")
+        .replace("Block 5 3 1", "Block 5 3 2");
+    let mut copy = Game::parse(&renumbered).unwrap();
+    copy.transform_level(Transform::MirrorV).unwrap();
+    copy.transform_level(Transform::Rotate(1)).unwrap();
+    assert_ne!(copy.to_text(), game.to_text());
+    assert_eq!(copy.canonical_form(), game.canonical_form());
+
+    let moved = Game::parse(&text.replacen("Wall 4 2 0 0 0", "Wall 3 2 0 0 0", 1)).unwrap();
+    assert_ne!(moved.canonical_form(), game.canonical_form());
+    let other = fs::read_to_string("levels/vanilla/1by1_inf.txt").unwrap();
+    let other = Game::parse(&other).unwrap();
+
+    let levels = [game, copy, moved, other];
+    let duplicates = canonical::find_duplicates(&levels, 2);
+    let pairs = duplicates
+        .iter()
+        .map(|duplicate| (duplicate.first, duplicate.second, duplicate.distance))
+        .collect::<Vec<_>>();
+    assert_eq!(pairs, vec![(0, 1, 0), (0, 2, 2), (1, 2, 2)]);
+}
@@ -0,0 +1,102 @@
+use parabox::engine::*;
+
+const LEVEL: &str = "\
+version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tBlock 1 1 1 3 3 0.6 0.8 1 1 1 0 0 0 0 0 0
+\tRef 2 2 1 1 0 0 0 0 0 0 0 0 0 0 0
+\tRef 3 3 1 1 0 0 0 0 0 0 0 0 0 0 0
+\tFloor 3 1 Button
+Floor 0 0 Button
+";
+
+#[test]
+fn test_validate() {
+    let game = Game::parse(LEVEL).unwrap();
+    let issues = game.validate();
+
+    assert!(issues.contains(&LevelIssue::NoPlayers));
+    assert!(issues.iter().any(|issue| matches!(
+        issue,
+        LevelIssue::GoalOutsideBlock { gpos } if gpos.pos == Pos(0, 0)
+    )));
+    assert!(issues.iter().any(|issue| matches!(
+        issue,
+        LevelIssue::MultipleExits { target_no: BlockNo(1), reference_ids } if reference_ids.len() == 2
+    )));
+    assert_eq!(
+        issues
+            .iter()
+            .filter(|issue| matches!(issue, LevelIssue::ReferenceToSolidBlock { .. }))
+            .count(),
+        2
+    );
+
+    // three cells can't cover four goals
+    assert!(!issues
+        .iter()
+        .any(|issue| matches!(issue, LevelIssue::TooFewCells { .. })));
+    let level = LEVEL.replace(
+        "\tFloor 3 1",
+        "\tFloor 1 3 Button\n\tFloor 3 0 Button\n\tFloor 3 1",
+    );
+    let (_, issues) = Game::parse_with_warnings(&level).unwrap();
+    assert!(issues.contains(&LevelIssue::TooFewCells { goals: 4, cells: 3 }));
+
+    // errors come first
+    assert!(issues
+        .windows(2)
+        .all(|pair| pair[0].severity() >= pair[1].severity()));
+
+    // a block that exits itself ends up inside itself, which a level file
+    // can't store
+    let text = std::fs::read_to_string("levels/vanilla/infexit_round_corner_d.txt").unwrap();
+    let solution =
+        std::fs::read_to_string("levels/vanilla/infexit_round_corner_d.solution").unwrap();
+    let mut game = Game::parse(&text).unwrap();
+    assert!(game.validate().is_empty());
+    for &direction in &parse_sequence(&solution).unwrap()[..30] {
+        game.play(direction);
+    }
+    let block_id = game.block_by_no(BlockNo(3)).unwrap().id;
+    assert!(game
+        .validate()
+        .contains(&LevelIssue::BlockInsideItself { block_id }));
+}
+
+#[test]
+fn test_issue_positions() {
+    let game = Game::parse(LEVEL).unwrap();
+    let issues = game.validate();
+    let multiple_exits = issues
+        .iter()
+        .find(|issue| matches!(issue, LevelIssue::MultipleExits { .. }))
+        .unwrap();
+    let mut positions = multiple_exits
+        .positions(&game)
+        .into_iter()
+        .map(|gpos| gpos.pos)
+        .collect::<Vec<_>>();
+    positions.sort_by_key(|&Pos(x, y)| (x, y));
+    assert_eq!(positions, vec![Pos(2, 2), Pos(3, 3)]);
+    assert!(LevelIssue::NoPlayers.positions(&game).is_empty());
+
+    // cells can't leave a top-level block whose only reference isn't its
+    // exit
+    let level = "version 4
+#
+Block -1 -1 0 5 5 0 0 0.8 1 0 0 0 0 0 0 0
+\tRef 2 2 0 0 0 0 0 0 0 0 0 0 0 0 0
+";
+    let issues = Game::parse(level).unwrap().validate();
+    assert!(issues.iter().any(|issue| matches!(
+        issue,
+        LevelIssue::MissingExit { target_no: BlockNo(0), reference_ids } if reference_ids.len() == 1
+    )));
+    let level = level.replace("\tRef 2 2 0 0", "\tRef 2 2 0 1");
+    let issues = Game::parse(&level).unwrap().validate();
+    assert!(!issues
+        .iter()
+        .any(|issue| matches!(issue, LevelIssue::MissingExit { .. })));
+}